@@ -0,0 +1,49 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Represents the sources the circuits are built from: gadgets of this crate, crypto crates and
+/// the workspace manifest pinning plonky2.
+const CIRCUIT_SOURCES: [&str; 5] = [
+    "src/prove_bft",
+    "src/prove_block_data",
+    "src/prove_crypto",
+    "../crypto",
+    "../Cargo.toml",
+];
+
+/// Adds `.rs` and `Cargo.toml` files in `path` to `files`.
+fn collect(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        for entry in fs::read_dir(path).unwrap() {
+            let path = entry.unwrap().path();
+            if !path.ends_with("target") {
+                collect(&path, files);
+            }
+        }
+    } else if path.extension().is_some_and(|ext| ext == "rs") || path.ends_with("Cargo.toml") {
+        files.push(path.to_path_buf());
+    }
+}
+
+fn main() {
+    // Checkpoints of proving stages are valid only for the circuits they were proven with, so
+    // SHA-256 of the circuit sources identifies them (see `Checkpoints::run`).
+    let mut files = vec![];
+    for source in CIRCUIT_SOURCES {
+        println!("cargo:rerun-if-changed={}", source);
+        collect(Path::new(source), &mut files);
+    }
+    files.sort();
+    let mut hasher = Sha256::new();
+    for file in files {
+        let bytes = fs::read(&file).unwrap();
+        hasher.update(file.to_string_lossy().as_bytes());
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    }
+    println!(
+        "cargo:rustc-env=CIRCUIT_SOURCES_DIGEST={}",
+        hex::encode(hasher.finalize())
+    );
+}
@@ -0,0 +1,61 @@
+use anyhow::{bail, Result};
+use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
+use log::{info, Level};
+use near_bft_finality::circuits::{build_circuit_manifest, CircuitManifest, CIRCUIT_MANIFEST_PATH};
+use near_bft_finality::utils::DEFAULT_EPOCHS_PATH;
+use plonky2::util::timing::TimingTree;
+use std::env;
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: circuit_manifest [--check [REFERENCE] | --write [REFERENCE]]
+Proves the reference blocks and prints the manifest of digests of every production circuit.
+The reference blocks are read from EPOCHS_PATH (../data/epochs by default).
+  --check  Fail if circuits differ from REFERENCE (near_bft_finality/circuits.json by default).
+  --write  Write the manifest to REFERENCE (near_bft_finality/circuits.json by default).";
+
+enum Mode {
+    Print,
+    Check(PathBuf),
+    Write(PathBuf),
+}
+
+/// Builds production circuits and prints their manifest. Exits with non-zero code if `--check`
+/// finds circuits that differ from the reference.
+pub fn main() -> Result<()> {
+    let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
+    let mut args = env::args().skip(1);
+    let reference =
+        |path: Option<String>| PathBuf::from(path.as_deref().unwrap_or(CIRCUIT_MANIFEST_PATH));
+    let mode = match args.next().as_deref() {
+        None => Mode::Print,
+        Some("--check") => Mode::Check(reference(args.next())),
+        Some("--write") => Mode::Write(reference(args.next())),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        Some(arg) => bail!("Unknown option {}\n{}", arg, USAGE),
+    };
+    if let Some(arg) = args.next() {
+        bail!("Unexpected argument {}\n{}", arg, USAGE);
+    }
+
+    let epochs_dir = env::var("EPOCHS_PATH").unwrap_or_else(|_| DEFAULT_EPOCHS_PATH.to_string());
+    let mut timing = TimingTree::new("build production circuits", Level::Info);
+    let manifest = build_circuit_manifest(&epochs_dir, &mut timing)?;
+    timing.print();
+    print!("{}", manifest.to_json()?);
+
+    match mode {
+        Mode::Print => {}
+        Mode::Check(path) => {
+            manifest.check(&CircuitManifest::read(&path)?)?;
+            info!("Circuits match {}", path.display());
+        }
+        Mode::Write(path) => {
+            manifest.write(&path)?;
+            info!("Manifest is written to {}", path.display());
+        }
+    }
+    Ok(())
+}
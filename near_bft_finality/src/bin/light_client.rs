@@ -0,0 +1,321 @@
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
+use log::{info, Level};
+use near_bft_finality::circuits::{CircuitManifest, CIRCUIT_MANIFEST_PATH};
+use near_bft_finality::cli::ProveArgs;
+use near_bft_finality::error::BftError;
+use near_bft_finality::network::NetworkProfile;
+use near_bft_finality::pipeline::ProvingPipeline;
+use near_bft_finality::source::BlockSource;
+use near_bft_finality::store::{FsProofStore, ProofKind, DEFAULT_PROOFS_PATH};
+use near_bft_finality::utils::{
+    find_epoch_first_block, parse_hash, resolve_block_input, resolve_epoch_input,
+};
+use near_bft_finality::verify::{
+    anchored_proof_dirs, inspect_artifacts, is_anchored_proof_dir, stored_proof_dirs,
+    verify_anchored_proof_dir, verify_proof_dir, ArtifactsFormat,
+};
+use near_primitives::hash::CryptoHash;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::util::timing::TimingTree;
+use plonky2_bn128::config::PoseidonBN128GoldilocksConfig;
+use proof_store::ProofStore;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+// Final proofs are wrapped in BN128 config before they are stored.
+type Cbn128 = PoseidonBN128GoldilocksConfig;
+
+/// Proves finality of NEAR blocks with plonky2, follows new epochs and checks stored proofs.
+#[derive(Parser)]
+#[command(name = "light_client")]
+struct Cli {
+    /// Directory of the proof store.
+    #[arg(long, global = true, default_value = DEFAULT_PROOFS_PATH)]
+    proofs_dir: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Proves a randomly selected block by its hash.
+    ProveBlock {
+        /// Hash of the block.
+        #[arg(long)]
+        hash: String,
+        #[command(flatten)]
+        prove: ProveArgs,
+    },
+    /// Proves Block_0 and the previous Block_n-1 of the epoch.
+    ProveEpoch {
+        /// epoch_id of the epoch, i.e. the hash of Block_n-1 two epochs before.
+        #[arg(long)]
+        epoch_id: String,
+        #[command(flatten)]
+        prove: ProveArgs,
+    },
+    /// Proves epochs continuously, starting after the last proven epoch or the genesis anchor.
+    Follow {
+        /// Seconds to wait for the next epoch to become final.
+        #[arg(long, default_value_t = 60)]
+        poll_interval: u64,
+        /// Stops after proving this number of epochs.
+        #[arg(long)]
+        max_epochs: Option<usize>,
+        #[command(flatten)]
+        prove: ProveArgs,
+    },
+    /// Verifies stored proofs and proofs of blocks anchored to the epoch chain, exits with non-zero
+    /// code if any proof is invalid or its circuit isn't a production circuit. Anchored proofs are
+    /// checked against the genesis anchor of the network profile (see `NETWORK_CONFIG`).
+    Verify {
+        /// Manifest of production circuits, see `circuit_manifest`.
+        #[arg(long, default_value = CIRCUIT_MANIFEST_PATH)]
+        manifest: PathBuf,
+        /// Read proof.json & verifier_data.json instead of proof.bin & verifier_data.bin.
+        #[arg(long)]
+        json: bool,
+        /// Directories of proofs, all proofs in the proof store by default.
+        dirs: Vec<PathBuf>,
+    },
+    /// Prints stored proofs and their public inputs without verifying them.
+    Inspect {
+        /// Hash of the proven block, all proofs are listed by default.
+        #[arg(long)]
+        hash: Option<String>,
+    },
+}
+
+/// Proves epochs one after another. Block_0 of the last proven epoch gives epoch_id of the next
+/// epoch; if the next epoch hasn't started or its first blocks aren't produced yet, waits for them.
+/// Stops with `BftError::SkippedBlock` if Block_1...Block_4 have a skipped height, such an epoch
+/// can't be proven.
+async fn follow(
+    pipeline: &ProvingPipeline,
+    source: &dyn BlockSource,
+    profile: &NetworkProfile,
+    poll_interval: Duration,
+    max_epochs: Option<usize>,
+) -> Result<()> {
+    let mut proven = 0;
+    while max_epochs.map_or(true, |max_epochs| proven < max_epochs) {
+        let last_b0_hash = match pipeline.store().latest(ProofKind::Epoch)? {
+            Some(record) => record.block_hash,
+            None => profile
+                .genesis
+                .map(|genesis| genesis.block_hash)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No proven epochs and no genesis anchor in the profile of {}",
+                        profile.chain_id
+                    )
+                })?,
+        };
+        let (_, last_b0) = source.block_by_hash(last_b0_hash).await?;
+        let epoch_id = last_b0.next_epoch_id().0;
+        info!(
+            "Last proven Block_0: {}, next epoch: {}",
+            last_b0_hash, epoch_id
+        );
+        let input = async {
+            let (b0_hash, _) = find_epoch_first_block(epoch_id, source, profile).await?;
+            resolve_epoch_input(b0_hash, source, pipeline.store(), profile).await
+        };
+        let input = match input.await {
+            Ok(input) => input,
+            Err(BftError::BlockNotFound(block)) => {
+                info!("Epoch {} isn't final yet, no block {}", epoch_id, block);
+                tokio::time::sleep(poll_interval).await;
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut timing = TimingTree::new("To prove epoch", Level::Info);
+        pipeline.prove_epoch(&input, &mut timing)?;
+        timing.print();
+        proven += 1;
+    }
+    Ok(())
+}
+
+/// Verifies proofs in `dirs` or all proofs in the proof store, including proofs anchored to the
+/// epoch chain, against the manifest of production circuits.
+fn verify(proofs_dir: &Path, manifest: &Path, json: bool, mut dirs: Vec<PathBuf>) -> Result<()> {
+    let manifest = CircuitManifest::read(manifest).map_err(|e| {
+        anyhow!(
+            "Can't read the manifest of production circuits {}: {}",
+            manifest.display(),
+            e
+        )
+    })?;
+    let format = if json {
+        ArtifactsFormat::Json
+    } else {
+        ArtifactsFormat::Binary
+    };
+    if dirs.is_empty() {
+        dirs = stored_proof_dirs(proofs_dir)?;
+        dirs.extend(anchored_proof_dirs(proofs_dir)?);
+        info!("Found {} proofs in {}", dirs.len(), proofs_dir.display());
+    }
+    if dirs.is_empty() {
+        return Err(anyhow!("No proofs to verify"));
+    }
+    // The epoch chain of anchored proofs must start from the genesis anchor.
+    let genesis = if dirs.iter().any(|dir| is_anchored_proof_dir(dir)) {
+        let profile = NetworkProfile::from_env()?;
+        let genesis = profile.genesis.ok_or_else(|| {
+            anyhow!(
+                "No genesis anchor in the profile of {} to verify anchored proofs",
+                profile.chain_id
+            )
+        })?;
+        Some(genesis.trusted_state().anchors())
+    } else {
+        None
+    };
+    let mut failed = 0;
+    for dir in &dirs {
+        let report = match &genesis {
+            Some(genesis) if is_anchored_proof_dir(dir) => {
+                verify_anchored_proof_dir::<F, C, D>(dir, format, &manifest, genesis)
+            }
+            _ => verify_proof_dir::<F, Cbn128, D>(dir, format, &manifest),
+        };
+        match report {
+            Ok(report) => println!("{}: OK\n{}\n", dir.display(), report),
+            Err(e) => {
+                println!("{}: FAILED\n{:#}\n", dir.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} proofs failed verification",
+            failed,
+            dirs.len()
+        ));
+    }
+    info!("All {} proofs are valid", dirs.len());
+    Ok(())
+}
+
+/// Prints the proofs of the block or lists all stored proofs.
+fn inspect(store: &FsProofStore, proofs_dir: &Path, hash: Option<CryptoHash>) -> Result<()> {
+    let Some(hash) = hash else {
+        for dir in stored_proof_dirs(proofs_dir)? {
+            let name = dir.file_name().and_then(|name| name.to_str());
+            let Some(hash) = name.and_then(|name| CryptoHash::from_str(name).ok()) else {
+                continue;
+            };
+            for kind in [ProofKind::Random, ProofKind::Epoch] {
+                if store.proof_dir(kind, &hash) != dir {
+                    continue;
+                }
+                if let Some(record) = store.record(kind, &hash)? {
+                    println!(
+                        "{:?} {} at height {}, epoch {}",
+                        kind, record.block_hash, record.height, record.epoch_id
+                    );
+                }
+            }
+        }
+        for kind in [ProofKind::Random, ProofKind::Epoch] {
+            let latest = store.latest(kind)?;
+            println!(
+                "Latest {:?} proof: {}",
+                kind,
+                latest.map_or("none".to_string(), |record| format!(
+                    "{} at height {}",
+                    record.block_hash, record.height
+                ))
+            );
+        }
+        return Ok(());
+    };
+    let mut found = false;
+    for kind in [ProofKind::Random, ProofKind::Epoch] {
+        let (Some(record), Some(artifacts)) = (store.record(kind, &hash)?, store.get(kind, &hash)?)
+        else {
+            continue;
+        };
+        found = true;
+        println!(
+            "{:?} proof of block {} at height {}, epoch {}",
+            kind, record.block_hash, record.height, record.epoch_id
+        );
+        println!("{}\n", inspect_artifacts::<F, Cbn128, D>(&artifacts)?);
+    }
+    if !found {
+        return Err(anyhow!("No proofs of block {}", hash));
+    }
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
+    let Cli {
+        proofs_dir,
+        command,
+    } = Cli::parse();
+    let store = Arc::new(FsProofStore::open(&proofs_dir)?);
+    match command {
+        Command::ProveBlock { hash, prove } => {
+            let (profile, source, pipeline) = prove.build(store.clone(), &proofs_dir)?;
+            let input = resolve_block_input(
+                parse_hash(&hash)?,
+                source.as_ref(),
+                store.as_ref(),
+                &profile,
+            )
+            .await?;
+            let mut timing = TimingTree::new("To prove block", Level::Info);
+            pipeline.prove_block(&input, &mut timing)?;
+            timing.print();
+        }
+        Command::ProveEpoch { epoch_id, prove } => {
+            let (profile, source, pipeline) = prove.build(store.clone(), &proofs_dir)?;
+            let (b0_hash, _) =
+                find_epoch_first_block(parse_hash(&epoch_id)?, source.as_ref(), &profile).await?;
+            let input =
+                resolve_epoch_input(b0_hash, source.as_ref(), store.as_ref(), &profile).await?;
+            let mut timing = TimingTree::new("To prove epoch", Level::Info);
+            pipeline.prove_epoch(&input, &mut timing)?;
+            timing.print();
+        }
+        Command::Follow {
+            poll_interval,
+            max_epochs,
+            prove,
+        } => {
+            let (profile, source, pipeline) = prove.build(store.clone(), &proofs_dir)?;
+            follow(
+                &pipeline,
+                source.as_ref(),
+                &profile,
+                Duration::from_secs(poll_interval),
+                max_epochs,
+            )
+            .await?;
+        }
+        Command::Verify {
+            manifest,
+            json,
+            dirs,
+        } => verify(&proofs_dir, &manifest, json, dirs)?,
+        Command::Inspect { hash } => {
+            let hash = hash.as_deref().map(parse_hash).transpose()?;
+            inspect(&store, &proofs_dir, hash)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use anyhow::Result;
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{Request, Response, StatusCode};
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::Router;
+use clap::Parser;
+use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
+use log::{error, info};
+use near_bft_finality::cli::ProveArgs;
+use near_bft_finality::error::BftError;
+use near_bft_finality::service::{EpochProvingTask, ProvingService, RandomProvingTask};
+use near_bft_finality::store::{FsProofStore, DEFAULT_PROOFS_PATH};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// Serves proofs of the plonky2 backend over HTTP with the API of the risc0 proving server.
+#[derive(Parser)]
+#[command(name = "proving_server")]
+struct Cli {
+    /// Address of this server, EPOCH_SERVER_ADDRESS or 127.0.0.1:1337 by default.
+    #[arg(long)]
+    addr: Option<String>,
+    /// Directory of the proof store.
+    #[arg(long, default_value = DEFAULT_PROOFS_PATH)]
+    proofs_dir: PathBuf,
+    #[command(flatten)]
+    prove: ProveArgs,
+}
+
+#[derive(Clone)]
+struct ServerState {
+    active_requests: Arc<AtomicUsize>,
+    service: Arc<ProvingService>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
+    let cli = Cli::parse();
+    let addr = cli.addr.unwrap_or_else(|| {
+        std::env::var("EPOCH_SERVER_ADDRESS").unwrap_or_else(|_| "127.0.0.1:1337".to_string())
+    });
+
+    let store = Arc::new(FsProofStore::open(&cli.proofs_dir)?);
+    let (profile, source, pipeline) = cli.prove.build(store, &cli.proofs_dir)?;
+    info!("Network: {}", profile.chain_id);
+    let state = ServerState {
+        active_requests: Arc::new(AtomicUsize::new(0)),
+        service: Arc::new(ProvingService::new(profile, source, pipeline)),
+    };
+
+    let app = Router::new()
+        .route("/health", get(health_check))
+        .route("/ready", get(ready_check))
+        .route("/epoch/proof", post(epoch_proof))
+        .route("/random/proof", post(random_proof))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            count_requests_middleware,
+        ))
+        .with_state(state);
+
+    info!("Server running on {}", addr);
+    let listener = TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn epoch_proof(
+    State(state): State<ServerState>,
+    body: Bytes,
+) -> Result<impl IntoResponse, StatusCode> {
+    let task = parse_task::<EpochProvingTask>(&body)?;
+    info!("Start proving epoch block {}", task.epoch_id_i_block_hash);
+    let result = state.service.prove_epoch(&task).await.map_err(|err| {
+        error!("Failed to generate epoch change proof: {err}");
+        status_code(&err)
+    })?;
+    info!(
+        "Generated epoch proofs of {} and {}",
+        result.block_hash_n_0, result.block_hash_n_1
+    );
+    json_response(&result)
+}
+
+async fn random_proof(
+    State(state): State<ServerState>,
+    body: Bytes,
+) -> Result<impl IntoResponse, StatusCode> {
+    let task = parse_task::<RandomProvingTask>(&body)?;
+    info!("Start proving random block {}", task.epoch_id_i_hash_i);
+    let result = state.service.prove_random(&task).await.map_err(|err| {
+        error!("Failed to generate random block proof: {err}");
+        status_code(&err)
+    })?;
+    info!("Generated random proof of {}", result.epoch_id_i_block_hash);
+    json_response(&result)
+}
+
+fn parse_task<T: DeserializeOwned>(body: &[u8]) -> Result<T, StatusCode> {
+    serde_json::from_slice(body).map_err(|err| {
+        error!("Failed to deserialize request object: {err}");
+        StatusCode::BAD_REQUEST
+    })
+}
+
+/// Tasks which don't match the chain are rejected as bad requests, unknown blocks aren't found.
+fn status_code(err: &BftError) -> StatusCode {
+    match err {
+        BftError::InvalidInput(_) | BftError::HashMismatch(_) | BftError::SkippedBlock(_) => {
+            StatusCode::BAD_REQUEST
+        }
+        BftError::BlockNotFound(_) => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn json_response<T: Serialize>(result: &T) -> Result<Response<Body>, StatusCode> {
+    let json = serde_json::to_vec(result).map_err(|err| {
+        error!("Failed to serialize response to JSON: {err}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(json))
+        .map_err(|err| {
+            error!("Could not construct response for client: {err}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn health_check() -> impl IntoResponse {
+    StatusCode::OK
+}
+
+async fn ready_check(State(state): State<ServerState>) -> impl IntoResponse {
+    if state.active_requests.load(Ordering::SeqCst) > 0 {
+        StatusCode::CONFLICT
+    } else {
+        StatusCode::OK
+    }
+}
+
+async fn count_requests_middleware(
+    State(state): State<ServerState>,
+    req: Request<Body>,
+    next: Next,
+) -> impl IntoResponse {
+    // Requests to the ready endpoint aren't counted.
+    let counted = req.uri().path() != "/ready";
+    if counted {
+        state.active_requests.fetch_add(1, Ordering::SeqCst);
+    }
+    let response = next.run(req).await;
+    if counted {
+        state.active_requests.fetch_sub(1, Ordering::SeqCst);
+    }
+    response
+}
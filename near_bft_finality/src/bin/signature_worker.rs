@@ -0,0 +1,128 @@
+use anyhow::{anyhow, Result};
+use async_nats::jetstream;
+use async_nats::jetstream::consumer::PullConsumer;
+use async_nats::jetstream::stream::RetentionPolicy;
+use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
+use futures::stream::StreamExt;
+use log::{info, warn, Level};
+use near_bft_finality::prove_block_data::signature_prover::prove_signature_task;
+use near_bft_finality::types::{
+    InputTask, PROVE_SIGNATURE_SUBJECT, SIGNATURE_RESULT_SUBJECT, SIGNATURE_TASK_TIMEOUT_SECS,
+};
+use plonky2::plonk::circuit_data::CircuitData;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::timed;
+use plonky2::util::timing::TimingTree;
+use plonky2_ed25519::gadgets::eddsa::EDDSATargets;
+use serde_json::json;
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+const STREAM_NAME: &str = "SIGNATURES_STREAM";
+const CONSUMER_NAME: &str = "signature_worker";
+
+/// Proves the signature from the task and serializes the result.
+/// The circuit is built once for each message length and reused for the next tasks.
+fn prove_task(
+    task: &InputTask,
+    circuits: &mut HashMap<usize, (CircuitData<F, C, D>, EDDSATargets)>,
+) -> Result<Vec<u8>> {
+    let mut timing = TimingTree::new("prove signature", Level::Info);
+    let output = timed!(
+        timing,
+        "prove signature",
+        prove_signature_task::<F, C, D>(task, circuits)?
+    );
+    timing.print();
+    Ok(serde_json::to_vec(&json!(output))?)
+}
+
+/// Consumes signature proving tasks from `PROVE_SIGNATURE` subject and publishes proofs
+/// to `PROCESS_SIGNATURE_RESULT.{request_id}`. Several workers share one durable consumer.
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
+    let nats_url = env::var("NATS_URL").map_err(|_| anyhow!("NATS_URL parameter missed"))?;
+    info!("Nats URL: {}", nats_url);
+    let client = async_nats::ConnectOptions::new()
+        .reconnect_delay_callback(|attempts| {
+            info!("No of attempts to reconnect: {}", attempts);
+            Duration::from_millis(std::cmp::min((attempts * 100) as u64, 8000))
+        })
+        .connect(nats_url)
+        .await?;
+    let jetstream = jetstream::new(client.clone());
+    let consumer: PullConsumer = jetstream
+        .get_or_create_stream(jetstream::stream::Config {
+            name: STREAM_NAME.to_string(),
+            subjects: vec![PROVE_SIGNATURE_SUBJECT.into()],
+            retention: RetentionPolicy::WorkQueue,
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| anyhow!("Error creating stream: {}", e))?
+        .get_or_create_consumer(
+            CONSUMER_NAME,
+            jetstream::consumer::pull::Config {
+                durable_name: Some(CONSUMER_NAME.to_string()),
+                // Don't redeliver a task while it is being proven.
+                ack_wait: Duration::from_secs(SIGNATURE_TASK_TIMEOUT_SECS),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|e| anyhow!("Error creating consumer: {}", e))?;
+    info!("Connection: {:?}", client.connection_state());
+
+    // Cached proving schema and targets depending on message length.
+    let mut circuits: HashMap<usize, (CircuitData<F, C, D>, EDDSATargets)> = HashMap::new();
+    let mut messages = consumer
+        .stream()
+        .max_messages_per_batch(1)
+        .messages()
+        .await
+        .map_err(|e| anyhow!("Error subscribing to tasks: {}", e))?;
+    while let Some(message) = messages.next().await {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Error receiving task: {}", e);
+                continue;
+            }
+        };
+        match serde_json::from_slice::<InputTask>(&message.payload) {
+            Ok(task) => {
+                info!(
+                    "Incoming signature index: {} of request {}",
+                    task.signature_index, task.request_id
+                );
+                // A failed task is not answered, the coordinator republishes it on timeout.
+                match prove_task(&task, &mut circuits) {
+                    Ok(output_bytes) => {
+                        client
+                            .publish(
+                                format!("{SIGNATURE_RESULT_SUBJECT}.{}", task.request_id),
+                                output_bytes.into(),
+                            )
+                            .await?;
+                    }
+                    Err(e) => warn!(
+                        "Error proving signature {} of request {}: {:#}",
+                        task.signature_index, task.request_id, e
+                    ),
+                }
+            }
+            Err(e) => warn!("Received invalid task {:?}: {}", message.subject, e),
+        }
+        message
+            .ack()
+            .await
+            .map_err(|e| anyhow!("Error acknowledging task: {}", e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use anyhow::{anyhow, bail, Result};
+use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
+use log::info;
+use near_bft_finality::synthetic::{SyntheticChain, SyntheticChainConfig};
+use std::env;
+use std::str::FromStr;
+
+const USAGE: &str = "Usage: synthetic_chain [OPTIONS] OUT_DIR
+Generates a deterministic fake chain and writes it to OUT_DIR in the layout of data/epochs.
+  --seed N               Seed of keys, stakes and other values (0 by default).
+  --epochs N             Number of epochs (5 by default).
+  --epoch-length N       Number of heights in an epoch (64 by default).
+  --validators N         Number of validators in each epoch (16 by default).
+  --rotated N            Number of validators replaced in each epoch (1 by default).
+  --missing-approvals N  Number of validators that don't sign approvals (0 by default).
+  --skip H1,H2,...       Heights without blocks.
+  --genesis-height N     Height of the first block (1 by default).
+  --random-offset N      Offset of random-0.json in an epoch (epoch length / 2 by default).";
+
+fn parse<T: FromStr>(name: &str, value: Option<String>) -> Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    let value = value.ok_or_else(|| anyhow!("Missing value of {}\n{}", name, USAGE))?;
+    value
+        .parse()
+        .map_err(|e| anyhow!("Invalid value {} of {}: {}", value, name, e))
+}
+
+/// Generates a synthetic chain and prints epoch_ids of its epochs, which are used to load
+/// data for provers.
+pub fn main() -> Result<()> {
+    let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
+    let mut config = SyntheticChainConfig::default();
+    let mut random_offset = None;
+    let mut out_dir = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => config.seed = parse(&arg, args.next())?,
+            "--epochs" => config.num_epochs = parse(&arg, args.next())?,
+            "--epoch-length" => config.epoch_length = parse(&arg, args.next())?,
+            "--validators" => config.num_validators = parse(&arg, args.next())?,
+            "--rotated" => config.rotated_validators = parse(&arg, args.next())?,
+            "--missing-approvals" => config.missing_approvals = parse(&arg, args.next())?,
+            "--skip" => {
+                let heights: String = parse(&arg, args.next())?;
+                for height in heights.split(',').filter(|h| !h.is_empty()) {
+                    config
+                        .skipped_heights
+                        .insert(parse(&arg, Some(height.to_string()))?);
+                }
+            }
+            "--genesis-height" => config.genesis_height = parse(&arg, args.next())?,
+            "--random-offset" => random_offset = Some(parse(&arg, args.next())?),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ if arg.starts_with('-') => bail!("Unknown option {}\n{}", arg, USAGE),
+            _ if out_dir.is_none() => out_dir = Some(arg.clone()),
+            _ => bail!("Unexpected argument {}\n{}", arg, USAGE),
+        }
+    }
+    let out_dir = out_dir.ok_or_else(|| anyhow!("Missing OUT_DIR\n{}", USAGE))?;
+    config.random_offset = random_offset.unwrap_or(config.epoch_length / 2);
+
+    let chain = SyntheticChain::generate(config)?;
+    chain.write(&out_dir)?;
+    info!(
+        "Chain of {} epochs is written to {}",
+        chain.epochs.len(),
+        out_dir
+    );
+    for (k, epoch) in chain.epochs.iter().enumerate() {
+        println!(
+            "Epoch {}: {} (heights {}..={})",
+            k,
+            epoch.epoch_id,
+            epoch.first_block().height(),
+            epoch.last_block().height()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,364 @@
+use crate::circuits::ConfigName;
+use crate::error::{BftError, Result};
+use crate::store::write_atomic;
+use log::{info, warn};
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::{CircuitData, VerifierCircuitData};
+use plonky2::plonk::config::{GenericConfig, GenericHashOut};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::serialization::DefaultGateSerializer;
+use plonky2_field::extension::Extendable;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Represents the default root directory of checkpoints of proving stages.
+pub const DEFAULT_CHECKPOINTS_PATH: &str = "./checkpoints";
+
+/// Represents SHA-256 of the sources the circuits of this build are built from in hex, computed by
+/// `build.rs`. A change of any gadget changes it, so stages of older circuits are never resumed.
+pub const CIRCUIT_SOURCES_DIGEST: &str = env!("CIRCUIT_SOURCES_DIGEST");
+
+const MANIFEST_FILE: &str = "manifest.json";
+const PROOF_FILE: &str = "proof.bin";
+const VERIFIER_DATA_FILE: &str = "verifier_data.bin";
+
+/// Represents the identity of the circuit of a finished stage, it is written after the proof.
+///
+/// # Fields
+///
+/// * `stage` - The name of the stage.
+/// * `version` - The version of the crate that proved the stage.
+/// * `circuit_sources` - SHA-256 of the circuit sources the stage was proven with in hex (see
+///   `CIRCUIT_SOURCES_DIGEST`).
+/// * `config` - The name of `GenericConfig` of the circuit (see `ConfigName`).
+/// * `circuit_digest` - The digest of the circuit in hex.
+/// * `degree_bits` - Log2 of the number of rows of the circuit.
+/// * `num_public_inputs` - The number of public inputs of the proof.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageManifest {
+    pub stage: String,
+    pub version: String,
+    pub circuit_sources: String,
+    pub config: String,
+    pub circuit_digest: String,
+    pub degree_bits: usize,
+    pub num_public_inputs: usize,
+}
+
+impl StageManifest {
+    fn new<F, C, const D: usize>(stage: &str, data: &VerifierCircuitData<F, C, D>) -> Self
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + ConfigName,
+    {
+        Self {
+            stage: stage.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            circuit_sources: CIRCUIT_SOURCES_DIGEST.to_string(),
+            config: C::NAME.to_string(),
+            circuit_digest: hex::encode(data.verifier_only.circuit_digest.to_bytes()),
+            degree_bits: data.common.degree_bits(),
+            num_public_inputs: data.common.num_public_inputs,
+        }
+    }
+}
+
+/// Represents checkpoints of intermediate proofs, so an interrupted run is resumed from the last
+/// finished stage instead of proving everything again.
+///
+/// Each stage is stored in `{root}/{run}/{scope...}/{stage}` as `verifier_data.bin`, `proof.bin`
+/// and `manifest.json`. Disabled checkpoints store nothing and prove every stage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checkpoints {
+    dir: Option<PathBuf>,
+}
+
+impl Checkpoints {
+    /// Creates checkpoints that store nothing.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Opens checkpoints stored in `root`, creating the directory if it doesn't exist.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref();
+        fs::create_dir_all(root)?;
+        Ok(Self {
+            dir: Some(root.to_path_buf()),
+        })
+    }
+
+    /// Returns the directory of checkpoints, `None` if they are disabled.
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Returns checkpoints of the run proving `inputs`. The run is identified by SHA-256 of the
+    /// inputs, the version of the crate and the circuit sources (see `CIRCUIT_SOURCES_DIGEST`),
+    /// so a rerun with the same inputs and circuits finds finished stages.
+    pub fn run(&self, inputs: &[&[u8]]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.update(CIRCUIT_SOURCES_DIGEST.as_bytes());
+        for input in inputs {
+            hasher.update((input.len() as u64).to_le_bytes());
+            hasher.update(input);
+        }
+        self.scope(&hex::encode(hasher.finalize()))
+    }
+
+    /// Returns checkpoints nested in `name`, e.g. for stages of one of several proven blocks.
+    pub fn scope(&self, name: &str) -> Self {
+        Self {
+            dir: self.dir.as_ref().map(|dir| dir.join(name)),
+        }
+    }
+
+    /// Returns the stored proof of the stage `name`, or proves it with `prove` and stores the result.
+    ///
+    /// A stored proof is used only if its manifest matches the circuit sources of this build, the
+    /// crate version and the config, and the proof is valid for the stored circuit. Otherwise the
+    /// stage is proven again.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors of `prove` and `BftError::Io` / `BftError::Serialization` if the proof can't be stored.
+    pub fn stage<F, C, const D: usize>(
+        &self,
+        name: &str,
+        prove: impl FnOnce() -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>,
+    ) -> Result<(VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + ConfigName,
+    {
+        let dir = match &self.dir {
+            Some(dir) => dir.join(name),
+            None => {
+                let (data, proof) = prove()?;
+                return Ok((data.verifier_data(), proof));
+            }
+        };
+        if dir.join(MANIFEST_FILE).exists() {
+            match load::<F, C, D>(&dir, name) {
+                Ok(stage) => {
+                    info!("Stage {} is resumed from {}", name, dir.display());
+                    return Ok(stage);
+                }
+                Err(e) => warn!(
+                    "Checkpoint of stage {} is invalid, proving again: {}",
+                    name, e
+                ),
+            }
+        }
+        let (data, proof) = prove()?;
+        let data = data.verifier_data();
+        save(&dir, name, &data, &proof)?;
+        Ok((data, proof))
+    }
+
+    /// Returns manifests of finished stages stored directly in this directory, sorted by name.
+    pub fn stages(&self) -> Result<Vec<StageManifest>> {
+        let dir = match &self.dir {
+            Some(dir) if dir.exists() => dir,
+            _ => return Ok(vec![]),
+        };
+        let mut stages = vec![];
+        for entry in fs::read_dir(dir)? {
+            let manifest_path = entry?.path().join(MANIFEST_FILE);
+            if manifest_path.exists() {
+                stages.push(serde_json::from_slice::<StageManifest>(&fs::read(
+                    manifest_path,
+                )?)?);
+            }
+        }
+        stages.sort_by(|a, b| a.stage.cmp(&b.stage));
+        Ok(stages)
+    }
+
+    /// Removes all stored stages, e.g. after the final proof is stored.
+    pub fn clear(&self) -> Result<()> {
+        if let Some(dir) = &self.dir {
+            if dir.exists() {
+                fs::remove_dir_all(dir)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the stage from `dir` and checks its manifest against this build.
+fn load<F, C, const D: usize>(
+    dir: &Path,
+    name: &str,
+) -> Result<(VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + ConfigName,
+{
+    let manifest: StageManifest = serde_json::from_slice(&fs::read(dir.join(MANIFEST_FILE))?)?;
+    let data = VerifierCircuitData::<F, C, D>::from_bytes(
+        fs::read(dir.join(VERIFIER_DATA_FILE))?,
+        &DefaultGateSerializer,
+    )
+    .map_err(|e| BftError::Serialization(format!("Error reading verifier data: {:?}", e)))?;
+    let expected = StageManifest::new(name, &data);
+    if manifest != expected {
+        return Err(BftError::HashMismatch(format!(
+            "stored stage {:?} doesn't match {:?}",
+            manifest, expected
+        )));
+    }
+    let proof =
+        ProofWithPublicInputs::<F, C, D>::from_bytes(fs::read(dir.join(PROOF_FILE))?, &data.common)
+            .map_err(|e| BftError::Serialization(format!("Error reading proof: {:#}", e)))?;
+    data.verify(proof.clone())
+        .map_err(|e| BftError::Proving(format!("Proof verification failed: {:#}", e)))?;
+    Ok((data, proof))
+}
+
+/// Writes the stage to `dir`. The manifest is written last, so an interrupted write is never resumed.
+fn save<F, C, const D: usize>(
+    dir: &Path,
+    name: &str,
+    data: &VerifierCircuitData<F, C, D>,
+    proof: &ProofWithPublicInputs<F, C, D>,
+) -> Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + ConfigName,
+{
+    fs::create_dir_all(dir)?;
+    let manifest_path = dir.join(MANIFEST_FILE);
+    if manifest_path.exists() {
+        fs::remove_file(&manifest_path)?;
+    }
+    let verifier_data = data
+        .to_bytes(&DefaultGateSerializer)
+        .map_err(|e| BftError::Serialization(format!("Error writing verifier data: {:?}", e)))?;
+    write_atomic(&dir.join(VERIFIER_DATA_FILE), &verifier_data)?;
+    write_atomic(&dir.join(PROOF_FILE), &proof.to_bytes())?;
+    write_atomic(
+        &manifest_path,
+        &serde_json::to_vec_pretty(&StageManifest::new(name, data))?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+    use plonky2_field::types::Field;
+    use std::cell::Cell;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("checkpoints_{:016x}", rand::random::<u64>()))
+    }
+
+    /// Proves a circuit exposing `value` as the public input, in place of a proving stage.
+    fn prove_value(
+        value: u64,
+    ) -> crate::error::Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let target = builder.add_virtual_target();
+        builder.register_public_input(target);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(target, F::from_canonical_u64(value));
+        let proof = data.prove(pw)?;
+        Ok((data, proof))
+    }
+
+    #[test]
+    fn test_stage_is_resumed() -> Result<()> {
+        let root = temp_dir();
+        let checkpoints = Checkpoints::open(&root)?.run(&[b"block", b"validators"]);
+        let calls = Cell::new(0);
+        let prove = || {
+            calls.set(calls.get() + 1);
+            prove_value(7)
+        };
+        let (data, proof) = checkpoints.stage("header", prove)?;
+        assert_eq!(calls.get(), 1);
+        // A rerun with the same inputs reads the stored stage.
+        let rerun = Checkpoints::open(&root)?.run(&[b"block", b"validators"]);
+        let (stored_data, stored_proof) = rerun.stage("header", || {
+            calls.set(calls.get() + 1);
+            prove_value(7)
+        })?;
+        assert_eq!(calls.get(), 1);
+        assert_eq!(stored_proof, proof);
+        assert_eq!(stored_data.verifier_only, data.verifier_only);
+        // The config is identified by its stable name, not by the compiler.
+        let manifest: StageManifest = serde_json::from_slice(&fs::read(
+            checkpoints
+                .dir()
+                .expect("enabled")
+                .join("header")
+                .join(MANIFEST_FILE),
+        )?)?;
+        assert_eq!(manifest.config, C::NAME);
+        assert_eq!(checkpoints.stages()?, vec![manifest]);
+        assert!(Checkpoints::disabled().stages()?.is_empty());
+        // Other inputs are a different run.
+        let other = Checkpoints::open(&root)?.run(&[b"blockvalidators"]);
+        assert_ne!(other, rerun);
+        other.stage("header", || {
+            calls.set(calls.get() + 1);
+            prove_value(7)
+        })?;
+        assert_eq!(calls.get(), 2);
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_stage_is_proven_again() -> Result<()> {
+        let root = temp_dir();
+        let checkpoints = Checkpoints::open(&root)?.scope("block_0");
+        checkpoints.stage("finality", || prove_value(1))?;
+        let dir = root.join("block_0").join("finality");
+        // Corrupt the stored proof.
+        let mut proof = fs::read(dir.join(PROOF_FILE))?;
+        let len = proof.len();
+        proof[len - 8] ^= 1;
+        fs::write(dir.join(PROOF_FILE), proof)?;
+        let (_, proof) = checkpoints.stage("finality", || prove_value(2))?;
+        assert_eq!(proof.public_inputs, vec![F::TWO]);
+        // The checkpoint is replaced by the new proof.
+        let (_, proof) = checkpoints.stage("finality", || prove_value(3))?;
+        assert_eq!(proof.public_inputs, vec![F::TWO]);
+        // A stage proven with other circuit sources is proven again.
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let mut manifest: StageManifest = serde_json::from_slice(&fs::read(&manifest_path)?)?;
+        manifest.circuit_sources = hex::encode([0u8; 32]);
+        fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?)?;
+        let (_, proof) = checkpoints.stage("finality", || prove_value(5))?;
+        assert_eq!(proof.public_inputs, vec![F::from_canonical_u64(5)]);
+        // A stage without the manifest is not finished.
+        fs::remove_file(dir.join(MANIFEST_FILE))?;
+        let (_, proof) = checkpoints.stage("finality", || prove_value(3))?;
+        assert_eq!(proof.public_inputs, vec![F::from_canonical_u64(3)]);
+        checkpoints.clear()?;
+        assert!(!root.join("block_0").exists());
+        assert_eq!(
+            Checkpoints::disabled()
+                .stage("finality", || prove_value(4))?
+                .1
+                .public_inputs,
+            vec![F::from_canonical_u64(4)]
+        );
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+}
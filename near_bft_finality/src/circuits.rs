@@ -0,0 +1,377 @@
+use crate::checkpoint::{Checkpoints, StageManifest};
+use crate::error::{BftError, Result};
+use crate::pipeline::{
+    wrap_final_proof, BLOCK_0_BN128_STAGE, BLOCK_I_BN128_STAGE, BLOCK_N_1_BN128_STAGE,
+};
+use crate::prove_bft::epoch_chain::{
+    verifier_data_hash, AnchoredBlockCircuit, EpochChainCircuit, EPOCH_PROOF_DEGREE_BITS,
+};
+use crate::prove_bft::scheduler::StageScheduler;
+use crate::prove_block_data::signature_prover::RayonSignatureProver;
+use crate::prove_crypto::recursion::normalize_proof;
+use crate::store::{write_atomic, ProofKind};
+use crate::utils::{load_block_input, load_epoch_input};
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::{CircuitData, VerifierCircuitData};
+use plonky2::plonk::config::{GenericConfig, GenericHashOut, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::timed;
+use plonky2::util::timing::TimingTree;
+use plonky2_bn128::config::PoseidonBN128GoldilocksConfig;
+use plonky2_field::extension::Extendable;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Represents the default path of the reference manifest of production circuits, committed next
+/// to `Cargo.toml` of the crate, so it doesn't depend on the working directory.
+pub const CIRCUIT_MANIFEST_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/circuits.json");
+/// Represents epochs of the reference blocks the circuits are built for, from Epoch_i to
+/// Epoch_i-3. They are the epochs of the conformance vectors.
+pub const REFERENCE_EPOCH_IDS: [&str; 4] = [
+    "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t",
+    "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae",
+    "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi",
+    "89PT9SkLXB1FZHvW7EdQHxiSpm5ybuTCvjrGZWWhXMTz",
+];
+
+const RANDOM_SCOPE: &str = "random";
+const EPOCH_SCOPE: &str = "epoch";
+
+/// Represents the stage of the circuit of the epoch chain (see `EpochChainCircuit`) in the manifest.
+pub const EPOCH_CHAIN_STAGE: &str = "chain/epoch_chain";
+/// Represents the stage of the circuit anchoring random blocks to the epoch chain (see
+/// `AnchoredBlockCircuit`) in the manifest. Anchored proofs aren't wrapped in BN128 config.
+pub const ANCHORED_BLOCK_STAGE: &str = "chain/anchored_block";
+
+/// Returns stages of the manifest, which build circuits of final proofs of `kind` stored by
+/// `ProvingPipeline`.
+pub fn final_proof_stages(kind: ProofKind) -> Vec<String> {
+    match kind {
+        ProofKind::Random => vec![format!("{RANDOM_SCOPE}/{BLOCK_I_BN128_STAGE}")],
+        ProofKind::Epoch => vec![
+            format!("{EPOCH_SCOPE}/{BLOCK_0_BN128_STAGE}"),
+            format!("{EPOCH_SCOPE}/{BLOCK_N_1_BN128_STAGE}"),
+        ],
+    }
+}
+
+/// Represents the stable name of `GenericConfig` in manifests, unlike `std::any::type_name` it
+/// doesn't depend on the compiler and module paths.
+pub trait ConfigName {
+    const NAME: &'static str;
+}
+
+impl ConfigName for PoseidonGoldilocksConfig {
+    const NAME: &'static str = "PoseidonGoldilocksConfig";
+}
+
+impl ConfigName for PoseidonBN128GoldilocksConfig {
+    const NAME: &'static str = "PoseidonBN128GoldilocksConfig";
+}
+
+/// Represents the identity of a production circuit. A verifier key deployed on-chain stays valid
+/// only while all of these fields are unchanged.
+///
+/// # Fields
+///
+/// * `stage` - The name of the proving stage that builds the circuit.
+/// * `circuit_digest` - The digest of the circuit in hex.
+/// * `degree_bits` - Log2 of the number of rows of the circuit.
+/// * `num_public_inputs` - The number of public inputs.
+/// * `config` - The name of `GenericConfig` of the circuit (see `ConfigName`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitManifestEntry {
+    pub stage: String,
+    pub circuit_digest: String,
+    pub degree_bits: usize,
+    pub num_public_inputs: usize,
+    pub config: String,
+}
+
+impl CircuitManifestEntry {
+    /// Creates the entry of the circuit `data` built by the stage `stage`.
+    pub fn new<F, C, const D: usize>(stage: &str, data: &CircuitData<F, C, D>) -> Self
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + ConfigName,
+    {
+        Self {
+            stage: stage.to_string(),
+            circuit_digest: hex::encode(data.verifier_only.circuit_digest.to_bytes()),
+            degree_bits: data.common.degree_bits(),
+            num_public_inputs: data.common.num_public_inputs,
+            config: C::NAME.to_string(),
+        }
+    }
+}
+
+/// Represents the manifest of production circuits, grouped by the proven input.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitManifest {
+    pub entries: Vec<CircuitManifestEntry>,
+}
+
+impl CircuitManifest {
+    /// Adds entries of finished stages of `scope`, e.g. listed by `Checkpoints::stages`.
+    pub fn extend(&mut self, scope: &str, stages: Vec<StageManifest>) {
+        self.entries
+            .extend(stages.into_iter().map(|stage| CircuitManifestEntry {
+                stage: format!("{}/{}", scope, stage.stage),
+                circuit_digest: stage.circuit_digest,
+                degree_bits: stage.degree_bits,
+                num_public_inputs: stage.num_public_inputs,
+                config: stage.config,
+            }));
+    }
+
+    /// Reads the manifest from the json file.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Writes the manifest to the json file.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_atomic(path.as_ref(), self.to_json()?.as_bytes())
+    }
+
+    /// Returns the manifest as pretty-printed json.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// Returns descriptions of stages whose circuits differ from `reference`, including added
+    /// and removed stages. The list is empty if circuits didn't change.
+    pub fn drift(&self, reference: &CircuitManifest) -> Vec<String> {
+        let mut drift = vec![];
+        for expected in &reference.entries {
+            match self.entries.iter().find(|e| e.stage == expected.stage) {
+                None => drift.push(format!("stage {} is removed", expected.stage)),
+                Some(actual) if actual != expected => drift.push(format!(
+                    "stage {}: {:?} != {:?}",
+                    expected.stage, actual, expected
+                )),
+                Some(_) => {}
+            }
+        }
+        for actual in &self.entries {
+            if !reference.entries.iter().any(|e| e.stage == actual.stage) {
+                drift.push(format!("stage {} is added", actual.stage));
+            }
+        }
+        drift
+    }
+
+    /// Checks that circuits are the same as in `reference`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::CircuitDrift` listing all changed stages.
+    pub fn check(&self, reference: &CircuitManifest) -> Result<()> {
+        let drift = self.drift(reference);
+        if drift.is_empty() {
+            Ok(())
+        } else {
+            Err(BftError::CircuitDrift(drift.join("; ")))
+        }
+    }
+}
+
+/// Builds every circuit used to prove blocks and returns their manifest.
+///
+/// Circuits of block headers, validators and signatures depend on the size of their data, so they
+/// are built by proving the reference random block and epoch blocks in `epochs_dir` (see
+/// `REFERENCE_EPOCH_IDS`) the same way `ProvingPipeline` does: with `prove` of the inputs, then
+/// wrapping final proofs in BN128 config. Every stage is recorded as `{scope}/{stage}`, where the
+/// scope is `random` or `epoch`. Stages are stored in checkpoints in a temporary directory, which
+/// is removed afterwards. Circuits of the epoch chain and of anchored blocks are built for
+/// normalized final proofs and recorded as `EPOCH_CHAIN_STAGE` & `ANCHORED_BLOCK_STAGE`.
+///
+/// # Arguments
+///
+/// * `epochs_dir` - The path to the directory with stored epochs, e.g. `DEFAULT_EPOCHS_PATH`.
+/// * `timing_tree` - The timing tree of building circuits.
+///
+/// # Errors
+///
+/// Returns errors of loading the reference data and of proving.
+pub fn build_circuit_manifest(
+    epochs_dir: &str,
+    timing_tree: &mut TimingTree,
+) -> Result<CircuitManifest> {
+    let root = Checkpoints::open(
+        std::env::temp_dir().join(format!("circuit_manifest_{}", std::process::id())),
+    )?;
+    let manifest = prove_reference_blocks(epochs_dir, &root, timing_tree);
+    root.clear()?;
+    manifest
+}
+
+/// Proves the reference blocks with checkpoints in `root` and collects their stages.
+fn prove_reference_blocks(
+    epochs_dir: &str,
+    root: &Checkpoints,
+    timing_tree: &mut TimingTree,
+) -> Result<CircuitManifest> {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let [epoch_id_i, epoch_id_i_1, epoch_id_i_2, epoch_id_i_3] = REFERENCE_EPOCH_IDS;
+    let signature_prover = RayonSignatureProver::new();
+    let scheduler = StageScheduler::default();
+    let mut manifest = CircuitManifest::default();
+
+    // Random Block_i of Epoch_i-1.
+    let input = load_block_input(epochs_dir, epoch_id_i_1, epoch_id_i_2, epoch_id_i_3)?;
+    let checkpoints = root.scope(RANDOM_SCOPE);
+    let (bi_data, bi_proof) = timed!(
+        timing_tree,
+        "build circuits of random block",
+        input.prove::<F, C, D>(&signature_prover, &checkpoints, &scheduler, timing_tree)?
+    );
+    wrap_final_proof(
+        &checkpoints,
+        BLOCK_I_BN128_STAGE,
+        (&bi_data, &bi_proof),
+        timing_tree,
+    )?;
+    manifest.extend(RANDOM_SCOPE, checkpoints.stages()?);
+
+    // Block_0 of Epoch_i and Block_n-1 of Epoch_i-1.
+    let input = load_epoch_input(
+        epochs_dir,
+        epoch_id_i,
+        epoch_id_i_1,
+        epoch_id_i_2,
+        epoch_id_i_3,
+    )?;
+    let checkpoints = root.scope(EPOCH_SCOPE);
+    let ((b0_data, b0_proof), (bn_1_data, bn_1_proof)) = timed!(
+        timing_tree,
+        "build circuits of epoch blocks",
+        input.prove::<F, C, D>(&signature_prover, &checkpoints, &scheduler, timing_tree)?
+    );
+    wrap_final_proof(
+        &checkpoints,
+        BLOCK_0_BN128_STAGE,
+        (&b0_data, &b0_proof),
+        timing_tree,
+    )?;
+    wrap_final_proof(
+        &checkpoints,
+        BLOCK_N_1_BN128_STAGE,
+        (&bn_1_data, &bn_1_proof),
+        timing_tree,
+    )?;
+    manifest.extend(EPOCH_SCOPE, checkpoints.stages()?);
+
+    // The epoch chain and random blocks anchored to it verify normalized final proofs.
+    let normalize = |(data, proof): (
+        &VerifierCircuitData<F, C, D>,
+        &ProofWithPublicInputs<F, C, D>,
+    )| {
+        normalize_proof::<F, C, D>(
+            (&data.common, &data.verifier_only, proof),
+            EPOCH_PROOF_DEGREE_BITS,
+        )
+    };
+    let (bi_data, _) = normalize((&bi_data, &bi_proof))?;
+    let (b0_data, _) = normalize((&b0_data, &b0_proof))?;
+    let (bn_1_data, _) = normalize((&bn_1_data, &bn_1_proof))?;
+    let chain = timed!(
+        timing_tree,
+        "build epoch chain circuit",
+        EpochChainCircuit::<F, C, D>::build(&b0_data.common, &bn_1_data.common)?
+    );
+    let anchored = AnchoredBlockCircuit::<F, C, D>::build(
+        &chain.data.verifier_data(),
+        &verifier_data_hash::<F, C, D>(&[&b0_data.verifier_only, &bn_1_data.verifier_only]),
+        (&bi_data.common, &bi_data.verifier_only),
+    );
+    manifest.entries.extend([
+        CircuitManifestEntry::new(EPOCH_CHAIN_STAGE, &chain.data),
+        CircuitManifestEntry::new(ANCHORED_BLOCK_STAGE, &anchored.data),
+    ]);
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::DEFAULT_EPOCHS_PATH;
+    use anyhow::Result;
+    use log::Level;
+
+    fn entry(stage: &str, circuit_digest: &str) -> CircuitManifestEntry {
+        CircuitManifestEntry {
+            stage: stage.to_string(),
+            circuit_digest: circuit_digest.to_string(),
+            degree_bits: 12,
+            num_public_inputs: 32,
+            config: PoseidonGoldilocksConfig::NAME.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_circuit_manifest_drift() -> Result<()> {
+        let reference = CircuitManifest {
+            entries: vec![entry("header", "00"), entry("signatures", "01")],
+        };
+        assert!(reference.drift(&reference).is_empty());
+        reference.check(&reference)?;
+
+        let manifest = CircuitManifest {
+            entries: vec![entry("header", "02"), entry("heights", "03")],
+        };
+        let drift = manifest.drift(&reference);
+        assert_eq!(drift.len(), 3);
+        assert!(drift[0].starts_with("stage header:"));
+        assert_eq!(drift[1], "stage signatures is removed");
+        assert_eq!(drift[2], "stage heights is added");
+        assert!(matches!(
+            manifest.check(&reference),
+            Err(BftError::CircuitDrift(_))
+        ));
+        Ok(())
+    }
+
+    /// Fails if a production circuit differs from the committed `circuits.json`. If the change of
+    /// circuits is intended, the reference is updated with `UPDATE_CIRCUIT_MANIFEST=1 cargo test`
+    /// or `make circuit-manifest` and on-chain verifier keys have to be redeployed.
+    #[test]
+    fn test_circuits_match_reference_manifest() -> Result<()> {
+        let mut timing = TimingTree::new("build circuits", Level::Info);
+        let manifest = build_circuit_manifest(DEFAULT_EPOCHS_PATH, &mut timing)?;
+        timing.print();
+        for stage in [ProofKind::Random, ProofKind::Epoch]
+            .into_iter()
+            .flat_map(final_proof_stages)
+        {
+            assert!(manifest
+                .entries
+                .iter()
+                .any(|e| e.stage == stage && e.config == PoseidonBN128GoldilocksConfig::NAME));
+        }
+        for stage in [EPOCH_CHAIN_STAGE, ANCHORED_BLOCK_STAGE] {
+            assert!(manifest
+                .entries
+                .iter()
+                .any(|e| e.stage == stage && e.config == PoseidonGoldilocksConfig::NAME));
+        }
+
+        if std::env::var_os("UPDATE_CIRCUIT_MANIFEST").is_some() {
+            manifest.write(CIRCUIT_MANIFEST_PATH)?;
+            return Ok(());
+        }
+        let reference = CircuitManifest::read(CIRCUIT_MANIFEST_PATH).map_err(|e| {
+            anyhow::anyhow!(
+                "can't read {}: {}, generate it with UPDATE_CIRCUIT_MANIFEST=1",
+                CIRCUIT_MANIFEST_PATH,
+                e
+            )
+        })?;
+        manifest.check(&reference)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use crate::checkpoint::{Checkpoints, DEFAULT_CHECKPOINTS_PATH};
+use crate::error::{BftError, Result};
+use crate::network::NetworkProfile;
+use crate::pipeline::{ProvingPipeline, EPOCH_CHAIN_DIR};
+use crate::prove_bft::scheduler::GIB;
+use crate::prove_block_data::signature_prover::{
+    NatsSignatureProver, RayonSignatureProver, SignatureProver,
+};
+use crate::source::{
+    BlockSource, FixtureBlockSource, LakeBlockSource, RecordingBlockSource, RpcBlockSource,
+};
+use crate::store::ProofStore;
+use crate::utils::DEFAULT_EPOCHS_PATH;
+use clap::{Args, ValueEnum};
+use log::info;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// Represents the arguments of binaries that prove blocks: the network, the source of blocks,
+/// the signature backend and options of the proving pipeline.
+#[derive(Debug, Args)]
+pub struct ProveArgs {
+    #[command(flatten)]
+    pub network: NetworkArgs,
+    #[command(flatten)]
+    pub source: SourceArgs,
+    #[command(flatten)]
+    pub signatures: SignatureArgs,
+    #[command(flatten)]
+    pub pipeline: PipelineArgs,
+}
+
+impl ProveArgs {
+    /// Returns the network profile, the source of blocks and the pipeline storing proofs in
+    /// `store`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the profile can't be loaded, the source can't be opened or the
+    /// signature backend isn't reachable.
+    pub fn build(
+        &self,
+        store: Arc<dyn ProofStore>,
+        proofs_dir: &Path,
+    ) -> Result<(NetworkProfile, Box<dyn BlockSource>, ProvingPipeline)> {
+        let profile = self.network.profile()?;
+        let source = self.source.source(&profile)?;
+        let pipeline = self
+            .pipeline
+            .pipeline(self.signatures.prover()?, store, proofs_dir)?;
+        Ok((profile, source, pipeline))
+    }
+}
+
+/// Represents the arguments selecting the network profile.
+#[derive(Debug, Args)]
+pub struct NetworkArgs {
+    /// Built-in network profile: mainnet, testnet or localnet.
+    #[arg(long, conflicts_with = "network_config")]
+    pub network: Option<String>,
+    /// JSON file of the network profile, e.g. data/networks/localnet.json.
+    #[arg(long)]
+    pub network_config: Option<PathBuf>,
+    /// Overrides the RPC endpoint of the network.
+    #[arg(long)]
+    pub rpc_url: Option<String>,
+}
+
+impl NetworkArgs {
+    /// Returns the profile set by flags, otherwise by the environment
+    /// (see `NetworkProfile::from_env`).
+    pub fn profile(&self) -> Result<NetworkProfile> {
+        let mut profile = match (&self.network_config, &self.network) {
+            (Some(path), _) => NetworkProfile::load(path)?,
+            (_, Some(chain_id)) => NetworkProfile::named(chain_id)?,
+            _ => NetworkProfile::from_env()?,
+        };
+        if let Some(url) = &self.rpc_url {
+            profile.rpc_url = url.clone();
+        }
+        Ok(profile)
+    }
+}
+
+/// Represents the kind of the source of blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SourceKind {
+    /// RPC endpoint of the network.
+    Rpc,
+    /// Archival RPC endpoint of the network.
+    Archival,
+    /// Directory with the layout of data/epochs.
+    Fixtures,
+    /// Local copy of a NEAR Lake bucket to backfill old epochs.
+    Lake,
+    /// Responses recorded with --record.
+    Replay,
+}
+
+/// Represents the arguments selecting the source of blocks and validators.
+#[derive(Debug, Args)]
+pub struct SourceArgs {
+    /// Source of blocks and validators.
+    #[arg(long, value_enum, default_value_t = SourceKind::Rpc)]
+    pub source: SourceKind,
+    /// Directory of fixtures (data/epochs by default), the NEAR Lake export or recorded responses.
+    #[arg(long)]
+    pub data_dir: Option<PathBuf>,
+    /// Records responses of the source to the directory to replay them later.
+    #[arg(long)]
+    pub record: Option<PathBuf>,
+}
+
+impl SourceArgs {
+    /// Opens the source of blocks, wrapped in the recording source if `--record` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if the Lake or replayed source has no `--data-dir`.
+    pub fn source(&self, profile: &NetworkProfile) -> Result<Box<dyn BlockSource>> {
+        let source: Box<dyn BlockSource> = match self.source {
+            SourceKind::Rpc => Box::new(RpcBlockSource::from_profile(profile)?),
+            SourceKind::Archival => Box::new(RpcBlockSource::archival(profile)?),
+            SourceKind::Fixtures => {
+                let dir = self.data_dir.clone().unwrap_or(DEFAULT_EPOCHS_PATH.into());
+                Box::new(FixtureBlockSource::open(dir)?)
+            }
+            SourceKind::Lake => {
+                let dir = self.data_dir.as_ref().ok_or_else(|| {
+                    BftError::InvalidInput("--data-dir with the Lake export is required".into())
+                })?;
+                Box::new(LakeBlockSource::open(dir)?)
+            }
+            SourceKind::Replay => {
+                let dir = self.data_dir.as_ref().ok_or_else(|| {
+                    BftError::InvalidInput("--data-dir with recorded responses is required".into())
+                })?;
+                Box::new(RecordingBlockSource::replay(dir))
+            }
+        };
+        match &self.record {
+            Some(dir) => Ok(Box::new(RecordingBlockSource::record(source, dir)?)),
+            None => Ok(source),
+        }
+    }
+}
+
+/// Represents where signatures of validators are proven.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SignatureBackend {
+    /// Proves signatures in local threads.
+    Local,
+    /// Sends signatures to signature_worker instances through NATS.
+    Nats,
+}
+
+/// Represents the arguments selecting the signature backend.
+#[derive(Debug, Args)]
+pub struct SignatureArgs {
+    /// Where signatures of validators are proven.
+    #[arg(long, value_enum, default_value_t = SignatureBackend::Local)]
+    pub signatures: SignatureBackend,
+    /// URL of the NATS server, NATS_URL by default.
+    #[arg(long)]
+    pub nats_url: Option<String>,
+    /// Number of local threads proving signatures, all cores by default.
+    #[arg(long)]
+    pub threads: Option<usize>,
+}
+
+impl SignatureArgs {
+    /// Creates the signature prover of the selected backend.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if the NATS backend has no URL and errors of connecting
+    /// to the NATS server.
+    pub fn prover(&self) -> Result<Arc<dyn SignatureProver<F, C, D>>> {
+        match self.signatures {
+            SignatureBackend::Local => match self.threads {
+                Some(threads) => Ok(Arc::new(RayonSignatureProver::with_threads(threads)?)),
+                None => Ok(Arc::new(RayonSignatureProver::new())),
+            },
+            SignatureBackend::Nats => {
+                let url = match &self.nats_url {
+                    Some(url) => url.clone(),
+                    None => std::env::var("NATS_URL").map_err(|_| {
+                        BftError::InvalidInput("--nats-url or NATS_URL is required".into())
+                    })?,
+                };
+                info!("Nats URL: {}", url);
+                Ok(Arc::new(NatsSignatureProver::connect(&url)?))
+            }
+        }
+    }
+}
+
+/// Represents the options of the proving pipeline.
+#[derive(Debug, Args)]
+pub struct PipelineArgs {
+    /// Directory of checkpoints to resume interrupted runs.
+    #[arg(long, default_value = DEFAULT_CHECKPOINTS_PATH)]
+    pub checkpoints_dir: PathBuf,
+    /// Proves every stage again instead of resuming from checkpoints.
+    #[arg(long)]
+    pub no_checkpoints: bool,
+    /// Memory budget of concurrently proven stages in GiB.
+    #[arg(long)]
+    pub memory_budget_gib: Option<usize>,
+    /// Also writes final proofs in the input format of gnark-plonky2-verifier to the directory.
+    #[arg(long)]
+    pub gnark_dir: Option<PathBuf>,
+    /// Extends the epoch chain in `{proofs_dir}/chain` with proven epochs (IVC mode).
+    #[arg(long)]
+    pub epoch_chain: bool,
+}
+
+impl PipelineArgs {
+    /// Creates the pipeline storing final proofs in `store`, which is opened at `proofs_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory of checkpoints can't be created.
+    pub fn pipeline(
+        &self,
+        signature_prover: Arc<dyn SignatureProver<F, C, D>>,
+        store: Arc<dyn ProofStore>,
+        proofs_dir: &Path,
+    ) -> Result<ProvingPipeline> {
+        let mut pipeline = ProvingPipeline::new(signature_prover, store);
+        if !self.no_checkpoints {
+            pipeline = pipeline.with_checkpoints(Checkpoints::open(&self.checkpoints_dir)?);
+        }
+        if let Some(gib) = self.memory_budget_gib {
+            pipeline = pipeline.with_memory_budget(gib * GIB);
+        }
+        if let Some(dir) = &self.gnark_dir {
+            pipeline = pipeline.with_gnark_dir(dir);
+        }
+        if self.epoch_chain {
+            pipeline = pipeline.with_epoch_chain(proofs_dir.join(EPOCH_CHAIN_DIR));
+        }
+        Ok(pipeline)
+    }
+}
@@ -0,0 +1,450 @@
+use crate::circuits::ConfigName;
+use crate::error::{BftError, Result};
+use crate::mutation::{Mutation, RawProvingInput};
+use crate::prove_block_data::signature_prover::SignatureProver;
+use crate::public_inputs::BlockProofPublicInputs;
+use crate::utils::{load_block_input, load_epoch_input};
+use near_primitives::hash::CryptoHash;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use plonky2::util::timing::TimingTree;
+use plonky2_field::extension::Extendable;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+
+/// Represents the directory with conformance vectors shared by the plonky2 and risc0 backends.
+pub const DEFAULT_CONFORMANCE_PATH: &str = "../data/conformance";
+/// Represents the file with vectors of random and epoch blocks.
+pub const BLOCK_VECTORS_FILE: &str = "blocks.json";
+/// Represents the file with vectors of messages signed by validators.
+pub const SIGNED_MESSAGE_VECTORS_FILE: &str = "signed_messages.json";
+/// Represents the file with vectors of hashes of validators.
+pub const BP_HASH_VECTORS_FILE: &str = "bp_hashes.json";
+
+/// Represents the kind of the block proven by a vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorKind {
+    /// Block_i of Epoch_i, loaded from `random-*.json`.
+    Random,
+    /// Block_0 of Epoch_i and Block_n-1 of Epoch_i-1, loaded from `block-*.json`.
+    Epoch,
+}
+
+/// Represents the kind of the error that rejects an input, i.e. the variant of `BftError`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorKind {
+    /// `BftError::HashMismatch`.
+    HashMismatch,
+    /// `BftError::InsufficientStake`.
+    InsufficientStake,
+}
+
+impl ErrorKind {
+    /// Returns true if `error` is of this kind.
+    pub fn matches(&self, error: &BftError) -> bool {
+        match self {
+            ErrorKind::HashMismatch => matches!(error, BftError::HashMismatch(_)),
+            ErrorKind::InsufficientStake => matches!(error, BftError::InsufficientStake { .. }),
+        }
+    }
+}
+
+/// Represents hashes committed by both backends for an accepted vector.
+///
+/// # Fields
+///
+/// * `block_hash` - The hash of Block_i or Block_0.
+/// * `epoch_hash` - The stored hash of Block_n-1 (Epoch_i-2), i.e. epoch_id of Epoch_i, set for random blocks.
+///   Epoch blocks the proofs of epoch blocks are proven against aren't part of vectors.
+/// * `previous_epoch_hash` - The stored hash of Block_0 (Epoch_i-1), set for random blocks.
+/// * `previous_block_hash` - The hash of Block_n-1 (Epoch_i-1), set for epoch blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorOutputs {
+    pub block_hash: CryptoHash,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch_hash: Option<CryptoHash>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_epoch_hash: Option<CryptoHash>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_block_hash: Option<CryptoHash>,
+}
+
+impl VectorOutputs {
+    /// Collects hashes committed by both backends from public inputs of the proof of Block_i/Block_0
+    /// and, for epoch blocks, of the proof of Block_n-1.
+    pub fn from_public_inputs(
+        bi: &BlockProofPublicInputs,
+        bn_1: Option<&BlockProofPublicInputs>,
+    ) -> Self {
+        match bn_1 {
+            None => Self {
+                block_hash: bi.block_hash,
+                epoch_hash: Some(bi.ep2_last_block_hash),
+                previous_epoch_hash: Some(bi.ep1_first_block_hash),
+                previous_block_hash: None,
+            },
+            Some(bn_1) => Self {
+                block_hash: bi.block_hash,
+                epoch_hash: None,
+                previous_epoch_hash: None,
+                previous_block_hash: Some(bn_1.block_hash),
+            },
+        }
+    }
+}
+
+/// Represents the result of running a vector through a prover.
+///
+/// # Fields
+///
+/// * `accept` - Whether the input is accepted.
+/// * `outputs` - Committed hashes, set only if the input is accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    pub accept: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ErrorKind>,
+    pub outputs: Option<VectorOutputs>,
+}
+
+impl Verdict {
+    /// Creates the verdict of an accepted input.
+    pub fn accept(outputs: VectorOutputs) -> Self {
+        Self {
+            accept: true,
+            outputs: Some(outputs),
+        }
+    }
+
+    /// Creates the verdict of a rejected input.
+    pub fn reject() -> Self {
+        Self {
+            accept: false,
+            outputs: None,
+        }
+    }
+}
+
+/// Represents a conformance vector of a random or an epoch block.
+///
+/// # Fields
+///
+/// * `name` - The unique name of the vector.
+/// * `description` - What the vector checks.
+/// * `kind` - The kind of the proven block.
+/// * `epoch_ids` - [Epoch_i, Epoch_i-1, Epoch_i-2] or [Epoch_i, Epoch_i-1, Epoch_i-2, Epoch_i-3] for epoch blocks,
+///   which are loaded from the directory with epochs (see `load_block_input`).
+/// * `mutation` - The mutation applied to the loaded input.
+/// * `accept` - Whether the input must be accepted.
+/// * `errors` - Kinds of errors, one of which must reject an input that isn't accepted. Stages are proven
+///   in parallel, so an input that fails several checks may be rejected by any of them.
+/// * `outputs` - Hashes that must be committed for an accepted input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockVector {
+    pub name: String,
+    pub description: String,
+    pub kind: VectorKind,
+    pub epoch_ids: Vec<String>,
+    pub mutation: Option<Mutation>,
+    pub accept: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ErrorKind>,
+    pub outputs: Option<VectorOutputs>,
+}
+
+impl BlockVector {
+    /// Returns the verdict expected from every backend.
+    pub fn verdict(&self) -> Verdict {
+        Verdict {
+            accept: self.accept,
+            outputs: self.outputs.clone(),
+        }
+    }
+
+    /// Returns true if `error` is the expected rejection of the input.
+    pub fn rejects_with(&self, error: &BftError) -> bool {
+        !self.accept && self.errors.iter().any(|kind| kind.matches(error))
+    }
+
+    /// Loads the input of the vector from the directory with epochs and applies its mutation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the number of epoch_ids doesn't match the kind of the vector, the data
+    /// can't be loaded or the mutation can't be applied. It means the vector itself is broken.
+    pub fn input(&self, epochs_dir: &str) -> Result<RawProvingInput> {
+        let mut input = match (self.kind, self.epoch_ids.as_slice()) {
+            (VectorKind::Random, [i, i_1, i_2]) => {
+                RawProvingInput::try_from(&load_block_input(epochs_dir, i, i_1, i_2)?)?
+            }
+            (VectorKind::Epoch, [i, i_1, i_2, i_3]) => {
+                RawProvingInput::try_from(&load_epoch_input(epochs_dir, i, i_1, i_2, i_3)?)?
+            }
+            _ => {
+                return Err(BftError::InvalidInput(format!(
+                    "vector {} of kind {:?} has {} epoch_ids",
+                    self.name,
+                    self.kind,
+                    self.epoch_ids.len()
+                )))
+            }
+        };
+        if let Some(mutation) = self.mutation {
+            mutation.apply(&mut input)?;
+        }
+        Ok(input)
+    }
+}
+
+/// Represents a vector of the message signed by validators, see `generate_signed_message`.
+///
+/// # Fields
+///
+/// * `name` - The unique name of the vector.
+/// * `ch_height` - The height of the current block.
+/// * `nb_height` - The height of the next block.
+/// * `nb_prev_hash` - prev_hash of the next block.
+/// * `message` - The hex-encoded message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedMessageVector {
+    pub name: String,
+    pub ch_height: u64,
+    pub nb_height: u64,
+    pub nb_prev_hash: CryptoHash,
+    pub message: String,
+}
+
+/// Represents a vector of the hash of validators of an epoch, i.e. next_bp_hash of Block_0 (Epoch_i-1).
+///
+/// # Fields
+///
+/// * `epoch_id` - The epoch, whose validators are stored in `{epoch_id}/validators.json`.
+/// * `bp_hash` - The hash of its validators.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BpHashVector {
+    pub epoch_id: String,
+    pub bp_hash: CryptoHash,
+}
+
+fn load_vectors<T: DeserializeOwned>(dir: &str, file: &str) -> Result<Vec<T>> {
+    let file = File::open(format!("{dir}/{file}"))?;
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+/// Loads vectors of random and epoch blocks from `{dir}/blocks.json`.
+pub fn load_block_vectors(dir: &str) -> Result<Vec<BlockVector>> {
+    load_vectors(dir, BLOCK_VECTORS_FILE)
+}
+
+/// Loads vectors of signed messages from `{dir}/signed_messages.json`.
+pub fn load_signed_message_vectors(dir: &str) -> Result<Vec<SignedMessageVector>> {
+    load_vectors(dir, SIGNED_MESSAGE_VECTORS_FILE)
+}
+
+/// Loads vectors of hashes of validators from `{dir}/bp_hashes.json`.
+pub fn load_bp_hash_vectors(dir: &str) -> Result<Vec<BpHashVector>> {
+    load_vectors(dir, BP_HASH_VECTORS_FILE)
+}
+
+/// Runs the vector through `prove_block_bft`. An input is rejected only by an error of the kind
+/// expected by the vector.
+///
+/// # Arguments
+///
+/// * `vector` - The vector to run.
+/// * `epochs_dir` - The directory with epochs, from which the input is loaded.
+/// * `signature_prover` - The backend used to prove signatures, e.g. `LocalSignatureProver`.
+/// * `timing_tree` - A mutable reference to the timing tree.
+///
+/// # Returns
+///
+/// Returns the verdict of the plonky2 backend with hashes taken from public inputs of the proofs.
+///
+/// # Errors
+///
+/// Returns an error if the input of the vector can't be loaded, proving fails with an unexpected error
+/// or public inputs of an accepted input can't be decoded.
+pub fn run_block_vector<F, C, const D: usize>(
+    vector: &BlockVector,
+    epochs_dir: &str,
+    signature_prover: &dyn SignatureProver<F, C, D>,
+    timing_tree: &mut TimingTree,
+) -> Result<Verdict>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + ConfigName,
+    C::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    let input = vector.input(epochs_dir)?;
+    let ((_, bi_proof), bn_1_proof) = match input.prove::<F, C, D>(signature_prover, timing_tree) {
+        Ok(proofs) => proofs,
+        Err(e) if vector.rejects_with(&e) => return Ok(Verdict::reject()),
+        Err(e) => return Err(e),
+    };
+    let bi = BlockProofPublicInputs::decode(&bi_proof.public_inputs)?;
+    let bn_1 = bn_1_proof
+        .map(|(_, proof)| BlockProofPublicInputs::decode(&proof.public_inputs))
+        .transpose()?;
+    Ok(Verdict::accept(VectorOutputs::from_public_inputs(
+        &bi,
+        bn_1.as_ref(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mutation::ALL_MUTATIONS;
+    use crate::prove_block_data::header_bphash::prove_bp_hash;
+    use crate::prove_block_data::signature_prover::LocalSignatureProver;
+    use crate::prove_block_data::signatures::generate_signed_message;
+    use crate::utils::load_validators;
+    use anyhow::Result;
+    use log::{info, Level};
+    use near_primitives::borsh;
+    use near_primitives::hash::hash;
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+    use std::collections::HashSet;
+
+    const EPOCHS_PATH: &str = "../data/epochs";
+
+    fn validators_bytes(epoch_id: &str) -> Result<Vec<Vec<u8>>> {
+        load_validators(&format!("{EPOCHS_PATH}/{epoch_id}/validators.json"))?
+            .iter()
+            .map(|validator| Ok(borsh::to_vec(validator)?))
+            .collect()
+    }
+
+    #[test]
+    fn test_block_vectors_are_consistent() -> Result<()> {
+        let vectors = load_block_vectors(DEFAULT_CONFORMANCE_PATH)?;
+        let names: HashSet<&str> = vectors.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names.len(), vectors.len(), "Names of vectors aren't unique");
+        for vector in vectors.iter() {
+            let input = vector.input(EPOCHS_PATH)?;
+            let Some(outputs) = &vector.outputs else {
+                assert!(!vector.accept, "{} has no outputs", vector.name);
+                assert!(!vector.errors.is_empty(), "{} has no errors", vector.name);
+                continue;
+            };
+            assert!(
+                vector.accept,
+                "{} is rejected, but has outputs",
+                vector.name
+            );
+            assert!(
+                vector.mutation.is_none(),
+                "{} is accepted with mutation",
+                vector.name
+            );
+            assert!(vector.errors.is_empty(), "{} is accepted with errors", vector.name);
+            assert_eq!(outputs.block_hash.0.as_slice(), input.blocks[4].0.hash);
+            match vector.kind {
+                VectorKind::Random => {
+                    let epoch_hash = outputs.epoch_hash.expect("No Block_n-1 (Epoch_i-2)");
+                    let previous_epoch_hash =
+                        outputs.previous_epoch_hash.expect("No Block_0 (Epoch_i-1)");
+                    assert_eq!(epoch_hash.0.as_slice(), input.epoch_blocks[1].0);
+                    assert_eq!(previous_epoch_hash.0.as_slice(), input.epoch_blocks[0].0);
+                    assert!(outputs.previous_block_hash.is_none());
+                }
+                VectorKind::Epoch => {
+                    let previous_block_hash = outputs.previous_block_hash.expect("No Block_n-1");
+                    assert_eq!(previous_block_hash.0.as_slice(), input.blocks[5].0.hash);
+                    assert!(outputs.epoch_hash.is_none());
+                    assert!(outputs.previous_epoch_hash.is_none());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Vectors are shared with near_risc0, which has its own definitions of them. Both crates check
+    /// that their definitions read and write vector files without changes, so they agree on them.
+    #[test]
+    fn test_vector_files_round_trip() -> Result<()> {
+        fn round_trip<T: Serialize + DeserializeOwned>(file: &str) -> Result<()> {
+            let file = File::open(format!("{DEFAULT_CONFORMANCE_PATH}/{file}"))?;
+            let json: serde_json::Value = serde_json::from_reader(BufReader::new(file))?;
+            let vectors: Vec<T> = serde_json::from_value(json.clone())?;
+            assert_eq!(serde_json::to_value(&vectors)?, json);
+            Ok(())
+        }
+        round_trip::<BlockVector>(BLOCK_VECTORS_FILE)?;
+        round_trip::<SignedMessageVector>(SIGNED_MESSAGE_VECTORS_FILE)?;
+        round_trip::<BpHashVector>(BP_HASH_VECTORS_FILE)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_vectors_cover_every_mutation() -> Result<()> {
+        let vectors = load_block_vectors(DEFAULT_CONFORMANCE_PATH)?;
+        for mutation in ALL_MUTATIONS {
+            assert!(
+                vectors.iter().any(|v| v.mutation == Some(mutation)),
+                "No vector of {:?}",
+                mutation
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_signed_message_vectors() -> Result<()> {
+        for vector in load_signed_message_vectors(DEFAULT_CONFORMANCE_PATH)? {
+            let message =
+                generate_signed_message(vector.ch_height, vector.nb_height, vector.nb_prev_hash);
+            assert_eq!(hex::encode(message), vector.message, "{}", vector.name);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bp_hash_vectors_match_validators() -> Result<()> {
+        for vector in load_bp_hash_vectors(DEFAULT_CONFORMANCE_PATH)? {
+            let validators = validators_bytes(&vector.epoch_id)?;
+            let mut bytes = (validators.len() as u32).to_le_bytes().to_vec();
+            bytes.extend(validators.concat());
+            assert_eq!(hash(&bytes), vector.bp_hash, "{}", vector.epoch_id);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_bp_hash_vectors() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        for vector in load_bp_hash_vectors(DEFAULT_CONFORMANCE_PATH)? {
+            let validators = validators_bytes(&vector.epoch_id)?;
+            let (data, proof) = prove_bp_hash::<F, C, D>(&vector.bp_hash.0, validators)?;
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_vectors_on_plonky2() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        for vector in load_block_vectors(DEFAULT_CONFORMANCE_PATH)? {
+            let mut timing = TimingTree::new(&format!("prove {}", vector.name), Level::Info);
+            let verdict = run_block_vector::<F, C, D>(
+                &vector,
+                EPOCHS_PATH,
+                &LocalSignatureProver,
+                &mut timing,
+            )?;
+            info!("{}: accept = {}", vector.name, verdict.accept);
+            assert_eq!(verdict, vector.verdict(), "{}", vector.name);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use thiserror::Error;
+
+/// Represents errors returned by the public API of the crate.
+#[derive(Debug, Error)]
+pub enum BftError {
+    /// Indicates that the input has an unexpected shape, e.g. a wrong number of blocks,
+    /// a missing field or a slice of an invalid length.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the invalid input.
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    /// Indicates that two hashes expected to be equal differ, e.g. an epoch_id
+    /// that doesn't correspond to the hash of the epoch block.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the mismatch.
+    #[error("Hash mismatch: {0}")]
+    HashMismatch(String),
+
+    /// Indicates that a signature or a public key cannot be parsed or verified.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the signature error.
+    #[error("Signature error: {0}")]
+    Signature(String),
+
+    /// Indicates that validators with valid approvals don't give 2/3 of the total sum of all stakes.
+    ///
+    /// # Fields
+    /// * `valid_stake` - The sum of stakes of validators with valid approvals.
+    /// * `total_stake` - The total sum of stakes of all validators.
+    /// * `excluded` - Indices of validators whose approvals were present, but invalid.
+    #[error("Valid stake {valid_stake} is less than 2/3 of total stake {total_stake} ({} approvals excluded)", excluded.len())]
+    InsufficientStake {
+        valid_stake: u128,
+        total_stake: u128,
+        excluded: Vec<usize>,
+    },
+
+    /// Indicates that a request to the RPC node failed or returned an error.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the RPC error.
+    #[error("RPC error: {0}")]
+    Rpc(String),
+
+    /// Indicates that the requested block doesn't exist, e.g. the height was skipped.
+    ///
+    /// # Arguments
+    /// * `String` - The hash or the height of the block.
+    #[error("Block not found: {0}")]
+    BlockNotFound(String),
+
+    /// Indicates that the height is skipped permanently, i.e. a block at a later height exists.
+    ///
+    /// # Arguments
+    /// * `String` - The skipped height.
+    #[error("Skipped block: {0}")]
+    SkippedBlock(String),
+
+    /// Indicates an error while reading or writing data (files, NATS connection).
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Indicates an error while serializing or deserializing data.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the serialization error.
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    /// Indicates that building a circuit, proving or verifying failed.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the proving error.
+    #[error("Proving error: {0}")]
+    Proving(String),
+
+    /// Indicates that circuits differ from the reference manifest, e.g. after a change of a gadget.
+    ///
+    /// # Arguments
+    /// * `String` - The description of changed circuits.
+    #[error("Circuit drift: {0}")]
+    CircuitDrift(String),
+
+    /// Indicates that the update of the light client is already applied, e.g. a replayed epoch proof.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the stale update.
+    #[error("Stale update: {0}")]
+    StaleUpdate(String),
+
+    /// Indicates that the update of the light client conflicts with a trusted epoch.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the conflict.
+    #[error("Fork: {0}")]
+    Fork(String),
+
+    /// Indicates that the proof doesn't chain to any epoch trusted by the light client.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the proof.
+    #[error("Unknown epoch: {0}")]
+    UnknownEpoch(String),
+}
+
+impl BftError {
+    /// Converts an error of plonky2 (building a circuit, proving or verifying a proof) into
+    /// `BftError::Proving`. plonky2 returns `anyhow::Error` for any failure, so it's converted
+    /// explicitly where plonky2 is called and other failures keep their own variants.
+    pub fn proving(error: anyhow::Error) -> Self {
+        BftError::Proving(format!("{:#}", error))
+    }
+}
+
+impl From<serde_json::Error> for BftError {
+    fn from(error: serde_json::Error) -> Self {
+        BftError::Serialization(error.to_string())
+    }
+}
+
+impl From<proof_store::StoreError> for BftError {
+    fn from(error: proof_store::StoreError) -> Self {
+        match error {
+            proof_store::StoreError::InvalidInput(message) => BftError::InvalidInput(message),
+            proof_store::StoreError::HashMismatch(message) => BftError::HashMismatch(message),
+            proof_store::StoreError::Io(error) => BftError::Io(error),
+            proof_store::StoreError::Serialization(message) => BftError::Serialization(message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for BftError {
+    fn from(error: reqwest::Error) -> Self {
+        BftError::Rpc(error.to_string())
+    }
+}
+
+/// Represents the result type of the public API of the crate.
+pub type Result<T> = std::result::Result<T, BftError>;
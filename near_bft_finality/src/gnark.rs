@@ -0,0 +1,345 @@
+use crate::error::{BftError, Result};
+use crate::store::write_atomic;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::{
+    CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
+};
+use plonky2::plonk::config::GenericConfig;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2_field::extension::Extendable;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+/// Represents the file of the proof read by gnark-plonky2-verifier.
+pub const GNARK_PROOF_FILE: &str = "proof_with_public_inputs.json";
+/// Represents the file of verifier only circuit data read by gnark-plonky2-verifier.
+pub const GNARK_VERIFIER_DATA_FILE: &str = "verifier_only_circuit_data.json";
+/// Represents the file of common circuit data read by gnark-plonky2-verifier.
+pub const GNARK_COMMON_DATA_FILE: &str = "common_circuit_data.json";
+
+/// Represents `ProofWithPublicInputs` in the layout of `ProofWithPublicInputsRaw` of gnark-plonky2-verifier.
+/// Field elements are numbers, extension field elements are arrays of numbers and hashes are decimal strings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkProofWithPublicInputs {
+    pub proof: GnarkProof,
+    pub public_inputs: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkProof {
+    pub wires_cap: Vec<String>,
+    pub plonk_zs_partial_products_cap: Vec<String>,
+    pub quotient_polys_cap: Vec<String>,
+    pub openings: GnarkOpeningSet,
+    pub opening_proof: GnarkFriProof,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkOpeningSet {
+    pub constants: Vec<Vec<u64>>,
+    pub plonk_sigmas: Vec<Vec<u64>>,
+    pub wires: Vec<Vec<u64>>,
+    pub plonk_zs: Vec<Vec<u64>>,
+    pub plonk_zs_next: Vec<Vec<u64>>,
+    pub partial_products: Vec<Vec<u64>>,
+    pub quotient_polys: Vec<Vec<u64>>,
+    #[serde(default)]
+    pub lookup_zs: Vec<Vec<u64>>,
+    #[serde(default)]
+    pub lookup_zs_next: Vec<Vec<u64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkFriProof {
+    pub commit_phase_merkle_caps: Vec<Vec<String>>,
+    pub query_round_proofs: Vec<GnarkFriQueryRound>,
+    pub final_poly: GnarkPolynomialCoeffs,
+    pub pow_witness: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkFriQueryRound {
+    pub initial_trees_proof: GnarkFriInitialTreeProof,
+    pub steps: Vec<GnarkFriQueryStep>,
+}
+
+/// Represents evaluations of initial trees, each one is a pair `[leaf elements, merkle proof]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkFriInitialTreeProof {
+    pub evals_proofs: Vec<(Vec<u64>, GnarkMerkleProof)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkFriQueryStep {
+    pub evals: Vec<Vec<u64>>,
+    pub merkle_proof: GnarkMerkleProof,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkMerkleProof {
+    pub siblings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkPolynomialCoeffs {
+    pub coeffs: Vec<Vec<u64>>,
+}
+
+/// Represents `VerifierOnlyCircuitData` in the layout of `VerifierOnlyCircuitDataRaw` of gnark-plonky2-verifier.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkVerifierOnlyCircuitData {
+    pub constants_sigmas_cap: Vec<String>,
+    pub circuit_digest: String,
+}
+
+/// Represents `CommonCircuitData` in the layout of `CommonCircuitDataRaw` of gnark-plonky2-verifier.
+/// Gates are represented by their ids.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkCommonCircuitData {
+    pub config: GnarkCircuitConfig,
+    pub fri_params: GnarkFriParams,
+    pub gates: Vec<String>,
+    pub selectors_info: GnarkSelectorsInfo,
+    pub quotient_degree_factor: usize,
+    pub num_gate_constraints: usize,
+    pub num_constants: usize,
+    pub num_public_inputs: usize,
+    pub k_is: Vec<u64>,
+    pub num_partial_products: usize,
+    #[serde(default)]
+    pub num_lookup_polys: usize,
+    #[serde(default)]
+    pub num_lookup_selectors: usize,
+    #[serde(default)]
+    pub luts: Vec<Vec<(u16, u16)>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkCircuitConfig {
+    pub num_wires: usize,
+    pub num_routed_wires: usize,
+    pub num_constants: usize,
+    pub use_base_arithmetic_gate: bool,
+    pub security_bits: usize,
+    pub num_challenges: usize,
+    pub zero_knowledge: bool,
+    pub max_quotient_degree_factor: usize,
+    pub fri_config: GnarkFriConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkFriConfig {
+    pub rate_bits: usize,
+    pub cap_height: usize,
+    pub proof_of_work_bits: u32,
+    pub reduction_strategy: GnarkFriReductionStrategy,
+    pub num_query_rounds: usize,
+}
+
+/// Represents `FriReductionStrategy`, gnark-plonky2-verifier supports only `ConstantArityBits`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GnarkFriReductionStrategy {
+    Fixed(Vec<usize>),
+    ConstantArityBits(usize, usize),
+    MinSize(Option<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkFriParams {
+    pub config: GnarkFriConfig,
+    pub hiding: bool,
+    pub degree_bits: usize,
+    pub reduction_arity_bits: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnarkSelectorsInfo {
+    pub selector_indices: Vec<usize>,
+    pub groups: Vec<Range<usize>>,
+}
+
+/// Represents files of a proof in the input format of gnark-plonky2-verifier (see its `testdata`).
+///
+/// The verifier reads hashes as decimal strings, so the proof should be wrapped with
+/// `PoseidonBN128GoldilocksConfig`. Proofs of other configs are rejected.
+///
+/// # Fields
+///
+/// * `proof_with_public_inputs` - The proof in json (`proof_with_public_inputs.json`).
+/// * `verifier_only_circuit_data` - `VerifierOnlyCircuitData` in json (`verifier_only_circuit_data.json`).
+/// * `common_circuit_data` - `CommonCircuitData` in json (`common_circuit_data.json`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GnarkArtifacts {
+    pub proof_with_public_inputs: Vec<u8>,
+    pub verifier_only_circuit_data: Vec<u8>,
+    pub common_circuit_data: Vec<u8>,
+}
+
+impl GnarkArtifacts {
+    /// Exports the proof and its verifier circuit data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::Serialization` if some value doesn't fit the layout of the verifier,
+    /// e.g. hashes of the config are not serialized as strings.
+    pub fn from_verifier_data<F, C, const D: usize>(
+        data: &VerifierCircuitData<F, C, D>,
+        proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<Self>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        ProofWithPublicInputs<F, C, D>: Serialize,
+        VerifierOnlyCircuitData<C, D>: Serialize,
+        CommonCircuitData<F, D>: Serialize,
+    {
+        Ok(Self {
+            proof_with_public_inputs: export::<_, GnarkProofWithPublicInputs>(proof)?,
+            verifier_only_circuit_data: export::<_, GnarkVerifierOnlyCircuitData>(
+                &data.verifier_only,
+            )?,
+            common_circuit_data: export::<_, GnarkCommonCircuitData>(&data.common)?,
+        })
+    }
+
+    /// Reads files from the directory, e.g. `gnark-plonky2-verifier/testdata/test_circuit`.
+    pub fn read_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        Ok(Self {
+            proof_with_public_inputs: fs::read(dir.join(GNARK_PROOF_FILE))?,
+            verifier_only_circuit_data: fs::read(dir.join(GNARK_VERIFIER_DATA_FILE))?,
+            common_circuit_data: fs::read(dir.join(GNARK_COMMON_DATA_FILE))?,
+        })
+    }
+
+    /// Writes files to the directory, each file is written atomically.
+    pub fn write_dir(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        write_atomic(&dir.join(GNARK_PROOF_FILE), &self.proof_with_public_inputs)?;
+        write_atomic(
+            &dir.join(GNARK_VERIFIER_DATA_FILE),
+            &self.verifier_only_circuit_data,
+        )?;
+        write_atomic(&dir.join(GNARK_COMMON_DATA_FILE), &self.common_circuit_data)
+    }
+}
+
+/// Converts the serde representation of a plonky2 value into the layout `G` and writes it as pretty json.
+/// Fields missing in `G` are dropped, fields are ordered as in `G`.
+fn export<T: Serialize, G: Serialize + DeserializeOwned>(value: &T) -> Result<Vec<u8>> {
+    let exported: G = serde_json::from_value(serde_json::to_value(value)?).map_err(|e| {
+        BftError::Serialization(format!(
+            "Error exporting {} for gnark: {}",
+            std::any::type_name::<G>(),
+            e
+        ))
+    })?;
+    Ok(serde_json::to_vec_pretty(&exported)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+    use plonky2_bn128::config::PoseidonBN128GoldilocksConfig;
+    use plonky2_field::types::Field;
+    use serde_json::Value;
+
+    const D: usize = 2;
+    type Cbn128 = PoseidonBN128GoldilocksConfig;
+    type F = <Cbn128 as GenericConfig<D>>::F;
+
+    const FIXTURES_PATH: &str = "../gnark-plonky2-verifier/testdata/test_circuit";
+
+    fn temp_dir() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("gnark_{:016x}", rand::random::<u64>()))
+    }
+
+    #[test]
+    fn test_export_matches_fixtures() -> Result<()> {
+        let fixtures = GnarkArtifacts::read_dir(FIXTURES_PATH)?;
+        // Fixtures are read back as plonky2 values and exported again.
+        let proof: ProofWithPublicInputs<F, Cbn128, D> =
+            serde_json::from_slice(&fixtures.proof_with_public_inputs)?;
+        let verifier_only: VerifierOnlyCircuitData<Cbn128, D> =
+            serde_json::from_slice(&fixtures.verifier_only_circuit_data)?;
+        assert_eq!(
+            export::<_, GnarkProofWithPublicInputs>(&proof)?,
+            fixtures.proof_with_public_inputs
+        );
+        assert_eq!(
+            export::<_, GnarkVerifierOnlyCircuitData>(&verifier_only)?,
+            fixtures.verifier_only_circuit_data
+        );
+        // CommonCircuitData can't be deserialized, its layout is checked by the round trip.
+        let common: GnarkCommonCircuitData = serde_json::from_slice(&fixtures.common_circuit_data)?;
+        assert_eq!(
+            serde_json::to_vec_pretty(&common)?,
+            fixtures.common_circuit_data
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_of_bn128_proof() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let target = builder.add_virtual_target();
+        builder.register_public_input(target);
+        let data = builder.build::<Cbn128>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(target, F::from_canonical_u64(7));
+        let proof = data.prove(pw)?;
+        let artifacts = GnarkArtifacts::from_verifier_data(&data.verifier_data(), &proof)?;
+        let dir = temp_dir();
+        artifacts.write_dir(&dir)?;
+        assert_eq!(GnarkArtifacts::read_dir(&dir)?, artifacts);
+        fs::remove_dir_all(dir)?;
+        // Keys are in the order of fixtures.
+        let fixtures = GnarkArtifacts::read_dir(FIXTURES_PATH)?;
+        let keys = |json: &[u8]| -> Result<Vec<String>> {
+            let value: Value = serde_json::from_slice(json)?;
+            Ok(value
+                .as_object()
+                .map(|object| object.keys().cloned().collect())
+                .unwrap_or_default())
+        };
+        assert_eq!(
+            keys(&artifacts.common_circuit_data)?,
+            keys(&fixtures.common_circuit_data)?
+        );
+        let common: GnarkCommonCircuitData =
+            serde_json::from_slice(&artifacts.common_circuit_data)?;
+        let fixture_common: GnarkCommonCircuitData =
+            serde_json::from_slice(&fixtures.common_circuit_data)?;
+        assert_eq!(common.config, fixture_common.config);
+        assert_eq!(common.num_public_inputs, 1);
+        let exported: GnarkProofWithPublicInputs =
+            serde_json::from_slice(&artifacts.proof_with_public_inputs)?;
+        assert_eq!(exported.public_inputs, vec![7]);
+        assert_eq!(
+            exported.proof.wires_cap.len(),
+            1 << common.config.fri_config.cap_height
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_rejects_non_bn128_hashes() -> Result<()> {
+        let builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let data = builder.build::<PoseidonGoldilocksConfig>();
+        let proof = data.prove(PartialWitness::new())?;
+        assert!(matches!(
+            GnarkArtifacts::from_verifier_data(&data.verifier_data(), &proof),
+            Err(BftError::Serialization(_))
+        ));
+        Ok(())
+    }
+}
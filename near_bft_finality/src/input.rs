@@ -0,0 +1,616 @@
+use crate::checkpoint::Checkpoints;
+use crate::circuits::ConfigName;
+use crate::error::{BftError, Result};
+use crate::prove_bft::bft::prove_block_bft;
+use crate::prove_bft::scheduler::StageScheduler;
+use crate::prove_block_data::signature_prover::SignatureProver;
+use crate::types::HeaderDataFields;
+use near_primitives::{
+    block_header::BlockHeader, borsh, hash::CryptoHash,
+    types::validator_stake::ValidatorStake,
+};
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::timing::TimingTree;
+use plonky2_field::extension::Extendable;
+
+/// Represents the number of consecutive blocks required to prove BFT finality of a block.
+pub const FINALITY_BLOCKS: usize = 5;
+
+/// Represents an epoch block used as an anchor together with its hash stored in the contract.
+///
+/// # Fields
+///
+/// * `header` - The block header (e.g. Block_0 or Block_n-1 of some epoch).
+/// * `stored_hash` - The hash of this block that was previously proven and stored.
+#[derive(Debug, Clone)]
+pub struct AnchorBlock {
+    pub header: BlockHeader,
+    pub stored_hash: CryptoHash,
+}
+
+impl AnchorBlock {
+    pub fn new(header: BlockHeader, stored_hash: CryptoHash) -> Self {
+        Self {
+            header,
+            stored_hash,
+        }
+    }
+
+    /// Returns (borsh(stored_hash), borsh(header)), as expected by `prove_block_bft`.
+    fn to_bytes(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        Ok((
+            borsh::to_vec(&self.stored_hash)?,
+            borsh::to_vec(&self.header)?,
+        ))
+    }
+}
+
+/// Represents the data required to prove BFT finality of a randomly selected Block_i.
+///
+/// # Fields
+///
+/// * `ep2_last_block` - Block_n-1 (Epoch_i-2) and its stored hash. Proves epoch_id of Epoch_i.
+/// * `ep1_first_block` - Block_0 (Epoch_i-1) and its stored hash. Proves bp_hash of Epoch_i.
+/// * `blocks` - Block_i, Block_i+1, ..., Block_i+4 in ascending order of heights.
+/// * `validators` - The list of validators of Epoch_i.
+#[derive(Debug, Clone)]
+pub struct BlockProvingInput {
+    pub ep2_last_block: AnchorBlock,
+    pub ep1_first_block: AnchorBlock,
+    pub blocks: Vec<BlockHeader>,
+    pub validators: Vec<ValidatorStake>,
+}
+
+impl BlockProvingInput {
+    pub fn builder() -> BlockProvingInputBuilder {
+        BlockProvingInputBuilder::default()
+    }
+
+    /// Returns the hash of Block_i.
+    pub fn block_hash(&self) -> CryptoHash {
+        *self.blocks[0].hash()
+    }
+
+    /// Returns epoch blocks in the form [Block_0 (Epoch_i-1), Block_n-1 (Epoch_i-2)].
+    /// Each entry is represented as (borsh(stored_hash), borsh(header)).
+    pub fn epoch_blocks(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Ok(vec![
+            self.ep1_first_block.to_bytes()?,
+            self.ep2_last_block.to_bytes()?,
+        ])
+    }
+
+    /// Returns blocks in the form [Block_i+4, Block_i+3, Block_i+2, Block_i+1, Block_i].
+    pub fn blocks_data(&self) -> Result<Vec<(HeaderDataFields, Vec<u8>)>> {
+        self.blocks
+            .iter()
+            .rev()
+            .map(|header| header_data_fields(header, true))
+            .collect()
+    }
+
+    /// Returns checkpoints of the run proving this input in `root`.
+    pub fn checkpoints(&self, root: &Checkpoints) -> Result<Checkpoints> {
+        let headers: Vec<&BlockHeader> = self.blocks.iter().collect();
+        run_checkpoints(root, self.epoch_blocks()?, &headers, &[self.validators.as_slice()])
+    }
+
+    /// Proves BFT finality of Block_i.
+    ///
+    /// # Arguments
+    ///
+    /// * `signature_prover` - The backend used to prove signatures, e.g. `LocalSignatureProver`.
+    /// * `checkpoints` - Checkpoints of this input (see `checkpoints`), or `Checkpoints::disabled()`.
+    /// * `scheduler` - Runs independent proving stages concurrently, e.g. `StageScheduler::default()`.
+    /// * `timing_tree` - A mutable reference to the timing tree.
+    ///
+    /// # Returns
+    ///
+    /// Returns verifier circuit data and the proof of Block_i.
+    pub fn prove<F, C, const D: usize>(
+        &self,
+        signature_prover: &dyn SignatureProver<F, C, D>,
+        checkpoints: &Checkpoints,
+        scheduler: &StageScheduler,
+        timing_tree: &mut TimingTree,
+    ) -> Result<(VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + ConfigName,
+        C::Hasher: AlgebraicHasher<F>,
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        let epoch_blocks = self.epoch_blocks()?;
+        let (bi_data_proof, _) = prove_block_bft::<F, C, D>(
+            &epoch_blocks[1].1,
+            &epoch_blocks[1].0,
+            &epoch_blocks[0].1,
+            &epoch_blocks[0].0,
+            None,
+            None,
+            self.blocks_data()?,
+            Some(validators_bytes(&self.validators)?),
+            None,
+            signature_prover,
+            checkpoints,
+            scheduler,
+            timing_tree,
+        )?;
+        Ok(bi_data_proof)
+    }
+}
+
+/// Builds `BlockProvingInput` from headers, stored hashes and validators taken from any source.
+#[derive(Debug, Clone, Default)]
+pub struct BlockProvingInputBuilder {
+    ep2_last_block: Option<AnchorBlock>,
+    ep1_first_block: Option<AnchorBlock>,
+    blocks: Vec<BlockHeader>,
+    validators: Option<Vec<ValidatorStake>>,
+}
+
+impl BlockProvingInputBuilder {
+    /// Sets Block_n-1 (Epoch_i-2) and its stored hash.
+    pub fn ep2_last_block(mut self, header: BlockHeader, stored_hash: CryptoHash) -> Self {
+        self.ep2_last_block = Some(AnchorBlock::new(header, stored_hash));
+        self
+    }
+
+    /// Sets Block_0 (Epoch_i-1) and its stored hash.
+    pub fn ep1_first_block(mut self, header: BlockHeader, stored_hash: CryptoHash) -> Self {
+        self.ep1_first_block = Some(AnchorBlock::new(header, stored_hash));
+        self
+    }
+
+    /// Appends the next block. Blocks are expected in ascending order starting from Block_i.
+    pub fn block(mut self, header: BlockHeader) -> Self {
+        self.blocks.push(header);
+        self
+    }
+
+    /// Sets Block_i, Block_i+1, ..., Block_i+4 in ascending order of heights.
+    pub fn blocks(mut self, headers: Vec<BlockHeader>) -> Self {
+        self.blocks = headers;
+        self
+    }
+
+    /// Sets the list of validators of Epoch_i.
+    pub fn validators(mut self, validators: Vec<ValidatorStake>) -> Self {
+        self.validators = Some(validators);
+        self
+    }
+
+    /// Builds `BlockProvingInput`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if some data is missing, the number of blocks is not
+    /// equal to `FINALITY_BLOCKS` or blocks are not sorted by height.
+    pub fn build(self) -> Result<BlockProvingInput> {
+        check_blocks(&self.blocks)?;
+        Ok(BlockProvingInput {
+            ep2_last_block: self.ep2_last_block.ok_or_else(|| missing("Block_n-1 (Epoch_i-2)"))?,
+            ep1_first_block: self.ep1_first_block.ok_or_else(|| missing("Block_0 (Epoch_i-1)"))?,
+            blocks: self.blocks,
+            validators: check_validators(self.validators, "Epoch_i")?,
+        })
+    }
+}
+
+/// Represents the data required to prove Block_0 (Epoch_i) and Block_n-1 (Epoch_i-1).
+///
+/// # Fields
+///
+/// * `ep3_last_block` - Block_n-1 (Epoch_i-3) and its stored hash. Proves epoch_id of Epoch_i-1.
+/// * `ep2_last_block` - Block_n-1 (Epoch_i-2) and its stored hash. Proves epoch_id of Epoch_i
+///   and bp_hash of Epoch_i-1.
+/// * `ep1_first_block` - Block_0 (Epoch_i-1) and its stored hash. Proves bp_hash of Epoch_i.
+/// * `blocks` - Block_0, Block_1, ..., Block_4 of Epoch_i in ascending order of heights.
+/// * `ep1_last_block` - Block_n-1 (Epoch_i-1), the previous block of Block_0.
+/// * `validators` - The list of validators of Epoch_i.
+/// * `validators_n_1` - The list of validators of Epoch_i-1.
+#[derive(Debug, Clone)]
+pub struct EpochProvingInput {
+    pub ep3_last_block: AnchorBlock,
+    pub ep2_last_block: AnchorBlock,
+    pub ep1_first_block: AnchorBlock,
+    pub blocks: Vec<BlockHeader>,
+    pub ep1_last_block: BlockHeader,
+    pub validators: Vec<ValidatorStake>,
+    pub validators_n_1: Vec<ValidatorStake>,
+}
+
+impl EpochProvingInput {
+    pub fn builder() -> EpochProvingInputBuilder {
+        EpochProvingInputBuilder::default()
+    }
+
+    /// Returns the hash of Block_0 (Epoch_i).
+    pub fn block_hash(&self) -> CryptoHash {
+        *self.blocks[0].hash()
+    }
+
+    /// Returns epoch blocks in the form [Block_0 (Epoch_i-1), Block_n-1 (Epoch_i-2), Block_n-1 (Epoch_i-3)].
+    /// Each entry is represented as (borsh(stored_hash), borsh(header)).
+    pub fn epoch_blocks(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Ok(vec![
+            self.ep1_first_block.to_bytes()?,
+            self.ep2_last_block.to_bytes()?,
+            self.ep3_last_block.to_bytes()?,
+        ])
+    }
+
+    /// Returns blocks in the form [Block_4, Block_3, Block_2, Block_1, Block_0, Block_n-1].
+    pub fn blocks_data(&self) -> Result<Vec<(HeaderDataFields, Vec<u8>)>> {
+        let mut blocks = self
+            .blocks
+            .iter()
+            .rev()
+            .map(|header| header_data_fields(header, true))
+            .collect::<Result<Vec<_>>>()?;
+        blocks.push(header_data_fields(&self.ep1_last_block, false)?);
+        Ok(blocks)
+    }
+
+    /// Returns checkpoints of the run proving this input in `root`.
+    pub fn checkpoints(&self, root: &Checkpoints) -> Result<Checkpoints> {
+        let headers: Vec<&BlockHeader> = self
+            .blocks
+            .iter()
+            .chain(std::iter::once(&self.ep1_last_block))
+            .collect();
+        run_checkpoints(
+            root,
+            self.epoch_blocks()?,
+            &headers,
+            &[self.validators.as_slice(), self.validators_n_1.as_slice()],
+        )
+    }
+
+    /// Proves Block_0 (Epoch_i) and Block_n-1 (Epoch_i-1).
+    ///
+    /// # Arguments
+    ///
+    /// * `signature_prover` - The backend used to prove signatures, e.g. `LocalSignatureProver`.
+    /// * `checkpoints` - Checkpoints of this input (see `checkpoints`), or `Checkpoints::disabled()`.
+    /// * `scheduler` - Runs independent proving stages concurrently, e.g. `StageScheduler::default()`.
+    /// * `timing_tree` - A mutable reference to the timing tree.
+    ///
+    /// # Returns
+    ///
+    /// Returns verifier circuit data and proofs of Block_0 and Block_n-1.
+    pub fn prove<F, C, const D: usize>(
+        &self,
+        signature_prover: &dyn SignatureProver<F, C, D>,
+        checkpoints: &Checkpoints,
+        scheduler: &StageScheduler,
+        timing_tree: &mut TimingTree,
+    ) -> Result<(
+        (VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>),
+        (VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>),
+    )>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + ConfigName,
+        C::Hasher: AlgebraicHasher<F>,
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        let epoch_blocks = self.epoch_blocks()?;
+        let (b0_data_proof, bn_1_data_proof) = prove_block_bft::<F, C, D>(
+            &epoch_blocks[1].1,
+            &epoch_blocks[1].0,
+            &epoch_blocks[0].1,
+            &epoch_blocks[0].0,
+            Some(epoch_blocks[2].1.clone()),
+            Some(epoch_blocks[2].0.clone()),
+            self.blocks_data()?,
+            Some(validators_bytes(&self.validators)?),
+            Some(validators_bytes(&self.validators_n_1)?),
+            signature_prover,
+            checkpoints,
+            scheduler,
+            timing_tree,
+        )?;
+        let bn_1_data_proof = bn_1_data_proof
+            .ok_or_else(|| BftError::Proving("No proof for Block_n-1".to_string()))?;
+        Ok((b0_data_proof, bn_1_data_proof))
+    }
+}
+
+/// Builds `EpochProvingInput` from headers, stored hashes and validators taken from any source.
+#[derive(Debug, Clone, Default)]
+pub struct EpochProvingInputBuilder {
+    ep3_last_block: Option<AnchorBlock>,
+    ep2_last_block: Option<AnchorBlock>,
+    ep1_first_block: Option<AnchorBlock>,
+    blocks: Vec<BlockHeader>,
+    ep1_last_block: Option<BlockHeader>,
+    validators: Option<Vec<ValidatorStake>>,
+    validators_n_1: Option<Vec<ValidatorStake>>,
+}
+
+impl EpochProvingInputBuilder {
+    /// Sets Block_n-1 (Epoch_i-3) and its stored hash.
+    pub fn ep3_last_block(mut self, header: BlockHeader, stored_hash: CryptoHash) -> Self {
+        self.ep3_last_block = Some(AnchorBlock::new(header, stored_hash));
+        self
+    }
+
+    /// Sets Block_n-1 (Epoch_i-2) and its stored hash.
+    pub fn ep2_last_block(mut self, header: BlockHeader, stored_hash: CryptoHash) -> Self {
+        self.ep2_last_block = Some(AnchorBlock::new(header, stored_hash));
+        self
+    }
+
+    /// Sets Block_0 (Epoch_i-1) and its stored hash.
+    pub fn ep1_first_block(mut self, header: BlockHeader, stored_hash: CryptoHash) -> Self {
+        self.ep1_first_block = Some(AnchorBlock::new(header, stored_hash));
+        self
+    }
+
+    /// Sets Block_n-1 (Epoch_i-1).
+    pub fn ep1_last_block(mut self, header: BlockHeader) -> Self {
+        self.ep1_last_block = Some(header);
+        self
+    }
+
+    /// Appends the next block. Blocks are expected in ascending order starting from Block_0.
+    pub fn block(mut self, header: BlockHeader) -> Self {
+        self.blocks.push(header);
+        self
+    }
+
+    /// Sets Block_0, Block_1, ..., Block_4 of Epoch_i in ascending order of heights.
+    pub fn blocks(mut self, headers: Vec<BlockHeader>) -> Self {
+        self.blocks = headers;
+        self
+    }
+
+    /// Sets the list of validators of Epoch_i.
+    pub fn validators(mut self, validators: Vec<ValidatorStake>) -> Self {
+        self.validators = Some(validators);
+        self
+    }
+
+    /// Sets the list of validators of Epoch_i-1.
+    pub fn validators_n_1(mut self, validators: Vec<ValidatorStake>) -> Self {
+        self.validators_n_1 = Some(validators);
+        self
+    }
+
+    /// Builds `EpochProvingInput`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if some data is missing, the number of blocks is not
+    /// equal to `FINALITY_BLOCKS`, blocks are not sorted by height or Block_n-1 (Epoch_i-1)
+    /// is not the previous block of Block_0.
+    pub fn build(self) -> Result<EpochProvingInput> {
+        check_blocks(&self.blocks)?;
+        let ep1_last_block = self
+            .ep1_last_block
+            .ok_or_else(|| missing("Block_n-1 (Epoch_i-1)"))?;
+        if self.blocks[0].prev_hash() != ep1_last_block.hash() {
+            return Err(BftError::InvalidInput(format!(
+                "Block_0 {} doesn't follow Block_n-1 {}",
+                self.blocks[0].hash(),
+                ep1_last_block.hash()
+            )));
+        }
+        Ok(EpochProvingInput {
+            ep3_last_block: self.ep3_last_block.ok_or_else(|| missing("Block_n-1 (Epoch_i-3)"))?,
+            ep2_last_block: self.ep2_last_block.ok_or_else(|| missing("Block_n-1 (Epoch_i-2)"))?,
+            ep1_first_block: self.ep1_first_block.ok_or_else(|| missing("Block_0 (Epoch_i-1)"))?,
+            blocks: self.blocks,
+            ep1_last_block,
+            validators: check_validators(self.validators, "Epoch_i")?,
+            validators_n_1: check_validators(self.validators_n_1, "Epoch_i-1")?,
+        })
+    }
+}
+
+/// Converts a header into the representation used by `prove_block_bft`.
+///
+/// # Arguments
+///
+/// * `header` - The block header.
+/// * `with_approvals` - Whether to set approvals of the block. Approvals of Block_n-1
+///   are not used when proving epoch blocks.
+pub fn header_data_fields(
+    header: &BlockHeader,
+    with_approvals: bool,
+) -> Result<(HeaderDataFields, Vec<u8>)> {
+    let approvals = if with_approvals {
+        Some(
+            header
+                .approvals()
+                .iter()
+                .map(borsh::to_vec)
+                .collect::<std::result::Result<Vec<Vec<u8>>, _>>()?,
+        )
+    } else {
+        None
+    };
+    let block = HeaderDataFields {
+        hash: header.hash().0.to_vec(),
+        height: Some(header.height()),
+        prev_hash: Some(header.prev_hash().0.to_vec()),
+        bp_hash: Some(header.next_bp_hash().0.to_vec()),
+        epoch_id: Some(header.epoch_id().0 .0.to_vec()),
+        next_epoch_id: Some(header.next_epoch_id().0 .0.to_vec()),
+        last_ds_final_hash: Some(header.last_ds_final_block().0.to_vec()),
+        last_final_hash: Some(header.last_final_block().0.to_vec()),
+        approvals,
+    };
+    Ok((block, borsh::to_vec(header)?))
+}
+
+pub(crate) fn validators_bytes(validators: &[ValidatorStake]) -> Result<Vec<Vec<u8>>> {
+    Ok(validators
+        .iter()
+        .map(borsh::to_vec)
+        .collect::<std::result::Result<Vec<Vec<u8>>, _>>()?)
+}
+
+/// Returns checkpoints of the run identified by anchors, headers & lists of validators.
+fn run_checkpoints(
+    root: &Checkpoints,
+    epoch_blocks: Vec<(Vec<u8>, Vec<u8>)>,
+    headers: &[&BlockHeader],
+    validators: &[&[ValidatorStake]],
+) -> Result<Checkpoints> {
+    let mut inputs = vec![];
+    for (stored_hash, header) in epoch_blocks {
+        inputs.push(stored_hash);
+        inputs.push(header);
+    }
+    for header in headers {
+        inputs.push(borsh::to_vec(header)?);
+    }
+    for validators in validators {
+        inputs.push((validators.len() as u64).to_le_bytes().to_vec());
+        inputs.extend(validators_bytes(validators)?);
+    }
+    let inputs: Vec<&[u8]> = inputs.iter().map(Vec::as_slice).collect();
+    Ok(root.run(&inputs))
+}
+
+fn missing(name: &str) -> BftError {
+    BftError::InvalidInput(format!("{name} is not set"))
+}
+
+fn check_blocks(blocks: &[BlockHeader]) -> Result<()> {
+    if blocks.len() != FINALITY_BLOCKS {
+        return Err(BftError::InvalidInput(format!(
+            "Expected {} blocks, got {}",
+            FINALITY_BLOCKS,
+            blocks.len()
+        )));
+    }
+    if blocks.windows(2).any(|w| w[0].height() >= w[1].height()) {
+        return Err(BftError::InvalidInput(
+            "Blocks are not sorted by height".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn check_validators(
+    validators: Option<Vec<ValidatorStake>>,
+    epoch: &str,
+) -> Result<Vec<ValidatorStake>> {
+    match validators {
+        Some(validators) if !validators.is_empty() => Ok(validators),
+        _ => Err(BftError::InvalidInput(format!(
+            "No list of validators for {epoch}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{load_block_input, load_epoch_input};
+
+    const EPOCHS_PATH: &str = "../data/epochs";
+    const EPOCH_ID_I: &str = "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t";
+    const EPOCH_ID_I_1: &str = "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae";
+    const EPOCH_ID_I_2: &str = "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi";
+    const EPOCH_ID_I_3: &str = "89PT9SkLXB1FZHvW7EdQHxiSpm5ybuTCvjrGZWWhXMTz";
+
+    #[test]
+    fn test_block_input_order() -> anyhow::Result<()> {
+        let input = load_block_input(EPOCHS_PATH, EPOCH_ID_I_1, EPOCH_ID_I_2, EPOCH_ID_I_3)?;
+        let blocks = input.blocks_data()?;
+        assert_eq!(blocks.len(), FINALITY_BLOCKS);
+        assert_eq!(blocks[4].0.hash, input.block_hash().0.to_vec());
+        assert!(blocks.iter().all(|(block, _)| block.approvals.is_some()));
+        let epoch_blocks = input.epoch_blocks()?;
+        assert_eq!(epoch_blocks.len(), 2);
+        assert_eq!(epoch_blocks[0].0, borsh::to_vec(&input.ep1_first_block.stored_hash)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_epoch_input_order() -> anyhow::Result<()> {
+        let input = load_epoch_input(
+            EPOCHS_PATH,
+            EPOCH_ID_I,
+            EPOCH_ID_I_1,
+            EPOCH_ID_I_2,
+            EPOCH_ID_I_3,
+        )?;
+        let blocks = input.blocks_data()?;
+        assert_eq!(blocks.len(), FINALITY_BLOCKS + 1);
+        assert_eq!(blocks[4].0.hash, input.block_hash().0.to_vec());
+        assert_eq!(blocks[5].0.hash, input.ep1_last_block.hash().0.to_vec());
+        assert!(blocks[5].0.approvals.is_none());
+        assert_eq!(input.epoch_blocks()?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoints_of_input() -> anyhow::Result<()> {
+        let root = Checkpoints::open(std::env::temp_dir().join("input_checkpoints"))?;
+        let input = load_block_input(EPOCHS_PATH, EPOCH_ID_I_1, EPOCH_ID_I_2, EPOCH_ID_I_3)?;
+        let checkpoints = input.checkpoints(&root)?;
+        assert_eq!(checkpoints, input.clone().checkpoints(&root)?);
+        let mut other = input.clone();
+        other.validators.pop();
+        assert_ne!(checkpoints, other.checkpoints(&root)?);
+        assert_eq!(input.checkpoints(&Checkpoints::disabled())?, Checkpoints::disabled());
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_input_builder_wrong_number_of_blocks() -> anyhow::Result<()> {
+        let input = load_block_input(EPOCHS_PATH, EPOCH_ID_I_1, EPOCH_ID_I_2, EPOCH_ID_I_3)?;
+        let result = BlockProvingInput::builder()
+            .ep2_last_block(input.ep2_last_block.header.clone(), input.ep2_last_block.stored_hash)
+            .ep1_first_block(input.ep1_first_block.header.clone(), input.ep1_first_block.stored_hash)
+            .blocks(input.blocks[..4].to_vec())
+            .validators(input.validators.clone())
+            .build();
+        assert!(matches!(result, Err(BftError::InvalidInput(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_input_builder_unsorted_blocks() -> anyhow::Result<()> {
+        let input = load_block_input(EPOCHS_PATH, EPOCH_ID_I_1, EPOCH_ID_I_2, EPOCH_ID_I_3)?;
+        let mut blocks = input.blocks.clone();
+        blocks.swap(0, 1);
+        let result = BlockProvingInput::builder()
+            .ep2_last_block(input.ep2_last_block.header.clone(), input.ep2_last_block.stored_hash)
+            .ep1_first_block(input.ep1_first_block.header.clone(), input.ep1_first_block.stored_hash)
+            .blocks(blocks)
+            .validators(input.validators.clone())
+            .build();
+        assert!(matches!(result, Err(BftError::InvalidInput(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_epoch_input_builder_missing_anchor() -> anyhow::Result<()> {
+        let input = load_epoch_input(
+            EPOCHS_PATH,
+            EPOCH_ID_I,
+            EPOCH_ID_I_1,
+            EPOCH_ID_I_2,
+            EPOCH_ID_I_3,
+        )?;
+        let result = EpochProvingInput::builder()
+            .ep2_last_block(input.ep2_last_block.header.clone(), input.ep2_last_block.stored_hash)
+            .ep1_first_block(input.ep1_first_block.header.clone(), input.ep1_first_block.stored_hash)
+            .ep1_last_block(input.ep1_last_block.clone())
+            .blocks(input.blocks.clone())
+            .validators(input.validators.clone())
+            .validators_n_1(input.validators_n_1.clone())
+            .build();
+        assert!(matches!(result, Err(BftError::InvalidInput(_))));
+        Ok(())
+    }
+}
@@ -8,15 +8,50 @@
 //!
 //! # Modules
 //!
+//! - `checkpoint`: Stores intermediate proofs to resume interrupted proving runs.
+//! - `cli`: Defines command line arguments shared by binaries.
+//! - `circuits`: Builds the manifest of production circuits to detect their unintended changes.
+//! - `conformance`: Runs conformance vectors shared with the risc0 backend through the plonky2 prover.
+//! - `error`: Defines the error type returned by the crate.
+//! - `gnark`: Exports BN128-wrapped proofs in the input format of gnark-plonky2-verifier.
+//! - `input`: Defines typed inputs and their builders to prove random and epoch blocks.
+//! - `light_client`: Verifies proofs of both backends and advances trusted epochs.
+//! - `mutation`: Applies targeted mutations to valid inputs to test that provers reject them.
+//! - `network`: Defines network profiles: chain id, epoch length, RPC endpoints and the genesis anchor.
+//! - `pipeline`: Proves inputs, wraps final proofs in BN128 config and stores them.
 //! - `prove_bft`: Contains functionality for proving block BFT finality.
 //! - `prove_block_data`: Contains functionality for proving block entities.
 //! - `prove_crypto`: Provides cryptographic proof functionalities.
-//! - `service`: Defines services for handling proving blocks.
+//! - `prover`: Defines the input and the `Prover` trait shared by the plonky2 and risc0 backends.
+//! - `public_inputs`: Defines the layout of public inputs of block proofs.
+//! - `service`: Proves tasks of the proving server, which are shared with the risc0 backend.
+//! - `source`: Defines sources of blocks and validators: RPC, stored epochs, NEAR Lake exports and recorded responses.
+//! - `store`: Stores plonky2 proofs in the proof store shared with the risc0 backend (`proof_store`).
+//! - `synthetic`: Generates deterministic fake chains to test provers without network.
 //! - `types`: Defines custom data types used across the crate.
 //! - `utils`: Contains utility functions and helpers to load blocks, validators and converting types.
+//! - `verify`: Verifies stored proofs and reports statistics of their circuits.
 
+pub mod checkpoint;
+pub mod circuits;
+pub mod cli;
+pub mod conformance;
+pub mod error;
+pub mod gnark;
+pub mod input;
+pub mod light_client;
+pub mod mutation;
+pub mod network;
+pub mod pipeline;
 pub mod prove_bft;
 pub mod prove_block_data;
 pub mod prove_crypto;
+pub mod prover;
+pub mod public_inputs;
+pub mod service;
+pub mod source;
+pub mod store;
+pub mod synthetic;
 pub mod types;
 pub mod utils;
+pub mod verify;
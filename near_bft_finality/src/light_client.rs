@@ -0,0 +1,1044 @@
+use crate::error::{BftError, Result};
+use crate::prove_bft::epoch_chain::{AnchoredBlockPublicInputs, EpochAnchors};
+use crate::public_inputs::BlockProofPublicInputs;
+use crate::store::{ProofArtifacts, ProofKind};
+use crate::types::PK_HASH_BYTES;
+use near_primitives::block_header::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2_bn128::config::PoseidonBN128GoldilocksConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const D: usize = 2;
+// Final proofs are wrapped in BN128 config before they are stored.
+type C = PoseidonBN128GoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+// Proofs of blocks anchored to the epoch chain aren't wrapped.
+type Cgl = PoseidonGoldilocksConfig;
+
+/// Represents the size of a word of the ABI encoding of risc0 journals.
+const WORD_BYTES: usize = 32;
+
+/// Represents the trusted state of the light client after Epoch_i is proven by the pair
+/// {Block_0 (Epoch_i), Block_n-1 (Epoch_i-1)}.
+///
+/// # Fields
+///
+/// * `epoch_id` - epoch_id of Epoch_i, i.e. the hash of Block_n-1 (Epoch_i-2).
+/// * `next_epoch_id` - epoch_id of Epoch_i+1, i.e. the hash of Block_n-1 (Epoch_i-1).
+/// * `bp_hash` - next_bp_hash of Block_0 (Epoch_i), i.e. the hash of validators of Epoch_i+1.
+/// * `height` - The height of Block_0 (Epoch_i).
+/// * `bp_block_hash` - The hash of Block_0 (Epoch_i). Proofs of blocks of Epoch_i+1 commit to this
+///   block instead of bp_hash itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedState {
+    pub epoch_id: CryptoHash,
+    pub next_epoch_id: CryptoHash,
+    pub bp_hash: CryptoHash,
+    pub height: u64,
+    pub bp_block_hash: CryptoHash,
+}
+
+impl TrustedState {
+    /// Creates the state of the epoch started by Block_0 with `header`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::HashMismatch` if the header doesn't hash to `block_hash`, e.g. the proven
+    /// hash of Block_0.
+    pub fn from_block_0(header: &BlockHeader, block_hash: CryptoHash) -> Result<Self> {
+        // The hash is computed from the header, `BlockHeader::hash` is set by its creator.
+        let computed = BlockHeader::compute_hash(
+            *header.prev_hash(),
+            &header.inner_lite_bytes(),
+            &header.inner_rest_bytes(),
+        );
+        if computed != block_hash {
+            return Err(BftError::HashMismatch(format!(
+                "header of Block_0 has hash {}, expected {}",
+                computed, block_hash
+            )));
+        }
+        Ok(Self {
+            epoch_id: header.epoch_id().0,
+            next_epoch_id: header.next_epoch_id().0,
+            bp_hash: *header.next_bp_hash(),
+            height: header.height(),
+            bp_block_hash: block_hash,
+        })
+    }
+
+    /// Returns hashes of epoch blocks that proofs of the next epoch are checked against.
+    pub fn anchors(&self) -> EpochAnchors {
+        EpochAnchors {
+            prev_last_block_hash: self.epoch_id,
+            last_block_hash: self.next_epoch_id,
+            first_block_hash: self.bp_block_hash,
+        }
+    }
+
+    /// Returns true if blocks of Epoch_i+1 are signed by validators this state trusts.
+    fn is_parent_of(&self, epoch_id: &CryptoHash, bp_block_hash: &CryptoHash) -> bool {
+        self.next_epoch_id == *epoch_id && self.bp_block_hash == *bp_block_hash
+    }
+}
+
+/// Represents the image ID of the risc0 guest, as `risc0_zkvm::sha::Digest` words.
+pub type Risc0ImageId = [u32; 8];
+
+/// Verifies receipts of the risc0 guest. It is implemented by the environment of the light client,
+/// e.g. natively with `risc0_zkvm::Receipt::verify`.
+pub trait Risc0Verifier {
+    /// Verifies the receipt for the image ID of the guest and returns its journal.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the receipt is invalid or is produced by another guest.
+    fn verify(&self, receipt: &[u8], image_id: &Risc0ImageId) -> Result<Vec<u8>>;
+}
+
+/// Represents a proof of the transition to the next epoch.
+#[derive(Debug, Clone)]
+pub enum EpochProof {
+    /// Stored plonky2 proofs of Block_0 (Epoch_i) and Block_n-1 (Epoch_i-1). Public inputs commit
+    /// only hashes of blocks, so next_bp_hash and the height are read from `block_0_header`, which
+    /// hash is checked against the proof.
+    Plonky2 {
+        block_0: ProofArtifacts,
+        block_n_1: ProofArtifacts,
+        block_0_header: BlockHeader,
+    },
+    /// The receipt of the risc0 guest that commits `PublicValuesEpoch`.
+    Risc0(Vec<u8>),
+}
+
+/// Represents a proof of a randomly selected block.
+#[derive(Debug, Clone)]
+pub enum BlockProof {
+    /// The stored plonky2 proof of the block.
+    Plonky2(ProofArtifacts),
+    /// The receipt of the risc0 guest that commits `PublicValuesRandom`.
+    Risc0(Vec<u8>),
+    /// The plonky2 proof of the block anchored to the epoch chain (see `AnchoredBlockCircuit`), stored in
+    /// `{proofs_dir}/chain/anchored/{block_hash}`. The chain proves the epoch of the block, so the proof
+    /// doesn't depend on epochs trusted after the first state.
+    Anchored(ProofArtifacts),
+}
+
+/// Represents the epoch transition proven by an epoch proof.
+///
+/// # Fields
+///
+/// * `parent` - Anchors the proof is proven against.
+/// * `state` - The state after the transition.
+struct EpochUpdate {
+    parent: EpochAnchors,
+    state: TrustedState,
+}
+
+/// Represents the light client that verifies epoch proofs of both backends and block proofs of both
+/// backends against trusted epochs, or anchored to the epoch chain that starts from the first trusted state.
+///
+/// It doesn't read or write files, so it can be embedded in a contract-like environment that
+/// persists `history` between calls.
+pub struct LightClient {
+    history: Vec<TrustedState>,
+    plonky2_circuits: HashMap<ProofKind, VerifierCircuitData<F, C, D>>,
+    risc0_verifier: Option<(Box<dyn Risc0Verifier>, Risc0ImageId)>,
+    anchored_circuit: Option<VerifierCircuitData<F, Cgl, D>>,
+}
+
+impl LightClient {
+    /// Creates the light client trusting `state`, e.g. anchors stored in the contract.
+    pub fn new(state: TrustedState) -> Self {
+        Self {
+            history: vec![state],
+            plonky2_circuits: HashMap::new(),
+            risc0_verifier: None,
+            anchored_circuit: None,
+        }
+    }
+
+    /// Restores the light client from trusted states, the last one is the current state.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if `history` is empty or its states don't chain.
+    pub fn from_history(history: Vec<TrustedState>) -> Result<Self> {
+        let Some(state) = history.first() else {
+            return Err(BftError::InvalidInput(
+                "history of trusted states is empty".to_string(),
+            ));
+        };
+        if let Some(pair) = history
+            .windows(2)
+            .find(|pair| pair[1].epoch_id != pair[0].next_epoch_id)
+        {
+            return Err(BftError::InvalidInput(format!(
+                "trusted state of epoch {} doesn't follow epoch {}",
+                pair[1].epoch_id, pair[0].epoch_id
+            )));
+        }
+        let mut client = Self::new(*state);
+        client.history = history;
+        Ok(client)
+    }
+
+    /// Trusts plonky2 proofs of `kind` blocks that are valid for the circuit `data`, e.g. the
+    /// verifier data of the deployed circuit. Verifier data stored with proofs is never used.
+    pub fn with_plonky2_circuit(
+        mut self,
+        kind: ProofKind,
+        data: VerifierCircuitData<F, C, D>,
+    ) -> Self {
+        self.plonky2_circuits.insert(kind, data);
+        self
+    }
+
+    /// Trusts risc0 receipts of the guest with `image_id`, verified with `verifier`.
+    pub fn with_risc0_verifier(
+        mut self,
+        verifier: Box<dyn Risc0Verifier>,
+        image_id: Risc0ImageId,
+    ) -> Self {
+        self.risc0_verifier = Some((verifier, image_id));
+        self
+    }
+
+    /// Trusts proofs of blocks anchored to the epoch chain that are valid for the circuit `data`, e.g.
+    /// `AnchoredBlockCircuit::data` built for the deployed chain circuit. The circuit fixes circuits
+    /// of the chain, of epoch proofs and of block proofs, the genesis of the chain is checked against
+    /// the first trusted state.
+    pub fn with_anchored_circuit(mut self, data: VerifierCircuitData<F, Cgl, D>) -> Self {
+        self.anchored_circuit = Some(data);
+        self
+    }
+
+    /// Returns the current trusted state.
+    pub fn state(&self) -> &TrustedState {
+        self.history.last().expect("history is never empty")
+    }
+
+    /// Returns all trusted states, the last one is the current state.
+    pub fn history(&self) -> &[TrustedState] {
+        &self.history
+    }
+
+    /// Verifies the proof of the next epoch against the current state and advances the state.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors of verification of the proof, `BftError::StaleUpdate` if the epoch is already
+    /// trusted, `BftError::Fork` if the proof conflicts with a trusted epoch and `BftError::UnknownEpoch`
+    /// if it is proven against anchors that aren't trusted.
+    pub fn apply_epoch_proof(&mut self, proof: &EpochProof) -> Result<&TrustedState> {
+        let update = match proof {
+            EpochProof::Plonky2 {
+                block_0,
+                block_n_1,
+                block_0_header,
+            } => {
+                let b0 = self.verify_plonky2(block_0, ProofKind::Epoch)?;
+                let bn_1 = self.verify_plonky2(block_n_1, ProofKind::Epoch)?;
+                let parent = EpochAnchors::of_epoch_proofs(&b0, &bn_1);
+                parent.check_epoch_proofs(&b0, &bn_1)?;
+                let state = TrustedState::from_block_0(block_0_header, b0.block_hash)?;
+                if state.anchors() != parent.next(&b0, &bn_1) {
+                    return Err(BftError::HashMismatch(format!(
+                        "epoch of Block_0 {} doesn't follow the proven anchors {:?}",
+                        b0.block_hash, parent
+                    )));
+                }
+                EpochUpdate { parent, state }
+            }
+            EpochProof::Risc0(receipt) => {
+                let journal = PublicValuesEpoch::abi_decode(&self.verify_risc0(receipt)?)?;
+                EpochUpdate {
+                    parent: EpochAnchors {
+                        prev_last_block_hash: journal.previous_epoch_id,
+                        last_block_hash: journal.epoch_id,
+                        first_block_hash: journal.previous_epoch_block_hash,
+                    },
+                    state: TrustedState {
+                        epoch_id: journal.epoch_id,
+                        next_epoch_id: journal.next_epoch_id,
+                        bp_hash: journal.next_bp_hash,
+                        height: journal.current_block_height,
+                        bp_block_hash: journal.current_block_hash,
+                    },
+                }
+            }
+        };
+        self.advance(update)
+    }
+
+    /// Verifies the proof of a randomly selected block and checks that its epoch follows a trusted one.
+    ///
+    /// # Returns
+    ///
+    /// Returns the hash of the proven block.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors of verification of the proof, `BftError::UnknownEpoch` if validators of
+    /// the block aren't trusted and `BftError::HashMismatch` if the block is anchored to the chain
+    /// of another genesis.
+    pub fn verify_block_proof(&self, proof: &BlockProof) -> Result<CryptoHash> {
+        let (block_hash, epoch_id, bp_block_hash) = match proof {
+            BlockProof::Plonky2(artifacts) => {
+                let pi = self.verify_plonky2(artifacts, ProofKind::Random)?;
+                (
+                    pi.block_hash,
+                    pi.ep2_last_block_hash,
+                    pi.ep1_first_block_hash,
+                )
+            }
+            BlockProof::Risc0(receipt) => {
+                let journal = PublicValuesRandom::abi_decode(&self.verify_risc0(receipt)?)?;
+                (
+                    journal.current_block_hash,
+                    journal.current_epoch_hash,
+                    journal.previous_epoch_hash,
+                )
+            }
+            BlockProof::Anchored(artifacts) => return self.verify_anchored(artifacts),
+        };
+        if !self
+            .history
+            .iter()
+            .any(|state| state.is_parent_of(&epoch_id, &bp_block_hash))
+        {
+            return Err(BftError::UnknownEpoch(format!(
+                "block {} is proven for epoch {} with validators of block {}",
+                block_hash, epoch_id, bp_block_hash
+            )));
+        }
+        Ok(block_hash)
+    }
+
+    /// Appends the state of the update, if it is proven against the current state.
+    fn advance(&mut self, update: EpochUpdate) -> Result<&TrustedState> {
+        let current = self.state();
+        if update.parent == current.anchors() {
+            self.history.push(update.state);
+            return Ok(self.state());
+        }
+        let Some(pos) = self
+            .history
+            .iter()
+            .position(|state| state.anchors() == update.parent)
+        else {
+            return Err(BftError::UnknownEpoch(format!(
+                "epoch {} is proven against untrusted anchors {:?}",
+                update.state.epoch_id, update.parent
+            )));
+        };
+        let trusted = self.history[pos + 1];
+        if trusted.anchors() == update.state.anchors() {
+            Err(BftError::StaleUpdate(format!(
+                "epoch {} is already trusted",
+                trusted.epoch_id
+            )))
+        } else {
+            Err(BftError::Fork(format!(
+                "Block_0 {} conflicts with trusted Block_0 {} of epoch {}",
+                update.state.bp_block_hash, trusted.bp_block_hash, trusted.epoch_id
+            )))
+        }
+    }
+
+    /// Verifies the plonky2 proof against the trusted circuit of `kind` blocks and returns its
+    /// public inputs. Verifier data in `artifacts` is ignored, it is chosen by the prover.
+    fn verify_plonky2(
+        &self,
+        artifacts: &ProofArtifacts,
+        kind: ProofKind,
+    ) -> Result<BlockProofPublicInputs> {
+        let trusted = self
+            .plonky2_circuits
+            .get(&kind)
+            .ok_or_else(|| BftError::Proving(format!("no trusted circuit of {:?} blocks", kind)))?;
+        let proof =
+            ProofWithPublicInputs::<F, C, D>::from_bytes(artifacts.proof.clone(), &trusted.common)
+                .map_err(|e| BftError::Serialization(format!("Error reading proof: {:#}", e)))?;
+        let public_inputs = BlockProofPublicInputs::decode(&proof.public_inputs)?;
+        trusted
+            .verify(proof)
+            .map_err(|e| BftError::Proving(format!("Proof verification failed: {:#}", e)))?;
+        if public_inputs.kind != kind {
+            return Err(BftError::InvalidInput(format!(
+                "expected a proof of {:?} block, got {:?}",
+                kind, public_inputs.kind
+            )));
+        }
+        Ok(public_inputs)
+    }
+
+    /// Verifies the proof of the block anchored to the epoch chain against the trusted circuit and
+    /// returns the hash of the block. Verifier data in `artifacts` is ignored.
+    fn verify_anchored(&self, artifacts: &ProofArtifacts) -> Result<CryptoHash> {
+        let trusted = self.anchored_circuit.as_ref().ok_or_else(|| {
+            BftError::Proving("no trusted circuit of anchored blocks".to_string())
+        })?;
+        let proof = ProofWithPublicInputs::<F, Cgl, D>::from_bytes(
+            artifacts.proof.clone(),
+            &trusted.common,
+        )
+        .map_err(|e| BftError::Serialization(format!("Error reading proof: {:#}", e)))?;
+        let public_inputs = AnchoredBlockPublicInputs::decode(&proof.public_inputs)?;
+        trusted
+            .verify(proof)
+            .map_err(|e| BftError::Proving(format!("Proof verification failed: {:#}", e)))?;
+        public_inputs.check_genesis(&self.history[0].anchors().commitment())?;
+        Ok(public_inputs.block.block_hash)
+    }
+
+    /// Verifies the risc0 receipt against the trusted image ID and returns its journal.
+    fn verify_risc0(&self, receipt: &[u8]) -> Result<Vec<u8>> {
+        let (verifier, image_id) = self
+            .risc0_verifier
+            .as_ref()
+            .ok_or_else(|| BftError::Proving("risc0 verifier is not set".to_string()))?;
+        verifier.verify(receipt, image_id)
+    }
+}
+
+/// Represents the journal of a random block committed by the risc0 guest (`PublicValuesRandom`).
+///
+/// # Fields
+///
+/// * `current_block_hash` - The hash of the proven block.
+/// * `current_epoch_hash` - The hash of Block_n-1 (Epoch_i-2), i.e. epoch_id of the block.
+/// * `previous_epoch_hash` - The hash of Block_0 (Epoch_i-1), its next_bp_hash is the hash of validators of the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PublicValuesRandom {
+    current_block_hash: CryptoHash,
+    current_epoch_hash: CryptoHash,
+    previous_epoch_hash: CryptoHash,
+}
+
+impl PublicValuesRandom {
+    /// Represents the selector of the journal of a random block.
+    const SELECTOR: u64 = 0;
+
+    /// Decodes the ABI encoding of the journal.
+    fn abi_decode(journal: &[u8]) -> Result<Self> {
+        let [selector, current_block_hash, current_epoch_hash, previous_epoch_hash] =
+            abi_words(journal)?;
+        let selector = uint64_from_word(selector)?;
+        if selector != Self::SELECTOR {
+            return Err(BftError::InvalidInput(format!(
+                "journal has selector {}, expected {}",
+                selector,
+                Self::SELECTOR
+            )));
+        }
+        Ok(Self {
+            current_block_hash: hash_from_word(current_block_hash),
+            current_epoch_hash: hash_from_word(current_epoch_hash),
+            previous_epoch_hash: hash_from_word(previous_epoch_hash),
+        })
+    }
+}
+
+/// Represents the journal of epoch blocks committed by the risc0 guest (`PublicValuesEpoch`).
+///
+/// # Fields
+///
+/// * `current_block_hash` - The hash of Block_0 (Epoch_i).
+/// * `previous_block_hash` - The hash of Block_n-1 (Epoch_i-1).
+/// * `current_block_height` - The height of Block_0 (Epoch_i).
+/// * `previous_block_height` - The height of Block_n-1 (Epoch_i-1).
+/// * `epoch_id` - epoch_id of Block_0 (Epoch_i), i.e. the hash of Block_n-1 (Epoch_i-2).
+/// * `next_epoch_id` - next_epoch_id of Block_0 (Epoch_i), i.e. the hash of Block_n-1 (Epoch_i-1).
+/// * `next_bp_hash` - next_bp_hash of Block_0 (Epoch_i).
+/// * `previous_epoch_id` - The hash of Block_n-1 (Epoch_i-3), i.e. epoch_id of Epoch_i-1.
+/// * `previous_epoch_block_hash` - The hash of Block_0 (Epoch_i-1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PublicValuesEpoch {
+    current_block_hash: CryptoHash,
+    previous_block_hash: CryptoHash,
+    current_block_height: u64,
+    previous_block_height: u64,
+    epoch_id: CryptoHash,
+    next_epoch_id: CryptoHash,
+    next_bp_hash: CryptoHash,
+    previous_epoch_id: CryptoHash,
+    previous_epoch_block_hash: CryptoHash,
+}
+
+impl PublicValuesEpoch {
+    /// Represents the selector of the journal of epoch blocks.
+    const SELECTOR: u64 = 1;
+
+    /// Decodes the ABI encoding of the journal.
+    fn abi_decode(journal: &[u8]) -> Result<Self> {
+        let [selector, current_block_hash, previous_block_hash, current_block_height, previous_block_height, epoch_id, next_epoch_id, next_bp_hash, previous_epoch_id, previous_epoch_block_hash] =
+            abi_words(journal)?;
+        let selector = uint64_from_word(selector)?;
+        if selector != Self::SELECTOR {
+            return Err(BftError::InvalidInput(format!(
+                "journal has selector {}, expected {}",
+                selector,
+                Self::SELECTOR
+            )));
+        }
+        Ok(Self {
+            current_block_hash: hash_from_word(current_block_hash),
+            previous_block_hash: hash_from_word(previous_block_hash),
+            current_block_height: uint64_from_word(current_block_height)?,
+            previous_block_height: uint64_from_word(previous_block_height)?,
+            epoch_id: hash_from_word(epoch_id),
+            next_epoch_id: hash_from_word(next_epoch_id),
+            next_bp_hash: hash_from_word(next_bp_hash),
+            previous_epoch_id: hash_from_word(previous_epoch_id),
+            previous_epoch_block_hash: hash_from_word(previous_epoch_block_hash),
+        })
+    }
+}
+
+/// Splits the ABI encoding of a static struct of `N` fields into words.
+fn abi_words<const N: usize>(journal: &[u8]) -> Result<[&[u8]; N]> {
+    if journal.len() != N * WORD_BYTES {
+        return Err(BftError::Serialization(format!(
+            "journal has {} bytes, expected {}",
+            journal.len(),
+            N * WORD_BYTES
+        )));
+    }
+    let words: Vec<&[u8]> = journal.chunks(WORD_BYTES).collect();
+    Ok(words.try_into().expect("length is checked"))
+}
+
+/// Decodes ABI `bytes32`.
+fn hash_from_word(word: &[u8]) -> CryptoHash {
+    let mut hash = [0u8; PK_HASH_BYTES];
+    hash.copy_from_slice(word);
+    CryptoHash(hash)
+}
+
+/// Decodes ABI `uint32` or `uint64`, which are big-endian and left-padded with zeros.
+fn uint64_from_word(word: &[u8]) -> Result<u64> {
+    let (padding, value) = word.split_at(WORD_BYTES - 8);
+    if padding.iter().any(|byte| *byte != 0) {
+        return Err(BftError::Serialization(format!(
+            "word {} is not uint64",
+            hex::encode(word)
+        )));
+    }
+    Ok(u64::from_be_bytes(value.try_into().expect("8 bytes")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prove_bft::epoch_chain::{
+        verifier_data_hash, AnchoredBlockCircuit, EpochChainCircuit,
+    };
+    use crate::utils::load_block_header;
+    use crate::verify::inspect_artifacts;
+    use anyhow::Result;
+    use near_primitives::block_header::BlockHeaderInnerLite;
+    use near_primitives::types::EpochId;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use plonky2::plonk::config::GenericHashOut;
+    use std::sync::Arc;
+
+    const IMAGE_ID: Risc0ImageId = [7; 8];
+
+    /// Accepts any receipt of `IMAGE_ID`, the receipt is the journal itself.
+    struct JournalVerifier;
+
+    impl Risc0Verifier for JournalVerifier {
+        fn verify(&self, receipt: &[u8], image_id: &Risc0ImageId) -> crate::error::Result<Vec<u8>> {
+            if *image_id != IMAGE_ID {
+                return Err(BftError::Proving(format!(
+                    "unknown image ID {:?}",
+                    image_id
+                )));
+            }
+            Ok(receipt.to_vec())
+        }
+    }
+
+    fn hash(n: u8) -> CryptoHash {
+        CryptoHash([n; 32])
+    }
+
+    fn uint_word(value: u64) -> Vec<u8> {
+        let mut word = vec![0u8; WORD_BYTES - 8];
+        word.extend_from_slice(&value.to_be_bytes());
+        word
+    }
+
+    /// Encodes `PublicValuesRandom` of the block in the epoch following `parent`.
+    fn random_receipt(parent: &TrustedState, block: CryptoHash) -> Vec<u8> {
+        [
+            uint_word(PublicValuesRandom::SELECTOR),
+            block.0.to_vec(),
+            parent.next_epoch_id.0.to_vec(),
+            parent.bp_block_hash.0.to_vec(),
+        ]
+        .concat()
+    }
+
+    /// Encodes `PublicValuesEpoch` of the transition from `parent` to `state`.
+    fn epoch_receipt(parent: &TrustedState, state: &TrustedState) -> Vec<u8> {
+        [
+            uint_word(PublicValuesEpoch::SELECTOR),
+            state.bp_block_hash.0.to_vec(),
+            state.next_epoch_id.0.to_vec(),
+            uint_word(state.height),
+            uint_word(state.height - 1),
+            state.epoch_id.0.to_vec(),
+            state.next_epoch_id.0.to_vec(),
+            state.bp_hash.0.to_vec(),
+            parent.epoch_id.0.to_vec(),
+            parent.bp_block_hash.0.to_vec(),
+        ]
+        .concat()
+    }
+
+    fn genesis() -> TrustedState {
+        TrustedState {
+            epoch_id: hash(1),
+            next_epoch_id: hash(2),
+            bp_hash: hash(12),
+            height: 100,
+            bp_block_hash: hash(3),
+        }
+    }
+
+    /// Returns the header of Block_0 of the epoch following `parent`, with Block_n-1 `block_n_1`
+    /// and validators of the next epoch `bp_hash`. Other fields are taken from a mainnet block.
+    fn block_0_header(
+        parent: &TrustedState,
+        block_n_1: CryptoHash,
+        bp_hash: CryptoHash,
+    ) -> Result<BlockHeader> {
+        let (_, header) = load_block_header("../data/block_header_small.json")?;
+        let edit = |inner_lite: &mut BlockHeaderInnerLite| {
+            inner_lite.height = parent.height + 100;
+            inner_lite.epoch_id = EpochId(parent.next_epoch_id);
+            inner_lite.next_epoch_id = EpochId(block_n_1);
+            inner_lite.next_bp_hash = bp_hash;
+        };
+        // `init` recomputes the hash of the edited header.
+        Ok(match header {
+            BlockHeader::BlockHeaderV1(mut header) => {
+                let header_mut = Arc::make_mut(&mut header);
+                edit(&mut header_mut.inner_lite);
+                header_mut.init();
+                BlockHeader::BlockHeaderV1(header)
+            }
+            BlockHeader::BlockHeaderV2(mut header) => {
+                let header_mut = Arc::make_mut(&mut header);
+                edit(&mut header_mut.inner_lite);
+                header_mut.init();
+                BlockHeader::BlockHeaderV2(header)
+            }
+            BlockHeader::BlockHeaderV3(mut header) => {
+                let header_mut = Arc::make_mut(&mut header);
+                edit(&mut header_mut.inner_lite);
+                header_mut.init();
+                BlockHeader::BlockHeaderV3(header)
+            }
+            BlockHeader::BlockHeaderV4(mut header) => {
+                let header_mut = Arc::make_mut(&mut header);
+                edit(&mut header_mut.inner_lite);
+                header_mut.init();
+                BlockHeader::BlockHeaderV4(header)
+            }
+        })
+    }
+
+    /// Proves a circuit exposing `pi` as public inputs, in place of the final block proof.
+    /// If `forge` is set, the circuit has an extra gate and claims the digest of `forge`.
+    fn prove_circuit(
+        pi: &BlockProofPublicInputs,
+        forge: Option<&VerifierCircuitData<F, C, D>>,
+    ) -> Result<(ProofArtifacts, VerifierCircuitData<F, C, D>)> {
+        let pi = pi.encode::<F>();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = builder.add_virtual_targets(pi.len());
+        builder.register_public_inputs(&targets);
+        if forge.is_some() {
+            let one = builder.one();
+            builder.add(targets[0], one);
+        }
+        let mut data = builder.build::<C>();
+        if let Some(trusted) = forge {
+            data.prover_only.circuit_digest = trusted.verifier_only.circuit_digest;
+            data.verifier_only.circuit_digest = trusted.verifier_only.circuit_digest;
+        }
+        let mut pw = PartialWitness::new();
+        for (target, value) in targets.into_iter().zip(pi) {
+            pw.set_target(target, value);
+        }
+        let proof = data.prove(pw)?;
+        Ok((
+            ProofArtifacts::from_proof(&data, &proof)?,
+            data.verifier_data(),
+        ))
+    }
+
+    fn artifacts(
+        pi: &BlockProofPublicInputs,
+    ) -> Result<(ProofArtifacts, VerifierCircuitData<F, C, D>)> {
+        prove_circuit(pi, None)
+    }
+
+    /// Proves the transition from `parent` to the epoch with Block_n-1 `block_n_1` and validators
+    /// of the next epoch `bp_hash`. Returns the proof, the circuit of epoch blocks & the new state.
+    fn epoch_proof(
+        parent: &TrustedState,
+        block_n_1: CryptoHash,
+        bp_hash: CryptoHash,
+    ) -> Result<(EpochProof, VerifierCircuitData<F, C, D>, TrustedState)> {
+        let anchors = parent.anchors();
+        let block_0_header = block_0_header(parent, block_n_1, bp_hash)?;
+        let block_0_hash = *block_0_header.hash();
+        let (block_0, circuit) = artifacts(&BlockProofPublicInputs::new(
+            ProofKind::Epoch,
+            block_0_hash,
+            anchors.last_block_hash,
+            anchors.first_block_hash,
+        ))?;
+        let (bn_1, _) = artifacts(&BlockProofPublicInputs::new(
+            ProofKind::Epoch,
+            block_n_1,
+            anchors.prev_last_block_hash,
+            anchors.last_block_hash,
+        ))?;
+        let state = TrustedState {
+            epoch_id: parent.next_epoch_id,
+            next_epoch_id: block_n_1,
+            bp_hash,
+            height: parent.height + 100,
+            bp_block_hash: block_0_hash,
+        };
+        Ok((
+            EpochProof::Plonky2 {
+                block_0,
+                block_n_1: bn_1,
+                block_0_header,
+            },
+            circuit,
+            state,
+        ))
+    }
+
+    #[test]
+    fn test_epoch_proofs_advance_state() -> Result<()> {
+        let (proof, circuit, expected) = epoch_proof(&genesis(), hash(5), hash(13))?;
+        let mut client =
+            LightClient::new(genesis()).with_plonky2_circuit(ProofKind::Epoch, circuit);
+        let state = *client.apply_epoch_proof(&proof)?;
+        assert_eq!(state, expected);
+        assert_eq!(
+            (
+                state.epoch_id,
+                state.next_epoch_id,
+                state.bp_hash,
+                state.height
+            ),
+            (hash(2), hash(5), hash(13), 200)
+        );
+        // Replayed proof.
+        assert!(matches!(
+            client.apply_epoch_proof(&proof),
+            Err(BftError::StaleUpdate(_))
+        ));
+        // Another Block_0 proven against the same parent.
+        assert!(matches!(
+            client.apply_epoch_proof(&epoch_proof(&genesis(), hash(5), hash(14))?.0),
+            Err(BftError::Fork(_))
+        ));
+        // The proof skips an epoch.
+        let unknown = TrustedState {
+            epoch_id: hash(7),
+            ..state
+        };
+        assert!(matches!(
+            client.apply_epoch_proof(&epoch_proof(&unknown, hash(9), hash(15))?.0),
+            Err(BftError::UnknownEpoch(_))
+        ));
+        let (next, _, next_state) = epoch_proof(&state, hash(9), hash(15))?;
+        assert_eq!(*client.apply_epoch_proof(&next)?, next_state);
+        assert_eq!(client.history().len(), 3);
+
+        let restored = LightClient::from_history(client.history().to_vec())?;
+        assert_eq!(restored.state(), client.state());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plonky2_epoch_proof_checks_header_of_block_0() -> Result<()> {
+        let (proof, circuit, _) = epoch_proof(&genesis(), hash(5), hash(13))?;
+        let mut client =
+            LightClient::new(genesis()).with_plonky2_circuit(ProofKind::Epoch, circuit);
+        let EpochProof::Plonky2 {
+            block_0, block_n_1, ..
+        } = proof
+        else {
+            unreachable!()
+        };
+        // The header of another Block_0.
+        let other = EpochProof::Plonky2 {
+            block_0: block_0.clone(),
+            block_n_1: block_n_1.clone(),
+            block_0_header: block_0_header(&genesis(), hash(5), hash(14))?,
+        };
+        assert!(matches!(
+            client.apply_epoch_proof(&other),
+            Err(BftError::HashMismatch(_))
+        ));
+        // The header claims the hash of the proven Block_0, but hashes to another one.
+        let mut forged = block_0_header(&genesis(), hash(5), hash(14))?;
+        let BlockHeader::BlockHeaderV4(header) = &mut forged else {
+            panic!("block_header_small.json is a V4 header");
+        };
+        Arc::make_mut(header).hash = *block_0_header(&genesis(), hash(5), hash(13))?.hash();
+        let forged = EpochProof::Plonky2 {
+            block_0,
+            block_n_1,
+            block_0_header: forged,
+        };
+        assert!(matches!(
+            client.apply_epoch_proof(&forged),
+            Err(BftError::HashMismatch(_))
+        ));
+        assert_eq!(client.history().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_risc0_epoch_proofs_advance_state() -> Result<()> {
+        let state = TrustedState {
+            epoch_id: hash(2),
+            next_epoch_id: hash(5),
+            bp_hash: hash(13),
+            height: 200,
+            bp_block_hash: hash(4),
+        };
+        let receipt = epoch_receipt(&genesis(), &state);
+        // No verifier or another guest.
+        assert!(LightClient::new(genesis())
+            .apply_epoch_proof(&EpochProof::Risc0(receipt.clone()))
+            .is_err());
+        assert!(LightClient::new(genesis())
+            .with_risc0_verifier(Box::new(JournalVerifier), [8; 8])
+            .apply_epoch_proof(&EpochProof::Risc0(receipt.clone()))
+            .is_err());
+
+        let mut client =
+            LightClient::new(genesis()).with_risc0_verifier(Box::new(JournalVerifier), IMAGE_ID);
+        assert_eq!(
+            *client.apply_epoch_proof(&EpochProof::Risc0(receipt.clone()))?,
+            state
+        );
+        assert!(matches!(
+            client.apply_epoch_proof(&EpochProof::Risc0(receipt)),
+            Err(BftError::StaleUpdate(_))
+        ));
+        let forked = TrustedState {
+            bp_block_hash: hash(6),
+            ..state
+        };
+        assert!(matches!(
+            client.apply_epoch_proof(&EpochProof::Risc0(epoch_receipt(&genesis(), &forked))),
+            Err(BftError::Fork(_))
+        ));
+        // The journal of a random block.
+        assert!(client
+            .apply_epoch_proof(&EpochProof::Risc0(random_receipt(&state, hash(10))))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_risc0_block_proof_chains_to_known_epoch() -> Result<()> {
+        let state = TrustedState {
+            epoch_id: hash(2),
+            next_epoch_id: hash(5),
+            bp_hash: hash(13),
+            height: 200,
+            bp_block_hash: hash(4),
+        };
+        let client = LightClient::from_history(vec![genesis(), state])?
+            .with_risc0_verifier(Box::new(JournalVerifier), IMAGE_ID);
+        // Blocks of the epoch of the current state & of the next epoch.
+        for parent in [genesis(), state] {
+            let block =
+                client.verify_block_proof(&BlockProof::Risc0(random_receipt(&parent, hash(10))))?;
+            assert_eq!(block, hash(10));
+        }
+        let unknown = TrustedState {
+            bp_block_hash: hash(11),
+            ..state
+        };
+        assert!(matches!(
+            client.verify_block_proof(&BlockProof::Risc0(random_receipt(&unknown, hash(10)))),
+            Err(BftError::UnknownEpoch(_))
+        ));
+        // The journal of another selector.
+        let mut receipt = random_receipt(&state, hash(10));
+        receipt[WORD_BYTES - 1] = 1;
+        assert!(client
+            .verify_block_proof(&BlockProof::Risc0(receipt))
+            .is_err());
+        // The journal of epoch blocks.
+        assert!(client
+            .verify_block_proof(&BlockProof::Risc0(epoch_receipt(&genesis(), &state)))
+            .is_err());
+        // No verifier.
+        assert!(LightClient::new(genesis())
+            .verify_block_proof(&BlockProof::Risc0(random_receipt(&genesis(), hash(10))))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plonky2_proofs() -> Result<()> {
+        let genesis = genesis();
+        let (proof, epoch_circuit, expected) = epoch_proof(&genesis, hash(5), hash(13))?;
+
+        // The circuit is not trusted.
+        assert!(LightClient::new(genesis).apply_epoch_proof(&proof).is_err());
+
+        let mut client =
+            LightClient::new(genesis).with_plonky2_circuit(ProofKind::Epoch, epoch_circuit);
+        let state = *client.apply_epoch_proof(&proof)?;
+        assert_eq!(state, expected);
+        assert!(matches!(
+            client.apply_epoch_proof(&proof),
+            Err(BftError::StaleUpdate(_))
+        ));
+
+        let random_pi = BlockProofPublicInputs::new(
+            ProofKind::Random,
+            hash(10),
+            state.next_epoch_id,
+            state.bp_block_hash,
+        );
+        let (random, random_circuit) = artifacts(&random_pi)?;
+        // The circuit of random blocks is not trusted yet.
+        assert!(matches!(
+            client.verify_block_proof(&BlockProof::Plonky2(random.clone())),
+            Err(BftError::Proving(_))
+        ));
+        // Another circuit claiming the digest of the trusted one.
+        let (forged, _) = prove_circuit(&random_pi, Some(&random_circuit))?;
+        assert_eq!(
+            inspect_artifacts::<F, C, D>(&forged)?.stats.circuit_digest,
+            hex::encode(random_circuit.verifier_only.circuit_digest.to_bytes())
+        );
+        let client = client.with_plonky2_circuit(ProofKind::Random, random_circuit);
+        assert!(matches!(
+            client.verify_block_proof(&BlockProof::Plonky2(forged)),
+            Err(BftError::Proving(_))
+        ));
+        assert_eq!(
+            client.verify_block_proof(&BlockProof::Plonky2(random))?,
+            hash(10)
+        );
+        let (forked, _) = artifacts(&BlockProofPublicInputs::new(
+            ProofKind::Random,
+            hash(10),
+            state.next_epoch_id,
+            hash(11),
+        ))?;
+        assert!(matches!(
+            client.verify_block_proof(&BlockProof::Plonky2(forked)),
+            Err(BftError::UnknownEpoch(_))
+        ));
+        Ok(())
+    }
+
+    /// Proves a circuit exposing `pi` as public inputs in Goldilocks config, in place of a normalized
+    /// block proof.
+    fn goldilocks_proof(
+        pi: &BlockProofPublicInputs,
+    ) -> Result<(CircuitData<F, Cgl, D>, ProofWithPublicInputs<F, Cgl, D>)> {
+        let pi = pi.encode::<F>();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = builder.add_virtual_targets(pi.len());
+        builder.register_public_inputs(&targets);
+        let data = builder.build::<Cgl>();
+        let mut pw = PartialWitness::new();
+        for (target, value) in targets.into_iter().zip(pi) {
+            pw.set_target(target, value);
+        }
+        let proof = data.prove(pw)?;
+        Ok((data, proof))
+    }
+
+    #[test]
+    fn test_anchored_block_proofs() -> Result<()> {
+        // The chain of one epoch from the genesis.
+        let anchors = genesis().anchors();
+        let (b0_data, b0_proof) = goldilocks_proof(&BlockProofPublicInputs::new(
+            ProofKind::Epoch,
+            hash(4),
+            anchors.last_block_hash,
+            anchors.first_block_hash,
+        ))?;
+        let (bn_1_data, bn_1_proof) = goldilocks_proof(&BlockProofPublicInputs::new(
+            ProofKind::Epoch,
+            hash(5),
+            anchors.prev_last_block_hash,
+            anchors.last_block_hash,
+        ))?;
+        let chain = EpochChainCircuit::<F, Cgl, D>::build(&b0_data.common, &bn_1_data.common)?;
+        let chain_proof = chain.prove_step(
+            None,
+            (&b0_data.common, &b0_data.verifier_only, &b0_proof),
+            (&bn_1_data.common, &bn_1_data.verifier_only, &bn_1_proof),
+        )?;
+
+        // A random block of the next epoch.
+        let (block_data, block_proof) = goldilocks_proof(&BlockProofPublicInputs::new(
+            ProofKind::Random,
+            hash(10),
+            hash(5),
+            hash(4),
+        ))?;
+        let anchored = AnchoredBlockCircuit::<F, Cgl, D>::build(
+            &chain.data.verifier_data(),
+            &verifier_data_hash::<F, Cgl, D>(&[&b0_data.verifier_only, &bn_1_data.verifier_only]),
+            (&block_data.common, &block_data.verifier_only),
+        );
+        let proof = anchored.prove(
+            &chain_proof,
+            (&block_data.common, &block_data.verifier_only, &block_proof),
+        )?;
+        let proof = BlockProof::Anchored(ProofArtifacts::from_proof(&anchored.data, &proof)?);
+
+        // The circuit is not trusted.
+        assert!(matches!(
+            LightClient::new(genesis()).verify_block_proof(&proof),
+            Err(BftError::Proving(_))
+        ));
+        // Epochs after the genesis don't have to be trusted.
+        let client =
+            LightClient::new(genesis()).with_anchored_circuit(anchored.data.verifier_data());
+        assert_eq!(client.verify_block_proof(&proof)?, hash(10));
+        // The chain starts from another genesis.
+        let other = TrustedState {
+            bp_block_hash: hash(6),
+            ..genesis()
+        };
+        assert!(matches!(
+            LightClient::new(other)
+                .with_anchored_circuit(anchored.data.verifier_data())
+                .verify_block_proof(&proof),
+            Err(BftError::HashMismatch(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,384 @@
+use crate::checkpoint::Checkpoints;
+use crate::circuits::ConfigName;
+use crate::error::{BftError, Result};
+use crate::input::{validators_bytes, BlockProvingInput, EpochProvingInput};
+use crate::prove_bft::bft::prove_block_bft;
+use crate::prove_bft::scheduler::StageScheduler;
+use crate::prove_block_data::signature_prover::SignatureProver;
+use crate::prove_block_data::signatures::validator_stake;
+use crate::types::{HeaderDataFields, BLOCK_HEIGHT_BYTES, PK_HASH_BYTES, SIG_BYTES, TYPE_BYTE};
+use near_primitives::hash::hash;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::timing::TimingTree;
+use plonky2_field::extension::Extendable;
+use serde::{Deserialize, Serialize};
+
+/// Represents the index of Block_i+1 in `RawProvingInput::blocks`.
+const BLOCK_I_1: usize = 3;
+/// Represents the index of Block_i/Block_0 in `RawProvingInput::blocks`.
+const BLOCK_I: usize = 4;
+/// Represents the index of Block_n-1 (Epoch_i-2) in `RawProvingInput::epoch_blocks`.
+const EP2_LAST_BLOCK: usize = 1;
+/// Represents the offset of epoch_id in a serialized header: type, prev_hash and height precede it.
+const EPOCH_ID_OFFSET: usize = TYPE_BYTE + PK_HASH_BYTES + BLOCK_HEIGHT_BYTES;
+/// Represents the size of a serialized approval: Option type, signature type and signature.
+const APPROVAL_BYTES: usize = TYPE_BYTE + TYPE_BYTE + SIG_BYTES;
+
+/// Represents the input of `prove_block_bft` in the raw form, in which it's mutated.
+///
+/// # Fields
+///
+/// * `epoch_blocks` - [Block_0 (Epoch_i-1), Block_n-1 (Epoch_i-2), Block_n-1 (Epoch_i-3) (for epoch blocks)]
+///   as (borsh(stored_hash), borsh(header)).
+/// * `blocks` - [Block_i+4, Block_i+3, Block_i+2, Block_i+1, Block_i] or
+///   [Block_4, Block_3, Block_2, Block_1, Block_0, Block_n-1] with their header fields.
+/// * `validators` - Serialized validators of Epoch_i.
+/// * `validators_n_1` - Serialized validators of Epoch_i-1, set for epoch blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawProvingInput {
+    pub epoch_blocks: Vec<(Vec<u8>, Vec<u8>)>,
+    pub blocks: Vec<(HeaderDataFields, Vec<u8>)>,
+    pub validators: Vec<Vec<u8>>,
+    pub validators_n_1: Option<Vec<Vec<u8>>>,
+}
+
+impl TryFrom<&BlockProvingInput> for RawProvingInput {
+    type Error = BftError;
+
+    fn try_from(input: &BlockProvingInput) -> Result<Self> {
+        Ok(Self {
+            epoch_blocks: input.epoch_blocks()?,
+            blocks: input.blocks_data()?,
+            validators: validators_bytes(&input.validators)?,
+            validators_n_1: None,
+        })
+    }
+}
+
+impl TryFrom<&EpochProvingInput> for RawProvingInput {
+    type Error = BftError;
+
+    fn try_from(input: &EpochProvingInput) -> Result<Self> {
+        Ok(Self {
+            epoch_blocks: input.epoch_blocks()?,
+            blocks: input.blocks_data()?,
+            validators: validators_bytes(&input.validators)?,
+            validators_n_1: Some(validators_bytes(&input.validators_n_1)?),
+        })
+    }
+}
+
+impl RawProvingInput {
+    /// Proves BFT finality of Block_i, or Block_0 and Block_n-1 for epoch blocks, without checkpoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `signature_prover` - The backend used to prove signatures, e.g. `LocalSignatureProver`.
+    /// * `timing_tree` - A mutable reference to the timing tree.
+    ///
+    /// # Returns
+    ///
+    /// Returns verifier circuit data and the proof of Block_i/Block_0 and, for epoch blocks,
+    /// of Block_n-1.
+    pub fn prove<F, C, const D: usize>(
+        &self,
+        signature_prover: &dyn SignatureProver<F, C, D>,
+        timing_tree: &mut TimingTree,
+    ) -> Result<(
+        (VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>),
+        Option<(VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>,
+    )>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + ConfigName,
+        C::Hasher: AlgebraicHasher<F>,
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        if self.epoch_blocks.len() <= EP2_LAST_BLOCK {
+            return Err(invalid("No Block_n-1(Epochi-2) in epoch blocks"));
+        }
+        let ep3_last_block = self.epoch_blocks.get(EP2_LAST_BLOCK + 1);
+        prove_block_bft::<F, C, D>(
+            &self.epoch_blocks[EP2_LAST_BLOCK].1,
+            &self.epoch_blocks[EP2_LAST_BLOCK].0,
+            &self.epoch_blocks[0].1,
+            &self.epoch_blocks[0].0,
+            ep3_last_block.map(|(_, header)| header.clone()),
+            ep3_last_block.map(|(stored_hash, _)| stored_hash.clone()),
+            self.blocks.clone(),
+            Some(self.validators.clone()),
+            self.validators_n_1.clone(),
+            signature_prover,
+            &Checkpoints::disabled(),
+            &StageScheduler::default(),
+            timing_tree,
+        )
+    }
+}
+
+/// Represents a targeted mutation of a valid proving input. Every mutation must be rejected by
+/// `prove_block_bft`. The native verifier of near_risc0 is tested against the same list of
+/// mutations, see `lib::mutation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mutation {
+    /// Flips one byte of the first approval of Block_i+1, both in its fields and in its header.
+    FlipApprovalByte,
+    /// Swaps the first two validators of Epoch_i.
+    SwapValidators,
+    /// Adds one to the stake of the last validator of Epoch_i.
+    ChangeStake,
+    /// Replaces epoch_id of Block_i with its next_epoch_id, both in its fields and in its header.
+    ReplaceEpochId,
+    /// Replaces prev_hash of Block_i+1, so that it doesn't point to Block_i.
+    BreakPrevHash,
+    /// Drops approvals of Block_i+1 until their stake is below 2/3 of the total stake.
+    DropApprovals,
+    /// Removes the last byte of inner_rest of Block_i.
+    TruncateInnerRest,
+    /// Flips one byte of the stored hash of Block_n-1 (Epoch_i-2).
+    ReplaceStoredHash,
+}
+
+/// Represents all mutations in the order they are applied by tests.
+pub const ALL_MUTATIONS: [Mutation; 8] = [
+    Mutation::FlipApprovalByte,
+    Mutation::SwapValidators,
+    Mutation::ChangeStake,
+    Mutation::ReplaceEpochId,
+    Mutation::BreakPrevHash,
+    Mutation::DropApprovals,
+    Mutation::TruncateInnerRest,
+    Mutation::ReplaceStoredHash,
+];
+
+impl Mutation {
+    /// Applies the mutation to the input of a random or an epoch block.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if the input doesn't contain the data to mutate,
+    /// e.g. approvals of Block_i+1.
+    pub fn apply(&self, input: &mut RawProvingInput) -> Result<()> {
+        if input.blocks.len() <= BLOCK_I || input.epoch_blocks.len() <= EP2_LAST_BLOCK {
+            return Err(invalid("Block_i or Block_n-1(Epochi-2) is missing"));
+        }
+        match self {
+            Mutation::FlipApprovalByte => {
+                let (fields, header) = &mut input.blocks[BLOCK_I_1];
+                let approval = fields
+                    .approvals
+                    .as_mut()
+                    .and_then(|approvals| {
+                        approvals
+                            .iter_mut()
+                            .find(|approval| approval.len() == APPROVAL_BYTES)
+                    })
+                    .ok_or_else(|| invalid("No approvals of Block_i+1"))?;
+                // The header contains the signature without the Option type.
+                let pos = find(header, &approval[TYPE_BYTE..])
+                    .ok_or_else(|| invalid("Approval is not found in the header of Block_i+1"))?;
+                approval[TYPE_BYTE + TYPE_BYTE] ^= 1;
+                header[pos + TYPE_BYTE] ^= 1;
+            }
+            Mutation::SwapValidators => {
+                if input.validators.len() < 2 {
+                    return Err(invalid("Less than two validators"));
+                }
+                input.validators.swap(0, 1);
+            }
+            Mutation::ChangeStake => {
+                let validator = input
+                    .validators
+                    .last_mut()
+                    .ok_or_else(|| invalid("No validators"))?;
+                let stake = (validator_stake(validator) + 1).to_le_bytes();
+                let len = validator.len();
+                validator[(len - stake.len())..].copy_from_slice(&stake);
+            }
+            Mutation::ReplaceEpochId => {
+                let (fields, header) = &mut input.blocks[BLOCK_I];
+                let next_epoch_id = fields
+                    .next_epoch_id
+                    .clone()
+                    .ok_or_else(|| invalid("No next_epoch_id of Block_i"))?;
+                if fields.epoch_id.as_ref() == Some(&next_epoch_id) {
+                    return Err(invalid("epoch_id and next_epoch_id of Block_i are equal"));
+                }
+                header[EPOCH_ID_OFFSET..(EPOCH_ID_OFFSET + PK_HASH_BYTES)]
+                    .copy_from_slice(&next_epoch_id);
+                fields.epoch_id = Some(next_epoch_id);
+            }
+            Mutation::BreakPrevHash => {
+                let (fields, header) = &mut input.blocks[BLOCK_I_1];
+                let prev_hash = fields
+                    .prev_hash
+                    .as_ref()
+                    .ok_or_else(|| invalid("No prev_hash of Block_i+1"))?;
+                let prev_hash = hash(prev_hash).0;
+                header[TYPE_BYTE..(TYPE_BYTE + PK_HASH_BYTES)].copy_from_slice(&prev_hash);
+                fields.prev_hash = Some(prev_hash.to_vec());
+            }
+            Mutation::DropApprovals => {
+                let validators = &input.validators;
+                let approvals = input.blocks[BLOCK_I_1]
+                    .0
+                    .approvals
+                    .as_mut()
+                    .ok_or_else(|| invalid("No approvals of Block_i+1"))?;
+                let total_stake: u128 = validators.iter().map(|v| validator_stake(v)).sum();
+                let mut approved_stake: u128 = approvals
+                    .iter()
+                    .zip(validators)
+                    .filter(|(approval, _)| approval.len() == APPROVAL_BYTES)
+                    .map(|(_, validator)| validator_stake(validator))
+                    .sum();
+                for (approval, validator) in approvals.iter_mut().zip(validators) {
+                    if approved_stake * 3 < total_stake * 2 {
+                        break;
+                    }
+                    if approval.len() == APPROVAL_BYTES {
+                        approved_stake -= validator_stake(validator);
+                    }
+                    // Serialized None.
+                    *approval = vec![0];
+                }
+            }
+            Mutation::TruncateInnerRest => {
+                let header = &mut input.blocks[BLOCK_I].1;
+                if header.len() <= EPOCH_ID_OFFSET + TYPE_BYTE + SIG_BYTES {
+                    return Err(invalid("Header of Block_i is too short"));
+                }
+                header.remove(header.len() - TYPE_BYTE - SIG_BYTES - 1);
+            }
+            Mutation::ReplaceStoredHash => {
+                input.epoch_blocks[EP2_LAST_BLOCK].0[0] ^= 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns a copy of the input with the mutation applied.
+pub fn mutate(input: &RawProvingInput, mutation: Mutation) -> Result<RawProvingInput> {
+    let mut mutated = input.clone();
+    mutation.apply(&mut mutated)?;
+    Ok(mutated)
+}
+
+/// Returns the position of `needle` in `data`.
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn invalid(msg: &str) -> BftError {
+    BftError::InvalidInput(msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prove_block_data::signature_prover::LocalSignatureProver;
+    use crate::utils::load_block_input;
+    use anyhow::Result;
+    use log::{info, Level};
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const EPOCHS_PATH: &str = "../data/epochs";
+    const EPOCH_ID_I: &str = "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae";
+    const EPOCH_ID_I_1: &str = "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi";
+    const EPOCH_ID_I_2: &str = "89PT9SkLXB1FZHvW7EdQHxiSpm5ybuTCvjrGZWWhXMTz";
+
+    fn valid_input() -> Result<RawProvingInput> {
+        let input = load_block_input(EPOCHS_PATH, EPOCH_ID_I, EPOCH_ID_I_1, EPOCH_ID_I_2)?;
+        Ok(RawProvingInput::try_from(&input)?)
+    }
+
+    #[test]
+    fn test_every_mutation_changes_input() -> Result<()> {
+        let input = valid_input()?;
+        for mutation in ALL_MUTATIONS {
+            let mutated = mutate(&input, mutation)?;
+            assert_ne!(mutated, input, "{:?} doesn't change the input", mutation);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_approvals_leaves_less_than_2_3_of_stakes() -> Result<()> {
+        let input = mutate(&valid_input()?, Mutation::DropApprovals)?;
+        let total_stake: u128 = input.validators.iter().map(|v| validator_stake(v)).sum();
+        let approved_stake: u128 = input.blocks[BLOCK_I_1]
+            .0
+            .approvals
+            .as_ref()
+            .expect("No approvals")
+            .iter()
+            .zip(&input.validators)
+            .filter(|(approval, _)| approval.len() == APPROVAL_BYTES)
+            .map(|(_, validator)| validator_stake(validator))
+            .sum();
+        assert!(approved_stake * 3 < total_stake * 2);
+        Ok(())
+    }
+
+    /// Returns true if `error` is the rejection of `mutation` by `prove_block_bft`.
+    fn is_expected_error(mutation: Mutation, error: &BftError) -> bool {
+        match mutation {
+            // Changed headers and stored hashes don't match their hashes.
+            Mutation::FlipApprovalByte
+            | Mutation::ReplaceEpochId
+            | Mutation::BreakPrevHash
+            | Mutation::TruncateInnerRest
+            | Mutation::ReplaceStoredHash => matches!(error, BftError::HashMismatch(_)),
+            // Changed validators don't match bp_hash.
+            Mutation::ChangeStake => matches!(error, BftError::HashMismatch(_)),
+            // Approvals of swapped validators are excluded before bp_hash is checked, so the rest
+            // may give less than 2/3 of stakes.
+            Mutation::SwapValidators => matches!(
+                error,
+                BftError::HashMismatch(_) | BftError::InsufficientStake { .. }
+            ),
+            Mutation::DropApprovals => matches!(error, BftError::InsufficientStake { .. }),
+        }
+    }
+
+    #[test]
+    fn test_prove_block_bft_accepts_valid_input() -> Result<()> {
+        let mut timing = TimingTree::new("prove valid input", Level::Info);
+        let ((data, proof), bn_1) =
+            valid_input()?.prove::<F, C, D>(&LocalSignatureProver, &mut timing)?;
+        assert!(bn_1.is_none());
+        data.verify(proof)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_block_bft_rejects_every_mutation() -> Result<()> {
+        let input = valid_input()?;
+        for mutation in ALL_MUTATIONS {
+            let mutated = mutate(&input, mutation)?;
+            let mut timing = TimingTree::new(&format!("prove {:?}", mutation), Level::Info);
+            match mutated.prove::<F, C, D>(&LocalSignatureProver, &mut timing) {
+                Ok(_) => panic!("{:?} is accepted by prove_block_bft", mutation),
+                Err(e) => {
+                    assert!(
+                        is_expected_error(mutation, &e),
+                        "{:?} is rejected with unexpected error: {}",
+                        mutation,
+                        e
+                    );
+                    info!("{:?} is rejected: {}", mutation, e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+use crate::error::{BftError, Result};
+use crate::light_client::TrustedState;
+use crate::store::ProofStore;
+use crate::types::EPOCH_DURATION;
+use near_primitives::hash::CryptoHash;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Represents the environment variable with the path to the JSON file of the network profile.
+pub const NETWORK_CONFIG_ENV: &str = "NETWORK_CONFIG";
+/// Represents the environment variable with the name of a built-in network profile.
+pub const NEAR_NETWORK_ENV: &str = "NEAR_NETWORK";
+/// Represents the environment variable that overrides the RPC endpoint of the network profile.
+pub const NEAR_RPC_ENV: &str = "NEAR_RPC";
+/// Represents the directory with JSON files of built-in network profiles.
+pub const DEFAULT_NETWORKS_PATH: &str = "../data/networks";
+
+/// Represents the trusted epoch the chain of proofs starts from. Its epoch blocks are never proven,
+/// so their hashes are trusted as stored hashes (see `NetworkProfile::stored_hash`).
+///
+/// # Fields
+///
+/// * `epoch_id` - epoch_id of Epoch_i, i.e. the hash of Block_n-1 (Epoch_i-2).
+/// * `next_epoch_id` - epoch_id of Epoch_i+1, i.e. the hash of Block_n-1 (Epoch_i-1).
+/// * `block_hash` - The hash of Block_0 (Epoch_i).
+/// * `bp_hash` - next_bp_hash of Block_0 (Epoch_i), i.e. the hash of validators of Epoch_i+1.
+/// * `height` - The height of Block_0 (Epoch_i), the first last known height of the epoch follower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisAnchor {
+    pub epoch_id: CryptoHash,
+    pub next_epoch_id: CryptoHash,
+    pub block_hash: CryptoHash,
+    pub bp_hash: CryptoHash,
+    pub height: u64,
+}
+
+impl GenesisAnchor {
+    /// Returns the initial state of the light client.
+    pub fn trusted_state(&self) -> TrustedState {
+        TrustedState {
+            epoch_id: self.epoch_id,
+            next_epoch_id: self.next_epoch_id,
+            bp_hash: self.bp_hash,
+            height: self.height,
+            bp_block_hash: self.block_hash,
+        }
+    }
+
+    /// Returns true if the block is one of the anchored epoch blocks.
+    pub fn contains(&self, block_hash: &CryptoHash) -> bool {
+        [self.epoch_id, self.next_epoch_id, self.block_hash].contains(block_hash)
+    }
+}
+
+/// Represents the parameters of a NEAR network the provers run against.
+///
+/// # Fields
+///
+/// * `chain_id` - The chain id of the network, e.g. `mainnet`.
+/// * `epoch_length` - The number of heights in an epoch.
+/// * `rpc_url` - The URL of the RPC endpoint for recent blocks.
+/// * `archival_rpc_url` - The URL of the archival RPC endpoint for old blocks and validators.
+/// * `genesis` - The trusted epoch the chain of proofs starts from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkProfile {
+    pub chain_id: String,
+    pub epoch_length: u64,
+    pub rpc_url: String,
+    pub archival_rpc_url: String,
+    #[serde(default)]
+    pub genesis: Option<GenesisAnchor>,
+}
+
+impl Default for NetworkProfile {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+impl NetworkProfile {
+    /// Creates the profile of NEAR mainnet.
+    pub fn mainnet() -> Self {
+        Self {
+            chain_id: "mainnet".to_string(),
+            epoch_length: EPOCH_DURATION,
+            rpc_url: "https://rpc.mainnet.near.org".to_string(),
+            archival_rpc_url: "https://archival-rpc.mainnet.near.org".to_string(),
+            genesis: None,
+        }
+    }
+
+    /// Creates the profile of NEAR testnet.
+    pub fn testnet() -> Self {
+        Self {
+            chain_id: "testnet".to_string(),
+            epoch_length: 43_200,
+            rpc_url: "https://rpc.testnet.near.org".to_string(),
+            archival_rpc_url: "https://archival-rpc.testnet.near.org".to_string(),
+            genesis: None,
+        }
+    }
+
+    /// Creates the profile of a local node started by `neard init`, which doesn't keep archival data
+    /// separately and has short epochs.
+    pub fn localnet() -> Self {
+        Self {
+            chain_id: "localnet".to_string(),
+            epoch_length: 500,
+            rpc_url: "http://127.0.0.1:3030".to_string(),
+            archival_rpc_url: "http://127.0.0.1:3030".to_string(),
+            genesis: None,
+        }
+    }
+
+    /// Returns the built-in profile by the chain id.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if there is no built-in profile of the network.
+    pub fn named(chain_id: &str) -> Result<Self> {
+        match chain_id {
+            "mainnet" => Ok(Self::mainnet()),
+            "testnet" => Ok(Self::testnet()),
+            "localnet" => Ok(Self::localnet()),
+            _ => Err(BftError::InvalidInput(format!(
+                "Unknown network {chain_id}, expected mainnet, testnet or localnet"
+            ))),
+        }
+    }
+
+    /// Loads the profile from the JSON file, e.g. `data/networks/localnet.json`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let profile: Self = serde_json::from_slice(&fs::read(path)?)?;
+        if profile.epoch_length == 0 {
+            return Err(BftError::InvalidInput(format!(
+                "Epoch length of {} must be positive",
+                profile.chain_id
+            )));
+        }
+        Ok(profile)
+    }
+
+    /// Returns the profile set in the environment: the file in `NETWORK_CONFIG` or the built-in profile
+    /// named in `NEAR_NETWORK`. `NEAR_RPC` overrides the RPC endpoint of the profile.
+    /// Returns `None` if none of them is set.
+    pub fn from_env_if_set() -> Result<Option<Self>> {
+        let mut profile = match (env::var(NETWORK_CONFIG_ENV), env::var(NEAR_NETWORK_ENV)) {
+            (Ok(path), _) => Self::load(path)?,
+            (_, Ok(chain_id)) => Self::named(&chain_id)?,
+            _ if env::var(NEAR_RPC_ENV).is_ok() => Self::mainnet(),
+            _ => return Ok(None),
+        };
+        if let Ok(url) = env::var(NEAR_RPC_ENV) {
+            profile.rpc_url = url;
+        }
+        Ok(Some(profile))
+    }
+
+    /// Returns the profile set in the environment (see `from_env_if_set`) or mainnet.
+    pub fn from_env() -> Result<Self> {
+        Ok(Self::from_env_if_set()?.unwrap_or_default())
+    }
+
+    /// Returns heights of Block_n-1 (Epoch_i) and Block_0 (Epoch_i+1) if no heights are skipped,
+    /// where `first_block_height` is the height of Block_0 (Epoch_i).
+    pub fn next_epoch_heights(&self, first_block_height: u64) -> (u64, u64) {
+        let next_first_block_height = first_block_height + self.epoch_length;
+        (next_first_block_height - 1, next_first_block_height)
+    }
+
+    /// Returns the hash of the epoch block stored in the contract. Blocks of the genesis anchor
+    /// aren't proven, so their hashes are returned as is.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if the epoch block wasn't proven and isn't anchored.
+    pub fn stored_hash(
+        &self,
+        store: &dyn ProofStore,
+        block_hash: &CryptoHash,
+    ) -> Result<CryptoHash> {
+        match self.genesis {
+            Some(genesis) if genesis.contains(block_hash) => Ok(*block_hash),
+            _ => Ok(store.stored_hash(block_hash)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::InMemoryProofStore;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_builtin_profiles_match_files() -> Result<()> {
+        // near_risc0 parses the same files, so every file must be a built-in profile of both crates.
+        let mut chain_ids = vec![];
+        for entry in fs::read_dir(DEFAULT_NETWORKS_PATH)? {
+            let path = entry?.path();
+            let profile = NetworkProfile::load(&path)?;
+            assert_eq!(path.file_stem(), Some(profile.chain_id.as_ref()));
+            assert_eq!(profile, NetworkProfile::named(&profile.chain_id)?);
+            chain_ids.push(profile.chain_id);
+        }
+        chain_ids.sort();
+        assert_eq!(chain_ids, ["localnet", "mainnet", "testnet"]);
+        assert!(NetworkProfile::named("betanet").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_profile_with_genesis() -> Result<()> {
+        let hash = |s: &str| CryptoHash::from_str(s).map_err(|e| anyhow::anyhow!("{e}"));
+        let json = r#"{
+            "chain_id": "localnet",
+            "epoch_length": 60,
+            "rpc_url": "http://127.0.0.1:3030",
+            "archival_rpc_url": "http://127.0.0.1:3030",
+            "genesis": {
+                "epoch_id": "89PT9SkLXB1FZHvW7EdQHxiSpm5ybuTCvjrGZWWhXMTz",
+                "next_epoch_id": "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi",
+                "block_hash": "4H927QKMVXLw3LzVB1eQaXzC39Rkq8ZWJvWY74V66NER",
+                "bp_hash": "4H927QKMVXLw3LzVB1eQaXzC39Rkq8ZWJvWY74V66NER",
+                "height": 61
+            }
+        }"#;
+        let path = env::temp_dir().join(format!("network_{:016x}.json", rand::random::<u64>()));
+        fs::write(&path, json)?;
+        let profile = NetworkProfile::load(&path)?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(profile.next_epoch_heights(61), (120, 121));
+        let genesis = profile.genesis.expect("No genesis");
+        assert_eq!(genesis.trusted_state().height, 61);
+        // Anchored blocks aren't proven.
+        let store = InMemoryProofStore::new();
+        let anchored = hash("3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi")?;
+        assert_eq!(profile.stored_hash(&store, &anchored)?, anchored);
+        let other = hash("HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae")?;
+        assert!(profile.stored_hash(&store, &other).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,540 @@
+use crate::checkpoint::Checkpoints;
+use crate::error::{BftError, Result};
+use crate::gnark::GnarkArtifacts;
+use crate::input::{BlockProvingInput, EpochProvingInput};
+use crate::prove_bft::epoch_chain::{
+    verifier_data_hash, AnchoredBlockCircuit, EpochAnchors, EpochChainCircuit,
+    EpochChainPublicInputs, EPOCH_PROOF_DEGREE_BITS,
+};
+use crate::prove_bft::scheduler::{StageScheduler, DEFAULT_MEMORY_BUDGET};
+use crate::prove_block_data::signature_prover::SignatureProver;
+use crate::prove_crypto::recursion::{expose_all, expose_public_inputs, normalize_proof};
+use crate::public_inputs::BlockProofPublicInputs;
+use crate::store::{write_atomic, Artifacts, ProofArtifacts, ProofKind, ProofRecord, ProofStore};
+use crate::verify::{read_artifacts, ArtifactsFormat};
+use log::info;
+use near_primitives::block_header::BlockHeader;
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::timed;
+use plonky2::util::timing::TimingTree;
+use plonky2_bn128::config::PoseidonBN128GoldilocksConfig;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+// Final proofs are wrapped in BN128 config before they are stored.
+type Cbn128 = PoseidonBN128GoldilocksConfig;
+
+/// Represents the directory in the proof store with the proof of the epoch chain.
+pub const EPOCH_CHAIN_DIR: &str = "chain";
+/// Represents the file in `EPOCH_CHAIN_DIR` with the number of epochs of the last written step.
+/// Steps are written to `{dir}/{num_epochs}`, the file is replaced atomically after the step is
+/// written, so an interrupted write never corrupts the last step.
+pub const EPOCH_CHAIN_LATEST_FILE: &str = "latest";
+/// Represents the directory in `EPOCH_CHAIN_DIR` with proofs of random blocks anchored to the chain,
+/// `{dir}/anchored/{block_hash}`.
+pub const ANCHORED_DIR: &str = "anchored";
+
+/// Represents the stage wrapping the proof of the random Block_i in BN128 config.
+pub(crate) const BLOCK_I_BN128_STAGE: &str = "block_i_bn128";
+/// Represents the stage wrapping the proof of Block_0 (Epoch_i) in BN128 config.
+pub(crate) const BLOCK_0_BN128_STAGE: &str = "block_0_bn128";
+/// Represents the stage wrapping the proof of Block_n-1 (Epoch_i-1) in BN128 config.
+pub(crate) const BLOCK_N_1_BN128_STAGE: &str = "block_n_1_bn128";
+
+/// Represents the final proof stored by the pipeline.
+///
+/// # Fields
+///
+/// * `record` - The record of the proven block in the proof store.
+/// * `artifacts` - The BN128-wrapped proof and its circuit data.
+/// * `public_inputs` - The decoded public inputs of the proof.
+#[derive(Debug, Clone)]
+pub struct StoredProof {
+    pub record: ProofRecord,
+    pub artifacts: ProofArtifacts,
+    pub public_inputs: BlockProofPublicInputs,
+}
+
+/// Represents the plonky2 proving pipeline: proves the input, wraps final proofs in BN128 config,
+/// stores them and optionally exports them for gnark-plonky2-verifier, extends the epoch chain and
+/// anchors random blocks to it.
+///
+/// # Fields
+///
+/// * `signature_prover` - Proves signatures of validators, locally or by workers.
+/// * `store` - The store of final proofs.
+/// * `checkpoints` - Checkpoints of intermediate proofs, disabled by default.
+/// * `scheduler` - Runs independent stages concurrently within the memory budget.
+/// * `gnark_dir` - The directory to export final proofs for gnark-plonky2-verifier.
+/// * `epoch_chain_dir` - The directory with the proof of the epoch chain (IVC mode).
+/// * `epoch_chain` - The circuit of the epoch chain, built for the first proven epoch and reused
+///   for the next ones.
+pub struct ProvingPipeline {
+    signature_prover: Arc<dyn SignatureProver<F, C, D>>,
+    store: Arc<dyn ProofStore>,
+    checkpoints: Checkpoints,
+    scheduler: StageScheduler,
+    gnark_dir: Option<PathBuf>,
+    epoch_chain_dir: Option<PathBuf>,
+    epoch_chain: Mutex<Option<EpochChainCircuit<F, C, D>>>,
+}
+
+impl ProvingPipeline {
+    /// Creates the pipeline without checkpoints, gnark export and the epoch chain.
+    pub fn new(
+        signature_prover: Arc<dyn SignatureProver<F, C, D>>,
+        store: Arc<dyn ProofStore>,
+    ) -> Self {
+        Self {
+            signature_prover,
+            store,
+            checkpoints: Checkpoints::disabled(),
+            scheduler: StageScheduler::new(DEFAULT_MEMORY_BUDGET),
+            gnark_dir: None,
+            epoch_chain_dir: None,
+            epoch_chain: Mutex::new(None),
+        }
+    }
+
+    /// Resumes interrupted runs from `checkpoints`.
+    pub fn with_checkpoints(mut self, checkpoints: Checkpoints) -> Self {
+        self.checkpoints = checkpoints;
+        self
+    }
+
+    /// Runs independent stages concurrently while their estimated memory fits `memory_budget`
+    /// bytes.
+    pub fn with_memory_budget(mut self, memory_budget: usize) -> Self {
+        self.scheduler = StageScheduler::new(memory_budget);
+        self
+    }
+
+    /// Writes final proofs in the input format of gnark-plonky2-verifier to `{dir}/{block_hash}`.
+    pub fn with_gnark_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.gnark_dir = Some(dir.into());
+        self
+    }
+
+    /// Extends the epoch chain stored in `dir` with each proven epoch (IVC mode) and anchors proven
+    /// random blocks of the next epoch to it (see `AnchoredBlockCircuit`).
+    pub fn with_epoch_chain(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.epoch_chain_dir = Some(dir.into());
+        self
+    }
+
+    /// Returns the store of final proofs.
+    pub fn store(&self) -> &dyn ProofStore {
+        self.store.as_ref()
+    }
+
+    /// Proves the randomly selected Block_i and stores its final proof.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if proving fails or the proof can't be stored.
+    pub fn prove_block(
+        &self,
+        input: &BlockProvingInput,
+        timing_tree: &mut TimingTree,
+    ) -> Result<StoredProof> {
+        // Finished stages of an interrupted run with the same input are read from checkpoints.
+        let checkpoints = input.checkpoints(&self.checkpoints)?;
+        let (bi_data, bi_proof) = input.prove::<F, C, D>(
+            self.signature_prover.as_ref(),
+            &checkpoints,
+            &self.scheduler,
+            timing_tree,
+        )?;
+        let stored = self.wrap_and_store(
+            ProofKind::Random,
+            &input.blocks[0],
+            &checkpoints,
+            BLOCK_I_BN128_STAGE,
+            (&bi_data, &bi_proof),
+            timing_tree,
+        )?;
+        if let Some(dir) = &self.epoch_chain_dir {
+            anchor_block(dir, (&bi_data, &bi_proof), timing_tree)?;
+        }
+        // The proof is stored, the run is finished.
+        checkpoints.clear()?;
+        Ok(stored)
+    }
+
+    /// Proves Block_0 (Epoch_i) & Block_n-1 (Epoch_i-1) and stores their final proofs.
+    /// Their hashes become stored hashes for proofs of the next epochs.
+    ///
+    /// # Returns
+    ///
+    /// Returns stored proofs of Block_0 and Block_n-1.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if proving fails or proofs can't be stored.
+    pub fn prove_epoch(
+        &self,
+        input: &EpochProvingInput,
+        timing_tree: &mut TimingTree,
+    ) -> Result<(StoredProof, StoredProof)> {
+        // Finished stages of an interrupted run with the same input are read from checkpoints.
+        let checkpoints = input.checkpoints(&self.checkpoints)?;
+        let ((b0_data, b0_proof), (bn_1_data, bn_1_proof)) = input.prove::<F, C, D>(
+            self.signature_prover.as_ref(),
+            &checkpoints,
+            &self.scheduler,
+            timing_tree,
+        )?;
+        let b0 = self.wrap_and_store(
+            ProofKind::Epoch,
+            &input.blocks[0],
+            &checkpoints,
+            BLOCK_0_BN128_STAGE,
+            (&b0_data, &b0_proof),
+            timing_tree,
+        )?;
+        let bn_1 = self.wrap_and_store(
+            ProofKind::Epoch,
+            &input.ep1_last_block,
+            &checkpoints,
+            BLOCK_N_1_BN128_STAGE,
+            (&bn_1_data, &bn_1_proof),
+            timing_tree,
+        )?;
+        // The chain is extended after proofs are stored, a rerun resumes from checkpoints and
+        // doesn't extend the chain with the same epoch twice.
+        if let Some(dir) = &self.epoch_chain_dir {
+            let anchors = EpochAnchors::of_epoch_proofs(&b0.public_inputs, &bn_1.public_inputs)
+                .next(&b0.public_inputs, &bn_1.public_inputs);
+            prove_epoch_chain_step(
+                dir,
+                &mut self.epoch_chain.lock().unwrap(),
+                anchors,
+                (&b0_data, &b0_proof),
+                (&bn_1_data, &bn_1_proof),
+                timing_tree,
+            )?;
+        }
+        // Proofs are stored, the run is finished.
+        checkpoints.clear()?;
+        Ok((b0, bn_1))
+    }
+
+    /// Wraps the proof of the block in BN128 config, stores it and exports it for gnark.
+    fn wrap_and_store(
+        &self,
+        kind: ProofKind,
+        header: &BlockHeader,
+        checkpoints: &Checkpoints,
+        stage: &str,
+        (data, proof): (
+            &VerifierCircuitData<F, C, D>,
+            &ProofWithPublicInputs<F, C, D>,
+        ),
+        timing_tree: &mut TimingTree,
+    ) -> Result<StoredProof> {
+        let (w_data, w_proof) = wrap_final_proof(checkpoints, stage, (data, proof), timing_tree)?;
+        let record = ProofRecord::new(kind, header);
+        let artifacts = ProofArtifacts::from_verifier_data(&w_data, &w_proof)?;
+        self.store.put(&record, &artifacts)?;
+        info!("Proof of {:?} block {} is stored", kind, record.block_hash);
+        if let Some(dir) = &self.gnark_dir {
+            let dir = dir.join(record.block_hash.to_string());
+            GnarkArtifacts::from_verifier_data(&w_data, &w_proof)?.write_dir(&dir)?;
+            info!(
+                "Proof for gnark-plonky2-verifier is written to {}",
+                dir.display()
+            );
+        }
+        let public_inputs = BlockProofPublicInputs::decode(&w_proof.public_inputs)?;
+        info!(
+            "Final PI {:?}, version {:?}",
+            public_inputs.kind, public_inputs.version
+        );
+        info!("Current block hash: {}", public_inputs.block_hash);
+        info!(
+            "Epoch_i-2 block hash: {}",
+            public_inputs.ep2_last_block_hash
+        );
+        info!(
+            "Epoch_i-1 block hash: {}",
+            public_inputs.ep1_first_block_hash
+        );
+        Ok(StoredProof {
+            record,
+            artifacts,
+            public_inputs,
+        })
+    }
+}
+
+/// Wraps the final proof of a block in BN128 config, exposing all its public inputs. The wrapped
+/// proof is stored in `checkpoints` as the stage `stage`.
+pub(crate) fn wrap_final_proof(
+    checkpoints: &Checkpoints,
+    stage: &str,
+    (data, proof): (
+        &VerifierCircuitData<F, C, D>,
+        &ProofWithPublicInputs<F, C, D>,
+    ),
+    timing_tree: &mut TimingTree,
+) -> Result<(
+    VerifierCircuitData<F, Cbn128, D>,
+    ProofWithPublicInputs<F, Cbn128, D>,
+)> {
+    checkpoints.stage(stage, || {
+        timed!(
+            timing_tree,
+            "aggregate final proof using BN128 config",
+            expose_public_inputs::<F, Cbn128, C, D>(
+                (&data.common, &data.verifier_only, proof),
+                &expose_all(proof.public_inputs.len()),
+            )
+        )
+    })
+}
+
+/// Proves the next step of the epoch chain (IVC) with proofs of Block_0 (Epoch_i)
+/// & Block_n-1 (Epoch_i-1), normalized to `EPOCH_PROOF_DEGREE_BITS` (see `normalize_proof`).
+/// The proof of the previous step is read from `dir`, the new one replaces it (see
+/// `write_epoch_chain_step`). The chain is started, if there is no proof of the previous step.
+/// The step is skipped, if the chain already ends with `anchors` of this epoch.
+///
+/// Normalized proofs of all epochs have the same circuits, so the chain circuit is built once and
+/// kept in `chain`. It is rebuilt only if normalized circuits differ from the ones it is built for.
+fn prove_epoch_chain_step(
+    dir: &Path,
+    chain: &mut Option<EpochChainCircuit<F, C, D>>,
+    anchors: EpochAnchors,
+    b0: (
+        &VerifierCircuitData<F, C, D>,
+        &ProofWithPublicInputs<F, C, D>,
+    ),
+    bn_1: (
+        &VerifierCircuitData<F, C, D>,
+        &ProofWithPublicInputs<F, C, D>,
+    ),
+    timing_tree: &mut TimingTree,
+) -> Result<()> {
+    // Circuits of epoch proofs depend on the number of validators, the chain verifies normalized proofs.
+    let (b0_data, b0_proof) = timed!(
+        timing_tree,
+        "normalize proof of Block_0",
+        normalize_proof::<F, C, D>(
+            (&b0.0.common, &b0.0.verifier_only, b0.1),
+            EPOCH_PROOF_DEGREE_BITS,
+        )?
+    );
+    let (bn_1_data, bn_1_proof) = timed!(
+        timing_tree,
+        "normalize proof of Block_n-1",
+        normalize_proof::<F, C, D>(
+            (&bn_1.0.common, &bn_1.0.verifier_only, bn_1.1),
+            EPOCH_PROOF_DEGREE_BITS,
+        )?
+    );
+    if !chain
+        .as_ref()
+        .is_some_and(|chain| chain.is_built_for(&b0_data.common, &bn_1_data.common))
+    {
+        *chain = Some(timed!(
+            timing_tree,
+            "build epoch chain circuit",
+            EpochChainCircuit::<F, C, D>::build(&b0_data.common, &bn_1_data.common)?
+        ));
+    }
+    let chain = chain.as_ref().expect("the chain circuit is built above");
+    let previous = match latest_epoch_chain_step(dir)? {
+        Some(step_dir) => Some(
+            ProofWithPublicInputs::<F, C, D>::from_bytes(
+                fs::read(step_dir.join("proof.bin"))?,
+                &chain.data.common,
+            )
+            .map_err(|e| {
+                BftError::Serialization(format!("Error reading epoch chain proof: {:#}", e))
+            })?,
+        ),
+        None => None,
+    };
+    if let Some(previous) = &previous {
+        let pi = EpochChainPublicInputs::<F>::decode(&previous.public_inputs)?;
+        if pi.anchors == anchors {
+            info!(
+                "Epoch chain already ends with Block_0 {}, {} epochs",
+                anchors.first_block_hash, pi.num_epochs
+            );
+            return Ok(());
+        }
+    }
+    let proof = timed!(
+        timing_tree,
+        "prove the next step of epoch chain",
+        chain.prove_step(
+            previous.as_ref(),
+            (&b0_data.common, &b0_data.verifier_only, &b0_proof),
+            (&bn_1_data.common, &bn_1_data.verifier_only, &bn_1_proof),
+        )?
+    );
+    // The new step continues the chain from the same genesis, the first step starts it from anchors
+    // of its epoch proofs.
+    let genesis = match &previous {
+        Some(previous) => EpochChainPublicInputs::<F>::decode(&previous.public_inputs)?.genesis,
+        None => EpochAnchors::of_epoch_proofs(
+            &BlockProofPublicInputs::decode(&b0_proof.public_inputs)?,
+            &BlockProofPublicInputs::decode(&bn_1_proof.public_inputs)?,
+        )
+        .commitment(),
+    };
+    let epoch_verifier_hash =
+        verifier_data_hash::<F, C, D>(&[&b0_data.verifier_only, &bn_1_data.verifier_only]);
+    let pi = chain.verify(&proof, &genesis, &epoch_verifier_hash)?;
+    info!(
+        "Epoch chain: {} epochs, last Block_n-1 {}, last Block_0 {}",
+        pi.num_epochs, pi.anchors.last_block_hash, pi.anchors.first_block_hash
+    );
+    write_epoch_chain_step(
+        dir,
+        pi.num_epochs,
+        &ProofArtifacts::from_proof(&chain.data, &proof)?,
+    )
+}
+
+/// Anchors the final proof of a random block to the last step of the epoch chain in `dir` and writes
+/// the anchored proof to `{dir}/anchored/{block_hash}`. The block proof is normalized to
+/// `EPOCH_PROOF_DEGREE_BITS` first, so blocks of all epochs are anchored by the same circuit.
+/// Only blocks of the epoch next to the last proven one can be anchored, other blocks are skipped.
+fn anchor_block(
+    dir: &Path,
+    block: (
+        &VerifierCircuitData<F, C, D>,
+        &ProofWithPublicInputs<F, C, D>,
+    ),
+    timing_tree: &mut TimingTree,
+) -> Result<()> {
+    let block_pi = BlockProofPublicInputs::decode(&block.1.public_inputs)?;
+    let Some(step_dir) = latest_epoch_chain_step(dir)? else {
+        info!(
+            "Epoch chain isn't started, block {} isn't anchored",
+            block_pi.block_hash
+        );
+        return Ok(());
+    };
+    let (chain_data, chain_proof) = read_artifacts::<F, C, D>(
+        &ProofArtifacts::read_dir(&step_dir)?,
+        ArtifactsFormat::Binary,
+    )?;
+    let chain_pi = EpochChainPublicInputs::<F>::decode(&chain_proof.public_inputs)?;
+    if !chain_pi.anchors.is_parent_of(&block_pi) {
+        info!(
+            "Block {} isn't a block of the epoch after Block_0 {} of the chain, it isn't anchored",
+            block_pi.block_hash, chain_pi.anchors.first_block_hash
+        );
+        return Ok(());
+    }
+    let (block_data, block_proof) = timed!(
+        timing_tree,
+        "normalize proof of Block_i",
+        normalize_proof::<F, C, D>(
+            (&block.0.common, &block.0.verifier_only, block.1),
+            EPOCH_PROOF_DEGREE_BITS,
+        )?
+    );
+    let anchored = timed!(
+        timing_tree,
+        "build anchored block circuit",
+        AnchoredBlockCircuit::<F, C, D>::build(
+            &chain_data,
+            &chain_pi.epoch_verifier_hash,
+            (&block_data.common, &block_data.verifier_only),
+        )
+    );
+    let proof = timed!(
+        timing_tree,
+        "anchor Block_i to epoch chain",
+        anchored.prove(
+            &chain_proof,
+            (&block_data.common, &block_data.verifier_only, &block_proof),
+        )?
+    );
+    anchored.verify(&proof, &chain_pi.genesis)?;
+    let anchored_dir = dir.join(ANCHORED_DIR).join(block_pi.block_hash.to_string());
+    ProofArtifacts::from_proof(&anchored.data, &proof)?.write_dir(&anchored_dir)?;
+    info!(
+        "Block {} is anchored to the epoch chain of {} epochs, the proof is written to {}",
+        block_pi.block_hash,
+        chain_pi.num_epochs,
+        anchored_dir.display()
+    );
+    Ok(())
+}
+
+/// Returns the directory of the last written step of the epoch chain in `dir`, `None` if the chain
+/// isn't started.
+fn latest_epoch_chain_step(dir: &Path) -> Result<Option<PathBuf>> {
+    let latest_path = dir.join(EPOCH_CHAIN_LATEST_FILE);
+    if !latest_path.exists() {
+        return Ok(None);
+    }
+    let num_epochs = fs::read_to_string(&latest_path)?;
+    let num_epochs = num_epochs.trim().parse::<u64>().map_err(|e| {
+        BftError::Serialization(format!("Invalid {}: {}", latest_path.display(), e))
+    })?;
+    Ok(Some(dir.join(num_epochs.to_string())))
+}
+
+/// Writes the step of the epoch chain to `{dir}/{num_epochs}`, then atomically points
+/// `EPOCH_CHAIN_LATEST_FILE` to it and removes the previous step.
+/// If the write is interrupted, the previous step stays the last one.
+fn write_epoch_chain_step(dir: &Path, num_epochs: u64, artifacts: &ProofArtifacts) -> Result<()> {
+    let previous = latest_epoch_chain_step(dir)?;
+    let step_dir = dir.join(num_epochs.to_string());
+    artifacts.write_dir(&step_dir)?;
+    write_atomic(
+        &dir.join(EPOCH_CHAIN_LATEST_FILE),
+        num_epochs.to_string().as_bytes(),
+    )?;
+    if let Some(previous) = previous.filter(|previous| *previous != step_dir) {
+        fs::remove_dir_all(previous)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::env;
+
+    fn artifacts(proof: &[u8]) -> ProofArtifacts {
+        ProofArtifacts {
+            proof: proof.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_write_epoch_chain_step() -> Result<()> {
+        let dir = env::temp_dir().join(format!("epoch_chain_{:016x}", rand::random::<u64>()));
+        assert!(latest_epoch_chain_step(&dir)?.is_none());
+
+        write_epoch_chain_step(&dir, 1, &artifacts(b"step 1"))?;
+        write_epoch_chain_step(&dir, 2, &artifacts(b"step 2"))?;
+        let latest = latest_epoch_chain_step(&dir)?.unwrap();
+        assert_eq!(latest, dir.join("2"));
+        assert_eq!(ProofArtifacts::read_dir(&latest)?.proof, b"step 2");
+        // The previous step is removed once the new one is the last.
+        assert!(!dir.join("1").exists());
+
+        // A step interrupted before the pointer is replaced isn't read.
+        artifacts(b"step 3").write_dir(dir.join("3"))?;
+        assert_eq!(latest_epoch_chain_step(&dir)?.unwrap(), dir.join("2"));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -1,17 +1,44 @@
+use crate::checkpoint::Checkpoints;
+use crate::circuits::ConfigName;
+use crate::error::{BftError, Result};
 use crate::prove_bft::block_finality::*;
-use crate::prove_block_data::{signatures::generate_signed_message, primitives::prove_eq_array};
-use crate::prove_crypto::recursion::recursive_proof;
+use crate::prove_bft::scheduler::{StageGraph, StageId, StageScheduler, GIB};
+use crate::prove_block_data::{
+    primitives::prove_eq_array, signature_prover::SignatureProver,
+    signatures::generate_signed_message,
+};
+use crate::prove_crypto::recursion::{expose_public_inputs, recursive_proof};
+use crate::public_inputs::{
+    hash_from_pi, height_from_pi, BlockProofPublicInputs, HEADER_HASH_RANGE, HEADER_HEIGHT_RANGE,
+    HEADER_PREV_HASH_RANGE,
+};
+use crate::store::ProofKind;
 use crate::types::*;
-use anyhow::Result;
-use near_primitives::hash::CryptoHash;
 use plonky2::hash::hash_types::RichField;
-use plonky2::plonk::circuit_data::CircuitData;
+use plonky2::plonk::circuit_data::{
+    CircuitData, CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
+};
 use plonky2::plonk::config::Hasher;
 use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
 use plonky2::plonk::proof::ProofWithPublicInputs;
 use plonky2::timed;
 use plonky2::util::timing::TimingTree;
 use plonky2_field::extension::Extendable;
+use plonky2_field::types::PrimeField64;
+use std::sync::Arc;
+
+/// Represents the estimated peak memory of proving a block header.
+const HEADER_MEMORY: usize = 2 * GIB;
+/// Represents the estimated peak memory of a recursive proof or a proof of heights.
+const RECURSION_MEMORY: usize = GIB;
+/// Represents the estimated peak memory of proving signatures of approvals, keys & stakes.
+const SIGNATURES_MEMORY: usize = 8 * GIB;
+/// Represents the estimated peak memory of proving block finality.
+const FINALITY_MEMORY: usize = 4 * GIB;
+
+/// Represents the output of a proving stage.
+type StageProof<F, C, const D: usize> =
+    (VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>);
 
 /// Prove BFT finality of the block. The function may be used for both epoch & randomly selected blocks.
 ///
@@ -28,6 +55,11 @@ use plonky2_field::extension::Extendable;
 ///              It is in the following form: [Block_4, Block_3, Block_2, Block_1, Block_0, Block_n-1], when proving epoch blocks.
 /// * `validators` - A list of validators that contains public keys & stakes for Epochi.
 /// * `validators_n_1` - A list of validators that contains public keys & stakes for Epochi-1.
+/// * `checkpoints` - Checkpoints of this run (see `Checkpoints::run`). Header proofs, the aggregate of signatures,
+///                   the proof of heights and the finality proofs are stored as stages, so an interrupted run
+///                   is resumed from the last finished stage.
+/// * `scheduler` - Runs independent stages (headers, signatures, heights) concurrently within its memory budget.
+///                 The timing tree gets the critical path of stages.
 ///
 /// # Returns
 ///
@@ -35,7 +67,16 @@ use plonky2_field::extension::Extendable;
 /// * one proof when proving ramdomly selected block.
 /// * two proofs when proving epoch blocks Block_0 & Block_n-1.
 ///
-pub fn prove_block_bft<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+/// # Errors
+///
+/// Returns `BftError::InvalidInput` if the number of blocks is not 5 or 6, some data required for the chosen
+/// mode is missing or has an unexpected length. Errors of inner proofs (hash mismatch, 2/3 of stakes, etc.) are propagated.
+///
+pub fn prove_block_bft<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + ConfigName,
+    const D: usize,
+>(
     // Block_n-1(Epochi-2) from RPC.
     ep2_last_block_bytes: &[u8],
     // Extracted from contract.
@@ -53,513 +94,610 @@ pub fn prove_block_bft<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     validators: Option<Vec<Vec<u8>>>,
     // List of validators for Block_n-1 (when proving epoch blocks).
     validators_n_1: Option<Vec<Vec<u8>>>,
-    client: Option<nats::Connection>,
+    // Backend used to prove signatures of approvals.
+    signature_prover: &dyn SignatureProver<F, C, D>,
+    // Checkpoints of the run, finished stages are read from them.
+    checkpoints: &Checkpoints,
+    // Runs independent stages concurrently.
+    scheduler: &StageScheduler,
     timing_tree: &mut TimingTree,
 ) -> Result<(
-    (CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>),
-    Option<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>,
+    (VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>),
+    Option<(VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>,
 )>
 where
     C::Hasher: AlgebraicHasher<F>,
     [(); C::Hasher::HASH_SIZE]:,
 {
-    // Prove Block_n-1(Epochi-2). Set its hash & bp_hash as PI. 
-    // Hash is used to prove epoch_id of the current block.
-    // If this function proves epoch blocks {Bn-1, B0}, then this proof proves epoch_id for B0 and bp_hash (list of validators) for Bn-1.
-    let (ep2_lb_data, ep2_lb_proof) = prove_block_header::<F, C, D>(
-        ep2_last_block_hash_bytes,
-        ep2_last_block_bytes,
-        None,
-        None,
-        None,
-        None,
-        None,
-        Some(
-            ep2_last_block_bytes[(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES
-                - PK_HASH_BYTES
-                - PK_HASH_BYTES)
-                ..(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES - PK_HASH_BYTES)]
-                .to_vec(),
-        ),
-        None,
-        timing_tree,
-    )?;
-    // Prove Block_0(Epochi-1). Set its hash & bp_hash as PI. 
-    // Its bp_hash tehe list of validators for Bi or B0, if this function proves epoch blocks {Bn-1, B0}.
-    let (mut ep1_fb_data, mut ep1_fb_proof) = prove_block_header::<F, C, D>(
-        ep1_first_block_hash_bytes,
-        ep1_first_block_bytes,
-        None,
-        None,
-        None,
-        None,
-        None,
-        Some(
-            ep1_first_block_bytes[(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES
-                - PK_HASH_BYTES
-                - PK_HASH_BYTES)
-                ..(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES - PK_HASH_BYTES)]
-                .to_vec(),
-        ),
-	Some(
-            ep1_first_block_bytes[(TYPE_BYTE + PK_HASH_BYTES + BLOCK_HEIGHT_BYTES + PK_HASH_BYTES)
-                ..(TYPE_BYTE + PK_HASH_BYTES + BLOCK_HEIGHT_BYTES + PK_HASH_BYTES + PK_HASH_BYTES)]
-                .to_vec(),
-        ),
-        timing_tree,
-    )?;
-    // Prove next_epoch_id of Block_0(Epochi-1) and hash of Block_n-1(Epochi-2).
-    let ep2_lb_hash: Vec<u8> = ep2_lb_proof.public_inputs[0..32]
-        .iter()
-        .map(|x| x.to_canonical_u64() as u8)
-        .collect();
-    let ep1_fb_neph_bytes: Vec<u8> = ep1_fb_proof.public_inputs[(ep1_fb_proof.public_inputs.len() - 32)..]
-	.iter()
-        .map(|x| x.to_canonical_u64() as u8)
-        .collect();
-    let ep1_fb_neph = CryptoHash(ep1_fb_neph_bytes.clone().try_into().unwrap());
-    let (neph_data, neph_proof) = timed!(
-        timing_tree,
-        "prove next_epoch_id",
-        prove_eq_array::<F, C, D>(&ep2_lb_hash, &ep1_fb_neph_bytes)?
-    );
-    (ep1_fb_data, ep1_fb_proof) = timed!(
-        timing_tree,
-        "verify proof of Block_n-1(Epochi-2)",
-        recursive_proof::<F, C, C, D>(
-            (&ep1_fb_data.common, &ep1_fb_data.verifier_only, &ep1_fb_proof),
-            Some((
-                &neph_data.common,
-                &neph_data.verifier_only,
-                &neph_proof
-            )),
-            Some(&ep1_fb_proof.public_inputs),
-        )?
-    );
-    
-    assert!(blocks.len() > 0);
-    // Prove Block_i+4.
-    let (b4_data, b4_proof) = prove_block_header::<F, C, D>(
-        &blocks[0].0.hash,
-        &blocks[0].1,
-        blocks[0].0.height.clone(),
-        blocks[0].0.epoch_id.clone(),
-        blocks[0].0.prev_hash.clone(),
-        None,
-        None,
-        None,
-        None,
-        timing_tree,
-    )?;
-    // Prove Block_i+3.
-    let (b3_data, b3_proof) = prove_block_header::<F, C, D>(
-        &blocks[1].0.hash,
-        &blocks[1].1,
-        blocks[1].0.height.clone(),
-        blocks[1].0.epoch_id.clone(),
-        blocks[1].0.prev_hash.clone(),
-        None,
-        None,
-        None,
-        None,
-        timing_tree,
-    )?;
-    // Prove Block_i+2.
-    let (mut b2_data, mut b2_proof) = prove_block_header::<F, C, D>(
-        &blocks[2].0.hash,
-        &blocks[2].1,
-        blocks[2].0.height.clone(),
-        blocks[2].0.epoch_id.clone(),
-        blocks[2].0.prev_hash.clone(),
-        blocks[2].0.last_ds_final_hash.clone(),
-        blocks[2].0.last_final_hash.clone(),
-        None,
-        None,
-        timing_tree,
-    )?;
-    // Prove consecutive heights for Block_i+2, Block_i+3, Block_i+4.
-    let (ch_data, ch_proof) = prove_consecutive_heights_proofs::<F, C, D>([
-        (b4_data.common, b4_data.verifier_only, b4_proof),
-        (b3_data.common, b3_data.verifier_only, b3_proof),
-        (b2_data.common.clone(), b2_data.verifier_only.clone(), b2_proof.clone()),
-    ].to_vec())?;
-    // Aggregate proofs for heights & Bi+2.
-    (b2_data, b2_proof) = recursive_proof::<F, C, C, D>(
-        (&b2_data.common, &b2_data.verifier_only, &b2_proof),
-        Some((&ch_data.common, &ch_data.verifier_only, &ch_proof)),
-        Some(&b2_proof.public_inputs),
-    )?;
+    const MIN_BLOCK_BYTES: usize = TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES;
+    if blocks.len() != 5 && blocks.len() != 6 {
+        return Err(BftError::InvalidInput(format!(
+            "Invalid blocks.len() {}",
+            blocks.len()
+        )));
+    }
+    if ep2_last_block_bytes.len() < MIN_BLOCK_BYTES || ep1_first_block_bytes.len() < MIN_BLOCK_BYTES
+    {
+        return Err(BftError::InvalidInput(
+            "epoch block header is too short".to_string(),
+        ));
+    }
+    if validators.is_none() {
+        return Err(BftError::InvalidInput("No list of validators.".to_string()));
+    }
+    if blocks.len() == 6
+        && (ep3_last_block_bytes.is_none()
+            || ep3_last_block_hash_bytes.is_none()
+            || validators_n_1.is_none())
+    {
+        return Err(BftError::InvalidInput(
+            "No Block_n-1(Epochi-3) or list of validators for Epochi-1 to prove epoch blocks."
+                .to_string(),
+        ));
+    }
+    let ep3_last_block = ep3_last_block_hash_bytes.zip(ep3_last_block_bytes);
+    let blocks = &blocks;
+    let validators = &validators;
+    let validators_n_1 = &validators_n_1;
+    let ep3_last_block = &ep3_last_block;
+    let mut graph = StageGraph::new();
+    // Headers of Block_i+1 and Block_i/{Block_0 & Block_n-1} are added first, since signatures, the most
+    // expensive stages, depend only on them. The scheduler starts ready stages in the order they are added.
     // Prove header for Block_i+1.
-    let (b1_data, b1_proof) = prove_block_header::<F, C, D>(
-        &blocks[3].0.hash,
-        &blocks[3].1,
-        blocks[3].0.height.clone(),
-        blocks[3].0.epoch_id.clone(),
-        blocks[3].0.prev_hash.clone(),
-        blocks[3].0.last_ds_final_hash.clone(),
-        blocks[3].0.last_final_hash.clone(),
-        None,
-        None,
-        timing_tree,
-    )?;
+    let b1 = add_stage(
+        &mut graph,
+        checkpoints,
+        "block_1_header",
+        &[],
+        HEADER_MEMORY,
+        move |_, timing_tree| {
+            prove_block_header::<F, C, D>(
+                &blocks[3].0.hash,
+                &blocks[3].1,
+                blocks[3].0.height,
+                blocks[3].0.epoch_id.clone(),
+                blocks[3].0.prev_hash.clone(),
+                blocks[3].0.last_ds_final_hash.clone(),
+                blocks[3].0.last_final_hash.clone(),
+                None,
+                None,
+                timing_tree,
+            )
+        },
+    );
     // Prove header(s) for Block_i/{Block_0 & Block_n-1} to check their heights before proving their finality.
-    let ((bi0_header_data, bi0_header_proof), bn_1_header_data_proof) = match blocks.len() {
+    let (bi0, bn_1) = match blocks.len() {
         // Prove ramdomly selected block.
         5 => {
-            let (bi_header_data, bi_header_proof) = prove_block_header::<F, C, D>(
-                &blocks[4].0.hash,
-                &blocks[4].1,
-                blocks[4].0.height.clone(),
-                blocks[4].0.epoch_id.clone(),
-                None,
-                None,
+            let bi = add_stage(
+                &mut graph,
+                checkpoints,
+                "block_i_header",
+                &[],
+                HEADER_MEMORY,
+                move |_, timing_tree| {
+                    prove_block_header::<F, C, D>(
+                        &blocks[4].0.hash,
+                        &blocks[4].1,
+                        blocks[4].0.height,
+                        blocks[4].0.epoch_id.clone(),
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        timing_tree,
+                    )
+                },
+            );
+            (bi, None)
+        }
+        // Prove epoch blocks.
+        _ => {
+            let b0 = add_stage(
+                &mut graph,
+                checkpoints,
+                "block_0_header",
+                &[],
+                HEADER_MEMORY,
+                move |_, timing_tree| {
+                    prove_block_header::<F, C, D>(
+                        &blocks[4].0.hash,
+                        &blocks[4].1,
+                        blocks[4].0.height,
+                        blocks[4].0.epoch_id.clone(),
+                        blocks[4].0.prev_hash.clone(),
+                        blocks[4].0.last_ds_final_hash.clone(),
+                        None,
+                        None,
+                        None,
+                        timing_tree,
+                    )
+                },
+            );
+            let bn_1 = add_stage(
+                &mut graph,
+                checkpoints,
+                "block_n_1_header",
+                &[],
+                HEADER_MEMORY,
+                move |_, timing_tree| {
+                    prove_block_header::<F, C, D>(
+                        &blocks[5].0.hash,
+                        &blocks[5].1,
+                        blocks[5].0.height,
+                        blocks[5].0.epoch_id.clone(),
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        timing_tree,
+                    )
+                },
+            );
+            (b0, Some(bn_1))
+        }
+    };
+    let (block_i_0, kind) = match bn_1 {
+        None => ("block_i", ProofKind::Random),
+        Some(_) => ("block_0", ProofKind::Epoch),
+    };
+    // Prove signatures of approvals for Block_i/Block_0 extracted from Block_i+1.
+    let bi0_signatures = add_stage(
+        &mut graph,
+        checkpoints,
+        &format!("{}_signatures", block_i_0),
+        &[b1, bi0],
+        SIGNATURES_MEMORY,
+        move |deps, timing_tree| {
+            prove_block_signatures::<F, C, D>(
+                &signed_message(&deps[0].1, &deps[1].1)?,
+                blocks[3].0.approvals.clone(),
+                validators.clone(),
+                signature_prover,
+                timing_tree,
+            )
+        },
+    );
+    // Prove Block_n-1(Epochi-2). Set its hash & bp_hash as PI.
+    // Hash is used to prove epoch_id of the current block.
+    // If this function proves epoch blocks {Bn-1, B0}, then this proof proves epoch_id for B0 and bp_hash (list of validators) for Bn-1.
+    let ep2 = add_stage(
+        &mut graph,
+        checkpoints,
+        "ep2_last_block_header",
+        &[],
+        HEADER_MEMORY,
+        move |_, timing_tree| {
+            prove_block_header::<F, C, D>(
+                ep2_last_block_hash_bytes,
+                ep2_last_block_bytes,
                 None,
                 None,
                 None,
-                timing_tree,
-            )?;
-            ((bi_header_data, bi_header_proof), None)
-        }
-        // Prove epoch blocks.
-        6 => {
-            let (b0_header_data, b0_header_proof) = prove_block_header::<F, C, D>(
-                &blocks[4].0.hash,
-                &blocks[4].1,
-                blocks[4].0.height.clone(),
-                blocks[4].0.epoch_id.clone(),
-                blocks[4].0.prev_hash.clone(),
-                blocks[4].0.last_ds_final_hash.clone(),
                 None,
                 None,
+                Some(
+                    ep2_last_block_bytes[(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES
+                        - PK_HASH_BYTES
+                        - PK_HASH_BYTES)
+                        ..(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES - PK_HASH_BYTES)]
+                        .to_vec(),
+                ),
                 None,
                 timing_tree,
-            )?;
-            let (bn_1_header_data, bn_1_header_proof) = prove_block_header::<F, C, D>(
-                &blocks[5].0.hash,
-                &blocks[5].1,
-                blocks[5].0.height.clone(),
-                blocks[5].0.epoch_id.clone(),
+            )
+        },
+    );
+    // Prove Block_0(Epochi-1). Set its hash & bp_hash as PI.
+    // Its bp_hash tehe list of validators for Bi or B0, if this function proves epoch blocks {Bn-1, B0}.
+    let ep1_fb = add_stage(
+        &mut graph,
+        checkpoints,
+        "ep1_first_block_header",
+        &[],
+        HEADER_MEMORY,
+        move |_, timing_tree| {
+            prove_block_header::<F, C, D>(
+                ep1_first_block_hash_bytes,
+                ep1_first_block_bytes,
                 None,
                 None,
                 None,
                 None,
                 None,
+                Some(
+                    ep1_first_block_bytes[(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES
+                        - PK_HASH_BYTES
+                        - PK_HASH_BYTES)
+                        ..(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES - PK_HASH_BYTES)]
+                        .to_vec(),
+                ),
+                Some(
+                    ep1_first_block_bytes[(TYPE_BYTE
+                        + PK_HASH_BYTES
+                        + BLOCK_HEIGHT_BYTES
+                        + PK_HASH_BYTES)
+                        ..(TYPE_BYTE
+                            + PK_HASH_BYTES
+                            + BLOCK_HEIGHT_BYTES
+                            + PK_HASH_BYTES
+                            + PK_HASH_BYTES)]
+                        .to_vec(),
+                ),
                 timing_tree,
-            )?;
-            (
-                (b0_header_data, b0_header_proof),
-                Some((bn_1_header_data, bn_1_header_proof)),
             )
-        }
-        _ => {
-            panic!("Invalid blocks.len() {}", blocks.len());
-        }
-    };
-    // Prove heights Block_i+2, Block_i+1, Block_i/{Block_0 & Block_n-1}.
-    // This proof is optional, since its absence does not affect the proof of block finality. 
-    // It is an additional check of finality, in the case when the heights are consecutive. 
-    let h1_bytes: Vec<u8> = b2_proof.public_inputs[32..40]
-        .iter()
-        .map(|x| x.to_canonical_u64() as u8)
-        .collect();
-    let h2_bytes: Vec<u8> = b1_proof.public_inputs[32..40]
-        .iter()
-        .map(|x| x.to_canonical_u64() as u8)
-        .collect();
-    let h3_bytes: Vec<u8> = bi0_header_proof.public_inputs[32..40]
-        .iter()
-        .map(|x| x.to_canonical_u64() as u8)
-        .collect();
-    let h1 = u64::from_le_bytes(h1_bytes.try_into().unwrap());
-    let h2 = u64::from_le_bytes(h2_bytes.try_into().unwrap());
-    let h3 = u64::from_le_bytes(h3_bytes.try_into().unwrap());
-    let consecutive_heights = match bn_1_header_data_proof.clone() {
-        Some((b_n_1_data, b_n_1_proof)) => {
-            let h4_bytes: Vec<u8> = b_n_1_proof.public_inputs[32..40]
-                .iter()
-                .map(|x| x.to_canonical_u64() as u8)
-                .collect();
-            let h4 = u64::from_le_bytes(h4_bytes.try_into().unwrap());
-            if (h1 + 1) == h2 && (h2 + 1) == h3 && (h3 + 1) == h4 {
-                let (data, proof) = prove_consecutive_heights_proofs(
-                    [
-                        (b2_data.common.clone(), b2_data.verifier_only.clone(), b2_proof.clone()),
-                        (b1_data.common.clone(), b1_data.verifier_only.clone(), b1_proof.clone()),
-                        (bi0_header_data.common.clone(), bi0_header_data.verifier_only.clone(), bi0_header_proof.clone()),
-                        (b_n_1_data.common.clone(), b_n_1_data.verifier_only.clone(), b_n_1_proof.clone()),
-                    ]
-                    .to_vec(),
-                )?;
-		Some((data.common, data.verifier_only, proof))
-            }
-            else {
-                None
-            }
-        }
-        None => {
-            if (h1 + 1) == h2 && (h2 + 1) == h3 {
-                let (data, proof) = prove_consecutive_heights_proofs(
-                    [
-                        (b2_data.common.clone(), b2_data.verifier_only.clone(), b2_proof.clone()),
-                        (b1_data.common.clone(), b1_data.verifier_only.clone(), b1_proof.clone()),
-                        (bi0_header_data.common.clone(), bi0_header_data.verifier_only.clone(), bi0_header_proof.clone()),
-                    ]
-                    .to_vec(),
-                )?;
-		Some((data.common, data.verifier_only, proof))
-            }
-            else {
-                None
-            }
-        }
-    };
-    // Prove BFT. Since this function proves both epoch and randomly selected blocks, b_n_1_data_proof is optional, i.e. it is used for epoch blocks.
-    let ((b_i_0_data, b_i_0_proof), b_n_1_data_proof) = match blocks.len() {
-        // Prove ramdomly selected block.
-        5 => {
-            // Next block prev_hash.
-            let nb_prev_hash: Vec<u8> = b1_proof.public_inputs[72..104]
-                .iter()
-                .map(|x| x.to_canonical_u64() as u8)
-                .collect();
-            let nb_prev_hash = CryptoHash(nb_prev_hash.try_into().unwrap());
-            // Next block height.
-            let nb_height_bytes: Vec<u8> = b1_proof.public_inputs[32..40]
+        },
+    );
+    // Prove next_epoch_id of Block_0(Epochi-1) and hash of Block_n-1(Epochi-2).
+    let ep1_fb = add_stage(
+        &mut graph,
+        checkpoints,
+        "ep1_first_block_next_epoch_id",
+        &[ep2, ep1_fb],
+        RECURSION_MEMORY,
+        move |deps, timing_tree| {
+            let (ep2_lb_proof, (ep1_fb_data, ep1_fb_proof)) = (&deps[0].1, &*deps[1]);
+            let ep2_lb_hash: Vec<u8> = ep2_lb_proof.public_inputs[HEADER_HASH_RANGE]
                 .iter()
                 .map(|x| x.to_canonical_u64() as u8)
                 .collect();
-            let nb_height = u64::from_le_bytes(nb_height_bytes.try_into().unwrap());
-            // Current block height.
-            let cb_height_bytes: Vec<u8> = bi0_header_proof.public_inputs[32..40]
+            let ep1_fb_neph_bytes: Vec<u8> = ep1_fb_proof.public_inputs
+                [(ep1_fb_proof.public_inputs.len() - 32)..]
                 .iter()
                 .map(|x| x.to_canonical_u64() as u8)
                 .collect();
-            let cb_height = u64::from_le_bytes(cb_height_bytes.try_into().unwrap());
-            // Message to sign.
-            let msg_to_sign = generate_signed_message(cb_height, nb_height, nb_prev_hash);
-            let (bi_data, bi_proof) = prove_block_finality::<F, C, D>(
-                (
-                    &bi0_header_data.common,
-                    &bi0_header_data.verifier_only,
-                    &bi0_header_proof,
-                ),
-                Some(msg_to_sign),
-                blocks[3].0.approvals.clone(),
-                validators.clone(),
-                [
-                    (
-                        ep2_lb_data.common.clone(),
-                        ep2_lb_data.verifier_only.clone(),
-                        ep2_lb_proof.clone(),
-                    ),
-                    (
-                        ep1_fb_data.common.clone(),
-                        ep1_fb_data.verifier_only.clone(),
-                        ep1_fb_proof.clone(),
-                    ),
-                    (
-                        b1_data.common.clone(),
-                        b1_data.verifier_only.clone(),
-                        b1_proof.clone(),
-                    ),
-                    (
-                        b2_data.common.clone(),
-                        b2_data.verifier_only.clone(),
-                        b2_proof.clone(),
-                    ),
-                ]
-                .to_vec(),
-                consecutive_heights.clone(),
-                client.clone(),
+            let (neph_data, neph_proof) = timed!(
                 timing_tree,
-            )?;
-            // Set three hashes: of the current block, Bn-1 and B0, as PI in Block_i.
-            let len = bi_proof.public_inputs.len();
-            let mut pi = vec![];
-            pi.push(F::ZERO);
-            pi.append(&mut bi_proof.public_inputs[0..32].to_vec().clone());
-            pi.append(&mut bi_proof.public_inputs[(len - 64)..].to_vec().clone());
-            let (bi_data, bi_proof) = timed!(
+                "prove next_epoch_id",
+                prove_eq_array::<F, C, D>(&ep2_lb_hash, &ep1_fb_neph_bytes)?
+            );
+            timed!(
                 timing_tree,
-                "recursion for Block_0 to set three hashes as PI",
+                "verify proof of Block_n-1(Epochi-2)",
                 recursive_proof::<F, C, C, D>(
-                    (&bi_data.common, &bi_data.verifier_only, &bi_proof),
-                    None,
-                    Some(&pi),
-                )?
-            );
-            ((bi_data, bi_proof), None)
-        }
-        // Prove epoch blocks.
-        6 => {
-            // Next block prev_hash.
-            let nb_prev_hash: Vec<u8> = b1_proof.public_inputs[72..104]
-                .iter()
-                .map(|x| x.to_canonical_u64() as u8)
-                .collect();
-            let nb_prev_hash = CryptoHash(nb_prev_hash.try_into().unwrap());
-            // Next block height.
-            let nb_height_bytes: Vec<u8> = b1_proof.public_inputs[32..40]
-                .iter()
-                .map(|x| x.to_canonical_u64() as u8)
-                .collect();
-            let nb_height = u64::from_le_bytes(nb_height_bytes.try_into().unwrap());
-            // Current block height.
-            let cb_height_bytes: Vec<u8> = bi0_header_proof.public_inputs[32..40]
-                .iter()
-                .map(|x| x.to_canonical_u64() as u8)
-                .collect();
-            let cb_height = u64::from_le_bytes(cb_height_bytes.try_into().unwrap());
-            // Message to sign.
-            let msg_to_sign = generate_signed_message(cb_height, nb_height, nb_prev_hash);
-            // Prove Block_0.
-            let (b0_data, b0_proof) = prove_block_finality::<F, C, D>(
-                (
-                    &bi0_header_data.common,
-                    &bi0_header_data.verifier_only,
-                    &bi0_header_proof,
-                ),
-                Some(msg_to_sign),
-                blocks[3].0.approvals.clone(),
-                validators.clone(),
-                [
-                    (
-                        ep2_lb_data.common.clone(),
-                        ep2_lb_data.verifier_only.clone(),
-                        ep2_lb_proof.clone(),
-                    ),
-                    (
-                        ep1_fb_data.common.clone(),
-                        ep1_fb_data.verifier_only.clone(),
-                        ep1_fb_proof.clone(),
-                    ),
                     (
-                        b1_data.common.clone(),
-                        b1_data.verifier_only.clone(),
-                        b1_proof.clone(),
+                        &ep1_fb_data.common,
+                        &ep1_fb_data.verifier_only,
+                        ep1_fb_proof
                     ),
-                    (
-                        b2_data.common.clone(),
-                        b2_data.verifier_only.clone(),
-                        b2_proof.clone(),
-                    ),
-                ]
-                .to_vec(),
-                consecutive_heights.clone(),
-                client.clone(),
-                timing_tree,
-            )?;
-            // Prove epoch_id block for Block_n-1.
-            let ep3_last_block_hash_bytes = ep3_last_block_hash_bytes.expect(
-                "No hash for Block_n-1(Epochi-3) to prove epoch_id of Block_n-1(Epochi-1).",
-            );
-            let ep3_last_block_bytes = ep3_last_block_bytes.expect(
-                "No hash for Block_n-1(Epochi-3) to prove epoch_id of Block_n-1(Epochi-1).",
-            );
-            let (ep3_lb_data, ep3_lb_proof) = prove_block_header::<F, C, D>(
-                &ep3_last_block_hash_bytes,
-                &ep3_last_block_bytes,
+                    Some((&neph_data.common, &neph_data.verifier_only, &neph_proof)),
+                    Some(&ep1_fb_proof.public_inputs),
+                )
+            )
+        },
+    );
+    // Prove Block_i+4.
+    let b4 = add_stage(
+        &mut graph,
+        checkpoints,
+        "block_4_header",
+        &[],
+        HEADER_MEMORY,
+        move |_, timing_tree| {
+            prove_block_header::<F, C, D>(
+                &blocks[0].0.hash,
+                &blocks[0].1,
+                blocks[0].0.height,
+                blocks[0].0.epoch_id.clone(),
+                blocks[0].0.prev_hash.clone(),
                 None,
                 None,
                 None,
                 None,
+                timing_tree,
+            )
+        },
+    );
+    // Prove Block_i+3.
+    let b3 = add_stage(
+        &mut graph,
+        checkpoints,
+        "block_3_header",
+        &[],
+        HEADER_MEMORY,
+        move |_, timing_tree| {
+            prove_block_header::<F, C, D>(
+                &blocks[1].0.hash,
+                &blocks[1].1,
+                blocks[1].0.height,
+                blocks[1].0.epoch_id.clone(),
+                blocks[1].0.prev_hash.clone(),
+                None,
                 None,
                 None,
                 None,
                 timing_tree,
-            )?;
-            // Next block prev_hash.
-            let nb_prev_hash: Vec<u8> = b0_proof.public_inputs[72..104]
-                .iter()
-                .map(|x| x.to_canonical_u64() as u8)
-                .collect();
-            let nb_prev_hash = CryptoHash(nb_prev_hash.try_into().unwrap());
-            // Next block height.
-            let nb_height_bytes: Vec<u8> = b0_proof.public_inputs[32..40]
-                .iter()
-                .map(|x| x.to_canonical_u64() as u8)
-                .collect();
-            let nb_height = u64::from_le_bytes(nb_height_bytes.try_into().unwrap());
-            // Current block height.  
-            let cb_height_bytes: Vec<u8> = bn_1_header_data_proof.clone().expect("No Bn-1 header proof.").1.public_inputs[32..40]
-                .iter()
-                .map(|x| x.to_canonical_u64() as u8)
-                .collect();
-            let cb_height = u64::from_le_bytes(cb_height_bytes.try_into().unwrap());
-            // Message to sing.
-            let msg_to_sign = generate_signed_message(cb_height, nb_height, nb_prev_hash);
-            // Prove Block_n-1.
-            let (b_n_1_data, b_n_1_proof) = prove_block_finality::<F, C, D>(
-                ( 
-                    &bn_1_header_data_proof.clone().expect("No Bn-1 header proof.").0.common,
-                    &bn_1_header_data_proof.clone().expect("No Bn-1 header proof.").0.verifier_only,
-                    &bn_1_header_data_proof.clone().expect("No Bn-1 header proof.").1,
-                ),
-                Some(msg_to_sign),
-                blocks[4].0.approvals.clone(),
-                validators_n_1.clone(),
-                [
-                    (
-                        ep3_lb_data.common.clone(),
-                        ep3_lb_data.verifier_only.clone(),
-                        ep3_lb_proof.clone(),
-                    ),
-                    (
-                        ep2_lb_data.common.clone(),
-                        ep2_lb_data.verifier_only.clone(),
-                        ep2_lb_proof.clone(),
-                    ),
-                    (
-                        b0_data.common.clone(),
-                        b0_data.verifier_only.clone(),
-                        b0_proof.clone(),
-                    ),
-                    (
-                        b1_data.common.clone(),
-                        b1_data.verifier_only.clone(),
-                        b1_proof.clone(),
-                    ),
-                ]
-                .to_vec(),
-                consecutive_heights.clone(),
-                client.clone(),
+            )
+        },
+    );
+    // Prove Block_i+2.
+    let b2 = add_stage(
+        &mut graph,
+        checkpoints,
+        "block_2_header",
+        &[],
+        HEADER_MEMORY,
+        move |_, timing_tree| {
+            prove_block_header::<F, C, D>(
+                &blocks[2].0.hash,
+                &blocks[2].1,
+                blocks[2].0.height,
+                blocks[2].0.epoch_id.clone(),
+                blocks[2].0.prev_hash.clone(),
+                blocks[2].0.last_ds_final_hash.clone(),
+                blocks[2].0.last_final_hash.clone(),
+                None,
+                None,
                 timing_tree,
+            )
+        },
+    );
+    let b2 = add_stage(
+        &mut graph,
+        checkpoints,
+        "block_2_heights",
+        &[b4, b3, b2],
+        RECURSION_MEMORY,
+        move |deps, _| {
+            // Prove consecutive heights for Block_i+2, Block_i+3, Block_i+4.
+            let (ch_data, ch_proof) = prove_consecutive_heights_proofs::<F, C, D>(
+                deps.iter().map(|stage| inner_proof(stage)).collect(),
             )?;
-            // Set three hashes: of the current block, Bn-1 and B0, as PI in Block_0.
-            let len = b0_proof.public_inputs.len();
-            let mut pi = vec![];
-            pi.push(F::ONE);
-            pi.append(&mut b0_proof.public_inputs[0..32].to_vec().clone());
-            pi.append(&mut b0_proof.public_inputs[(len - 64)..].to_vec().clone());
-            let (b0_data, b0_proof) = timed!(
-                timing_tree,
-                "recursion for Block_0 to set three hashes as PI",
-                recursive_proof::<F, C, C, D>(
-                    (&b0_data.common, &b0_data.verifier_only, &b0_proof),
-                    None,
-                    Some(&pi),
-                )?
+            // Aggregate proofs for heights & Bi+2.
+            let (b2_data, b2_proof) = &*deps[2];
+            recursive_proof::<F, C, C, D>(
+                (&b2_data.common, &b2_data.verifier_only, b2_proof),
+                Some((&ch_data.common, &ch_data.verifier_only, &ch_proof)),
+                Some(&b2_proof.public_inputs),
+            )
+        },
+    );
+    // Prove heights Block_i+2, Block_i+1, Block_i/{Block_0 & Block_n-1}.
+    // This proof is optional, since its absence does not affect the proof of block finality.
+    // It is an additional check of finality, in the case when the heights are consecutive.
+    // Heights are set as PI of header proofs, so the stage is planned by heights of the input.
+    let heights: Vec<Option<u64>> = blocks[2..]
+        .iter()
+        .map(|(fields, _)| fields.height)
+        .collect();
+    let consecutive = heights
+        .windows(2)
+        .all(|pair| matches!(pair, [Some(h1), Some(h2)] if h1.checked_add(1) == Some(*h2)));
+    let consecutive_heights = if consecutive {
+        let deps: Vec<StageId> = [b2, b1, bi0].into_iter().chain(bn_1).collect();
+        Some(add_stage(
+            &mut graph,
+            checkpoints,
+            "consecutive_heights",
+            &deps,
+            RECURSION_MEMORY,
+            move |deps, _| {
+                prove_consecutive_heights_proofs(
+                    deps.iter().map(|stage| inner_proof(stage)).collect(),
+                )
+            },
+        ))
+    } else {
+        None
+    };
+    // Prove BFT of Block_i/Block_0.
+    // The header, signatures, proofs [Block_n-1(Epochi-2), Block_0(Epochi-1), Block_i+1, Block_i+2] and consecutive heights.
+    let deps: Vec<StageId> = [bi0, bi0_signatures, ep2, ep1_fb, b1, b2]
+        .into_iter()
+        .chain(consecutive_heights)
+        .collect();
+    let bi0_finality = add_stage(
+        &mut graph,
+        checkpoints,
+        &format!("{}_finality", block_i_0),
+        &deps,
+        FINALITY_MEMORY,
+        move |deps, timing_tree| prove_finality_of_stages(&deps, validators.clone(), timing_tree),
+    );
+    // Set three hashes: of the current block, Bn-1 and B0, as PI in Block_i/Block_0.
+    let bi0_public_inputs =
+        add_public_inputs_stage(&mut graph, checkpoints, block_i_0, kind, bi0_finality);
+    // Prove BFT of Block_n-1, when proving epoch blocks.
+    let bn_1_public_inputs = match (bn_1, ep3_last_block) {
+        (Some(bn_1), Some((ep3_last_block_hash_bytes, ep3_last_block_bytes))) => {
+            // Prove epoch_id block for Block_n-1.
+            let ep3 = add_stage(
+                &mut graph,
+                checkpoints,
+                "ep3_last_block_header",
+                &[],
+                HEADER_MEMORY,
+                move |_, timing_tree| {
+                    prove_block_header::<F, C, D>(
+                        ep3_last_block_hash_bytes,
+                        ep3_last_block_bytes,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        timing_tree,
+                    )
+                },
             );
-            // Set three hashes: of the current block, Bn-1 and B0, as PI in Block_n-1.
-            let len = b_n_1_proof.public_inputs.len();
-            let mut pi = vec![];
-            pi.push(F::ONE);
-            pi.append(&mut b_n_1_proof.public_inputs[0..32].to_vec().clone());
-            pi.append(&mut b_n_1_proof.public_inputs[(len - 64)..].to_vec().clone());
-            let (b_n_1_data, b_n_1_proof) = timed!(
-                timing_tree,
-                "recursion for Block_0 to set three hashes as PI",
-                recursive_proof::<F, C, C, D>(
-                    (&b_n_1_data.common, &b_n_1_data.verifier_only, &b_n_1_proof),
-                    None,
-                    Some(&pi),
-                )?
+            // Signatures of approvals for Block_n-1 are extracted from Block_0,
+            // the signed message is built from PI of the proof of Block_0.
+            let bn_1_signatures = add_stage(
+                &mut graph,
+                checkpoints,
+                "block_n_1_signatures",
+                &[bi0_finality, bn_1],
+                SIGNATURES_MEMORY,
+                move |deps, timing_tree| {
+                    prove_block_signatures::<F, C, D>(
+                        &signed_message(&deps[0].1, &deps[1].1)?,
+                        blocks[4].0.approvals.clone(),
+                        validators_n_1.clone(),
+                        signature_prover,
+                        timing_tree,
+                    )
+                },
             );
-	    ((b0_data, b0_proof), Some((b_n_1_data, b_n_1_proof)))
+            // The header, signatures, proofs [Block_n-1(Epochi-3), Block_n-1(Epochi-2), Block_0, Block_1] and consecutive heights.
+            let deps: Vec<StageId> = [bn_1, bn_1_signatures, ep3, ep2, bi0_finality, b1]
+                .into_iter()
+                .chain(consecutive_heights)
+                .collect();
+            let bn_1_finality = add_stage(
+                &mut graph,
+                checkpoints,
+                "block_n_1_finality",
+                &deps,
+                FINALITY_MEMORY,
+                move |deps, timing_tree| {
+                    prove_finality_of_stages(&deps, validators_n_1.clone(), timing_tree)
+                },
+            );
+            // Set three hashes: of the current block, Bn-1 and B0, as PI in Block_n-1.
+            Some(add_public_inputs_stage(
+                &mut graph,
+                checkpoints,
+                "block_n_1",
+                ProofKind::Epoch,
+                bn_1_finality,
+            ))
         }
-        _ => {
-            panic!("Invalid blocks.len() {}", blocks.len());
+        (Some(_), None) => {
+            return Err(BftError::InvalidInput(
+                "No Block_n-1(Epochi-3) to prove epoch_id of Block_n-1(Epochi-1).".to_string(),
+            ));
         }
+        (None, _) => None,
+    };
+    let mut outputs = scheduler.run(graph, timing_tree)?;
+    let bi0_data_proof = outputs.take(bi0_public_inputs)?;
+    let bn_1_data_proof = match bn_1_public_inputs {
+        Some(id) => Some(outputs.take(id)?),
+        None => None,
     };
-    Ok(((b_i_0_data, b_i_0_proof), b_n_1_data_proof))
+    Ok((bi0_data_proof, bn_1_data_proof))
+}
+
+/// Adds the stage that proves `prove` and stores its proof in checkpoints under the name of the stage.
+fn add_stage<'a, F, C, const D: usize>(
+    graph: &mut StageGraph<'a, StageProof<F, C, D>>,
+    checkpoints: &'a Checkpoints,
+    name: &str,
+    deps: &[StageId],
+    memory: usize,
+    prove: impl FnOnce(
+            &[Arc<StageProof<F, C, D>>],
+            &mut TimingTree,
+        ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+        + Send
+        + 'a,
+) -> StageId
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + ConfigName,
+{
+    let stage = name.to_string();
+    graph.add(name, deps, memory, move |deps, timing_tree| {
+        checkpoints.stage(&stage, || prove(&deps, timing_tree))
+    })
+}
+
+/// Adds the stage that sets three hashes: of the current block, Bn-1 and B0, as PI of the proof of `finality`.
+fn add_public_inputs_stage<'a, F, C, const D: usize>(
+    graph: &mut StageGraph<'a, StageProof<F, C, D>>,
+    checkpoints: &'a Checkpoints,
+    block: &str,
+    kind: ProofKind,
+    finality: StageId,
+) -> StageId
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + ConfigName,
+    C::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    add_stage(
+        graph,
+        checkpoints,
+        &format!("{}_public_inputs", block),
+        &[finality],
+        RECURSION_MEMORY,
+        move |deps, timing_tree| {
+            let (data, proof) = &*deps[0];
+            // PI are connected to PI of the finality proof, so the final circuit can't claim other hashes.
+            let pi =
+                BlockProofPublicInputs::exposed_finality_pi::<F>(kind, proof.public_inputs.len())?;
+            timed!(
+                timing_tree,
+                "recursion to set three hashes as PI",
+                expose_public_inputs::<F, C, C, D>((&data.common, &data.verifier_only, proof), &pi)
+            )
+        },
+    )
+}
+
+/// Proves finality with outputs of stages in the following order: the header of the block, its signatures,
+/// four proofs required by `prove_block_finality` and, optionally, the proof of consecutive heights.
+fn prove_finality_of_stages<F, C, const D: usize>(
+    deps: &[Arc<StageProof<F, C, D>>],
+    validators: Option<Vec<Vec<u8>>>,
+    timing_tree: &mut TimingTree,
+) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    let ((header_data, header_proof), (signatures_data, signatures_proof)) = (&*deps[0], &*deps[1]);
+    prove_block_finality::<F, C, D>(
+        (
+            &header_data.common,
+            &header_data.verifier_only,
+            header_proof,
+        ),
+        Some((
+            &signatures_data.common,
+            &signatures_data.verifier_only,
+            signatures_proof,
+        )),
+        validators,
+        deps[2..6].iter().map(|stage| inner_proof(stage)).collect(),
+        deps.get(6).map(|stage| inner_proof(stage)),
+        timing_tree,
+    )
+}
+
+/// Returns the message signed by approvals of the current block, built from PI of header proofs
+/// of the next block (prev_hash & height) and the current block (height).
+fn signed_message<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    next_block_proof: &ProofWithPublicInputs<F, C, D>,
+    current_block_proof: &ProofWithPublicInputs<F, C, D>,
+) -> Result<Vec<u8>> {
+    // Next block prev_hash.
+    let nb_prev_hash = hash_from_pi(&next_block_proof.public_inputs[HEADER_PREV_HASH_RANGE])?;
+    // Next block height.
+    let nb_height = height_from_pi(&next_block_proof.public_inputs[HEADER_HEIGHT_RANGE])?;
+    // Current block height.
+    let cb_height = height_from_pi(&current_block_proof.public_inputs[HEADER_HEIGHT_RANGE])?;
+    Ok(generate_signed_message(cb_height, nb_height, nb_prev_hash))
+}
+
+/// Returns the proof of a stage in the form of inner proofs of `prove_block_finality`.
+fn inner_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    (data, proof): &StageProof<F, C, D>,
+) -> (
+    CommonCircuitData<F, D>,
+    VerifierOnlyCircuitData<C, D>,
+    ProofWithPublicInputs<F, C, D>,
+) {
+    (
+        data.common.clone(),
+        data.verifier_only.clone(),
+        proof.clone(),
+    )
 }
@@ -1,11 +1,14 @@
 use crate::prove_block_data::header_bphash::*;
 use crate::prove_block_data::keys_stakes::prove_valid_keys_stakes_in_valiators_list;
 use crate::prove_block_data::primitives::{prove_consecutive_heights, prove_eq_array};
-use crate::prove_block_data::signatures::{prove_approvals, prove_approvals_with_client};
+use crate::prove_block_data::signature_prover::SignatureProver;
+use crate::prove_block_data::signatures::prove_approvals_with_prover;
 use crate::prove_crypto::recursion::recursive_proof;
+use crate::error::{BftError, Result};
+use crate::public_inputs::{
+    HEADER_EPOCH_ID_RANGE, HEADER_HASH_RANGE, HEADER_HEIGHT_RANGE, HEADER_PREV_HASH_RANGE,
+};
 use crate::types::*;
-use anyhow::{Ok, Result};
-use core::panic;
 use log::info;
 use plonky2::hash::hash_types::RichField;
 use plonky2::plonk::circuit_data::{CircuitData, CommonCircuitData, VerifierOnlyCircuitData};
@@ -42,16 +45,26 @@ where
     C::Hasher: AlgebraicHasher<F>,
     [(); C::Hasher::HASH_SIZE]:,
 {
-    assert!(proofs.len() >= 3);
-    let h1_bytes: Vec<u8> = proofs[0].2.public_inputs[32..40]
+    if !(3..=4).contains(&proofs.len()) {
+        return Err(BftError::InvalidInput(format!(
+            "expected 3 or 4 proofs to prove consecutive heights, got {}",
+            proofs.len()
+        )));
+    }
+    if proofs.iter().any(|p| p.2.public_inputs.len() < 40) {
+        return Err(BftError::InvalidInput(
+            "header proof doesn't contain a height in its public inputs".to_string(),
+        ));
+    }
+    let h1_bytes: Vec<u8> = proofs[0].2.public_inputs[HEADER_HEIGHT_RANGE]
         .iter()
         .map(|x| x.to_canonical_u64() as u8)
         .collect();
-    let h2_bytes: Vec<u8> = proofs[1].2.public_inputs[32..40]
+    let h2_bytes: Vec<u8> = proofs[1].2.public_inputs[HEADER_HEIGHT_RANGE]
         .iter()
         .map(|x| x.to_canonical_u64() as u8)
         .collect();
-    let h3_bytes: Vec<u8> = proofs[2].2.public_inputs[32..40]
+    let h3_bytes: Vec<u8> = proofs[2].2.public_inputs[HEADER_HEIGHT_RANGE]
         .iter()
         .map(|x| x.to_canonical_u64() as u8)
         .collect();
@@ -63,7 +76,7 @@ where
         None,
     )?;
     if proofs.len() == 4 {
-        let h4_bytes: Vec<u8> = proofs[3].2.public_inputs[32..40]
+        let h4_bytes: Vec<u8> = proofs[3].2.public_inputs[HEADER_HEIGHT_RANGE]
             .iter()
             .map(|x| x.to_canonical_u64() as u8)
             .collect();
@@ -115,6 +128,19 @@ where
     C::Hasher: AlgebraicHasher<F>,
     [(); C::Hasher::HASH_SIZE]:,
 {
+    if hash_bytes.len() != PK_HASH_BYTES {
+        return Err(BftError::InvalidInput(format!(
+            "block hash should be {} bytes, got {}",
+            PK_HASH_BYTES,
+            hash_bytes.len()
+        )));
+    }
+    if block_bytes.len() < TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES + TYPE_BYTE + SIG_BYTES {
+        return Err(BftError::InvalidInput(format!(
+            "block header is too short: {} bytes",
+            block_bytes.len()
+        )));
+    }
     // Prepare public inputs.
     let mut public_inputs = hash_bytes.to_vec();
     // Add height.
@@ -141,7 +167,11 @@ where
     // Add bp_hash_bytes.
     if let Some(mut bp_hash) = bp_hash_bytes {
         // If bp_hash_bytes, then no prev_hash_bytes, last_ds_final_hash_bytes, last_final_hash_bytes.
-        assert!(public_inputs.len() == 32);
+        if public_inputs.len() != PK_HASH_BYTES {
+            return Err(BftError::InvalidInput(
+                "bp_hash can't be combined with height, epoch_id or other hashes".to_string(),
+            ));
+        }
         public_inputs.append(&mut bp_hash);
         // Add next_epoch_id_bytes.
         if let Some(mut next_epoch_id) = next_epoch_id_bytes {
@@ -178,6 +208,100 @@ where
     Ok((header_hash_data, header_hash_proof))
 }
 
+/// Prove signatures of approvals for the block, keys of valid signatures & 2/3 of stakes.
+///
+/// # Arguments
+///
+/// * `msg_to_sign` - The data that was signed by validators.
+/// * `next_block_approvals_bytes` - A list of signatures for the current block that is extracted from the next block.
+/// * `validators` - A list of validators that contains public keys & stakes.
+/// * `signature_prover` - The backend used to prove signatures.
+///
+/// # Returns
+///
+/// Returns a result containing the aggregate of proofs with the list of valid keys and the sum of their stakes as PI.
+///
+/// # Errors
+///
+/// Returns `BftError::InvalidInput` if approvals or validators are missing. Errors of inner proofs are propagated.
+///
+pub fn prove_block_signatures<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    msg_to_sign: &[u8],
+    next_block_approvals_bytes: Option<Vec<Vec<u8>>>,
+    validators: Option<Vec<Vec<u8>>>,
+    signature_prover: &dyn SignatureProver<F, C, D>,
+    timing_tree: &mut TimingTree,
+) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+where
+    C::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    let approvals = next_block_approvals_bytes
+        .ok_or_else(|| BftError::InvalidInput("List of signatures is empty.".to_string()))?;
+    let validators = validators
+        .ok_or_else(|| BftError::InvalidInput("List of validators is empty.".to_string()))?;
+    // Prove sig-s.
+    let ((cb_sig_data, cb_sig_proof), valid_keys, excluded) = timed!(
+        timing_tree,
+        "prove signatures",
+        prove_approvals_with_prover::<F, C, D>(
+            msg_to_sign,
+            approvals,
+            validators.clone(),
+            signature_prover
+        )?
+    );
+    info!(
+        "Size of proof for aggregated signatures: {} bytes",
+        cb_sig_proof.to_bytes().len()
+    );
+    if !excluded.is_empty() {
+        info!("Excluded invalid approvals of validators: {:?}", excluded);
+    }
+    let valid_keys_hash: Vec<u8> = cb_sig_proof
+        .public_inputs
+        .iter()
+        .map(|x| x.to_canonical_u64() as u8)
+        .collect();
+    // Prove keys used to verify valid signatures.
+    // Prove 2/3 of stakes.
+    let (cb_keys_23stakes_data, cb_keys_23stakes_proof) = timed!(
+        timing_tree,
+        "prove keys used to verify valid signatures",
+        prove_valid_keys_stakes_in_valiators_list::<F, C, D>(
+            valid_keys,
+            valid_keys_hash,
+            validators,
+        )?
+    );
+    info!(
+        "Size of proof for aggregated keys: {} bytes",
+        cb_keys_23stakes_proof.to_bytes().len()
+    );
+    // Aggregate proofs & set list of valid keys and sum as PI.
+    timed!(
+        timing_tree,
+        "aggregate signatures and valid keys proof",
+        recursive_proof::<F, C, C, D>(
+            (
+                &cb_sig_data.common,
+                &cb_sig_data.verifier_only,
+                &cb_sig_proof,
+            ),
+            Some((
+                &cb_keys_23stakes_data.common,
+                &cb_keys_23stakes_data.verifier_only,
+                &cb_keys_23stakes_proof,
+            )),
+            Some(&cb_keys_23stakes_proof.public_inputs),
+        )
+    )
+}
+
 /// Prove finality (both Doomslug and BFT) of the block.
 ///
 /// This function generates proofs of computational integrity of block data to prove its finality.
@@ -185,8 +309,7 @@ where
 /// # Arguments
 ///
 /// * `current_block_header_proof` - A proof for header for the current block.
-/// * `msg_to_sign` - The data that was signed by validators. It is used when proving signatues.
-/// * `next_block_approvals_bytes` - A list of signatures for the current block that is extracted from the next block.
+/// * `signatures` - The aggregate of signatures for the current block (see `prove_block_signatures`), if signatures are proven.
 /// * `validators` - A list of validators that contains public keys & stakes.
 /// * `proofs` - A set of proofs that is used to ensure Doomslug/BFT finality.
 ///              It should contain proofs in the following order: [Proof_Block_n-1(Epochi-2), Proof_Block_0(Epochi-1), Proof_Block_i+1(Epochi), Proof_Block_i+2(Epochi)]
@@ -207,8 +330,11 @@ pub fn prove_block_finality<
         &VerifierOnlyCircuitData<C, D>,
         &ProofWithPublicInputs<F, C, D>,
     ),
-    msg_to_sign: Option<Vec<u8>>,
-    next_block_approvals_bytes: Option<Vec<Vec<u8>>>,
+    signatures: Option<(
+        &CommonCircuitData<F, D>,
+        &VerifierOnlyCircuitData<C, D>,
+        &ProofWithPublicInputs<F, C, D>,
+    )>,
     validators: Option<Vec<Vec<u8>>>,
     proofs: Vec<(
         CommonCircuitData<F, D>,
@@ -220,104 +346,39 @@ pub fn prove_block_finality<
         VerifierOnlyCircuitData<C, D>,
         ProofWithPublicInputs<F, C, D>,
     )>,
-    client: Option<nats::Connection>,
     timing_tree: &mut TimingTree,
 ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
 where
     C::Hasher: AlgebraicHasher<F>,
     [(); C::Hasher::HASH_SIZE]:,
 {
-    // Aggregation of the obtained proofs.
-    let mut aggregation: Option<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> = None;
+    if !(3..=4).contains(&proofs.len()) {
+        return Err(BftError::InvalidInput(format!(
+            "expected 3 or 4 proofs to prove finality, got {}",
+            proofs.len()
+        )));
+    }
+    if current_block_header_proof.2.public_inputs.len() < 72 {
+        return Err(BftError::InvalidInput(
+            "current block header proof doesn't contain hash, height & epoch_id".to_string(),
+        ));
+    }
     // Current block hash extracted from PI of the proof.
-    let current_block_hash_bytes: Vec<u8> = current_block_header_proof.2.public_inputs[0..32]
+    let current_block_hash_bytes: Vec<u8> = current_block_header_proof.2.public_inputs
+        [HEADER_HASH_RANGE]
         .iter()
         .map(|x| x.to_canonical_u64() as u8)
         .collect();
     // Current block epoch_id extracted from PI of the proof.
-    let current_block_epoch_id_bytes: Vec<u8> = current_block_header_proof.2.public_inputs[40..72]
+    let current_block_epoch_id_bytes: Vec<u8> = current_block_header_proof.2.public_inputs
+        [HEADER_EPOCH_ID_RANGE]
         .iter()
         .map(|x| x.to_canonical_u64() as u8)
         .collect();
-    // Prove current_block signatures. Prove keys & stakes.
-    aggregation = match msg_to_sign {
-        Some(msg) => {
-            let approvals = next_block_approvals_bytes.expect("List of signatures is empty.");
-            let validators = validators.clone().expect("List of validators is empty.");
-            // Prove sig-s.
-            let ((cb_sig_data, cb_sig_proof), valid_keys) = match client {
-                None => {
-                    timed!(
-                        timing_tree,
-                        "prove signatures",
-                        prove_approvals::<F, C, D>(&msg, approvals, validators.clone())?
-                    )
-                }
-                Some(client_connection) => {
-                    timed!(
-                        timing_tree,
-                        "prove signatures using nats client",
-                        prove_approvals_with_client::<F, C, D>(
-                            &msg,
-                            approvals,
-                            validators.clone(),
-                            client_connection
-                        )?
-                    )
-                }
-            };
-            info!(
-                "Size of proof for aggregated signatures: {} bytes",
-                cb_sig_proof.to_bytes().len()
-            );
-            let valid_keys_hash: Vec<u8> = cb_sig_proof
-                .public_inputs
-                .iter()
-                .map(|x| x.to_canonical_u64() as u8)
-                .collect();
-            // Prove keys used to verify valid signatures.
-            // Prove 2/3 of stakes.
-            let (cb_keys_23stakes_data, cb_keys_23stakes_proof) = timed!(
-                timing_tree,
-                "prove keys used to verify valid signatures",
-                prove_valid_keys_stakes_in_valiators_list::<F, C, D>(
-                    valid_keys.clone(),
-                    valid_keys_hash,
-                    validators.clone(),
-                )?
-            );
-            info!(
-                "Size of proof for aggregated keys: {} bytes",
-                cb_keys_23stakes_proof.to_bytes().len()
-            );
-            // Aggregate proofs & set list of valid keys and sum as PI.
-            let (agg_data, agg_proof) = timed!(
-                timing_tree,
-                "aggregate signatures and valid keys proof",
-                recursive_proof::<F, C, C, D>(
-                    (
-                        &cb_sig_data.common,
-                        &cb_sig_data.verifier_only,
-                        &cb_sig_proof,
-                    ),
-                    Some((
-                        &cb_keys_23stakes_data.common,
-                        &cb_keys_23stakes_data.verifier_only,
-                        &cb_keys_23stakes_proof,
-                    )),
-                    Some(&cb_keys_23stakes_proof.public_inputs),
-                )?
-            );
-            Some((agg_data, agg_proof))
-        }
-        None => None,
-    };
-    assert!(proofs.len() >= 3);
-    assert!(proofs.len() <= 4);
     // Check epoch proofs: B_n-1 (Epoch_i-2) & B_0 (Epoch_i-1).
     let (agg_data, agg_proof) = {
         // Verify proof of Block_n-1(Epoch_i-2). This proof stores the hash of the block (PI are 32 bytes).
-        let epoch_hash: Vec<u8> = proofs[0].2.public_inputs[0..32]
+        let epoch_hash: Vec<u8> = proofs[0].2.public_inputs[HEADER_HASH_RANGE]
             .iter()
             .map(|x| x.to_canonical_u64() as u8)
             .collect();
@@ -341,7 +402,7 @@ where
                     &epoch_id_data.verifier_only,
                     &epoch_id_proof
                 )),
-                Some(&proofs[0].2.public_inputs[0..32]),
+                Some(&proofs[0].2.public_inputs[HEADER_HASH_RANGE]),
             )?
         );
         // Verify Block_0(Epochi-1). Prove bp_hash optionally.
@@ -364,7 +425,7 @@ where
                     recursive_proof::<F, C, C, D>(
                         (&proofs[1].0, &proofs[1].1, &proofs[1].2,),
                         Some((&bp_d.common, &bp_d.verifier_only, &bp_p)),
-                        Some(&proofs[1].2.public_inputs[0..32]),
+                        Some(&proofs[1].2.public_inputs[HEADER_HASH_RANGE]),
                     )?
                 )
             }
@@ -375,7 +436,7 @@ where
                     recursive_proof::<F, C, C, D>(
                         (&proofs[1].0, &proofs[1].1, &proofs[1].2,),
                         None,
-                        Some(&proofs[1].2.public_inputs[0..32]),
+                        Some(&proofs[1].2.public_inputs[HEADER_HASH_RANGE]),
                     )?
                 )
             }
@@ -401,33 +462,29 @@ where
     };
     // Aggregate proofs for Bn-1/B0 and signatures.
     // Make an aggregation of proofs: Block_n-1(Epochi-1) & Block_0(Epochi-1) as the initial one, otherwise.
-    aggregation = match aggregation {
-        Some((agg_sig_data, agg_sig_proof)) => {
+    let aggregation = match signatures {
+        Some(signatures) => {
             // Aggregate obtained proofs.
             let (agg_d, agg_p) = timed!(
                 timing_tree,
                 "aggregate proofs: Block_n-1(Epochi-1) & Block_0(Epochi-1)",
                 recursive_proof::<F, C, C, D>(
-                    (
-                        &agg_sig_data.common,
-                        &agg_sig_data.verifier_only,
-                        &agg_sig_proof
-                    ),
+                    signatures,
                     Some((&agg_data.common, &agg_data.verifier_only, &agg_proof)),
                     Some(&agg_proof.public_inputs),
                 )?
             );
-            Some((agg_d, agg_p))
+            (agg_d, agg_p)
         }
-        None => Some((agg_data, agg_proof.clone())),
+        None => (agg_data, agg_proof.clone()),
     };
     // Prove block finality.
-    aggregation = match proofs.len() {
+    let aggregation = match proofs.len() {
         // Prove Doomslug finality.
         3 => {
             // This proof stores its hash (32 bytes), height (8 bytes), epoch_id (32 bytes),
             // prev_hash (32 bytes), last_ds_final_hash_bytes (32 bytes) & last_final_hash_bytes (32 bytes).
-            let prev_hash: Vec<u8> = proofs[2].2.public_inputs[72..104]
+            let prev_hash: Vec<u8> = proofs[2].2.public_inputs[HEADER_PREV_HASH_RANGE]
                 .iter()
                 .map(|x| x.to_canonical_u64() as u8)
                 .collect();
@@ -516,30 +573,26 @@ where
                 "aggreagtion",
                 recursive_proof::<F, C, C, D>(
                     (
-                        &aggregation.clone().expect("No common data.").0.common,
-                        &aggregation
-                            .clone()
-                            .expect("No verifier data.")
-                            .0
-                            .verifier_only,
-                        &aggregation.clone().expect("No proof.").1,
+                        &aggregation.0.common,
+                        &aggregation.0.verifier_only,
+                        &aggregation.1,
                     ),
                     Some((
                         &block_i_1_data.common,
                         &block_i_1_data.verifier_only,
                         &block_i_1_proof
                     )),
-                    Some(&aggregation.clone().expect("No proof.").1.public_inputs),
+                    Some(&aggregation.1.public_inputs),
                 )?
             );
-            Some((agg_data, agg_proof))
+            (agg_data, agg_proof)
         }
         // Prove BFT finality.
         4 => {
             // Prove Doomslug finality with Block_i+1.
             // This proof stores its hash (32 bytes), height (8 bytes), epoch_id (32 bytes),
             // prev_hash (32 bytes), last_ds_final_hash_bytes (32 bytes).
-            let prev_hash: Vec<u8> = proofs[2].2.public_inputs[72..104]
+            let prev_hash: Vec<u8> = proofs[2].2.public_inputs[HEADER_PREV_HASH_RANGE]
                 .iter()
                 .map(|x| x.to_canonical_u64() as u8)
                 .collect();
@@ -690,48 +743,40 @@ where
                 "aggreagtion",
                 recursive_proof::<F, C, C, D>(
                     (
-                        &aggregation.clone().expect("No common data.").0.common,
-                        &aggregation
-                            .clone()
-                            .expect("No verifier data.")
-                            .0
-                            .verifier_only,
-                        &aggregation.clone().expect("No proof.").1,
+                        &aggregation.0.common,
+                        &aggregation.0.verifier_only,
+                        &aggregation.1,
                     ),
                     Some((&agg_data.common, &agg_data.verifier_only, &agg_proof)),
-                    Some(&aggregation.clone().expect("No proof.").1.public_inputs),
+                    Some(&aggregation.1.public_inputs),
                 )?
             );
-            Some((agg_data, agg_proof))
+            (agg_data, agg_proof)
         }
         _ => {
-            panic!("Invalid proofs.len() {}", proofs.len());
+            return Err(BftError::InvalidInput(format!(
+                "Invalid proofs.len() {}",
+                proofs.len()
+            )));
         }
     };
     // Aggregate PI of the proofs for the header of the current block and blocks Bn-1 & B0.
     let mut public_inputs = current_block_header_proof.2.public_inputs.clone();
-    public_inputs.append(&mut aggregation.clone().expect("No proof.").1.public_inputs);
-    aggregation = {
-        Some(timed!(
+    public_inputs.append(&mut aggregation.1.public_inputs.clone());
+    let aggregation = {
+        timed!(
             timing_tree,
             "aggreagtion with proof for header",
             recursive_proof::<F, C, C, D>(
                 (
-                    &aggregation.clone().expect("No common data.").0.common,
-                    &aggregation
-                        .clone()
-                        .expect("No verifier data.")
-                        .0
-                        .verifier_only,
-                    &aggregation.clone().expect("No proof.").1,
+                    &aggregation.0.common,
+                    &aggregation.0.verifier_only,
+                    &aggregation.1,
                 ),
                 Some(current_block_header_proof),
                 Some(&public_inputs),
             )?
-        ))
+        )
     };
-    Ok((
-        aggregation.clone().expect("No circuit data.").0,
-        aggregation.clone().expect("No proof.").1,
-    ))
+    Ok(aggregation)
 }
@@ -0,0 +1,958 @@
+use crate::error::{BftError, Result};
+use crate::public_inputs::{
+    pi_to_bytes, BlockProofPublicInputs, BLOCK_HASH_RANGE, EP1_FIRST_BLOCK_HASH_RANGE,
+    EP2_LAST_BLOCK_HASH_RANGE, KIND_INDEX,
+};
+use crate::store::ProofKind;
+use crate::types::PK_HASH_BYTES;
+use near_primitives::hash::CryptoHash;
+use plonky2::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{
+    CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitData, VerifierCircuitTarget,
+    VerifierOnlyCircuitData,
+};
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut, Hasher};
+use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use plonky2::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
+use plonky2::recursion::dummy_circuit::cyclic_base_proof;
+use plonky2_field::extension::Extendable;
+use plonky2_field::types::{Field, PrimeField64};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Represents the range of the genesis commitment in public inputs of the chain proof.
+pub const GENESIS_RANGE: Range<usize> = 0..4;
+/// Represents the range of the hash of verifier data of epoch proofs in public inputs of the chain proof.
+pub const EPOCH_VERIFIER_HASH_RANGE: Range<usize> = 4..8;
+/// Represents the range of anchors (3 hashes) in public inputs of the chain proof.
+pub const ANCHORS_RANGE: Range<usize> = 8..104;
+/// Represents the index of the number of proven epochs in public inputs of the chain proof.
+pub const NUM_EPOCHS_INDEX: usize = 104;
+/// Represents the start of verifier data of the chain circuit in public inputs of the chain proof.
+pub const CHAIN_VERIFIER_DATA_START: usize = 105;
+/// Represents the index of the number of proven epochs in public inputs of the anchored block proof.
+pub const ANCHORED_NUM_EPOCHS_INDEX: usize = 4;
+/// Represents the start of public inputs of the block proof in public inputs of the anchored block proof.
+pub const ANCHORED_BLOCK_PI_START: usize = 5;
+
+/// Represents log2 of the number of rows of the circuit epoch proofs are padded to before they are chained
+/// (see `normalize_proof`), so epochs with different numbers of validators are verified by the same circuit.
+/// Proofs of random blocks are padded to it before they are anchored to the chain.
+pub const EPOCH_PROOF_DEGREE_BITS: usize = 13;
+
+/// The number of attempts to find the circuit of the chain that verifies proofs of itself.
+const MAX_COMMON_DATA_ITERATIONS: usize = 8;
+
+/// Represents hashes of epoch blocks that proofs of the next epoch blocks are checked against.
+///
+/// After the pair {Block_0 (Epoch_i), Block_n-1 (Epoch_i-1)} is proven, `last_block_hash` is epoch_id
+/// of Epoch_i+1 and the header of `first_block_hash` contains next_bp_hash, i.e. validators of Epoch_i+1.
+///
+/// # Fields
+///
+/// * `prev_last_block_hash` - The hash of Block_n-1 (Epoch_i-2).
+/// * `last_block_hash` - The hash of Block_n-1 (Epoch_i-1).
+/// * `first_block_hash` - The hash of Block_0 (Epoch_i).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochAnchors {
+    pub prev_last_block_hash: CryptoHash,
+    pub last_block_hash: CryptoHash,
+    pub first_block_hash: CryptoHash,
+}
+
+impl EpochAnchors {
+    /// Returns anchors the pair of epoch proofs is proven against, i.e. hashes stored in the contract.
+    pub fn of_epoch_proofs(b0: &BlockProofPublicInputs, bn_1: &BlockProofPublicInputs) -> Self {
+        Self {
+            prev_last_block_hash: bn_1.ep2_last_block_hash,
+            last_block_hash: b0.ep2_last_block_hash,
+            first_block_hash: b0.ep1_first_block_hash,
+        }
+    }
+
+    /// Returns anchors after the pair of epoch proofs is proven.
+    pub fn next(&self, b0: &BlockProofPublicInputs, bn_1: &BlockProofPublicInputs) -> Self {
+        Self {
+            prev_last_block_hash: self.last_block_hash,
+            last_block_hash: bn_1.block_hash,
+            first_block_hash: b0.block_hash,
+        }
+    }
+
+    /// Checks that the pair of epoch proofs is proven against these anchors.
+    pub fn check_epoch_proofs(
+        &self,
+        b0: &BlockProofPublicInputs,
+        bn_1: &BlockProofPublicInputs,
+    ) -> Result<()> {
+        if *self != Self::of_epoch_proofs(b0, bn_1)
+            || bn_1.ep1_first_block_hash != self.last_block_hash
+        {
+            return Err(BftError::HashMismatch(format!(
+                "epoch proofs of {} & {} don't continue the chain with anchors {:?}",
+                b0.block_hash, bn_1.block_hash, self
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns true if the block is proven against these anchors, i.e. it is a block of the epoch
+    /// next to the last proven one.
+    pub fn is_parent_of(&self, block: &BlockProofPublicInputs) -> bool {
+        block.ep2_last_block_hash == self.last_block_hash
+            && block.ep1_first_block_hash == self.first_block_hash
+    }
+
+    /// Encodes anchors as field elements, one element per byte.
+    pub fn to_fields<F: Field>(&self) -> Vec<F> {
+        [
+            self.prev_last_block_hash.0,
+            self.last_block_hash.0,
+            self.first_block_hash.0,
+        ]
+        .concat()
+        .into_iter()
+        .map(F::from_canonical_u8)
+        .collect()
+    }
+
+    /// Returns the commitment to anchors, used as the genesis of the chain.
+    pub fn commitment<F: RichField>(&self) -> HashOut<F> {
+        PoseidonHash::hash_no_pad(&self.to_fields::<F>())
+    }
+}
+
+/// Represents public inputs of the chain proof.
+///
+/// # Fields
+///
+/// * `genesis` - The commitment to anchors of the first proven epoch (see `EpochAnchors::commitment`).
+/// * `epoch_verifier_hash` - The hash of verifier data of Block_0 & Block_n-1 proofs, the same for all epochs.
+/// * `anchors` - Anchors after the last proven epoch.
+/// * `num_epochs` - The number of proven epochs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochChainPublicInputs<F: RichField> {
+    pub genesis: HashOut<F>,
+    pub epoch_verifier_hash: HashOut<F>,
+    pub anchors: EpochAnchors,
+    pub num_epochs: u64,
+}
+
+impl<F: RichField> EpochChainPublicInputs<F> {
+    /// Decodes public inputs of the chain proof. Verifier data of the chain circuit is skipped.
+    pub fn decode(pi: &[F]) -> Result<Self> {
+        if pi.len() < CHAIN_VERIFIER_DATA_START {
+            return Err(BftError::InvalidInput(format!(
+                "chain proof has {} public inputs, expected at least {}",
+                pi.len(),
+                CHAIN_VERIFIER_DATA_START
+            )));
+        }
+        let bytes = pi_to_bytes(&pi[ANCHORS_RANGE])?;
+        let hash = |i: usize| {
+            CryptoHash(
+                bytes[i * PK_HASH_BYTES..(i + 1) * PK_HASH_BYTES]
+                    .try_into()
+                    .expect("length is checked above"),
+            )
+        };
+        Ok(Self {
+            genesis: HashOut::from_vec(pi[GENESIS_RANGE].to_vec()),
+            epoch_verifier_hash: HashOut::from_vec(pi[EPOCH_VERIFIER_HASH_RANGE].to_vec()),
+            anchors: EpochAnchors {
+                prev_last_block_hash: hash(0),
+                last_block_hash: hash(1),
+                first_block_hash: hash(2),
+            },
+            num_epochs: pi[NUM_EPOCHS_INDEX].to_canonical_u64(),
+        })
+    }
+}
+
+/// Returns the hash of verifier data, the same as computed by the chain circuit.
+pub fn verifier_data_hash<F, C, const D: usize>(
+    verifier_data: &[&VerifierOnlyCircuitData<C, D>],
+) -> HashOut<F>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let elements: Vec<F> = verifier_data
+        .iter()
+        .flat_map(|data| {
+            std::iter::once(&data.circuit_digest)
+                .chain(&data.constants_sigmas_cap.0)
+                .flat_map(|hash| hash.to_vec())
+        })
+        .collect();
+    PoseidonHash::hash_no_pad(&elements)
+}
+
+/// Returns targets of verifier data in the order they are registered as public inputs
+/// by `add_verifier_data_public_inputs`: circuit_digest, then constants_sigmas_cap.
+fn verifier_data_targets(verifier_data: &VerifierCircuitTarget) -> Vec<Target> {
+    std::iter::once(&verifier_data.circuit_digest)
+        .chain(&verifier_data.constants_sigmas_cap.0)
+        .flat_map(|hash| hash.elements)
+        .collect()
+}
+
+fn connect_arrays<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    a: &[Target],
+    b: &[Target],
+) {
+    for (x, y) in a.iter().zip(b) {
+        builder.connect(*x, *y);
+    }
+}
+
+/// Represents targets of the chain circuit.
+struct EpochChainTargets<const D: usize> {
+    condition: BoolTarget,
+    inner: ProofWithPublicInputsTarget<D>,
+    verifier_data: VerifierCircuitTarget,
+    b0: ProofWithPublicInputsTarget<D>,
+    b0_verifier_data: VerifierCircuitTarget,
+    bn_1: ProofWithPublicInputsTarget<D>,
+    bn_1_verifier_data: VerifierCircuitTarget,
+}
+
+/// Represents the circuit of incremental epoch-chain recursion.
+///
+/// Each step verifies the proof of the previous step and epoch proofs of Block_0 (Epoch_i) & Block_n-1 (Epoch_i-1),
+/// which are proven against anchors of the previous step. The first step uses anchors of its epoch proofs,
+/// i.e. hashes stored in the contract, and commits to them as the genesis. So the proof of the last step proves
+/// all epochs since the genesis and doesn't depend on hashes stored after it.
+pub struct EpochChainCircuit<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    pub data: CircuitData<F, C, D>,
+    b0_common: CommonCircuitData<F, D>,
+    bn_1_common: CommonCircuitData<F, D>,
+    targets: EpochChainTargets<D>,
+}
+
+impl<F, C, const D: usize> EpochChainCircuit<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    /// Builds the chain circuit for epoch proofs of the given circuits.
+    /// All epochs of the chain must be proven by the same circuits, circuits of Block_0 & Block_n-1 depend on
+    /// the number of signatures and the size of headers, so their proofs are normalized with `normalize_proof`
+    /// to `EPOCH_PROOF_DEGREE_BITS` first.
+    ///
+    /// # Arguments
+    ///
+    /// * `b0_common` - The common data of the circuit of Block_0 proofs.
+    /// * `bn_1_common` - The common data of the circuit of Block_n-1 proofs.
+    pub fn build(
+        b0_common: &CommonCircuitData<F, D>,
+        bn_1_common: &CommonCircuitData<F, D>,
+    ) -> Result<Self> {
+        let common = Self::cyclic_common_data(b0_common, bn_1_common)?;
+        let (builder, targets) = Self::build_step(b0_common, bn_1_common, &common, true)?;
+        Ok(Self {
+            data: builder.build::<C>(),
+            b0_common: b0_common.clone(),
+            bn_1_common: bn_1_common.clone(),
+            targets,
+        })
+    }
+
+    /// Finds the common data of the chain circuit, it is the common data of the inner proof as well.
+    /// The step circuit is rebuilt for the common data of the previous build until it doesn't change.
+    fn cyclic_common_data(
+        b0_common: &CommonCircuitData<F, D>,
+        bn_1_common: &CommonCircuitData<F, D>,
+    ) -> Result<CommonCircuitData<F, D>> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let proof = builder.add_virtual_proof_with_pis(b0_common);
+        let verifier_data =
+            builder.add_virtual_verifier_data(b0_common.config.fri_config.cap_height);
+        builder.verify_proof::<C>(&proof, &verifier_data, b0_common);
+        let mut common = builder.build::<C>().common;
+        for _ in 0..MAX_COMMON_DATA_ITERATIONS {
+            let (builder, _) = Self::build_step(b0_common, bn_1_common, &common, false)?;
+            let next = builder.build::<C>().common;
+            if next == common {
+                return Ok(common);
+            }
+            common = next;
+        }
+        Err(BftError::Proving(
+            "the circuit of the epoch chain doesn't converge".to_string(),
+        ))
+    }
+
+    /// Builds the step circuit. If `cyclic` is false, the inner proof is verified with the same gadgets,
+    /// but its circuit isn't required to be the circuit itself. It is used to find the common data.
+    fn build_step(
+        b0_common: &CommonCircuitData<F, D>,
+        bn_1_common: &CommonCircuitData<F, D>,
+        common: &CommonCircuitData<F, D>,
+        cyclic: bool,
+    ) -> Result<(CircuitBuilder<F, D>, EpochChainTargets<D>)> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let one = builder.one();
+        // Verify epoch proofs.
+        let b0 = builder.add_virtual_proof_with_pis(b0_common);
+        let b0_verifier_data =
+            builder.add_virtual_verifier_data(b0_common.config.fri_config.cap_height);
+        builder.verify_proof::<C>(&b0, &b0_verifier_data, b0_common);
+        let bn_1 = builder.add_virtual_proof_with_pis(bn_1_common);
+        let bn_1_verifier_data =
+            builder.add_virtual_verifier_data(bn_1_common.config.fri_config.cap_height);
+        builder.verify_proof::<C>(&bn_1, &bn_1_verifier_data, bn_1_common);
+
+        // Set PI: genesis, the hash of verifier data of epoch proofs, anchors, the number of epochs
+        // and verifier data of the chain circuit.
+        let genesis = builder.add_virtual_hash();
+        builder.register_public_inputs(&genesis.elements);
+        let epoch_verifier_hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(
+            [
+                verifier_data_targets(&b0_verifier_data),
+                verifier_data_targets(&bn_1_verifier_data),
+            ]
+            .concat(),
+        );
+        builder.register_public_inputs(&epoch_verifier_hash.elements);
+        let anchors = builder.add_virtual_targets(ANCHORS_RANGE.len());
+        builder.register_public_inputs(&anchors);
+        let num_epochs = builder.add_virtual_target();
+        builder.register_public_input(num_epochs);
+        let verifier_data = builder.add_verifier_data_public_inputs();
+        let mut common = common.clone();
+        common.num_public_inputs = builder.num_public_inputs();
+
+        // The inner proof is the proof of the previous step, or a dummy proof with genesis anchors.
+        let condition = builder.add_virtual_bool_target_safe();
+        let inner = builder.add_virtual_proof_with_pis(&common);
+        let inner_pi = &inner.public_inputs;
+        let inner_genesis = HashOutTarget::from_vec(inner_pi[GENESIS_RANGE].to_vec());
+        let inner_epoch_verifier_hash =
+            HashOutTarget::from_vec(inner_pi[EPOCH_VERIFIER_HASH_RANGE].to_vec());
+        let inner_anchors = &inner_pi[ANCHORS_RANGE];
+        let anchors_hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(inner_anchors.to_vec());
+        let genesis_in = builder.select_hash(condition, inner_genesis, anchors_hash);
+        builder.connect_hashes(genesis, genesis_in);
+        // Epoch proofs of all steps are proven by the same circuits.
+        let epoch_verifier_hash_in =
+            builder.select_hash(condition, inner_epoch_verifier_hash, epoch_verifier_hash);
+        builder.connect_hashes(epoch_verifier_hash, epoch_verifier_hash_in);
+        let num_epochs_out = builder.mul_add(condition.target, inner_pi[NUM_EPOCHS_INDEX], one);
+        builder.connect(num_epochs, num_epochs_out);
+
+        // Check epoch proofs against anchors of the previous step.
+        let prev_last = &inner_anchors[0..PK_HASH_BYTES];
+        let last = &inner_anchors[PK_HASH_BYTES..2 * PK_HASH_BYTES];
+        let first = &inner_anchors[2 * PK_HASH_BYTES..];
+        let b0_pi = &b0.public_inputs;
+        let bn_1_pi = &bn_1.public_inputs;
+        builder.connect(b0_pi[KIND_INDEX], one);
+        builder.connect(bn_1_pi[KIND_INDEX], one);
+        connect_arrays(&mut builder, &b0_pi[EP2_LAST_BLOCK_HASH_RANGE], last);
+        connect_arrays(&mut builder, &b0_pi[EP1_FIRST_BLOCK_HASH_RANGE], first);
+        connect_arrays(&mut builder, &bn_1_pi[EP2_LAST_BLOCK_HASH_RANGE], prev_last);
+        connect_arrays(&mut builder, &bn_1_pi[EP1_FIRST_BLOCK_HASH_RANGE], last);
+        // Set new anchors: Block_n-1 (Epoch_i-2), Block_n-1 (Epoch_i-1) & Block_0 (Epoch_i).
+        connect_arrays(&mut builder, &anchors[0..PK_HASH_BYTES], last);
+        connect_arrays(
+            &mut builder,
+            &anchors[PK_HASH_BYTES..2 * PK_HASH_BYTES],
+            &bn_1_pi[BLOCK_HASH_RANGE],
+        );
+        connect_arrays(
+            &mut builder,
+            &anchors[2 * PK_HASH_BYTES..],
+            &b0_pi[BLOCK_HASH_RANGE],
+        );
+
+        if cyclic {
+            builder
+                .conditionally_verify_cyclic_proof_or_dummy::<C>(condition, &inner, &common)
+                .map_err(BftError::proving)?;
+        } else {
+            builder
+                .conditionally_verify_proof_or_dummy::<C>(
+                    condition,
+                    &inner,
+                    &verifier_data,
+                    &common,
+                )
+                .map_err(BftError::proving)?;
+        }
+        Ok((
+            builder,
+            EpochChainTargets {
+                condition,
+                inner,
+                verifier_data,
+                b0,
+                b0_verifier_data,
+                bn_1,
+                bn_1_verifier_data,
+            },
+        ))
+    }
+
+    /// Proves the next step of the chain.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - The proof of the previous step, `None` to start the chain.
+    /// * `b0` - The proof of Block_0 (Epoch_i) with its circuit data.
+    /// * `bn_1` - The proof of Block_n-1 (Epoch_i-1) with its circuit data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if epoch proofs are proven by other circuits or aren't proofs of epoch blocks,
+    /// `BftError::HashMismatch` if they don't continue the chain.
+    pub fn prove_step(
+        &self,
+        previous: Option<&ProofWithPublicInputs<F, C, D>>,
+        b0: (
+            &CommonCircuitData<F, D>,
+            &VerifierOnlyCircuitData<C, D>,
+            &ProofWithPublicInputs<F, C, D>,
+        ),
+        bn_1: (
+            &CommonCircuitData<F, D>,
+            &VerifierOnlyCircuitData<C, D>,
+            &ProofWithPublicInputs<F, C, D>,
+        ),
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        if !self.is_built_for(b0.0, bn_1.0) {
+            return Err(BftError::InvalidInput(
+                "epoch proofs are proven by circuits other than the chain is built for".to_string(),
+            ));
+        }
+        let b0_pi = BlockProofPublicInputs::decode(&b0.2.public_inputs)?;
+        let bn_1_pi = BlockProofPublicInputs::decode(&bn_1.2.public_inputs)?;
+        if b0_pi.kind != ProofKind::Epoch || bn_1_pi.kind != ProofKind::Epoch {
+            return Err(BftError::InvalidInput(
+                "the chain accepts only proofs of epoch blocks".to_string(),
+            ));
+        }
+        let anchors = match previous {
+            Some(proof) => EpochChainPublicInputs::decode(&proof.public_inputs)?.anchors,
+            None => EpochAnchors::of_epoch_proofs(&b0_pi, &bn_1_pi),
+        };
+        anchors.check_epoch_proofs(&b0_pi, &bn_1_pi)?;
+
+        let mut pw = PartialWitness::new();
+        pw.set_bool_target(self.targets.condition, previous.is_some());
+        match previous {
+            Some(proof) => pw.set_proof_with_pis_target(&self.targets.inner, proof),
+            None => {
+                let genesis_pi: HashMap<usize, F> = anchors
+                    .to_fields()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, x)| (ANCHORS_RANGE.start + i, x))
+                    .collect();
+                pw.set_proof_with_pis_target(
+                    &self.targets.inner,
+                    &cyclic_base_proof(&self.data.common, &self.data.verifier_only, genesis_pi),
+                );
+            }
+        }
+        pw.set_verifier_data_target(&self.targets.verifier_data, &self.data.verifier_only);
+        pw.set_proof_with_pis_target(&self.targets.b0, b0.2);
+        pw.set_verifier_data_target(&self.targets.b0_verifier_data, b0.1);
+        pw.set_proof_with_pis_target(&self.targets.bn_1, bn_1.2);
+        pw.set_verifier_data_target(&self.targets.bn_1_verifier_data, bn_1.1);
+        self.data.prove(pw).map_err(BftError::proving)
+    }
+
+    /// Returns true if the chain is built for epoch proofs of circuits with the given common data.
+    pub fn is_built_for(
+        &self,
+        b0_common: &CommonCircuitData<F, D>,
+        bn_1_common: &CommonCircuitData<F, D>,
+    ) -> bool {
+        *b0_common == self.b0_common && *bn_1_common == self.bn_1_common
+    }
+
+    /// Verifies the chain proof, including verifier data in its public inputs, and decodes its public inputs.
+    /// The proof is valid for any genesis and any circuits of epoch proofs, so both are checked against
+    /// trusted values.
+    ///
+    /// # Arguments
+    ///
+    /// * `proof` - The proof of the chain.
+    /// * `genesis` - The commitment to trusted anchors the chain starts from (see `EpochAnchors::commitment`),
+    ///   e.g. anchors stored in the contract.
+    /// * `epoch_verifier_hash` - The hash of verifier data of trusted circuits of Block_0 & Block_n-1 proofs
+    ///   (see `verifier_data_hash`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::Proving` if the proof is invalid and `BftError::HashMismatch` if it starts from
+    /// another genesis or verifies epoch proofs of other circuits.
+    pub fn verify(
+        &self,
+        proof: &ProofWithPublicInputs<F, C, D>,
+        genesis: &HashOut<F>,
+        epoch_verifier_hash: &HashOut<F>,
+    ) -> Result<EpochChainPublicInputs<F>> {
+        check_cyclic_proof_verifier_data(proof, &self.data.verifier_only, &self.data.common)
+            .map_err(BftError::proving)?;
+        self.data.verify(proof.clone()).map_err(BftError::proving)?;
+        let pi = EpochChainPublicInputs::decode(&proof.public_inputs)?;
+        if pi.genesis != *genesis {
+            return Err(BftError::HashMismatch(format!(
+                "chain starts from genesis {:?}, expected {:?}",
+                pi.genesis, genesis
+            )));
+        }
+        if pi.epoch_verifier_hash != *epoch_verifier_hash {
+            return Err(BftError::HashMismatch(format!(
+                "chain verifies epoch proofs of circuits {:?}, expected {:?}",
+                pi.epoch_verifier_hash, epoch_verifier_hash
+            )));
+        }
+        Ok(pi)
+    }
+}
+
+/// Represents public inputs of the proof of a block anchored to the chain (see `AnchoredBlockCircuit`).
+///
+/// # Fields
+///
+/// * `genesis` - The commitment to anchors the chain starts from.
+/// * `num_epochs` - The number of epochs proven by the chain.
+/// * `block` - Public inputs of the block proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchoredBlockPublicInputs<F: RichField> {
+    pub genesis: HashOut<F>,
+    pub num_epochs: u64,
+    pub block: BlockProofPublicInputs,
+}
+
+impl<F: RichField> AnchoredBlockPublicInputs<F> {
+    /// Decodes public inputs of the anchored block proof.
+    pub fn decode(pi: &[F]) -> Result<Self> {
+        if pi.len() < ANCHORED_BLOCK_PI_START {
+            return Err(BftError::InvalidInput(format!(
+                "anchored block proof has {} public inputs, expected at least {}",
+                pi.len(),
+                ANCHORED_BLOCK_PI_START
+            )));
+        }
+        Ok(Self {
+            genesis: HashOut::from_vec(pi[GENESIS_RANGE].to_vec()),
+            num_epochs: pi[ANCHORED_NUM_EPOCHS_INDEX].to_canonical_u64(),
+            block: BlockProofPublicInputs::decode(&pi[ANCHORED_BLOCK_PI_START..])?,
+        })
+    }
+
+    /// Checks that the block is anchored to the chain that starts from the trusted `genesis`
+    /// (see `EpochAnchors::commitment`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::HashMismatch` if the chain starts from another genesis.
+    pub fn check_genesis(&self, genesis: &HashOut<F>) -> Result<()> {
+        if self.genesis != *genesis {
+            return Err(BftError::HashMismatch(format!(
+                "block {} is anchored to the chain from genesis {:?}, expected {:?}",
+                self.block.block_hash, self.genesis, genesis
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Represents the circuit that anchors proofs of random blocks to the chain proof: the block must be proven
+/// against anchors of the chain, so it is a block of the epoch next to the last proven one.
+///
+/// Verifier data of the chain circuit, the hash of verifier data of epoch proofs and verifier data of block
+/// proofs are constants of the circuit, so its digest identifies all of them and a verifier only checks
+/// the genesis. Block proofs are normalized with `normalize_proof` to `EPOCH_PROOF_DEGREE_BITS` first, so
+/// blocks of all epochs are anchored by the same circuit.
+///
+/// Public inputs: genesis (4), the number of epochs (1) & public inputs of the block proof.
+pub struct AnchoredBlockCircuit<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    pub data: CircuitData<F, C, D>,
+    block_common: CommonCircuitData<F, D>,
+    block_verifier_only: VerifierOnlyCircuitData<C, D>,
+    chain: ProofWithPublicInputsTarget<D>,
+    block: ProofWithPublicInputsTarget<D>,
+}
+
+impl<F, C, const D: usize> AnchoredBlockCircuit<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    /// Builds the circuit anchoring block proofs of the given circuit to proofs of the chain circuit.
+    ///
+    /// # Arguments
+    ///
+    /// * `chain` - Verifier data of the chain circuit, e.g. of `EpochChainCircuit::data`.
+    /// * `epoch_verifier_hash` - The hash of verifier data of epoch proofs the chain verifies (see `verifier_data_hash`).
+    /// * `block` - Common & verifier data of the circuit of block proofs.
+    pub fn build(
+        chain: &VerifierCircuitData<F, C, D>,
+        epoch_verifier_hash: &HashOut<F>,
+        block: (&CommonCircuitData<F, D>, &VerifierOnlyCircuitData<C, D>),
+    ) -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let zero = builder.zero();
+        // Verify the chain proof by the chain circuit, its inner proofs are verified with
+        // verifier data from public inputs, so it must be the chain circuit too.
+        let chain_proof = builder.add_virtual_proof_with_pis(&chain.common);
+        let chain_verifier_data = builder.constant_verifier_data(&chain.verifier_only);
+        builder.verify_proof::<C>(&chain_proof, &chain_verifier_data, &chain.common);
+        let chain_pi = &chain_proof.public_inputs;
+        connect_arrays(
+            &mut builder,
+            &chain_pi[CHAIN_VERIFIER_DATA_START..],
+            &verifier_data_targets(&chain_verifier_data),
+        );
+        let epoch_verifier_hash = builder.constant_hash(*epoch_verifier_hash);
+        connect_arrays(
+            &mut builder,
+            &chain_pi[EPOCH_VERIFIER_HASH_RANGE],
+            &epoch_verifier_hash.elements,
+        );
+        // Verify the block proof of a random block against anchors of the chain.
+        let block_proof = builder.add_virtual_proof_with_pis(block.0);
+        let block_verifier_data = builder.constant_verifier_data(block.1);
+        builder.verify_proof::<C>(&block_proof, &block_verifier_data, block.0);
+        let block_pi = &block_proof.public_inputs;
+        builder.connect(block_pi[KIND_INDEX], zero);
+        let anchors = &chain_pi[ANCHORS_RANGE];
+        connect_arrays(
+            &mut builder,
+            &block_pi[EP2_LAST_BLOCK_HASH_RANGE],
+            &anchors[PK_HASH_BYTES..2 * PK_HASH_BYTES],
+        );
+        connect_arrays(
+            &mut builder,
+            &block_pi[EP1_FIRST_BLOCK_HASH_RANGE],
+            &anchors[2 * PK_HASH_BYTES..],
+        );
+        builder.register_public_inputs(&chain_pi[GENESIS_RANGE]);
+        builder.register_public_input(chain_pi[NUM_EPOCHS_INDEX]);
+        builder.register_public_inputs(block_pi);
+        Self {
+            data: builder.build::<C>(),
+            block_common: block.0.clone(),
+            block_verifier_only: block.1.clone(),
+            chain: chain_proof,
+            block: block_proof,
+        }
+    }
+
+    /// Anchors the block proof to the chain proof.
+    ///
+    /// # Arguments
+    ///
+    /// * `chain_proof` - The proof of the chain.
+    /// * `block` - The proof of the block with its circuit data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if the block is proven by another circuit or isn't a random block,
+    /// `BftError::HashMismatch` if it isn't proven against anchors of the chain.
+    pub fn prove(
+        &self,
+        chain_proof: &ProofWithPublicInputs<F, C, D>,
+        block: (
+            &CommonCircuitData<F, D>,
+            &VerifierOnlyCircuitData<C, D>,
+            &ProofWithPublicInputs<F, C, D>,
+        ),
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        if *block.0 != self.block_common || *block.1 != self.block_verifier_only {
+            return Err(BftError::InvalidInput(
+                "the block is proven by a circuit other than the anchoring circuit is built for"
+                    .to_string(),
+            ));
+        }
+        let chain_pi = EpochChainPublicInputs::decode(&chain_proof.public_inputs)?;
+        let block_pi = BlockProofPublicInputs::decode(&block.2.public_inputs)?;
+        if block_pi.kind != ProofKind::Random {
+            return Err(BftError::InvalidInput(
+                "only proofs of random blocks are anchored".to_string(),
+            ));
+        }
+        if !chain_pi.anchors.is_parent_of(&block_pi) {
+            return Err(BftError::HashMismatch(format!(
+                "block {} isn't proven against anchors of the chain {:?}",
+                block_pi.block_hash, chain_pi.anchors
+            )));
+        }
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&self.chain, chain_proof);
+        pw.set_proof_with_pis_target(&self.block, block.2);
+        self.data.prove(pw).map_err(BftError::proving)
+    }
+
+    /// Verifies the anchored block proof and checks that the chain starts from the trusted `genesis`
+    /// (see `EpochAnchors::commitment`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::Proving` if the proof is invalid and `BftError::HashMismatch` if the chain starts
+    /// from another genesis.
+    pub fn verify(
+        &self,
+        proof: &ProofWithPublicInputs<F, C, D>,
+        genesis: &HashOut<F>,
+    ) -> Result<AnchoredBlockPublicInputs<F>> {
+        self.data.verify(proof.clone()).map_err(BftError::proving)?;
+        let pi = AnchoredBlockPublicInputs::decode(&proof.public_inputs)?;
+        pi.check_genesis(genesis)?;
+        Ok(pi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prove_crypto::recursion::normalize_proof;
+    use anyhow::Result;
+    use plonky2::gates::noop::NoopGate;
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Proves a circuit exposing public inputs of a final block proof, in place of the final proof.
+    fn block_proof(
+        kind: ProofKind,
+        hashes: [u8; 3],
+    ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+        sized_block_proof(kind, hashes, 0)
+    }
+
+    /// Proves a circuit with `num_gates` extra gates exposing public inputs of a final block proof, in place of
+    /// the final proof of a block with more validators.
+    fn sized_block_proof(
+        kind: ProofKind,
+        hashes: [u8; 3],
+        num_gates: usize,
+    ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+        let pi = BlockProofPublicInputs::new(
+            kind,
+            CryptoHash([hashes[0]; 32]),
+            CryptoHash([hashes[1]; 32]),
+            CryptoHash([hashes[2]; 32]),
+        )
+        .encode::<F>();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = builder.add_virtual_targets(pi.len());
+        builder.register_public_inputs(&targets);
+        for _ in 0..num_gates {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        for (target, value) in targets.into_iter().zip(pi) {
+            pw.set_target(target, value);
+        }
+        let proof = data.prove(pw)?;
+        Ok((data, proof))
+    }
+
+    #[test]
+    fn test_epoch_chain() -> Result<()> {
+        // Genesis anchors: Block_n-1 (Epoch_g-3), Block_n-1 (Epoch_g-2), Block_0 (Epoch_g-1).
+        let (b0_data, b0_proof) = block_proof(ProofKind::Epoch, [10, 2, 3])?;
+        let (bn_1_data, bn_1_proof) = block_proof(ProofKind::Epoch, [11, 1, 2])?;
+        let chain = EpochChainCircuit::<F, C, D>::build(&b0_data.common, &bn_1_data.common)?;
+        let proof = chain.prove_step(
+            None,
+            (&b0_data.common, &b0_data.verifier_only, &b0_proof),
+            (&bn_1_data.common, &bn_1_data.verifier_only, &bn_1_proof),
+        )?;
+        let genesis = EpochAnchors {
+            prev_last_block_hash: CryptoHash([1; 32]),
+            last_block_hash: CryptoHash([2; 32]),
+            first_block_hash: CryptoHash([3; 32]),
+        }
+        .commitment();
+        let epoch_verifier_hash =
+            verifier_data_hash::<F, C, D>(&[&b0_data.verifier_only, &bn_1_data.verifier_only]);
+        let pi = chain.verify(&proof, &genesis, &epoch_verifier_hash)?;
+        assert_eq!(pi.genesis, genesis);
+        assert_eq!(pi.epoch_verifier_hash, epoch_verifier_hash);
+        assert_eq!(pi.num_epochs, 1);
+        // The chain of another genesis or of other circuits of epoch proofs isn't trusted.
+        let other_genesis = EpochAnchors {
+            prev_last_block_hash: CryptoHash([4; 32]),
+            last_block_hash: CryptoHash([2; 32]),
+            first_block_hash: CryptoHash([3; 32]),
+        }
+        .commitment();
+        assert!(matches!(
+            chain.verify(&proof, &other_genesis, &epoch_verifier_hash),
+            Err(BftError::HashMismatch(_))
+        ));
+        let other_verifier_hash =
+            verifier_data_hash::<F, C, D>(&[&bn_1_data.verifier_only, &b0_data.verifier_only]);
+        assert!(matches!(
+            chain.verify(&proof, &genesis, &other_verifier_hash),
+            Err(BftError::HashMismatch(_))
+        ));
+        assert_eq!(
+            pi.anchors,
+            EpochAnchors {
+                prev_last_block_hash: CryptoHash([2; 32]),
+                last_block_hash: CryptoHash([11; 32]),
+                first_block_hash: CryptoHash([10; 32]),
+            }
+        );
+
+        // The next epoch must be proven against new anchors.
+        let (b0_data, b0_proof) = block_proof(ProofKind::Epoch, [20, 11, 10])?;
+        let (bad_bn_1_data, bad_bn_1_proof) = block_proof(ProofKind::Epoch, [21, 1, 11])?;
+        assert!(matches!(
+            chain.prove_step(
+                Some(&proof),
+                (&b0_data.common, &b0_data.verifier_only, &b0_proof),
+                (
+                    &bad_bn_1_data.common,
+                    &bad_bn_1_data.verifier_only,
+                    &bad_bn_1_proof
+                ),
+            ),
+            Err(BftError::HashMismatch(_))
+        ));
+        let (bn_1_data, bn_1_proof) = block_proof(ProofKind::Epoch, [21, 2, 11])?;
+        let proof = chain.prove_step(
+            Some(&proof),
+            (&b0_data.common, &b0_data.verifier_only, &b0_proof),
+            (&bn_1_data.common, &bn_1_data.verifier_only, &bn_1_proof),
+        )?;
+        let next_pi = chain.verify(&proof, &genesis, &epoch_verifier_hash)?;
+        assert_eq!(next_pi.genesis, pi.genesis);
+        assert_eq!(next_pi.num_epochs, 2);
+        assert_eq!(next_pi.anchors.last_block_hash, CryptoHash([21; 32]));
+        assert_eq!(next_pi.anchors.first_block_hash, CryptoHash([20; 32]));
+
+        // A random block of the next epoch is anchored to the chain.
+        let (block_data, block_proof) = block_proof(ProofKind::Random, [30, 21, 20])?;
+        let anchored = AnchoredBlockCircuit::<F, C, D>::build(
+            &chain.data.verifier_data(),
+            &epoch_verifier_hash,
+            (&block_data.common, &block_data.verifier_only),
+        );
+        let anchored_proof = anchored.prove(
+            &proof,
+            (&block_data.common, &block_data.verifier_only, &block_proof),
+        )?;
+        let anchored_pi = anchored.verify(&anchored_proof, &genesis)?;
+        assert_eq!(anchored_pi.num_epochs, 2);
+        assert_eq!(anchored_pi.block.block_hash, CryptoHash([30; 32]));
+        assert!(matches!(
+            anchored.verify(&anchored_proof, &other_genesis),
+            Err(BftError::HashMismatch(_))
+        ));
+        // Blocks of other epochs aren't anchored.
+        let (_, old_block_proof) = block_proof(ProofKind::Random, [31, 11, 10])?;
+        assert!(matches!(
+            anchored.prove(
+                &proof,
+                (
+                    &block_data.common,
+                    &block_data.verifier_only,
+                    &old_block_proof
+                ),
+            ),
+            Err(BftError::HashMismatch(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_epoch_chain_of_different_circuits() -> Result<()> {
+        let normalize = |(data, proof): (CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)| {
+            normalize_proof::<F, C, D>(
+                (&data.common, &data.verifier_only, &proof),
+                EPOCH_PROOF_DEGREE_BITS,
+            )
+        };
+        let (b0_data, b0_proof) = normalize(block_proof(ProofKind::Epoch, [10, 2, 3])?)?;
+        let (bn_1_data, bn_1_proof) = normalize(block_proof(ProofKind::Epoch, [11, 1, 2])?)?;
+        let chain = EpochChainCircuit::<F, C, D>::build(&b0_data.common, &bn_1_data.common)?;
+        let proof = chain.prove_step(
+            None,
+            (&b0_data.common, &b0_data.verifier_only, &b0_proof),
+            (&bn_1_data.common, &bn_1_data.verifier_only, &bn_1_proof),
+        )?;
+
+        // The next epoch has more validators, so its blocks are proven by larger circuits.
+        let (next_b0_data, next_b0_proof) =
+            sized_block_proof(ProofKind::Epoch, [20, 11, 10], 1 << 10)?;
+        let next_bn_1 = sized_block_proof(ProofKind::Epoch, [21, 2, 11], 1 << 10)?;
+        assert_ne!(next_b0_data.common, b0_data.common);
+        assert!(matches!(
+            chain.prove_step(
+                Some(&proof),
+                (
+                    &next_b0_data.common,
+                    &next_b0_data.verifier_only,
+                    &next_b0_proof
+                ),
+                (
+                    &next_bn_1.0.common,
+                    &next_bn_1.0.verifier_only,
+                    &next_bn_1.1
+                ),
+            ),
+            Err(BftError::InvalidInput(_))
+        ));
+        // Normalized proofs are verified by the same circuits.
+        let (next_b0_data, next_b0_proof) = normalize((next_b0_data, next_b0_proof))?;
+        let (next_bn_1_data, next_bn_1_proof) = normalize(next_bn_1)?;
+        assert_eq!(next_b0_data.verifier_only, b0_data.verifier_only);
+        assert_eq!(next_bn_1_data.verifier_only, bn_1_data.verifier_only);
+        let proof = chain.prove_step(
+            Some(&proof),
+            (
+                &next_b0_data.common,
+                &next_b0_data.verifier_only,
+                &next_b0_proof,
+            ),
+            (
+                &next_bn_1_data.common,
+                &next_bn_1_data.verifier_only,
+                &next_bn_1_proof,
+            ),
+        )?;
+        let genesis = EpochAnchors::of_epoch_proofs(
+            &BlockProofPublicInputs::decode(&b0_proof.public_inputs)?,
+            &BlockProofPublicInputs::decode(&bn_1_proof.public_inputs)?,
+        )
+        .commitment();
+        let pi = chain.verify(
+            &proof,
+            &genesis,
+            &verifier_data_hash::<F, C, D>(&[&b0_data.verifier_only, &bn_1_data.verifier_only]),
+        )?;
+        assert_eq!(pi.num_epochs, 2);
+        assert_eq!(pi.anchors.first_block_hash, CryptoHash([20; 32]));
+        Ok(())
+    }
+}
@@ -1,2 +1,4 @@
 pub mod block_finality;
-pub mod bft;
\ No newline at end of file
+pub mod bft;
+pub mod epoch_chain;
+pub mod scheduler;
@@ -0,0 +1,462 @@
+use crate::error::{BftError, Result};
+use log::{warn, Level};
+use plonky2::util::timing::TimingTree;
+use rayon::{Scope, ThreadPool};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Represents one gibibyte, the unit of memory estimates of stages.
+pub const GIB: usize = 1 << 30;
+
+/// Represents the default memory budget of concurrently running stages.
+pub const DEFAULT_MEMORY_BUDGET: usize = 16 * GIB;
+
+/// Represents the id of a stage in `StageGraph`, it is used to declare dependencies and to take outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StageId(usize);
+
+type StageFn<'a, T> = Box<dyn FnOnce(Vec<Arc<T>>, &mut TimingTree) -> Result<T> + Send + 'a>;
+
+struct Stage<'a, T> {
+    name: String,
+    deps: Vec<StageId>,
+    memory: usize,
+    run: StageFn<'a, T>,
+}
+
+/// Represents proving stages with dependencies between them.
+///
+/// A stage may depend only on stages added before it, so the graph is acyclic by construction.
+pub struct StageGraph<'a, T> {
+    stages: Vec<Stage<'a, T>>,
+}
+
+impl<'a, T> Default for StageGraph<'a, T> {
+    fn default() -> Self {
+        Self { stages: vec![] }
+    }
+}
+
+impl<'a, T> StageGraph<'a, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of stages.
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Returns true if there are no stages.
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Adds a stage.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the stage, it is shown in the timing tree.
+    /// * `deps` - Stages whose outputs are required, they are passed to `run` in the same order.
+    /// * `memory` - The estimated peak memory of the stage in bytes.
+    /// * `run` - Proves the stage with outputs of `deps` and the timing tree of the stage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if some dependency is not a stage of this graph.
+    pub fn add(
+        &mut self,
+        name: &str,
+        deps: &[StageId],
+        memory: usize,
+        run: impl FnOnce(Vec<Arc<T>>, &mut TimingTree) -> Result<T> + Send + 'a,
+    ) -> StageId {
+        let id = StageId(self.stages.len());
+        assert!(
+            deps.iter().all(|dep| dep.0 < id.0),
+            "stage {} depends on an unknown stage",
+            name
+        );
+        self.stages.push(Stage {
+            name: name.to_string(),
+            deps: deps.to_vec(),
+            memory,
+            run: Box::new(run),
+        });
+        id
+    }
+}
+
+/// Represents outputs of stages of a finished graph.
+#[derive(Debug)]
+pub struct StageOutputs<T> {
+    names: Vec<String>,
+    outputs: Vec<Option<T>>,
+}
+
+impl<T> StageOutputs<T> {
+    /// Returns the output of the stage.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::Proving` if the output is already taken.
+    pub fn take(&mut self, id: StageId) -> Result<T> {
+        self.outputs[id.0]
+            .take()
+            .ok_or_else(|| BftError::Proving(format!("Output of {} is taken", self.names[id.0])))
+    }
+}
+
+/// Runs independent stages of `StageGraph` concurrently on a rayon pool.
+///
+/// A ready stage is started only if the sum of memory estimates of running stages fits the budget.
+/// A stage is always started if nothing is running, so a stage larger than the budget runs alone.
+/// The calling thread waits for stages, so `run` shouldn't be called from a thread of the pool.
+#[derive(Debug)]
+pub struct StageScheduler {
+    memory_budget: usize,
+    pool: Option<ThreadPool>,
+}
+
+impl Default for StageScheduler {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMORY_BUDGET)
+    }
+}
+
+impl StageScheduler {
+    /// Creates the scheduler that uses the global rayon thread pool.
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            memory_budget,
+            pool: None,
+        }
+    }
+
+    /// Creates the scheduler that runs one stage at a time.
+    pub fn sequential() -> Self {
+        Self::new(0)
+    }
+
+    /// Creates the scheduler with a dedicated thread pool of `num_threads` threads.
+    pub fn with_threads(memory_budget: usize, num_threads: usize) -> Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .map_err(|e| BftError::Proving(format!("Error building thread pool: {}", e)))?;
+        Ok(Self {
+            memory_budget,
+            pool: Some(pool),
+        })
+    }
+
+    /// Returns the memory budget in bytes.
+    pub fn memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+
+    /// Runs all stages of the graph and returns their outputs.
+    ///
+    /// Each stage has its own timing tree, printed when the stage is finished. `timing_tree` gets
+    /// the wall time of the graph and the critical path, i.e. the chain of dependent stages that
+    /// took the longest time.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first failed stage, a panic of a stage is returned as
+    /// `BftError::Proving`. Stages already running are finished, no new stages are started.
+    pub fn run<T: Send + Sync>(
+        &self,
+        graph: StageGraph<'_, T>,
+        timing_tree: &mut TimingTree,
+    ) -> Result<StageOutputs<T>> {
+        let names: Vec<String> = graph
+            .stages
+            .iter()
+            .map(|stage| stage.name.clone())
+            .collect();
+        let deps: Vec<Vec<StageId>> = graph
+            .stages
+            .iter()
+            .map(|stage| stage.deps.clone())
+            .collect();
+        timing_tree.push(
+            &format!(
+                "prove {} stages, memory budget {} GiB",
+                names.len(),
+                self.memory_budget / GIB
+            ),
+            Level::Info,
+        );
+        let result = match &self.pool {
+            Some(pool) => pool.in_place_scope(|scope| self.execute(scope, graph)),
+            None => rayon::in_place_scope(|scope| self.execute(scope, graph)),
+        };
+        let result = result.map(|(outputs, durations)| {
+            let path = critical_path(&deps, &durations);
+            let total: Duration = path.iter().map(|id| durations[id.0]).sum();
+            timing_tree.push(
+                &format!(
+                    "critical path of {} stages: {:.4}s",
+                    path.len(),
+                    total.as_secs_f64()
+                ),
+                Level::Info,
+            );
+            for id in path {
+                timing_tree.push(
+                    &format!("{}: {:.4}s", names[id.0], durations[id.0].as_secs_f64()),
+                    Level::Info,
+                );
+                timing_tree.pop();
+            }
+            timing_tree.pop();
+            outputs
+        });
+        timing_tree.pop();
+        let outputs = result?
+            .into_iter()
+            .map(|output| {
+                output.map(|output| {
+                    Arc::try_unwrap(output).unwrap_or_else(|_| unreachable!("stages are finished"))
+                })
+            })
+            .collect();
+        Ok(StageOutputs { names, outputs })
+    }
+
+    /// Starts ready stages on the pool and collects their outputs until all stages are finished.
+    fn execute<'a, 's, T: Send + Sync>(
+        &self,
+        scope: &Scope<'s>,
+        graph: StageGraph<'a, T>,
+    ) -> Result<(Vec<Option<Arc<T>>>, Vec<Duration>)>
+    where
+        'a: 's,
+    {
+        let num_stages = graph.stages.len();
+        let memory: Vec<usize> = graph.stages.iter().map(|stage| stage.memory).collect();
+        let mut pending: Vec<Option<Stage<'a, T>>> = graph.stages.into_iter().map(Some).collect();
+        let mut outputs: Vec<Option<Arc<T>>> = (0..num_stages).map(|_| None).collect();
+        let mut durations = vec![Duration::ZERO; num_stages];
+        let (sender, receiver) = mpsc::channel();
+        let mut running = 0;
+        let mut memory_in_use = 0;
+        let mut error = None;
+        loop {
+            if error.is_none() {
+                for id in 0..num_stages {
+                    let ready = pending[id]
+                        .as_ref()
+                        .is_some_and(|stage| stage.deps.iter().all(|dep| outputs[dep.0].is_some()));
+                    if !ready || (running > 0 && memory_in_use + memory[id] > self.memory_budget) {
+                        continue;
+                    }
+                    let stage = pending[id].take().expect("stage is pending");
+                    let inputs: Vec<Arc<T>> = stage
+                        .deps
+                        .iter()
+                        .map(|dep| outputs[dep.0].clone().expect("dependency is finished"))
+                        .collect();
+                    running += 1;
+                    memory_in_use += memory[id];
+                    let sender = sender.clone();
+                    scope.spawn(move |_| {
+                        let mut stage_timing = TimingTree::new(&stage.name, Level::Info);
+                        let start = Instant::now();
+                        // A panicking stage fails the graph, otherwise it would wait for its output forever.
+                        let output = panic::catch_unwind(AssertUnwindSafe(|| {
+                            (stage.run)(inputs, &mut stage_timing)
+                        }))
+                        .unwrap_or_else(|payload| {
+                            stage_timing = TimingTree::new(&stage.name, Level::Info);
+                            Err(BftError::Proving(format!(
+                                "Stage {} panicked: {}",
+                                stage.name,
+                                panic_message(payload.as_ref())
+                            )))
+                        });
+                        if let Err(e) = &output {
+                            warn!("Stage {} failed: {}", stage.name, e);
+                        }
+                        // The receiver lives until all started stages are finished.
+                        let _ = sender.send((id, output, start.elapsed(), stage_timing));
+                    });
+                }
+            }
+            if running == 0 {
+                break;
+            }
+            let (id, output, elapsed, stage_timing) =
+                receiver.recv().expect("a running stage holds the sender");
+            running -= 1;
+            memory_in_use -= memory[id];
+            durations[id] = elapsed;
+            stage_timing.print();
+            match output {
+                Ok(output) => outputs[id] = Some(Arc::new(output)),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok((outputs, durations)),
+        }
+    }
+}
+
+/// Returns the message of the panic payload, it is a `&str` or a `String` for `panic!` with a message.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Returns the chain of dependent stages with the largest sum of durations.
+fn critical_path(deps: &[Vec<StageId>], durations: &[Duration]) -> Vec<StageId> {
+    // Dependencies precede stages, so finish times are computed in one pass.
+    let mut finish = vec![Duration::ZERO; deps.len()];
+    let mut prev: Vec<Option<StageId>> = vec![None; deps.len()];
+    for id in 0..deps.len() {
+        let slowest = deps[id].iter().max_by_key(|dep| finish[dep.0]).copied();
+        finish[id] = slowest.map_or(Duration::ZERO, |dep| finish[dep.0]) + durations[id];
+        prev[id] = slowest;
+    }
+    let mut path = vec![];
+    let mut last = (0..deps.len()).max_by_key(|&id| finish[id]).map(StageId);
+    while let Some(id) = last {
+        path.push(id);
+        last = prev[id.0];
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::thread::sleep;
+
+    #[test]
+    fn test_stages_respect_dependencies() -> Result<()> {
+        let order = Mutex::new(vec![]);
+        let mut graph = StageGraph::new();
+        let log = |name: &'static str| {
+            order.lock().unwrap().push(name);
+        };
+        let a = graph.add("a", &[], GIB, |_, _| {
+            log("a");
+            Ok(1)
+        });
+        let b = graph.add("b", &[], GIB, |_, _| {
+            log("b");
+            Ok(2)
+        });
+        let c = graph.add("c", &[a, b], GIB, |inputs, _| {
+            log("c");
+            Ok(*inputs[0] * 10 + *inputs[1])
+        });
+        let d = graph.add("d", &[c, a], GIB, |inputs, _| {
+            log("d");
+            Ok(*inputs[0] + *inputs[1])
+        });
+        let mut timing = TimingTree::new("test", Level::Debug);
+        let mut outputs = StageScheduler::new(4 * GIB).run(graph, &mut timing)?;
+        assert_eq!(outputs.take(c)?, 12);
+        assert_eq!(outputs.take(d)?, 13);
+        assert!(outputs.take(d).is_err());
+        let order = order.into_inner().unwrap();
+        assert_eq!(&order[2..], ["c", "d"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_budget_limits_concurrency() -> Result<()> {
+        for (budget, expected) in [(0, 1), (2 * GIB, 2), (8 * GIB, 4)] {
+            let running = AtomicUsize::new(0);
+            let max_running = AtomicUsize::new(0);
+            let mut graph = StageGraph::new();
+            for i in 0..4 {
+                let (running, max_running) = (&running, &max_running);
+                graph.add(&format!("stage {i}"), &[], GIB, move |_, _| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now, Ordering::SeqCst);
+                    sleep(Duration::from_millis(50));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(i)
+                });
+            }
+            let scheduler = StageScheduler::with_threads(budget, 4)?;
+            scheduler.run(graph, &mut TimingTree::new("test", Level::Debug))?;
+            assert!(max_running.load(Ordering::SeqCst) <= expected);
+            if expected == 1 {
+                assert_eq!(max_running.load(Ordering::SeqCst), 1);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_stage_stops_graph() {
+        let started = AtomicUsize::new(0);
+        let mut graph = StageGraph::new();
+        let a = graph.add("a", &[], GIB, |_, _| {
+            started.fetch_add(1, Ordering::SeqCst);
+            Err::<u32, _>(BftError::Proving("a failed".to_string()))
+        });
+        graph.add("b", &[a], GIB, |_, _| {
+            started.fetch_add(1, Ordering::SeqCst);
+            Ok(1)
+        });
+        let result =
+            StageScheduler::sequential().run(graph, &mut TimingTree::new("test", Level::Debug));
+        assert!(matches!(result, Err(BftError::Proving(_))));
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_panicked_stage_fails_graph() {
+        let mut graph = StageGraph::new();
+        let a = graph.add("a", &[], GIB, |_, _| Ok(1));
+        graph.add("b", &[a], GIB, |_, _| -> crate::error::Result<u32> {
+            panic!("b panicked")
+        });
+        graph.add("c", &[], GIB, |_, _| Ok(3));
+        let result = StageScheduler::with_threads(4 * GIB, 2)
+            .expect("thread pool")
+            .run(graph, &mut TimingTree::new("test", Level::Debug));
+        match result {
+            Err(BftError::Proving(e)) => assert!(e.contains("b panicked"), "{e}"),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_critical_path() {
+        let ms = Duration::from_millis;
+        // a -> c, b -> c, c -> d, e
+        let deps = vec![
+            vec![],
+            vec![],
+            vec![StageId(0), StageId(1)],
+            vec![StageId(2)],
+            vec![],
+        ];
+        let durations = [ms(10), ms(30), ms(5), ms(5), ms(35)];
+        assert_eq!(
+            critical_path(&deps, &durations),
+            vec![StageId(1), StageId(2), StageId(3)]
+        );
+        assert!(critical_path(&[], &[]).is_empty());
+    }
+}
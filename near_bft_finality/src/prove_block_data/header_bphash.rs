@@ -1,4 +1,4 @@
-use anyhow::Result;
+use crate::error::{BftError, Result};
 use near_primitives::borsh;
 use near_primitives::hash::hash;
 use plonky2::hash::hash_types::RichField;
@@ -37,6 +37,41 @@ pub fn prove_header_hash<F: RichField + Extendable<D>, C: GenericConfig<D, F = F
     public_inputs: Option<&[F]>,
     timing_tree: &mut TimingTree,
 ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+where
+    C::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    // Check the header hash before proving: hash(hash(hash(inner_lite) || hash(inner_rest)) || prev_hash).
+    let inner_hash = hash(
+        &[
+            hash(&header_data.inner_lite).0,
+            hash(&header_data.inner_rest).0,
+        ]
+        .concat(),
+    );
+    let computed_hash = hash(&[inner_hash.0.as_slice(), &header_data.prev_hash].concat());
+    if computed_hash.0.as_slice() != header_hash {
+        return Err(BftError::HashMismatch(format!(
+            "header hash {} doesn't match the computed one {}",
+            hex::encode(header_hash),
+            hex::encode(computed_hash.0)
+        )));
+    }
+    prove_checked_header_hash(header_hash, header_data, public_inputs, timing_tree)
+}
+
+/// Proves the header hash of `prove_header_hash` after its native check. The circuits reject
+/// a header that doesn't match `header_hash` on their own, tests call it to check that.
+fn prove_checked_header_hash<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    header_hash: &[u8],
+    header_data: HeaderData,
+    public_inputs: Option<&[F]>,
+    timing_tree: &mut TimingTree,
+) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
 where
     C::Hasher: AlgebraicHasher<F>,
     [(); C::Hasher::HASH_SIZE]:,
@@ -91,7 +126,7 @@ where
             None,
         )?
     );
-    d4.verify(p4.clone())?;
+    d4.verify(p4.clone()).map_err(BftError::proving)?;
     // Verify (d4, p4) to set public_inputs as PI.
     if let Some(PI) = public_inputs {
         let (d5, p5) = timed!(
@@ -126,14 +161,19 @@ pub fn prove_bp_hash<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     bp_hash: &[u8],
     validators: Vec<Vec<u8>>,
 ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
-    let validators_len = u32::try_from(validators.len())?;
+    let validators_len = u32::try_from(validators.len())
+        .map_err(|_| BftError::InvalidInput(format!("too many validators: {}", validators.len())))?;
     let mut final_bytes: Vec<u8> = vec![];
     final_bytes.append(&mut validators_len.to_le_bytes().to_vec());
-    let count = validators
-        .iter()
-        .map(|value| final_bytes.append(&mut (*value).to_vec()))
-        .count();
-    assert_eq!(count, validators.len());
+    for value in validators.iter() {
+        final_bytes.extend_from_slice(value);
+    }
+    if hash(&final_bytes).0.as_slice() != bp_hash {
+        return Err(BftError::HashMismatch(format!(
+            "bp_hash {} doesn't match the hash of validators",
+            hex::encode(bp_hash)
+        )));
+    }
     let (data, proof) = sha256_proof_u32::<F, C, D>(&final_bytes, bp_hash)?;
     Ok((data, proof))
 }
@@ -219,4 +259,68 @@ mod tests {
         info!("Bp_hash proof size: {} bytes", proof.to_bytes().len());
         Ok(())
     }
+
+    /// Native checks reject a changed header before proving, so the circuits get it directly:
+    /// they must reject it themselves.
+    #[test]
+    #[should_panic]
+    fn test_header_hash_circuits_reject_changed_header() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let path = "../data/block_header_small.json".to_string();
+        let (block_hash, block_header) = load_block_header(&path).unwrap();
+        let header_bytes = borsh::to_vec(&block_header).unwrap();
+        let header_hash_bytes = borsh::to_vec(&block_hash).unwrap();
+        let mut inner_lite = header_bytes
+            [(TYPE_BYTE + PK_HASH_BYTES)..(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES)]
+            .to_vec();
+        inner_lite[0] ^= 1;
+
+        let mut timing_tree = TimingTree::new("prove hash of changed header", Level::Info);
+        prove_checked_header_hash::<F, C, D>(
+            &header_hash_bytes,
+            HeaderData {
+                prev_hash: header_bytes[TYPE_BYTE..(TYPE_BYTE + PK_HASH_BYTES)].to_vec(),
+                inner_lite,
+                inner_rest: header_bytes[(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES)
+                    ..(header_bytes.len() - TYPE_BYTE - SIG_BYTES)]
+                    .to_vec(),
+            },
+            None,
+            &mut timing_tree,
+        )
+        .unwrap();
+    }
+
+    /// Native checks reject changed validators before proving, so the circuit gets them directly:
+    /// it must reject them itself.
+    #[test]
+    #[should_panic]
+    fn test_bp_hash_circuit_rejects_changed_validators() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let path = "../data/validators_ordered_small.json".to_string();
+        let validators = load_validators(&path).unwrap();
+        let path = "../data/prev_epoch_block_header_small.json".to_string();
+        let (_, prev_epoch_block_header) = load_block_header(&path).unwrap();
+        let prev_epoch_block_header_bytes = borsh::to_vec(&prev_epoch_block_header).unwrap();
+        let bp_hash = prev_epoch_block_header_bytes[(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES
+            - PK_HASH_BYTES
+            - PK_HASH_BYTES)
+            ..(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES - PK_HASH_BYTES)]
+            .to_vec();
+
+        let mut final_bytes = (validators.len() as u32).to_le_bytes().to_vec();
+        for value in validators.iter() {
+            final_bytes.extend_from_slice(&borsh::to_vec(value).unwrap());
+        }
+        // Change the stake of the last validator.
+        let last = final_bytes.len() - 1;
+        final_bytes[last] ^= 1;
+        sha256_proof_u32::<F, C, D>(&final_bytes, &bp_hash).unwrap();
+    }
 }
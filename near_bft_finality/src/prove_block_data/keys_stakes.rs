@@ -1,4 +1,4 @@
-use anyhow::Result;
+use crate::error::{BftError, Result};
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::target::{BoolTarget, Target};
 use plonky2::iop::witness::{PartialWitness, WitnessWrite};
@@ -15,6 +15,7 @@ use crate::types::*;
 /// Prove the existence of chosen keys while proving signatures in the validators list.
 /// Prove that the list of valid keys gives 2/3 of the total sum of all stakes.
 /// Public inputs: a set of valid keys with their indices & 2/3 of the total sum of all stakes.
+/// Indices are encoded as `VALIDATOR_INDEX_BYTES` little-endian bytes preceding each key.
 pub fn prove_valid_keys_stakes_in_valiators_list<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -28,6 +29,27 @@ where
     C::Hasher: AlgebraicHasher<F>,
     [(); C::Hasher::HASH_SIZE]:,
 {
+    if valid_keys.len() % (VALIDATOR_INDEX_BYTES + PK_HASH_BYTES) != 0 {
+        return Err(BftError::InvalidInput(format!(
+            "valid_keys length {} isn't a multiple of {}",
+            valid_keys.len(),
+            VALIDATOR_INDEX_BYTES + PK_HASH_BYTES
+        )));
+    }
+    if let Some(record) = valid_keys
+        .chunks(VALIDATOR_INDEX_BYTES + PK_HASH_BYTES)
+        .find(|record| {
+            let mut pos_bytes = [0u8; VALIDATOR_INDEX_BYTES];
+            pos_bytes.copy_from_slice(&record[..VALIDATOR_INDEX_BYTES]);
+            u32::from_le_bytes(pos_bytes) as usize >= validators.len()
+        })
+    {
+        return Err(BftError::InvalidInput(format!(
+            "validator index {:?} is out of range of {} validators",
+            &record[..VALIDATOR_INDEX_BYTES],
+            validators.len()
+        )));
+    }
     let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
     let zero = builder.zero();
     let neg_one = builder.neg_one();
@@ -62,14 +84,22 @@ where
     const STAKE_SUM_LEN: usize = STAKE_BYTES + 1;
     let mut valid_stake_sum: Vec<Target> = [builder.zero(); STAKE_SUM_LEN].to_vec();
     // Count a sum of stakes for a list of valid keys & check whether a list of validators contains these valid keys.
-    for i in (0..valid_keys_values.len()).step_by(PK_HASH_BYTES + 1) {
-        let pos = valid_keys_values[i].to_noncanonical_u64() as usize;
+    // Each valid key is stored as the validator index (VALIDATOR_INDEX_BYTES, little-endian) followed by the key.
+    for i in (0..valid_keys_values.len()).step_by(VALIDATOR_INDEX_BYTES + PK_HASH_BYTES) {
+        let pos_bytes: [u8; VALIDATOR_INDEX_BYTES] =
+            core::array::from_fn(|j| valid_keys_values[i + j].to_noncanonical_u64() as u8);
+        let pos = u32::from_le_bytes(pos_bytes) as usize;
         let len = all_validators_targets[pos].len();
+        // Check index, so the validator chosen by the circuit is the one committed in valid_keys.
+        for (j, byte) in pos_bytes.iter().enumerate() {
+            let index_byte = builder.constant(F::from_canonical_u8(*byte));
+            builder.connect(valid_keys_targets[i + j], index_byte);
+        }
         // Check key.
         for j in 0..PK_HASH_BYTES {
             builder.connect(
                 all_validators_targets[pos][(len - STAKE_BYTES - PK_HASH_BYTES) + j],
-                valid_keys_targets[(i + 1) + j],
+                valid_keys_targets[(i + VALIDATOR_INDEX_BYTES) + j],
             );
         }
         // Compute sum of valid stakes.
@@ -241,7 +271,7 @@ where
     builder.register_public_inputs(&valid_keys_targets);
     builder.register_public_inputs(&valid_stake_sum);
     let keys_stakes_data = builder.build();
-    let keys_stakes_proof = keys_stakes_data.prove(pw)?;
+    let keys_stakes_proof = keys_stakes_data.prove(pw).map_err(BftError::proving)?;
     // Check if valid_keys correnpond to valid_keys_hash that was set during signature verification.
     let len = keys_stakes_proof.public_inputs.len() - valid_stake_sum.len();
     let keys: Vec<u8> = keys_stakes_proof.public_inputs[0..len]
@@ -274,9 +304,32 @@ mod tests {
     };
     use anyhow::Result;
     use log::info;
-    use near_crypto::{KeyType, SecretKey};
-    use near_primitives::borsh::BorshSerialize;
+    use near_crypto::{KeyType, PublicKey, SecretKey, Signature};
+    use near_primitives::borsh::{BorshDeserialize, BorshSerialize};
+    use near_primitives::hash::hash;
+    use near_primitives::types::validator_stake::ValidatorStake;
     use plonky2::plonk::config::PoseidonGoldilocksConfig;
+    use plonky2_field::types::PrimeField64;
+
+    /// Generate a list of borsh-serialized validators with the same stake.
+    fn synthetic_validators(num: usize, stake: u128) -> Vec<Vec<u8>> {
+        (0..num)
+            .map(|i| {
+                let sk = SecretKey::from_seed(KeyType::ED25519, &format!("validator{i}"));
+                let account_id = format!("validator{i}.near").parse().unwrap();
+                borsh::to_vec(&ValidatorStake::new_v1(account_id, sk.public_key(), stake)).unwrap()
+            })
+            .collect()
+    }
+
+    /// Set a valid key in the format: validator index (u32, little-endian) followed by the public key.
+    fn push_valid_key(valid_keys: &mut Vec<u8>, pos: usize, validator: &[u8]) {
+        let validator_len = validator.len();
+        valid_keys.extend_from_slice(&(pos as u32).to_le_bytes());
+        valid_keys.extend_from_slice(
+            &validator[(validator_len - STAKE_BYTES - PK_HASH_BYTES)..(validator_len - STAKE_BYTES)],
+        );
+    }
 
     #[test]
     fn test_prove_block_prove_valid_keys_stakes_in_validators_list() -> Result<()> {
@@ -319,12 +372,7 @@ mod tests {
                 )?;
                 let verify: bool = sig.verify(&msg_to_sign, &pk);
                 if verify {
-                    valid_keys.push(pos as u8);
-                    valid_keys.append(
-                        &mut validators_bytes[pos][(validator_len - STAKE_BYTES - PK_HASH_BYTES)
-                            ..(validator_len - STAKE_BYTES)]
-                            .to_vec(),
-                    );
+                    push_valid_key(&mut valid_keys, pos, &validators_bytes[pos]);
                 }
             }
         }
@@ -340,4 +388,58 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_prove_valid_keys_stakes_with_more_than_256_validators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        const NUM_VALIDATORS: usize = 300;
+        let validators_bytes = synthetic_validators(NUM_VALIDATORS, 1_000_000_000_000_000_000_000_000);
+        // Exactly 2/3 of validators, including ones with indices above u8::MAX.
+        let mut valid_keys: Vec<u8> = vec![];
+        for pos in (0..NUM_VALIDATORS).filter(|pos| pos % 3 != 0) {
+            push_valid_key(&mut valid_keys, pos, &validators_bytes[pos]);
+        }
+        let valid_keys_hash = hash(&valid_keys).0.to_vec();
+        let (data, proof) = prove_valid_keys_stakes_in_valiators_list::<F, C, D>(
+            valid_keys.clone(),
+            valid_keys_hash,
+            validators_bytes,
+        )?;
+        // The indices are set as PI, so they should be decoded without truncation.
+        let keys: Vec<u8> = proof.public_inputs[0..valid_keys.len()]
+            .iter()
+            .map(|x| x.to_canonical_u64() as u8)
+            .collect();
+        assert_eq!(keys, valid_keys);
+        let last = keys.len() - (VALIDATOR_INDEX_BYTES + PK_HASH_BYTES);
+        let last_index = u32::from_le_bytes(keys[last..last + VALIDATOR_INDEX_BYTES].try_into()?);
+        assert_eq!(last_index, (NUM_VALIDATORS - 1) as u32);
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_prove_valid_keys_stakes_with_wrapped_validator_index() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        const NUM_VALIDATORS: usize = 300;
+        let validators_bytes = synthetic_validators(NUM_VALIDATORS, 1_000_000_000_000_000_000_000_000);
+        let mut valid_keys: Vec<u8> = vec![];
+        for pos in (0..NUM_VALIDATORS).filter(|pos| pos % 3 != 0) {
+            push_valid_key(&mut valid_keys, pos, &validators_bytes[pos]);
+        }
+        // Set the key of validator 289 with the index 33 (289 wrapped into u8).
+        push_valid_key(&mut valid_keys, 33, &validators_bytes[289]);
+        let valid_keys_hash = hash(&valid_keys).0.to_vec();
+        let _ = prove_valid_keys_stakes_in_valiators_list::<F, C, D>(
+            valid_keys,
+            valid_keys_hash,
+            validators_bytes,
+        );
+    }
 }
@@ -1,4 +1,5 @@
 pub mod primitives;
 pub mod header_bphash;
 pub mod signatures;
-pub mod keys_stakes;
\ No newline at end of file
+pub mod keys_stakes;
+pub mod signature_prover;
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use crate::error::{BftError, Result};
 use itertools::Itertools;
 use log::Level;
 use plonky2::iop::target::{BoolTarget, Target};
@@ -37,8 +37,14 @@ pub fn prove_consecutive_heights<
     height1: &[u8], // little-endian byte order
     height2: &[u8], // little-endian byte order
 ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
-    assert_eq!(height1.len(), BLOCK_HEIGHT_BYTES);
-    assert_eq!(height2.len(), BLOCK_HEIGHT_BYTES);
+    if height1.len() != BLOCK_HEIGHT_BYTES || height2.len() != BLOCK_HEIGHT_BYTES {
+        return Err(BftError::InvalidInput(format!(
+            "heights should be {} bytes, got {} and {}",
+            BLOCK_HEIGHT_BYTES,
+            height1.len(),
+            height2.len()
+        )));
+    }
     let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
     let height1_values: Vec<F> = height1.iter().map(|x| F::from_canonical_u8(*x)).collect();
     let height2_values: Vec<F> = height2.iter().map(|x| F::from_canonical_u8(*x)).collect();
@@ -105,9 +111,9 @@ pub fn prove_consecutive_heights<
     let data = builder.build();
     timing.print();
     let timing = TimingTree::new("prove", Level::Info);
-    let proof = data.prove(pw)?;
+    let proof = data.prove(pw).map_err(BftError::proving)?;
     timing.print();
-    data.verify(proof.clone())?;
+    data.verify(proof.clone()).map_err(BftError::proving)?;
     Ok((data, proof))
 }
 
@@ -123,11 +129,34 @@ pub fn prove_consecutive_heights<
 /// - `CircuitData<F, C, D>`: The circuit data generated during the proof generation process.
 /// - `ProofWithPublicInputs<F, C, D>`: The proof along with an array of bytes as public inputs.
 ///
+/// # Errors
+///
+/// Returns `BftError::HashMismatch` if arrays are not equal.
+///
 pub fn prove_eq_array<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     array1: &[u8],
     array2: &[u8],
 ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
-    assert_eq!(array1.len(), array2.len());
+    if array1 != array2 {
+        return Err(BftError::HashMismatch(format!(
+            "{} != {}",
+            hex::encode(array1),
+            hex::encode(array2)
+        )));
+    }
+    prove_checked_eq_array(array1, array2)
+}
+
+/// Proves equality of arrays of `prove_eq_array` after its native check. The circuit rejects
+/// different arrays on its own, tests call it to check that.
+fn prove_checked_eq_array<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    array1: &[u8],
+    array2: &[u8],
+) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
     let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
     let array1_values: Vec<F> = array1.iter().map(|x| F::from_canonical_u8(*x)).collect();
     let array2_values: Vec<F> = array2.iter().map(|x| F::from_canonical_u8(*x)).collect();
@@ -155,9 +184,9 @@ pub fn prove_eq_array<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     let data = builder.build();
     timing.print();
     let timing = TimingTree::new("prove", Level::Info);
-    let proof = data.prove(pw)?;
+    let proof = data.prove(pw).map_err(BftError::proving)?;
     timing.print();
-    data.verify(proof.clone())?;
+    data.verify(proof.clone()).map_err(BftError::proving)?;
     Ok((data, proof))
 }
 
@@ -340,7 +369,7 @@ where
     let data = builder.build();
     timing.print();
     let timing = TimingTree::new("prove", Level::Info);
-    let proof = data.prove(pw)?;
+    let proof = data.prove(pw).map_err(BftError::proving)?;
     timing.print();
     Ok((data, proof))
 }
@@ -432,10 +461,23 @@ mod tests {
 
         let s1 = "hello".to_string();
         let s2 = "olleh".to_string();
-        let (data, proof) = prove_eq_array::<F, C, D>(s1.as_bytes(), s2.as_bytes()).unwrap();
+        let (data, proof) =
+            prove_checked_eq_array::<F, C, D>(s1.as_bytes(), s2.as_bytes()).unwrap();
         data.verify(proof).unwrap();
     }
 
+    #[test]
+    fn test_not_equal_returns_hash_mismatch() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let s1 = "hello".to_string();
+        let s2 = "olleh".to_string();
+        let result = prove_eq_array::<F, C, D>(s1.as_bytes(), s2.as_bytes());
+        assert!(matches!(result, Err(BftError::HashMismatch(_))));
+    }
+
     #[test]
     fn test_heights() -> Result<()> {
         const D: usize = 2;
@@ -0,0 +1,667 @@
+use crate::error::{BftError, Result};
+use crate::prove_crypto::ed25519::{ed25519_proof_with_targets, get_ed25519_targets};
+use crate::types::*;
+use futures::StreamExt;
+use log::{info, warn};
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::{CircuitData, VerifierOnlyCircuitData};
+use plonky2::plonk::config::GenericConfig;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2_ed25519::gadgets::eddsa::EDDSATargets;
+use plonky2_field::extension::Extendable;
+use rayon::prelude::*;
+use rayon::ThreadPool;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::Future;
+use std::time::Duration;
+use tokio::runtime::{Handle, Runtime};
+
+/// Represents a signature to be proven.
+///
+/// # Fields
+///
+/// * `signature_index` - The index of the signature in array of approvals (the validator index).
+/// * `signature` - ED25519 signature (64 bytes).
+/// * `public_key` - ED25519 public key of the validator (32 bytes).
+#[derive(Debug, Clone)]
+pub struct SignatureTask {
+    pub signature_index: usize,
+    pub signature: Vec<u8>,
+    pub public_key: Vec<u8>,
+}
+
+/// Represents a backend that proves ED25519 signatures of approvals.
+///
+/// Proofs are aggregated by `prove_approvals_with_prover`, so implementations only prove
+/// single signatures using the circuit built for the message.
+pub trait SignatureProver<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>:
+    Send + Sync
+{
+    /// Proves signatures of `msg`.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message signed by validators.
+    /// * `tasks` - Signatures to be proven.
+    /// * `circuit_data` - ED25519 circuit built for the length of `msg`.
+    /// * `targets` - Targets of the circuit.
+    ///
+    /// # Returns
+    ///
+    /// Returns proofs of `circuit_data` with signature indices, one for each task.
+    fn prove_signatures(
+        &self,
+        msg: &[u8],
+        tasks: &[SignatureTask],
+        circuit_data: &CircuitData<F, C, D>,
+        targets: &EDDSATargets,
+    ) -> Result<Vec<(usize, ProofWithPublicInputs<F, C, D>)>>;
+}
+
+/// Proves signatures one by one in the current thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalSignatureProver;
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    SignatureProver<F, C, D> for LocalSignatureProver
+{
+    fn prove_signatures(
+        &self,
+        msg: &[u8],
+        tasks: &[SignatureTask],
+        circuit_data: &CircuitData<F, C, D>,
+        targets: &EDDSATargets,
+    ) -> Result<Vec<(usize, ProofWithPublicInputs<F, C, D>)>> {
+        tasks
+            .iter()
+            .map(|task| {
+                let proof = ed25519_proof_with_targets(
+                    msg,
+                    &task.signature,
+                    &task.public_key,
+                    circuit_data,
+                    targets,
+                )?;
+                Ok::<_, BftError>((task.signature_index, proof))
+            })
+            .collect()
+    }
+}
+
+/// Proves signatures in parallel on a rayon thread pool.
+#[derive(Debug, Default)]
+pub struct RayonSignatureProver {
+    pool: Option<ThreadPool>,
+}
+
+impl RayonSignatureProver {
+    /// Creates the prover that uses the global rayon thread pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the prover with a dedicated thread pool of `num_threads` threads.
+    pub fn with_threads(num_threads: usize) -> Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .map_err(|e| BftError::Proving(format!("Error building thread pool: {}", e)))?;
+        Ok(Self { pool: Some(pool) })
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    SignatureProver<F, C, D> for RayonSignatureProver
+{
+    fn prove_signatures(
+        &self,
+        msg: &[u8],
+        tasks: &[SignatureTask],
+        circuit_data: &CircuitData<F, C, D>,
+        targets: &EDDSATargets,
+    ) -> Result<Vec<(usize, ProofWithPublicInputs<F, C, D>)>> {
+        let prove = || -> Result<Vec<(usize, ProofWithPublicInputs<F, C, D>)>> {
+            tasks
+                .par_iter()
+                .map(|task| {
+                    let proof = ed25519_proof_with_targets(
+                        msg,
+                        &task.signature,
+                        &task.public_key,
+                        circuit_data,
+                        targets,
+                    )?;
+                    Ok::<_, BftError>((task.signature_index, proof))
+                })
+                .collect()
+        };
+        match &self.pool {
+            Some(pool) => pool.install(prove),
+            None => prove(),
+        }
+    }
+}
+
+/// Represents a job queue used by `DistributedSignatureProver` to send tasks to workers.
+pub trait SignatureTransport: Send + Sync {
+    /// Subscribes to results of the request. It is called before tasks of the request are published.
+    fn subscribe(&self, request_id: &str) -> Result<Box<dyn SignatureResults + '_>>;
+
+    /// Publishes the task to workers.
+    fn publish(&self, task: &InputTask) -> Result<()>;
+}
+
+/// Represents a subscription to results of a request.
+pub trait SignatureResults {
+    /// Waits for the next serialized `OutputTask`. Returns `None` if nothing arrived within `timeout`.
+    fn next_result(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>>;
+}
+
+/// Proves signatures by workers (see `signature_worker`) connected through a transport.
+///
+/// Each call uses a unique request id to correlate results. Results of other requests, duplicates
+/// and proofs of another circuit are ignored. If no result arrives within `timeout`, pending tasks are
+/// republished. After `max_retries` timeouts in a row without a new proof, `BftError::Proving` is
+/// returned.
+#[derive(Debug)]
+pub struct DistributedSignatureProver<T: SignatureTransport> {
+    transport: T,
+    timeout: Duration,
+    max_retries: usize,
+}
+
+impl<T: SignatureTransport> DistributedSignatureProver<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            timeout: Duration::from_secs(SIGNATURE_TASK_TIMEOUT_SECS),
+            max_retries: SIGNATURE_TASK_MAX_RETRIES,
+        }
+    }
+
+    /// Sets the time to wait for any result before republishing lost tasks.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the number of times lost tasks are republished without receiving a new proof.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+}
+
+impl<T, F, C, const D: usize> SignatureProver<F, C, D> for DistributedSignatureProver<T>
+where
+    T: SignatureTransport,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    fn prove_signatures(
+        &self,
+        msg: &[u8],
+        tasks: &[SignatureTask],
+        circuit_data: &CircuitData<F, C, D>,
+        _targets: &EDDSATargets,
+    ) -> Result<Vec<(usize, ProofWithPublicInputs<F, C, D>)>> {
+        let request_id = format!("{:016x}", rand::random::<u64>());
+        let mut results = self.transport.subscribe(&request_id)?;
+        let input_tasks: BTreeMap<usize, InputTask> = tasks
+            .iter()
+            .map(|task| {
+                (
+                    task.signature_index,
+                    InputTask {
+                        request_id: request_id.clone(),
+                        message: msg.to_vec(),
+                        approval: task.signature.clone(),
+                        validator: task.public_key.clone(),
+                        signature_index: task.signature_index,
+                    },
+                )
+            })
+            .collect();
+        for input_task in input_tasks.values() {
+            self.transport.publish(input_task)?;
+        }
+        // Indices of signatures, which proofs are not received yet.
+        let mut pending: BTreeSet<usize> = input_tasks.keys().copied().collect();
+        let mut proofs = Vec::with_capacity(pending.len());
+        // Timeouts since the last accepted proof, so slow workers don't fail a long batch.
+        let mut retries = 0;
+        while !pending.is_empty() {
+            let payload = match results.next_result(self.timeout)? {
+                Some(payload) => payload,
+                None => {
+                    if retries == self.max_retries {
+                        return Err(BftError::Proving(format!(
+                            "No proofs for signatures {:?} of request {}",
+                            pending, request_id
+                        )));
+                    }
+                    retries += 1;
+                    warn!(
+                        "Republish {} lost signature tasks, attempt {}.",
+                        pending.len(),
+                        retries
+                    );
+                    for pos in pending.iter() {
+                        self.transport.publish(&input_tasks[pos])?;
+                    }
+                    continue;
+                }
+            };
+            let output = match serde_json::from_slice::<OutputTask>(&payload) {
+                Ok(output) => output,
+                Err(e) => {
+                    warn!("Invalid signature result: {}, skipped.", e);
+                    continue;
+                }
+            };
+            if output.request_id != request_id || !pending.contains(&output.signature_index) {
+                warn!(
+                    "Unexpected or duplicate signature index: {}, skipped.",
+                    output.signature_index
+                );
+                continue;
+            }
+            // Accept only proofs of the same circuit, a wrong proof is treated as lost.
+            let verifier_only = VerifierOnlyCircuitData::<C, D>::from_bytes(output.verifier_data);
+            let proof =
+                ProofWithPublicInputs::<F, C, D>::from_bytes(output.proof, &circuit_data.common);
+            match (verifier_only, proof) {
+                (Ok(verifier_only), Ok(proof))
+                    if verifier_only == circuit_data.verifier_only
+                        && circuit_data.verify(proof.clone()).is_ok() =>
+                {
+                    info!("Processing signature: {}", output.signature_index);
+                    pending.remove(&output.signature_index);
+                    retries = 0;
+                    proofs.push((output.signature_index, proof));
+                }
+                _ => warn!(
+                    "Invalid proof of signature: {}, skipped.",
+                    output.signature_index
+                ),
+            }
+        }
+        Ok(proofs)
+    }
+}
+
+/// Sends tasks to `PROVE_SIGNATURE_SUBJECT` and receives results from
+/// `{SIGNATURE_RESULT_SUBJECT}.{request_id}` using async-nats.
+///
+/// The connection is driven by its own runtime, so the transport can be used both from synchronous code
+/// and from a multi-threaded tokio runtime.
+pub struct NatsTransport {
+    client: async_nats::Client,
+    // Taken only when the transport is dropped.
+    runtime: Option<Runtime>,
+}
+
+/// Proves signatures by workers connected through NATS.
+pub type NatsSignatureProver = DistributedSignatureProver<NatsTransport>;
+
+impl NatsSignatureProver {
+    /// Connects to the NATS server at `url`.
+    pub fn connect(url: &str) -> Result<Self> {
+        Ok(Self::new(NatsTransport::connect(url)?))
+    }
+}
+
+impl NatsTransport {
+    /// Connects to the NATS server at `url`.
+    pub fn connect(url: &str) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let client = block_on(&runtime, async_nats::connect(url)).map_err(nats_error)?;
+        Ok(Self {
+            client,
+            runtime: Some(runtime),
+        })
+    }
+
+    fn block_on<O>(&self, future: impl Future<Output = O>) -> O {
+        block_on(
+            self.runtime
+                .as_ref()
+                .expect("runtime is taken only on drop"),
+            future,
+        )
+    }
+}
+
+impl std::fmt::Debug for NatsTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NatsTransport")
+            .field("connection_state", &self.client.connection_state())
+            .finish()
+    }
+}
+
+impl Drop for NatsTransport {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which is not allowed inside another runtime.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl SignatureTransport for NatsTransport {
+    fn subscribe(&self, request_id: &str) -> Result<Box<dyn SignatureResults + '_>> {
+        let subscriber = self
+            .block_on(
+                self.client
+                    .subscribe(format!("{SIGNATURE_RESULT_SUBJECT}.{request_id}")),
+            )
+            .map_err(nats_error)?;
+        Ok(Box::new(NatsResults {
+            transport: self,
+            subscriber,
+        }))
+    }
+
+    fn publish(&self, task: &InputTask) -> Result<()> {
+        let input_bytes = serde_json::to_vec(&json!(task))?;
+        self.block_on(
+            self.client
+                .publish(PROVE_SIGNATURE_SUBJECT.to_string(), input_bytes.into()),
+        )
+        .map_err(nats_error)
+    }
+}
+
+struct NatsResults<'a> {
+    transport: &'a NatsTransport,
+    subscriber: async_nats::Subscriber,
+}
+
+impl SignatureResults for NatsResults<'_> {
+    fn next_result(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>> {
+        let subscriber = &mut self.subscriber;
+        match self
+            .transport
+            .block_on(async { tokio::time::timeout(timeout, subscriber.next()).await })
+        {
+            Ok(Some(message)) => Ok(Some(message.payload.to_vec())),
+            Ok(None) => Err(nats_error("subscription is closed")),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// Runs the future on `runtime`, also when called from a worker thread of another multi-threaded runtime
+/// (a current-thread runtime is not supported).
+fn block_on<O>(runtime: &Runtime, future: impl Future<Output = O>) -> O {
+    if Handle::try_current().is_ok() {
+        tokio::task::block_in_place(|| runtime.block_on(future))
+    } else {
+        runtime.block_on(future)
+    }
+}
+
+fn nats_error(error: impl std::fmt::Display) -> BftError {
+    BftError::Io(std::io::Error::other(format!("NATS: {}", error)))
+}
+
+/// Proves the signature from the task received by a worker.
+/// The circuit is built once for each message length and cached in `circuits`.
+///
+/// # Returns
+///
+/// Returns `OutputTask` with the serialized proof & verifier data.
+pub fn prove_signature_task<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    task: &InputTask,
+    circuits: &mut HashMap<usize, (CircuitData<F, C, D>, EDDSATargets)>,
+) -> Result<OutputTask> {
+    let msg_len_in_bits = task.message.len() * 8;
+    if !circuits.contains_key(&msg_len_in_bits) {
+        circuits.insert(msg_len_in_bits, get_ed25519_targets(msg_len_in_bits)?);
+    }
+    let (circuit_data, targets) = &circuits[&msg_len_in_bits];
+    let proof = ed25519_proof_with_targets(
+        &task.message,
+        &task.approval,
+        &task.validator,
+        circuit_data,
+        targets,
+    )?;
+    Ok(OutputTask {
+        request_id: task.request_id.clone(),
+        proof: proof.to_bytes(),
+        verifier_data: circuit_data
+            .verifier_only
+            .to_bytes()
+            .map_err(|e| BftError::Serialization(format!("{:?}", e)))?,
+        signature_index: task.signature_index,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prove_block_data::signatures::{
+        generate_signed_message, prove_approvals_with_prover,
+    };
+    use crate::utils::{load_block_header, load_validators};
+    use anyhow::Result;
+    use near_primitives::borsh;
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Loads the signed message, approvals & validators of the small test block (3 validators).
+    fn load_small_block() -> Result<(Vec<u8>, Vec<Vec<u8>>, Vec<Vec<u8>>)> {
+        let (_, block_header) = load_block_header("../data/block_header_small.json")?;
+        let (_, next_block_header) = load_block_header("../data/next_block_header_small.json")?;
+        let msg = generate_signed_message(
+            block_header.height(),
+            next_block_header.height(),
+            *next_block_header.prev_hash(),
+        );
+        let approvals = next_block_header
+            .approvals()
+            .iter()
+            .map(borsh::to_vec)
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let validators = load_validators("../data/validators_ordered_small.json")?
+            .iter()
+            .map(borsh::to_vec)
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok((msg, approvals, validators))
+    }
+
+    /// In-memory job queue, where tasks are proven on publishing as a worker would do.
+    /// The first task with index `lost_index` is dropped & each result is delivered twice.
+    #[derive(Default)]
+    struct InMemoryTransport {
+        lost_index: Option<usize>,
+        circuits: Mutex<HashMap<usize, (CircuitData<F, C, D>, EDDSATargets)>>,
+        results: Mutex<VecDeque<Vec<u8>>>,
+        published: Mutex<Vec<usize>>,
+    }
+
+    struct InMemoryResults<'a>(&'a InMemoryTransport);
+
+    impl SignatureTransport for InMemoryTransport {
+        fn subscribe(
+            &self,
+            _request_id: &str,
+        ) -> crate::error::Result<Box<dyn SignatureResults + '_>> {
+            Ok(Box::new(InMemoryResults(self)))
+        }
+
+        fn publish(&self, task: &InputTask) -> crate::error::Result<()> {
+            let mut published = self.published.lock().unwrap();
+            let lost = self.lost_index == Some(task.signature_index)
+                && !published.contains(&task.signature_index);
+            published.push(task.signature_index);
+            if lost {
+                return Ok(());
+            }
+            let output = prove_signature_task(task, &mut self.circuits.lock().unwrap())?;
+            let output_bytes = serde_json::to_vec(&output)?;
+            let mut results = self.results.lock().unwrap();
+            results.push_back(output_bytes.clone());
+            results.push_back(output_bytes);
+            Ok(())
+        }
+    }
+
+    impl SignatureResults for InMemoryResults<'_> {
+        fn next_result(&mut self, _timeout: Duration) -> crate::error::Result<Option<Vec<u8>>> {
+            // An empty queue is reported as a timeout, since all tasks are proven on publishing.
+            Ok(self.0.results.lock().unwrap().pop_front())
+        }
+    }
+
+    #[test]
+    fn test_prove_approvals_with_rayon_prover() -> Result<()> {
+        let (msg, approvals, validators) = load_small_block()?;
+        let prover = RayonSignatureProver::with_threads(2)?;
+        let ((data, proof), valid_keys, excluded) =
+            prove_approvals_with_prover::<F, C, D>(&msg, approvals, validators, &prover)?;
+        assert!(excluded.is_empty());
+        assert_eq!(
+            valid_keys.len(),
+            3 * (VALIDATOR_INDEX_BYTES + PK_HASH_BYTES)
+        );
+        data.verify(proof)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_distributed_prover_retries_lost_tasks_and_skips_duplicates() -> Result<()> {
+        let (msg, approvals, validators) = load_small_block()?;
+        let prover = DistributedSignatureProver::new(InMemoryTransport {
+            lost_index: Some(1),
+            ..Default::default()
+        })
+        .with_timeout(Duration::from_millis(10));
+        let ((data, proof), valid_keys, excluded) =
+            prove_approvals_with_prover::<F, C, D>(&msg, approvals, validators, &prover)?;
+        assert!(excluded.is_empty());
+        assert_eq!(
+            valid_keys.len(),
+            3 * (VALIDATOR_INDEX_BYTES + PK_HASH_BYTES)
+        );
+        // The lost task is republished once.
+        assert_eq!(
+            *prover.transport().published.lock().unwrap(),
+            vec![0, 1, 2, 1]
+        );
+        data.verify(proof)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_distributed_prover_fails_after_max_retries() -> Result<()> {
+        struct SilentTransport(Mutex<usize>);
+        struct NoResults;
+        impl SignatureTransport for SilentTransport {
+            fn subscribe(
+                &self,
+                _request_id: &str,
+            ) -> crate::error::Result<Box<dyn SignatureResults + '_>> {
+                Ok(Box::new(NoResults))
+            }
+            fn publish(&self, _task: &InputTask) -> crate::error::Result<()> {
+                *self.0.lock().unwrap() += 1;
+                Ok(())
+            }
+        }
+        impl SignatureResults for NoResults {
+            fn next_result(&mut self, _timeout: Duration) -> crate::error::Result<Option<Vec<u8>>> {
+                Ok(None)
+            }
+        }
+
+        let (msg, approvals, validators) = load_small_block()?;
+        let prover = DistributedSignatureProver::new(SilentTransport(Mutex::new(0)))
+            .with_timeout(Duration::from_millis(10))
+            .with_max_retries(2);
+        let result = prove_approvals_with_prover::<F, C, D>(&msg, approvals, validators, &prover);
+        assert!(matches!(result, Err(BftError::Proving(_))));
+        // 3 tasks published initially & twice republished.
+        assert_eq!(*prover.transport().0.lock().unwrap(), 9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_distributed_prover_resets_retries_on_progress() -> Result<()> {
+        /// A single slow worker, which proves one queued task after each timeout.
+        #[derive(Default)]
+        struct SlowTransport {
+            queue: Mutex<VecDeque<Vec<u8>>>,
+            proven: Mutex<Vec<usize>>,
+            circuits: Mutex<HashMap<usize, (CircuitData<F, C, D>, EDDSATargets)>>,
+            timeouts: Mutex<usize>,
+        }
+        struct SlowResults<'a>(&'a SlowTransport);
+        impl SignatureTransport for SlowTransport {
+            fn subscribe(
+                &self,
+                _request_id: &str,
+            ) -> crate::error::Result<Box<dyn SignatureResults + '_>> {
+                Ok(Box::new(SlowResults(self)))
+            }
+            fn publish(&self, task: &InputTask) -> crate::error::Result<()> {
+                self.queue
+                    .lock()
+                    .unwrap()
+                    .push_back(serde_json::to_vec(task)?);
+                Ok(())
+            }
+        }
+        impl SignatureResults for SlowResults<'_> {
+            fn next_result(&mut self, _timeout: Duration) -> crate::error::Result<Option<Vec<u8>>> {
+                let mut timeouts = self.0.timeouts.lock().unwrap();
+                *timeouts += 1;
+                if *timeouts % 2 == 1 {
+                    return Ok(None);
+                }
+                let mut proven = self.0.proven.lock().unwrap();
+                while let Some(payload) = self.0.queue.lock().unwrap().pop_front() {
+                    let task: InputTask = serde_json::from_slice(&payload)?;
+                    if proven.contains(&task.signature_index) {
+                        continue;
+                    }
+                    proven.push(task.signature_index);
+                    let output = prove_signature_task(&task, &mut self.0.circuits.lock().unwrap())?;
+                    return Ok(Some(serde_json::to_vec(&output)?));
+                }
+                Ok(None)
+            }
+        }
+
+        let (msg, approvals, validators) = load_small_block()?;
+        let prover = DistributedSignatureProver::new(SlowTransport::default())
+            .with_timeout(Duration::from_millis(10))
+            .with_max_retries(1);
+        // Each proof arrives after a timeout, 3 timeouts in total exceed `max_retries`.
+        let ((data, proof), _, excluded) =
+            prove_approvals_with_prover::<F, C, D>(&msg, approvals, validators, &prover)?;
+        assert!(excluded.is_empty());
+        assert_eq!(*prover.transport().proven.lock().unwrap(), vec![0, 1, 2]);
+        data.verify(proof)?;
+        Ok(())
+    }
+}
@@ -1,23 +1,20 @@
-use anyhow::Result;
-use log::info;
+use crate::error::{BftError, Result};
+use log::warn;
 use near_crypto::{PublicKey, Signature};
 use near_primitives::block_header::{Approval, ApprovalInner};
 use near_primitives::borsh::BorshDeserialize;
 use near_primitives::hash::{hash, CryptoHash};
 use plonky2::hash::hash_types::RichField;
-use plonky2::plonk::circuit_data::{CircuitData, VerifierOnlyCircuitData};
+use plonky2::plonk::circuit_data::CircuitData;
 use plonky2::plonk::config::Hasher;
 use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
 use plonky2::plonk::proof::ProofWithPublicInputs;
-use plonky2_ed25519::gadgets::eddsa::EDDSATargets;
 use plonky2_field::extension::Extendable;
-use serde_json::json;
-use std::collections::HashMap;
 
-use crate::prove_crypto::{
-    ed25519::{ed25519_proof_reuse_circuit, get_ed25519_targets},
-    recursion::recursive_proof,
+use crate::prove_block_data::signature_prover::{
+    LocalSignatureProver, SignatureProver, SignatureTask,
 };
+use crate::prove_crypto::{ed25519::get_ed25519_targets, recursion::recursive_proof};
 use crate::types::*;
 
 /// Generate a message to be signed by validators.
@@ -38,8 +35,80 @@ pub fn generate_signed_message(
     )
 }
 
+/// Extract the stake (last 16 bytes, little-endian) from a serialized validator.
+pub(crate) fn validator_stake(validator: &[u8]) -> u128 {
+    let mut stake = [0u8; STAKE_BYTES];
+    stake.copy_from_slice(&validator[(validator.len() - STAKE_BYTES)..]);
+    u128::from_le_bytes(stake)
+}
+
+/// Extract the public key (without the key type byte) from a serialized validator.
+fn validator_key(validator: &[u8]) -> &[u8] {
+    let validator_len = validator.len();
+    &validator[(validator_len - STAKE_BYTES - PK_HASH_BYTES)..(validator_len - STAKE_BYTES)]
+}
+
+/// Verify approvals natively & split validators into ones with valid and invalid approvals.
+/// Missing approvals are ignored, invalid ones (bad signature or public key) are excluded.
+/// Returns an error if valid approvals don't give 2/3 of the total sum of all stakes.
+fn filter_approvals(
+    msg: &[u8],
+    approvals: &[Vec<u8>],
+    validators: &[Vec<u8>],
+) -> Result<(Vec<usize>, Vec<usize>)> {
+    if approvals.len() != validators.len() {
+        return Err(BftError::InvalidInput(format!(
+            "{} approvals for {} validators",
+            approvals.len(),
+            validators.len()
+        )));
+    }
+    if let Some(pos) = validators
+        .iter()
+        .position(|v| v.len() < STAKE_BYTES + PK_HASH_BYTES + TYPE_BYTE)
+    {
+        return Err(BftError::InvalidInput(format!(
+            "validator {} is too short: {} bytes",
+            pos,
+            validators[pos].len()
+        )));
+    }
+    let mut valid: Vec<usize> = vec![];
+    let mut excluded: Vec<usize> = vec![];
+    for (pos, approval) in approvals.iter().enumerate() {
+        // Signature length is 64 bytes, plus Option type (byte), plus signature type (byte).
+        if approval.len() != SIG_BYTES + (TYPE_BYTE + TYPE_BYTE) {
+            continue;
+        }
+        let validator_len = validators[pos].len();
+        let sig = Signature::try_from_slice(&approval[1..]);
+        let pk = PublicKey::try_from_slice(
+            &validators[pos]
+                [(validator_len - STAKE_BYTES - PK_HASH_BYTES - TYPE_BYTE)..(validator_len - STAKE_BYTES)],
+        );
+        match (sig, pk) {
+            (Ok(sig), Ok(pk)) if sig.verify(msg, &pk) => valid.push(pos),
+            _ => {
+                warn!("Invalid signature or public key of validator {}, excluded.", pos);
+                excluded.push(pos);
+            }
+        }
+    }
+    let total_stake: u128 = validators.iter().map(|v| validator_stake(v)).sum();
+    let valid_stake: u128 = valid.iter().map(|pos| validator_stake(&validators[*pos])).sum();
+    if valid.is_empty() || valid_stake * 3 < total_stake * 2 {
+        return Err(BftError::InsufficientStake {
+            valid_stake,
+            total_stake,
+            excluded,
+        });
+    }
+    Ok((valid, excluded))
+}
+
 /// Prove signatures (approvals) from the next block using public keys (validators) from the previous epoch block
 /// for the message (hash or height depends on the existance of the next block) from the current block.
+/// Signatures are proven sequentially in the current thread, see `prove_approvals_with_prover`.
 pub fn prove_approvals<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     msg: &[u8],
     approvals: Vec<Vec<u8>>,
@@ -47,101 +116,20 @@ pub fn prove_approvals<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
 ) -> Result<(
     (CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>),
     Vec<u8>,
+    Vec<usize>,
 )>
 where
     C::Hasher: AlgebraicHasher<F>,
     [(); C::Hasher::HASH_SIZE]:,
 {
-    assert_eq!(approvals.len(), validators.len());
-    let mut ed25519_circuits: HashMap<usize, (CircuitData<F, C, D>, EDDSATargets)> = HashMap::new();
-    let mut agg_data_proof: Vec<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> = vec![];
-    let mut valid_keys: Vec<u8> = vec![];
-    let stakes_sum: u128 = validators
-        .iter()
-        .map(|item| {
-            let item_len = item.len();
-            let item_bytes = &item[item_len - STAKE_BYTES..];
-            let mut item_const = [0u8; 16];
-            item_const[..16].copy_from_slice(item_bytes);
-            u128::from_le_bytes(item_const)
-        })
-        .sum();
-    let mut valid_stakes_sum = 0;
-    for (pos, approval) in approvals.iter().enumerate() {
-        // Signature length is 64 bytes, plus Option type (byte), plus signature type (byte).
-        if approval.len() == SIG_BYTES + (TYPE_BYTE + TYPE_BYTE) {
-            let validator_len = validators[pos].len();
-            let sig = Signature::try_from_slice(&approval[1..])?;
-            let pk = PublicKey::try_from_slice(
-                &validators[pos][(validator_len - STAKE_BYTES - PK_HASH_BYTES - TYPE_BYTE)
-                    ..(validator_len - STAKE_BYTES)],
-            )?;
-            let verify: bool = sig.verify(msg, &pk);
-            if verify {
-                if agg_data_proof.is_empty() {
-                    agg_data_proof.push(ed25519_proof_reuse_circuit(
-                        msg,
-                        &approval[2..],
-                        &validators[pos][(validator_len - STAKE_BYTES - PK_HASH_BYTES)
-                            ..(validator_len - STAKE_BYTES)],
-                        &mut ed25519_circuits,
-                    )?);
-                } else {
-                    let (sig_d, sig_p) = ed25519_proof_reuse_circuit(
-                        msg,
-                        &approval[2..],
-                        &validators[pos][(validator_len - STAKE_BYTES - PK_HASH_BYTES)
-                            ..(validator_len - STAKE_BYTES)],
-                        &mut ed25519_circuits,
-                    )?;
-                    agg_data_proof[0] = recursive_proof::<F, C, C, D>(
-                        (
-                            &agg_data_proof[0].0.common,
-                            &agg_data_proof[0].0.verifier_only,
-                            &agg_data_proof[0].1,
-                        ),
-                        Some((&sig_d.common, &sig_d.verifier_only, &sig_p)),
-                        None,
-                    )?;
-                }
-                valid_keys.push(pos as u8);
-                valid_keys.append(
-                    &mut validators[pos]
-                        [(validator_len - STAKE_BYTES - PK_HASH_BYTES)..(validator_len - STAKE_BYTES)]
-                        .to_vec(),
-                );
-
-                let mut stake_vec = [0u8; 16];
-                stake_vec[..16].copy_from_slice(&validators[pos][(validator_len - STAKE_BYTES)..]);
-                let stake = u128::from_le_bytes(stake_vec);
-                valid_stakes_sum += stake;
-            }
-            else {
-                panic!("Invalid signature or public key.");
-            }
-        }
-    }
-    // Set hash of valid keys as PI.
-    let valid_keys_hash = hash(&valid_keys);
-    let valid_keys_hash_vec: Vec<F> = valid_keys_hash
-        .0
-        .iter()
-        .map(|x| F::from_canonical_u8(*x))
-        .collect();
-    let (aggregated_circuit_data, aggregated_proof) = recursive_proof::<F, C, C, D>(
-        (
-            &agg_data_proof[0].0.common,
-            &agg_data_proof[0].0.verifier_only,
-            &agg_data_proof[0].1,
-        ),
-        None,
-        Some(&valid_keys_hash_vec),
-    )?;
-    Ok(((aggregated_circuit_data, aggregated_proof), valid_keys))
+    prove_approvals_with_prover(msg, approvals, validators, &LocalSignatureProver)
 }
 
-/// Prove signatures (approvals) using nats client, assume that nats consumers are started.
-pub fn prove_approvals_with_client<
+/// Prove signatures (approvals) with the given backend & aggregate the proofs.
+/// Invalid approvals are not proven. Returns `BftError::InsufficientStake` if the rest give less than 2/3 of stakes.
+/// Returns the aggregated proof, a list of valid keys, where each key is prefixed by the validator index
+/// (`VALIDATOR_INDEX_BYTES`, little-endian) & indices of validators with excluded (invalid) approvals.
+pub fn prove_approvals_with_prover<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
     const D: usize,
@@ -149,115 +137,58 @@ pub fn prove_approvals_with_client<
     msg: &[u8],
     approvals: Vec<Vec<u8>>,
     validators: Vec<Vec<u8>>,
-    client: nats::Connection,
+    signature_prover: &dyn SignatureProver<F, C, D>,
 ) -> Result<(
     (CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>),
     Vec<u8>,
+    Vec<usize>,
 )>
 where
     C::Hasher: AlgebraicHasher<F>,
     [(); C::Hasher::HASH_SIZE]:,
 {
-    assert_eq!(approvals.len(), validators.len());
-    let mut signature_circuit_data: Vec<CircuitData<F, C, D>> = Vec::with_capacity(1);
-    let mut valid_keys: Vec<u8> = vec![];
-    let result_subscriber = client.subscribe("PROCESS_SIGNATURE_RESULT")?;
-    let mut main_counter = 0;
-    let stakes_sum: u128 = validators
+    let (valid, excluded) = filter_approvals(msg, &approvals, &validators)?;
+    let tasks: Vec<SignatureTask> = valid
         .iter()
-        .map(|item| {
-            let item_len = item.len();
-            let item_bytes = &item[item_len - STAKE_BYTES..];
-            let mut item_const = [0u8; 16];
-            item_const[..16].copy_from_slice(item_bytes);
-            u128::from_le_bytes(item_const)
+        .map(|&pos| SignatureTask {
+            signature_index: pos,
+            signature: approvals[pos][2..].to_vec(),
+            public_key: validator_key(&validators[pos]).to_vec(),
         })
-        .sum();
-    let mut valid_stakes_sum = 0;
-    for (pos, approval) in approvals.iter().enumerate() {
-        // Signature length is 64 bytes, plus Option type (byte), plus signature type (byte).
-        if approval.len() == SIG_BYTES + (TYPE_BYTE + TYPE_BYTE) {
-            let validator_len = validators[pos].len();
-            let sig = Signature::try_from_slice(&approval[1..])?;
-            let pk = PublicKey::try_from_slice(
-                &validators[pos][(validator_len - STAKE_BYTES - PK_HASH_BYTES - TYPE_BYTE)
-                    ..(validator_len - STAKE_BYTES)],
-            )?;
-            let verify: bool = sig.verify(msg, &pk);
-            if verify {
-                let input_task = InputTask {
-                    message: msg.to_vec(),
-                    approval: approval[2..].to_vec(),
-                    validator: validators[pos]
-                        [(validator_len - STAKE_BYTES - PK_HASH_BYTES)..(validator_len - STAKE_BYTES)]
-                        .to_vec(),
-                    signature_index: pos,
-                };
-                let input_bytes = serde_json::to_vec(&json!(input_task))?;
-                client
-                    .publish("PROVE_SIGNATURE", input_bytes)
-                    .expect("Error publishing proving task");
-                main_counter += 1;
-                let mut stake_vec = [0u8; 16];
-                stake_vec[..16].copy_from_slice(&validators[pos][(validator_len - STAKE_BYTES)..]);
-                let stake = u128::from_le_bytes(stake_vec);
-                valid_stakes_sum += stake;
-            }
-            else {
-                panic!("Invalid signature or public key.");
-            }
-        }
+        .collect();
+    let (signature_circuit_data, targets) = get_ed25519_targets::<F, C, D>(msg.len() * 8)?;
+    let mut proofs =
+        signature_prover.prove_signatures(msg, &tasks, &signature_circuit_data, &targets)?;
+    proofs.sort_by_key(|(pos, _)| *pos);
+    proofs.dedup_by_key(|(pos, _)| *pos);
+    if proofs.iter().map(|(pos, _)| *pos).ne(valid.iter().copied()) {
+        return Err(BftError::Proving(format!(
+            "{} proofs for {} valid signatures",
+            proofs.len(),
+            valid.len()
+        )));
     }
-    let msg_len_in_bits = msg.len() * 8;
-    let (circuit_data, _) = get_ed25519_targets(msg_len_in_bits).unwrap();
-    signature_circuit_data.push(circuit_data);
 
-    let mut agg_data = signature_circuit_data[0].clone();
-    let mut agg_proofs = Vec::with_capacity(1);
-    let mut aux_counter = 0;
-    loop {
-        if aux_counter == main_counter {
-            break;
-        }
-        if let Some(message) = result_subscriber.iter().next() {
-            if let Ok(payload) = serde_json::from_slice::<OutputTask>(&message.data) {
-                info!("Processing signature: {}", payload.signature_index);
-                let serialized_proof = ProofWithPublicInputs::<F, C, D>::from_bytes(
-                    payload.proof,
-                    &signature_circuit_data[0].common,
-                )?;
-                let verifier_only_data =
-                    VerifierOnlyCircuitData::from_bytes(payload.verifier_data).unwrap();
-                if agg_proofs.is_empty() {
-                    agg_proofs.push(serialized_proof);
-                    agg_data = CircuitData {
-                        prover_only: agg_data.prover_only,
-                        verifier_only: verifier_only_data,
-                        common: agg_data.common,
-                    }
-                } else {
-                    (agg_data, agg_proofs[0]) = recursive_proof::<F, C, C, D>(
-                        (&agg_data.common, &agg_data.verifier_only, &agg_proofs[0]),
-                        Some((
-                            &signature_circuit_data[0].common,
-                            &verifier_only_data,
-                            &serialized_proof,
-                        )),
-                        None,
-                    )?;
-                }
-                let signature_index = payload.signature_index;
-                valid_keys.push(signature_index as u8);
-                let validator_len = validators[signature_index].len();
-                valid_keys.append(
-                    &mut validators[signature_index]
-                        [(validator_len - STAKE_BYTES - PK_HASH_BYTES)..(validator_len - STAKE_BYTES)]
-                        .to_vec(),
-                );
-                aux_counter += 1;
-            }
-        }
+    let mut agg_data_proof: Option<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> = None;
+    let mut valid_keys: Vec<u8> = vec![];
+    for (pos, sig_p) in proofs {
+        agg_data_proof = Some(match agg_data_proof.take() {
+            None => (signature_circuit_data.clone(), sig_p),
+            Some((agg_data, agg_proof)) => recursive_proof::<F, C, C, D>(
+                (&agg_data.common, &agg_data.verifier_only, &agg_proof),
+                Some((
+                    &signature_circuit_data.common,
+                    &signature_circuit_data.verifier_only,
+                    &sig_p,
+                )),
+                None,
+            )?,
+        });
+        valid_keys.extend_from_slice(&(pos as u32).to_le_bytes());
+        valid_keys.extend_from_slice(validator_key(&validators[pos]));
     }
+    let (agg_data, agg_proof) = agg_data_proof
+        .ok_or_else(|| BftError::Proving("No proofs of signatures".to_string()))?;
     // Set hash of valid keys as PI.
     let valid_keys_hash = hash(&valid_keys);
     let valid_keys_hash_vec: Vec<F> = valid_keys_hash
@@ -265,12 +196,12 @@ where
         .iter()
         .map(|x| F::from_canonical_u8(*x))
         .collect();
-    (agg_data, agg_proofs[0]) = recursive_proof::<F, C, C, D>(
-        (&agg_data.common, &agg_data.verifier_only, &agg_proofs[0]),
+    let aggregated_data_proof = recursive_proof::<F, C, C, D>(
+        (&agg_data.common, &agg_data.verifier_only, &agg_proof),
         None,
         Some(&valid_keys_hash_vec),
     )?;
-    Ok(((agg_data, agg_proofs[0].clone()), valid_keys))
+    Ok((aggregated_data_proof, valid_keys, excluded))
 }
 
 #[cfg(test)]
@@ -281,7 +212,9 @@ mod tests {
     use log::info;
     use near_crypto::{KeyType, SecretKey};
     use near_primitives::borsh::BorshSerialize;
+    use near_primitives::types::validator_stake::ValidatorStake;
     use plonky2::plonk::config::PoseidonGoldilocksConfig;
+    use plonky2_field::types::PrimeField64;
     use sha2::Digest;
 
     #[test]
@@ -357,12 +290,148 @@ mod tests {
             .map(|value| borsh::to_vec(value).unwrap())
             .collect();
 
-        let ((_data, proof), _valid_keys) =
+        let ((_data, proof), _valid_keys, excluded) =
             prove_approvals::<F, C, D>(&msg_to_sign, approvals_bytes, validators_bytes)?;
+        assert!(excluded.is_empty());
         info!(
             "Size of proof for aggregated signatures: {} bytes",
             proof.to_bytes().len()
         );
         Ok(())
     }
+
+    #[test]
+    fn test_prove_approvals_with_validator_index_above_u8() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        const NUM_VALIDATORS: usize = 300;
+        let msg_to_sign = generate_signed_message(100, 101, CryptoHash::default());
+        // Only validators 1 & 299 approve the block.
+        let approving = [1, NUM_VALIDATORS - 1];
+        let secret_keys: Vec<SecretKey> = (0..NUM_VALIDATORS)
+            .map(|i| SecretKey::from_seed(KeyType::ED25519, &format!("validator{i}")))
+            .collect();
+        let validators_bytes: Vec<Vec<u8>> = secret_keys
+            .iter()
+            .enumerate()
+            .map(|(i, sk)| {
+                let account_id = format!("validator{i}.near").parse().unwrap();
+                // Approving validators hold most of the stake.
+                let stake = if approving.contains(&i) { 1_000_000 } else { 1 };
+                borsh::to_vec(&ValidatorStake::new_v1(account_id, sk.public_key(), stake)).unwrap()
+            })
+            .collect();
+        let approvals_bytes: Vec<Vec<u8>> = secret_keys
+            .iter()
+            .enumerate()
+            .map(|(i, sk)| {
+                let approval = approving
+                    .contains(&i)
+                    .then(|| Box::new(sk.sign(&msg_to_sign)));
+                borsh::to_vec(&approval).unwrap()
+            })
+            .collect();
+
+        let ((data, proof), valid_keys, _excluded) =
+            prove_approvals::<F, C, D>(&msg_to_sign, approvals_bytes, validators_bytes)?;
+        assert_eq!(valid_keys.len(), approving.len() * (VALIDATOR_INDEX_BYTES + PK_HASH_BYTES));
+        for (record, pos) in valid_keys
+            .chunks(VALIDATOR_INDEX_BYTES + PK_HASH_BYTES)
+            .zip(approving)
+        {
+            let index = u32::from_le_bytes(record[..VALIDATOR_INDEX_BYTES].try_into()?);
+            assert_eq!(index as usize, pos);
+            assert_eq!(
+                &record[VALIDATOR_INDEX_BYTES..],
+                secret_keys[pos].public_key().key_data()
+            );
+        }
+        let valid_keys_hash: Vec<u8> = proof
+            .public_inputs
+            .iter()
+            .map(|x| x.to_canonical_u64() as u8)
+            .collect();
+        assert_eq!(valid_keys_hash, hash(&valid_keys).0.to_vec());
+        data.verify(proof)
+    }
+
+    /// Generate validators with equal stakes & their approvals, the approval of `invalid` is signed by another key.
+    fn synthetic_approvals(
+        msg: &[u8],
+        num: usize,
+        invalid: &[usize],
+    ) -> (Vec<Vec<u8>>, Vec<Vec<u8>>) {
+        let secret_keys: Vec<SecretKey> = (0..num)
+            .map(|i| SecretKey::from_seed(KeyType::ED25519, &format!("validator{i}")))
+            .collect();
+        let validators_bytes: Vec<Vec<u8>> = secret_keys
+            .iter()
+            .enumerate()
+            .map(|(i, sk)| {
+                let account_id = format!("validator{i}.near").parse().unwrap();
+                borsh::to_vec(&ValidatorStake::new_v1(account_id, sk.public_key(), 1)).unwrap()
+            })
+            .collect();
+        let approvals_bytes: Vec<Vec<u8>> = secret_keys
+            .iter()
+            .enumerate()
+            .map(|(i, sk)| {
+                let sig = if invalid.contains(&i) {
+                    SecretKey::from_seed(KeyType::ED25519, "malicious").sign(msg)
+                } else {
+                    sk.sign(msg)
+                };
+                borsh::to_vec(&Some(Box::new(sig))).unwrap()
+            })
+            .collect();
+        (approvals_bytes, validators_bytes)
+    }
+
+    #[test]
+    fn test_prove_approvals_excludes_invalid_signature() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let msg_to_sign = generate_signed_message(100, 101, CryptoHash::default());
+        // 2 of 3 approvals are valid, that's exactly 2/3 of stakes.
+        let (approvals_bytes, validators_bytes) = synthetic_approvals(&msg_to_sign, 3, &[1]);
+        let ((data, proof), valid_keys, excluded) =
+            prove_approvals::<F, C, D>(&msg_to_sign, approvals_bytes, validators_bytes)?;
+        assert_eq!(excluded, vec![1]);
+        let indices: Vec<u32> = valid_keys
+            .chunks(VALIDATOR_INDEX_BYTES + PK_HASH_BYTES)
+            .map(|record| u32::from_le_bytes(record[..VALIDATOR_INDEX_BYTES].try_into().unwrap()))
+            .collect();
+        assert_eq!(indices, vec![0, 2]);
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_prove_approvals_insufficient_stake() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let msg_to_sign = generate_signed_message(100, 101, CryptoHash::default());
+        // 2 of 4 approvals are valid, that's less than 2/3 of stakes.
+        let (approvals_bytes, validators_bytes) = synthetic_approvals(&msg_to_sign, 4, &[0, 3]);
+        let err = prove_approvals::<F, C, D>(&msg_to_sign, approvals_bytes, validators_bytes)
+            .err()
+            .expect("Proving should fail with less than 2/3 of stakes.");
+        match err {
+            BftError::InsufficientStake {
+                valid_stake,
+                total_stake,
+                excluded,
+            } => {
+                assert_eq!(valid_stake, 2);
+                assert_eq!(total_stake, 4);
+                assert_eq!(excluded, vec![0, 3]);
+            }
+            err => panic!("Unexpected error: {err}"),
+        }
+    }
 }
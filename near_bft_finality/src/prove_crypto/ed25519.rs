@@ -1,4 +1,4 @@
-use anyhow::Result;
+use crate::error::{BftError, Result};
 use log::Level;
 use plonky2::{
     hash::hash_types::RichField,
@@ -15,6 +15,18 @@ use plonky2_ed25519::gadgets::eddsa::{ed25519_circuit, fill_ecdsa_targets, EDDSA
 use plonky2_field::extension::Extendable;
 use std::collections::HashMap;
 
+/// Check lengths of a signature (64 bytes) & a public key (32 bytes) before filling ED25519 targets.
+fn check_signature_key_len(sigv: &[u8], pkv: &[u8]) -> Result<()> {
+    if sigv.len() != 64 || pkv.len() != 32 {
+        return Err(BftError::Signature(format!(
+            "expected 64 bytes signature and 32 bytes public key, got {} and {}",
+            sigv.len(),
+            pkv.len()
+        )));
+    }
+    Ok(())
+}
+
 pub fn get_ed25519_circuit_targets<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -51,13 +63,14 @@ pub fn ed25519_proof_reuse_circuit<
     pkv: &[u8],
     ed25519_circuits: &mut HashMap<usize, (CircuitData<F, C, D>, EDDSATargets)>,
 ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+    check_signature_key_len(sigv, pkv)?;
     let len_in_bits = msg.len() * 8;
     let (circuit_data, targets): (CircuitData<F, C, D>, EDDSATargets) =
         get_ed25519_circuit_targets(len_in_bits, ed25519_circuits);
     let mut pw: PartialWitness<F> = PartialWitness::new();
     fill_ecdsa_targets::<F, D>(&mut pw, msg, sigv, pkv, &targets);
     let timing = TimingTree::new("prove", Level::Info);
-    let proof = circuit_data.prove(pw)?;
+    let proof = circuit_data.prove(pw).map_err(BftError::proving)?;
     timing.print();
     Ok((circuit_data, proof))
 }
@@ -69,7 +82,7 @@ pub fn get_ed25519_targets<
     const D: usize,
 >(
     msg_len_in_bits: usize,
-) -> anyhow::Result<(CircuitData<F, C, D>, EDDSATargets)> {
+) -> Result<(CircuitData<F, C, D>, EDDSATargets)> {
     let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::wide_ecc_config());
     let targets = ed25519_circuit(&mut builder, msg_len_in_bits);
     let circuit_data = builder.build::<C>();
@@ -93,11 +106,28 @@ pub fn ed25519_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     sigv: &[u8],
     pkv: &[u8],
     circuit_data: (CircuitData<F, C, D>, EDDSATargets),
-) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    ed25519_proof_with_targets(msg, sigv, pkv, &circuit_data.0, &circuit_data.1)
+}
+
+/// Computes an Ed25519 proof the same way as `ed25519_proof`, but borrows the proving schema & targets,
+/// so one circuit can be shared between threads.
+pub fn ed25519_proof_with_targets<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    msg: &[u8],
+    sigv: &[u8],
+    pkv: &[u8],
+    circuit_data: &CircuitData<F, C, D>,
+    targets: &EDDSATargets,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    check_signature_key_len(sigv, pkv)?;
     let mut pw: PartialWitness<F> = PartialWitness::new();
-    fill_ecdsa_targets::<F, D>(&mut pw, msg, sigv, pkv, &circuit_data.1);
+    fill_ecdsa_targets::<F, D>(&mut pw, msg, sigv, pkv, targets);
     let timing = TimingTree::new("Prove signature", Level::Info);
-    let proof = circuit_data.0.prove(pw)?;
+    let proof = circuit_data.prove(pw).map_err(BftError::proving)?;
     timing.print();
     Ok(proof)
 }
@@ -105,6 +135,7 @@ pub fn ed25519_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Result;
     use ed25519_compact::*;
     use plonky2::plonk::{circuit_data, config::PoseidonGoldilocksConfig};
     use plonky2_field::types::Field;
@@ -1,5 +1,6 @@
-use anyhow::Result;
+use crate::error::{BftError, Result};
 use plonky2::field::extension::Extendable;
+use plonky2::gates::noop::NoopGate;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::target::Target;
 use plonky2::iop::witness::{PartialWitness, WitnessWrite};
@@ -10,11 +11,65 @@ use plonky2::plonk::circuit_data::{
 };
 use plonky2::plonk::config::Hasher;
 use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
-use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+
+/// Represents a public input of a proof that exposes public inputs of its inner proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExposedInput<F> {
+    /// The public input of the inner proof at the index.
+    Inner(usize),
+    /// The constant of the circuit.
+    Constant(F),
+}
+
+/// Returns public inputs that expose all public inputs of an inner proof with `len` public inputs.
+pub fn expose_all<F>(len: usize) -> Vec<ExposedInput<F>> {
+    (0..len).map(ExposedInput::Inner).collect()
+}
+
+/// Adds targets of the inner proof & its verifier data to the builder, sets them in the witness and verifies the proof.
+fn add_inner_proof<F, InnerC, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    pw: &mut PartialWitness<F>,
+    (inner_common, inner_verifier, inner_proof): (
+        &CommonCircuitData<F, D>,
+        &VerifierOnlyCircuitData<InnerC, D>,
+        &ProofWithPublicInputs<F, InnerC, D>,
+    ),
+) -> ProofWithPublicInputsTarget<D>
+where
+    F: RichField + Extendable<D>,
+    InnerC: GenericConfig<D, F = F>,
+    InnerC::Hasher: AlgebraicHasher<F>,
+{
+    let proof_with_pis_target = builder.add_virtual_proof_with_pis(inner_common);
+    let verifier_circuit_target = VerifierCircuitTarget {
+        constants_sigmas_cap: builder.add_virtual_cap(inner_common.config.fri_config.cap_height),
+        circuit_digest: builder.add_virtual_hash(),
+    };
+    pw.set_proof_with_pis_target(&proof_with_pis_target, inner_proof);
+    pw.set_cap_target(
+        &verifier_circuit_target.constants_sigmas_cap,
+        &inner_verifier.constants_sigmas_cap,
+    );
+    pw.set_hash_target(
+        verifier_circuit_target.circuit_digest,
+        inner_verifier.circuit_digest,
+    );
+    builder.verify_proof::<InnerC>(
+        &proof_with_pis_target,
+        &verifier_circuit_target,
+        inner_common,
+    );
+    proof_with_pis_target
+}
 
 /// Recursively aggregates two proofs to one, verifies inner proofs and optionally set public inputs.
+///
+/// Public inputs are set from the witness and are not connected to public inputs of inner proofs,
+/// use `expose_public_inputs` when a verifier relies on them.
 pub fn recursive_proof<F, C, InnerC, const D: usize>(
-    (first_inner_common, first_inner_verifier, first_inner_proof): (
+    first_inner_data_proof: (
         &CommonCircuitData<F, D>,
         &VerifierOnlyCircuitData<InnerC, D>,
         &ProofWithPublicInputs<F, InnerC, D>,
@@ -34,55 +89,10 @@ where
     [(); C::Hasher::HASH_SIZE]:,
 {
     let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
-    let proof_with_pis_target_1 = builder.add_virtual_proof_with_pis(first_inner_common);
-    let verifier_circuit_target_1 = VerifierCircuitTarget {
-        constants_sigmas_cap: builder
-            .add_virtual_cap(first_inner_common.config.fri_config.cap_height),
-        circuit_digest: builder.add_virtual_hash(),
-    };
     let mut pw = PartialWitness::new();
-    pw.set_proof_with_pis_target(&proof_with_pis_target_1, first_inner_proof);
-    pw.set_cap_target(
-        &verifier_circuit_target_1.constants_sigmas_cap,
-        &first_inner_verifier.constants_sigmas_cap,
-    );
-    pw.set_hash_target(
-        verifier_circuit_target_1.circuit_digest,
-        first_inner_verifier.circuit_digest,
-    );
-    builder.verify_proof::<InnerC>(
-        &proof_with_pis_target_1,
-        &verifier_circuit_target_1,
-        first_inner_common,
-    );
-    if second_inner_data_proof.is_some() {
-        let proof_with_pis_target_2 =
-            builder.add_virtual_proof_with_pis(second_inner_data_proof.unwrap().0);
-        let verifier_circuit_target_2 = VerifierCircuitTarget {
-            constants_sigmas_cap: builder.add_virtual_cap(
-                second_inner_data_proof
-                    .unwrap()
-                    .0
-                    .config
-                    .fri_config
-                    .cap_height,
-            ),
-            circuit_digest: builder.add_virtual_hash(),
-        };
-        pw.set_proof_with_pis_target(&proof_with_pis_target_2, second_inner_data_proof.unwrap().2);
-        pw.set_cap_target(
-            &verifier_circuit_target_2.constants_sigmas_cap,
-            &second_inner_data_proof.unwrap().1.constants_sigmas_cap,
-        );
-        pw.set_hash_target(
-            verifier_circuit_target_2.circuit_digest,
-            second_inner_data_proof.unwrap().1.circuit_digest,
-        );
-        builder.verify_proof::<InnerC>(
-            &proof_with_pis_target_2,
-            &verifier_circuit_target_2,
-            second_inner_data_proof.unwrap().0,
-        );
+    add_inner_proof(&mut builder, &mut pw, first_inner_data_proof);
+    if let Some(second_inner_data_proof) = second_inner_data_proof {
+        add_inner_proof(&mut builder, &mut pw, second_inner_data_proof);
     }
     if let Some(pi) = public_inputs {
         let pi_targets: Vec<Target> = builder.add_virtual_targets(pi.len());
@@ -92,7 +102,146 @@ where
         }
     }
     let data_new = builder.build::<C>();
-    let proof_new = data_new.prove(pw)?;
+    let proof_new = data_new.prove(pw).map_err(BftError::proving)?;
+    Ok((data_new, proof_new))
+}
+
+/// Recursively verifies the proof and sets `public_inputs` as PI. Every PI is connected to a public input
+/// of the inner proof or is a constant, so the proof can't claim PI that the inner proof doesn't have.
+///
+/// # Errors
+///
+/// Returns `BftError::InvalidInput` if some public input refers to an index out of public inputs of the inner proof.
+pub fn expose_public_inputs<F, C, InnerC, const D: usize>(
+    inner_data_proof: (
+        &CommonCircuitData<F, D>,
+        &VerifierOnlyCircuitData<InnerC, D>,
+        &ProofWithPublicInputs<F, InnerC, D>,
+    ),
+    public_inputs: &[ExposedInput<F>],
+) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    InnerC: GenericConfig<D, F = F>,
+    InnerC::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    prove_exposed(inner_data_proof, public_inputs, None)
+}
+
+/// Recursively verifies the proof in a circuit of `2^degree_bits` rows, exposing all its public inputs.
+///
+/// The circuit is padded with no-op gates, so proofs of inner circuits of different sizes get the same
+/// common data. The digest of the circuit still depends on the inner circuit, see `normalize_proof`.
+///
+/// # Errors
+///
+/// Returns `BftError::InvalidInput` if the verifier of the proof doesn't fit `2^degree_bits` rows.
+pub fn pad_proof<F, C, InnerC, const D: usize>(
+    inner_data_proof: (
+        &CommonCircuitData<F, D>,
+        &VerifierOnlyCircuitData<InnerC, D>,
+        &ProofWithPublicInputs<F, InnerC, D>,
+    ),
+    degree_bits: usize,
+) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    InnerC: GenericConfig<D, F = F>,
+    InnerC::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    let public_inputs = expose_all(inner_data_proof.0.num_public_inputs);
+    prove_exposed(inner_data_proof, &public_inputs, Some(degree_bits))
+}
+
+/// Recursively verifies the proof by a circuit that doesn't depend on the size of the inner circuit,
+/// exposing all its public inputs.
+///
+/// The proof is verified by the circuit padded to `2^degree_bits` rows (see `pad_proof`), then the padded
+/// proof is verified by the circuit built for its common data, which is the same for all inner circuits.
+/// So e.g. proofs of blocks with different numbers of validators are verified by the same circuit.
+///
+/// # Errors
+///
+/// Returns `BftError::InvalidInput` if the verifier of the proof doesn't fit `2^degree_bits` rows.
+pub fn normalize_proof<F, C, const D: usize>(
+    inner_data_proof: (
+        &CommonCircuitData<F, D>,
+        &VerifierOnlyCircuitData<C, D>,
+        &ProofWithPublicInputs<F, C, D>,
+    ),
+    degree_bits: usize,
+) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    let (data, proof) = pad_proof::<F, C, C, D>(inner_data_proof, degree_bits)?;
+    expose_public_inputs::<F, C, C, D>(
+        (&data.common, &data.verifier_only, &proof),
+        &expose_all(proof.public_inputs.len()),
+    )
+}
+
+/// Builds and proves the circuit of `expose_public_inputs`, padded to `2^degree_bits` rows if it is set.
+fn prove_exposed<F, C, InnerC, const D: usize>(
+    inner_data_proof: (
+        &CommonCircuitData<F, D>,
+        &VerifierOnlyCircuitData<InnerC, D>,
+        &ProofWithPublicInputs<F, InnerC, D>,
+    ),
+    public_inputs: &[ExposedInput<F>],
+    degree_bits: Option<usize>,
+) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    InnerC: GenericConfig<D, F = F>,
+    InnerC::Hasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    let num_inner_pi = inner_data_proof.0.num_public_inputs;
+    if let Some(index) = public_inputs.iter().find_map(|input| match input {
+        ExposedInput::Inner(index) if *index >= num_inner_pi => Some(index),
+        _ => None,
+    }) {
+        return Err(BftError::InvalidInput(format!(
+            "public input {} of the inner proof is exposed, it has {}",
+            index, num_inner_pi
+        )));
+    }
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let mut pw = PartialWitness::new();
+    let inner = add_inner_proof(&mut builder, &mut pw, inner_data_proof);
+    for input in public_inputs {
+        let target = match input {
+            ExposedInput::Inner(index) => inner.public_inputs[*index],
+            ExposedInput::Constant(value) => builder.constant(*value),
+        };
+        builder.register_public_input(target);
+    }
+    if let Some(degree_bits) = degree_bits {
+        // `build` pads gates to the next power of two, so more than a half of rows is filled here.
+        while builder.num_gates() <= 1 << (degree_bits - 1) {
+            builder.add_gate(NoopGate, vec![]);
+        }
+    }
+    let data_new = builder.build::<C>();
+    if let Some(degree_bits) = degree_bits {
+        if data_new.common.degree_bits() != degree_bits {
+            return Err(BftError::InvalidInput(format!(
+                "the verifier of the proof has 2^{} rows, expected 2^{}",
+                data_new.common.degree_bits(),
+                degree_bits
+            )));
+        }
+    }
+    let proof_new = data_new.prove(pw).map_err(BftError::proving)?;
     Ok((data_new, proof_new))
 }
 
@@ -162,4 +311,109 @@ mod tests {
         )
         .unwrap();
     }
+
+    /// Returns the proof that 2/3 of random stakes are at least 2/3 of them.
+    fn two_thirds_proof<F, C, const D: usize>(
+    ) -> anyhow::Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let v: u128 = random::<u64>() as u128 * 3;
+        let v1: u128 = ((v / 3) * 2) + 5;
+        let mut v_bits = v.to_le_bytes().to_vec();
+        let mut v_i_bits = v1.to_le_bytes().to_vec();
+        v_bits.push(0);
+        v_i_bits.push(0);
+        Ok(two_thirds::<F, C, D>(&v_i_bits, &v_bits)?)
+    }
+
+    #[test]
+    fn test_expose_public_inputs() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let (cd, proof) = two_thirds_proof::<F, C, D>()?;
+        let len = proof.public_inputs.len();
+        let exposed = [
+            ExposedInput::Constant(F::ONE),
+            ExposedInput::Inner(0),
+            ExposedInput::Inner(len - 1),
+        ];
+        let (data, exposed_proof) =
+            expose_public_inputs::<F, C, C, D>((&cd.common, &cd.verifier_only, &proof), &exposed)?;
+        assert_eq!(
+            exposed_proof.public_inputs,
+            vec![F::ONE, proof.public_inputs[0], proof.public_inputs[len - 1]]
+        );
+        data.verify(exposed_proof)?;
+        // The circuit doesn't depend on values of public inputs, so it can be trusted once.
+        let (other_cd, other_proof) = two_thirds_proof::<F, C, D>()?;
+        let (other_data, _) = expose_public_inputs::<F, C, C, D>(
+            (&other_cd.common, &other_cd.verifier_only, &other_proof),
+            &exposed,
+        )?;
+        assert_eq!(
+            other_data.verifier_only.circuit_digest,
+            data.verifier_only.circuit_digest
+        );
+        assert!(expose_public_inputs::<F, C, C, D>(
+            (&cd.common, &cd.verifier_only, &proof),
+            &[ExposedInput::Inner(len)],
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_expose_public_inputs_tampered() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let (cd, proof) = two_thirds_proof::<F, C, D>().unwrap();
+        // The prover claims another value of a public input, it can only be set through the inner proof.
+        let mut tampered_proof = proof.clone();
+        tampered_proof.public_inputs[0] += F::ONE;
+        expose_public_inputs::<F, C, C, D>(
+            (&cd.common, &cd.verifier_only, &tampered_proof),
+            &expose_all(proof.public_inputs.len()),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_normalize_proof() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        const DEGREE_BITS: usize = 13;
+
+        let (cd, proof) = two_thirds_proof::<F, C, D>()?;
+        // A larger circuit with the same public inputs.
+        let (large_cd, large_proof) =
+            pad_proof::<F, C, C, D>((&cd.common, &cd.verifier_only, &proof), DEGREE_BITS + 1)?;
+        assert_ne!(large_cd.common, cd.common);
+
+        let (data, normalized) =
+            normalize_proof::<F, C, D>((&cd.common, &cd.verifier_only, &proof), DEGREE_BITS)?;
+        let (large_data, large_normalized) = normalize_proof::<F, C, D>(
+            (&large_cd.common, &large_cd.verifier_only, &large_proof),
+            DEGREE_BITS,
+        )?;
+        assert_eq!(large_data.common, data.common);
+        assert_eq!(large_data.verifier_only, data.verifier_only);
+        assert_eq!(normalized.public_inputs, proof.public_inputs);
+        assert_eq!(large_normalized.public_inputs, proof.public_inputs);
+        data.verify(large_normalized)?;
+        // The verifier doesn't fit a smaller circuit.
+        assert!(matches!(
+            pad_proof::<F, C, C, D>((&cd.common, &cd.verifier_only, &proof), 4),
+            Err(BftError::InvalidInput(_))
+        ));
+        Ok(())
+    }
 }
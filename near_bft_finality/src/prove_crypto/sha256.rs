@@ -1,5 +1,5 @@
 use crate::utils::vec_u32_to_u8;
-use anyhow::Result;
+use crate::error::{BftError, Result};
 use near_primitives::{borsh, hash::hash};
 use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
 use plonky2::plonk::config::Hasher;
@@ -34,9 +34,9 @@ pub const SHA256_BLOCK: usize = 512;
 /// - `CircuitData<F, C, D>`: The circuit data generated during the proof generation process.
 /// - `ProofWithPublicInputs<F, C, D>`: The proof along with public inputs in u32 limbs.
 ///
-/// # Panics
+/// # Errors
 ///
-/// This function panics if the proof generation fails.
+/// Returns `BftError::InvalidInput` if the hash isn't 32 bytes, or `BftError::Proving` if the proof generation fails.
 ///
 /// # Examples
 ///
@@ -67,6 +67,12 @@ pub fn sha256_proof_u32<
     msg: &[u8],
     hash: &[u8],
 ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+    if hash.len() != 32 {
+        return Err(BftError::InvalidInput(format!(
+            "sha256 hash should be 32 bytes, got {}",
+            hash.len()
+        )));
+    }
     let len_in_bits = msg.len() * 8;
     let block_num = (len_in_bits + 64 + 512) / 512;
     let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
@@ -79,7 +85,7 @@ pub fn sha256_proof_u32<
     let mut pw = PartialWitness::new();
     pw.set_sha256_input_target(&hash_target, msg);
     pw.set_sha256_output_target(&hash_output, hash);
-    let proof = data.prove(pw).unwrap();
+    let proof = data.prove(pw).map_err(BftError::proving)?;
     Ok((data, proof))
 }
 
@@ -174,6 +180,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Result;
     use plonky2::plonk::{circuit_data, config::PoseidonGoldilocksConfig};
     use plonky2_field::types::Field;
     use rand::random;
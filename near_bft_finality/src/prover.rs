@@ -0,0 +1,442 @@
+use crate::conformance::{VectorKind, VectorOutputs};
+use crate::error::{BftError, Result};
+use crate::input::{AnchorBlock, BlockProvingInput, EpochProvingInput};
+use crate::pipeline::{ProvingPipeline, StoredProof};
+use crate::public_inputs::BlockProofPublicInputs;
+use crate::store::ProofKind;
+use crate::types::ValidatorOrdered;
+use crate::utils::{
+    load_block_input, load_epoch_input, validator_ordered, validator_stake_from_ordered,
+};
+use log::Level;
+use near_primitives::block_header::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::validator_stake::ValidatorStake;
+use near_primitives::views::BlockHeaderView;
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::timing::TimingTree;
+use plonky2_bn128::config::PoseidonBN128GoldilocksConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const D: usize = 2;
+type F = <PoseidonGoldilocksConfig as GenericConfig<D>>::F;
+// Final proofs are wrapped in BN128 config before they are stored.
+type Cbn128 = PoseidonBN128GoldilocksConfig;
+
+/// Represents an epoch block of `CommonInput` together with its hash stored in the contract.
+///
+/// # Fields
+///
+/// * `header` - The block header as returned by the `block` RPC method.
+/// * `stored_hash` - The hash of this block that was previously proven and stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchorBlockView {
+    pub header: BlockHeaderView,
+    pub stored_hash: CryptoHash,
+}
+
+impl From<&AnchorBlock> for AnchorBlockView {
+    fn from(block: &AnchorBlock) -> Self {
+        Self {
+            header: block.header.clone().into(),
+            stored_hash: block.stored_hash,
+        }
+    }
+}
+
+/// Represents the input of a proof shared by the plonky2 and risc0 backends. Blocks and validators
+/// are kept in the form returned by RPC, so the same JSON is read by `near_risc0` (see
+/// `host::prover::CommonInput`) and both backends are compared on identical inputs.
+///
+/// # Fields
+///
+/// * `ep1_first_block` - Block_0 (Epoch_i-1) and its stored hash.
+/// * `ep2_last_block` - Block_n-1 (Epoch_i-2) and its stored hash.
+/// * `ep3_last_block` - Block_n-1 (Epoch_i-3) and its stored hash, set for epoch transitions.
+/// * `blocks` - Block_i, Block_i+1, ..., Block_i+4 (Block_0, ..., Block_4 for epoch transitions)
+///   in ascending order of heights.
+/// * `ep1_last_block` - Block_n-1 (Epoch_i-1), set for epoch transitions.
+/// * `validators` - Validators of Epoch_i as returned by `EXPERIMENTAL_validators_ordered`.
+/// * `validators_n_1` - Validators of Epoch_i-1, set for epoch transitions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommonInput {
+    pub ep1_first_block: AnchorBlockView,
+    pub ep2_last_block: AnchorBlockView,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ep3_last_block: Option<AnchorBlockView>,
+    pub blocks: Vec<BlockHeaderView>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ep1_last_block: Option<BlockHeaderView>,
+    pub validators: Vec<ValidatorOrdered>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validators_n_1: Option<Vec<ValidatorOrdered>>,
+}
+
+impl CommonInput {
+    /// Loads the input from the directory with the layout of data/epochs.
+    ///
+    /// # Arguments
+    ///
+    /// * `epochs_dir` - The directory with epochs.
+    /// * `epoch_ids` - [Epoch_i, Epoch_i-1, Epoch_i-2] to prove a random block from `random-*.json`
+    ///   or [Epoch_i, Epoch_i-1, Epoch_i-2, Epoch_i-3] to prove the epoch transition.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if the number of epoch ids is neither 3 nor 4 and errors
+    /// of loading blocks and validators.
+    pub fn load(epochs_dir: &str, epoch_ids: &[&str]) -> Result<Self> {
+        match epoch_ids {
+            [i, i_1, i_2] => Ok(Self::from(&load_block_input(epochs_dir, i, i_1, i_2)?)),
+            [i, i_1, i_2, i_3] => Ok(Self::from(&load_epoch_input(epochs_dir, i, i_1, i_2, i_3)?)),
+            _ => Err(BftError::InvalidInput(format!(
+                "Expected 3 or 4 epoch ids, got {}",
+                epoch_ids.len()
+            ))),
+        }
+    }
+
+    /// Returns the kind of the proven block: Block_n-1 (Epoch_i-1) is set only for epoch
+    /// transitions.
+    pub fn kind(&self) -> VectorKind {
+        match self.ep1_last_block {
+            Some(_) => VectorKind::Epoch,
+            None => VectorKind::Random,
+        }
+    }
+
+    /// Converts the input into the input of the plonky2 backend for a random block.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if it's the input of an epoch transition and errors of
+    /// `BlockProvingInputBuilder::build`.
+    pub fn to_block_input(&self) -> Result<BlockProvingInput> {
+        self.expect_kind(VectorKind::Random)?;
+        BlockProvingInput::builder()
+            .ep2_last_block(
+                header(&self.ep2_last_block.header),
+                self.ep2_last_block.stored_hash,
+            )
+            .ep1_first_block(
+                header(&self.ep1_first_block.header),
+                self.ep1_first_block.stored_hash,
+            )
+            .blocks(self.blocks.iter().map(header).collect())
+            .validators(validator_stakes(&self.validators)?)
+            .build()
+    }
+
+    /// Converts the input into the input of the plonky2 backend for the epoch transition.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if it's the input of a random block or some epoch data is
+    /// missing and errors of `EpochProvingInputBuilder::build`.
+    pub fn to_epoch_input(&self) -> Result<EpochProvingInput> {
+        self.expect_kind(VectorKind::Epoch)?;
+        let ep3_last_block = self.ep3_last_block.as_ref().ok_or_else(|| {
+            BftError::InvalidInput("Block_n-1 (Epoch_i-3) is not set".to_string())
+        })?;
+        let validators_n_1 = self.validators_n_1.as_ref().ok_or_else(|| {
+            BftError::InvalidInput("Validators of Epoch_i-1 are not set".to_string())
+        })?;
+        let mut builder = EpochProvingInput::builder()
+            .ep3_last_block(header(&ep3_last_block.header), ep3_last_block.stored_hash)
+            .ep2_last_block(
+                header(&self.ep2_last_block.header),
+                self.ep2_last_block.stored_hash,
+            )
+            .ep1_first_block(
+                header(&self.ep1_first_block.header),
+                self.ep1_first_block.stored_hash,
+            )
+            .blocks(self.blocks.iter().map(header).collect())
+            .validators(validator_stakes(&self.validators)?)
+            .validators_n_1(validator_stakes(validators_n_1)?);
+        if let Some(ep1_last_block) = &self.ep1_last_block {
+            builder = builder.ep1_last_block(header(ep1_last_block));
+        }
+        builder.build()
+    }
+
+    fn expect_kind(&self, kind: VectorKind) -> Result<()> {
+        if self.kind() != kind {
+            return Err(BftError::InvalidInput(format!(
+                "Expected the input of {:?} block, got {:?}",
+                kind,
+                self.kind()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl From<&BlockProvingInput> for CommonInput {
+    fn from(input: &BlockProvingInput) -> Self {
+        Self {
+            ep1_first_block: (&input.ep1_first_block).into(),
+            ep2_last_block: (&input.ep2_last_block).into(),
+            ep3_last_block: None,
+            blocks: input.blocks.iter().cloned().map(Into::into).collect(),
+            ep1_last_block: None,
+            validators: input.validators.iter().map(validator_ordered).collect(),
+            validators_n_1: None,
+        }
+    }
+}
+
+impl From<&EpochProvingInput> for CommonInput {
+    fn from(input: &EpochProvingInput) -> Self {
+        Self {
+            ep1_first_block: (&input.ep1_first_block).into(),
+            ep2_last_block: (&input.ep2_last_block).into(),
+            ep3_last_block: Some((&input.ep3_last_block).into()),
+            blocks: input.blocks.iter().cloned().map(Into::into).collect(),
+            ep1_last_block: Some(input.ep1_last_block.clone().into()),
+            validators: input.validators.iter().map(validator_ordered).collect(),
+            validators_n_1: Some(input.validators_n_1.iter().map(validator_ordered).collect()),
+        }
+    }
+}
+
+fn header(view: &BlockHeaderView) -> BlockHeader {
+    BlockHeader::from(view.clone())
+}
+
+fn validator_stakes(validators: &[ValidatorOrdered]) -> Result<Vec<ValidatorStake>> {
+    validators
+        .iter()
+        .cloned()
+        .map(validator_stake_from_ordered)
+        .collect()
+}
+
+/// Represents a backend proving NEAR blocks. Both backends take `CommonInput` and their proofs
+/// are verified into the same hashes, so applications and benchmarks switch backends freely.
+pub trait Prover {
+    /// Represents the proof of the backend.
+    type Proof;
+
+    /// Proves a randomly selected Block_i.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input isn't the input of a random block or it is rejected.
+    fn prove_random_block(&self, input: &CommonInput) -> Result<Self::Proof>;
+
+    /// Proves the transition to Epoch_i by Block_0 (Epoch_i) and Block_n-1 (Epoch_i-1).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input isn't the input of an epoch transition or it is rejected.
+    fn prove_epoch_transition(&self, input: &CommonInput) -> Result<Self::Proof>;
+
+    /// Verifies the proof and returns hashes it commits to, the same for both backends.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof is invalid.
+    fn verify(&self, proof: &Self::Proof) -> Result<VectorOutputs>;
+}
+
+/// Represents the final proofs of the plonky2 backend.
+///
+/// # Fields
+///
+/// * `block` - The proof of Block_i or Block_0 (Epoch_i).
+/// * `previous_block` - The proof of Block_n-1 (Epoch_i-1), set for epoch transitions.
+#[derive(Debug, Clone)]
+pub struct Plonky2Proof {
+    pub block: StoredProof,
+    pub previous_block: Option<StoredProof>,
+}
+
+/// Represents the plonky2 backend. Proofs are produced by the pipeline, so they are wrapped in
+/// BN128 config and stored as in production.
+///
+/// # Fields
+///
+/// * `pipeline` - The pipeline proving and storing blocks.
+/// * `circuits` - Trusted circuits of final proofs by kinds of proven blocks.
+pub struct Plonky2Prover {
+    pipeline: ProvingPipeline,
+    circuits: HashMap<ProofKind, VerifierCircuitData<F, Cbn128, D>>,
+}
+
+impl Plonky2Prover {
+    /// Creates the backend without trusted circuits, so its proofs aren't verified until
+    /// circuits are set by `with_circuit`.
+    pub fn new(pipeline: ProvingPipeline) -> Self {
+        Self {
+            pipeline,
+            circuits: HashMap::new(),
+        }
+    }
+
+    /// Verifies proofs of `kind` blocks against the circuit `data`, e.g. the verifier data of the
+    /// deployed circuit. Verifier data stored with proofs is never used.
+    pub fn with_circuit(
+        mut self,
+        kind: ProofKind,
+        data: VerifierCircuitData<F, Cbn128, D>,
+    ) -> Self {
+        self.circuits.insert(kind, data);
+        self
+    }
+
+    /// Verifies the stored proof against the trusted circuit of `kind` blocks and checks its kind.
+    fn verify_stored(
+        &self,
+        proof: &StoredProof,
+        kind: ProofKind,
+    ) -> Result<BlockProofPublicInputs> {
+        let trusted = self
+            .circuits
+            .get(&kind)
+            .ok_or_else(|| BftError::Proving(format!("no trusted circuit of {:?} blocks", kind)))?;
+        let proof_with_pis = ProofWithPublicInputs::<F, Cbn128, D>::from_bytes(
+            proof.artifacts.proof.clone(),
+            &trusted.common,
+        )
+        .map_err(|e| BftError::Serialization(format!("Error reading proof: {:#}", e)))?;
+        let pi = BlockProofPublicInputs::decode(&proof_with_pis.public_inputs)?;
+        trusted
+            .verify(proof_with_pis)
+            .map_err(|e| BftError::Proving(format!("Proof verification failed: {:#}", e)))?;
+        if pi.kind != kind {
+            return Err(BftError::InvalidInput(format!(
+                "Proof of {} has kind {:?}, expected {:?}",
+                pi.block_hash, pi.kind, kind
+            )));
+        }
+        Ok(pi)
+    }
+}
+
+impl Prover for Plonky2Prover {
+    type Proof = Plonky2Proof;
+
+    fn prove_random_block(&self, input: &CommonInput) -> Result<Plonky2Proof> {
+        let input = input.to_block_input()?;
+        let mut timing = TimingTree::new("To prove block", Level::Info);
+        let block = self.pipeline.prove_block(&input, &mut timing)?;
+        timing.print();
+        Ok(Plonky2Proof {
+            block,
+            previous_block: None,
+        })
+    }
+
+    fn prove_epoch_transition(&self, input: &CommonInput) -> Result<Plonky2Proof> {
+        let input = input.to_epoch_input()?;
+        let mut timing = TimingTree::new("To prove epoch", Level::Info);
+        let (block, previous_block) = self.pipeline.prove_epoch(&input, &mut timing)?;
+        timing.print();
+        Ok(Plonky2Proof {
+            block,
+            previous_block: Some(previous_block),
+        })
+    }
+
+    fn verify(&self, proof: &Plonky2Proof) -> Result<VectorOutputs> {
+        let kind = match proof.previous_block {
+            Some(_) => ProofKind::Epoch,
+            None => ProofKind::Random,
+        };
+        let bi = self.verify_stored(&proof.block, kind)?;
+        let bn_1 = proof
+            .previous_block
+            .as_ref()
+            .map(|previous_block| self.verify_stored(previous_block, kind))
+            .transpose()?;
+        Ok(VectorOutputs::from_public_inputs(&bi, bn_1.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    const EPOCHS_PATH: &str = "../data/epochs";
+    const COMMON_INPUT_PATH: &str = "../data/common_input";
+    const EPOCH_IDS: [&str; 4] = [
+        "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t",
+        "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae",
+        "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi",
+        "89PT9SkLXB1FZHvW7EdQHxiSpm5ybuTCvjrGZWWhXMTz",
+    ];
+
+    #[test]
+    fn test_common_input_of_random_block() -> Result<()> {
+        let [i, i_1, i_2, _] = EPOCH_IDS;
+        let expected = load_block_input(EPOCHS_PATH, i, i_1, i_2)?;
+        let common = CommonInput::load(EPOCHS_PATH, &[i, i_1, i_2])?;
+        assert_eq!(common.kind(), VectorKind::Random);
+        assert!(common.to_epoch_input().is_err());
+
+        // The input survives the JSON shared with the risc0 backend.
+        let common: CommonInput = serde_json::from_str(&serde_json::to_string(&common)?)?;
+        let input = common.to_block_input()?;
+        assert_eq!(input.epoch_blocks()?, expected.epoch_blocks()?);
+        assert_eq!(input.blocks_data()?, expected.blocks_data()?);
+        assert_eq!(input.validators, expected.validators);
+        Ok(())
+    }
+
+    #[test]
+    fn test_common_input_of_epoch_transition() -> Result<()> {
+        let [i, i_1, i_2, i_3] = EPOCH_IDS;
+        let expected = load_epoch_input(EPOCHS_PATH, i, i_1, i_2, i_3)?;
+        let common = CommonInput::load(EPOCHS_PATH, &EPOCH_IDS)?;
+        assert_eq!(common.kind(), VectorKind::Epoch);
+        assert!(common.to_block_input().is_err());
+
+        let common: CommonInput = serde_json::from_str(&serde_json::to_string(&common)?)?;
+        let input = common.to_epoch_input()?;
+        assert_eq!(input.epoch_blocks()?, expected.epoch_blocks()?);
+        assert_eq!(input.blocks_data()?, expected.blocks_data()?);
+        assert_eq!(input.validators, expected.validators);
+        assert_eq!(input.validators_n_1, expected.validators_n_1);
+
+        let common = CommonInput {
+            validators_n_1: None,
+            ..common
+        };
+        assert!(matches!(
+            common.to_epoch_input(),
+            Err(BftError::InvalidInput(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_common_input_invalid_epoch_ids() {
+        assert!(matches!(
+            CommonInput::load(EPOCHS_PATH, &EPOCH_IDS[..2]),
+            Err(BftError::InvalidInput(_))
+        ));
+    }
+
+    /// Fixtures are written by `host::prover::CommonInput` of near_risc0, so both backends agree
+    /// on the JSON of their inputs.
+    #[test]
+    fn test_common_input_fixtures() -> Result<()> {
+        let fixtures = [
+            ("random_block.json", &EPOCH_IDS[..3]),
+            ("epoch_transition.json", &EPOCH_IDS[..]),
+        ];
+        for (file, epoch_ids) in fixtures {
+            let data = std::fs::read_to_string(format!("{COMMON_INPUT_PATH}/{file}"))?;
+            let fixture: CommonInput = serde_json::from_str(&data)?;
+            assert_eq!(fixture, CommonInput::load(EPOCHS_PATH, epoch_ids)?, "{}", file);
+            let json: serde_json::Value = serde_json::from_str(&data)?;
+            assert_eq!(serde_json::to_value(&fixture)?, json, "{}", file);
+        }
+        Ok(())
+    }
+}
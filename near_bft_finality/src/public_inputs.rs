@@ -0,0 +1,325 @@
+use crate::error::{BftError, Result};
+use crate::prove_crypto::recursion::ExposedInput;
+use crate::store::ProofKind;
+use crate::types::{BLOCK_HEIGHT_BYTES, PK_HASH_BYTES};
+use near_primitives::hash::CryptoHash;
+use plonky2_field::types::{Field, PrimeField64};
+use serde::{Deserialize, Serialize};
+use std::iter;
+use std::ops::Range;
+
+/// Represents the range of the block hash in public inputs of a header proof.
+pub const HEADER_HASH_RANGE: Range<usize> = 0..32;
+/// Represents the range of the height (little-endian) in public inputs of a header proof.
+pub const HEADER_HEIGHT_RANGE: Range<usize> = 32..40;
+/// Represents the range of epoch_id in public inputs of a header proof.
+pub const HEADER_EPOCH_ID_RANGE: Range<usize> = 40..72;
+/// Represents the range of prev_hash in public inputs of a header proof.
+pub const HEADER_PREV_HASH_RANGE: Range<usize> = 72..104;
+
+/// Represents the index of the kind in public inputs of the final proof (V1).
+pub const KIND_INDEX: usize = 0;
+/// Represents the range of the proven block hash in public inputs of the final proof (V1).
+pub const BLOCK_HASH_RANGE: Range<usize> = 1..33;
+/// Represents the range of Block_n-1 (Epoch_i-2) hash in public inputs of the final proof (V1).
+pub const EP2_LAST_BLOCK_HASH_RANGE: Range<usize> = 33..65;
+/// Represents the range of Block_0 (Epoch_i-1) hash in public inputs of the final proof (V1).
+pub const EP1_FIRST_BLOCK_HASH_RANGE: Range<usize> = 65..97;
+
+/// Represents the layout of public inputs of the final block proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PublicInputsVersion {
+    /// [kind (0 - random, 1 - epoch), block hash, Block_n-1 hash, Block_0 hash], each hash is 32 bytes.
+    V1,
+}
+
+impl PublicInputsVersion {
+    /// Represents the version produced by the prover.
+    pub const LATEST: Self = PublicInputsVersion::V1;
+
+    /// Returns the number of public inputs (field elements) of this version.
+    pub fn num_public_inputs(&self) -> usize {
+        match self {
+            PublicInputsVersion::V1 => 1 + 3 * PK_HASH_BYTES,
+        }
+    }
+
+    /// Detects the version by the number of public inputs.
+    pub fn from_len(len: usize) -> Result<Self> {
+        [PublicInputsVersion::V1]
+            .into_iter()
+            .find(|version| version.num_public_inputs() == len)
+            .ok_or_else(|| {
+                BftError::InvalidInput(format!("Unknown layout of {} public inputs", len))
+            })
+    }
+}
+
+/// Represents public inputs of the final proof of a random or epoch block.
+///
+/// # Fields
+///
+/// * `version` - The layout of public inputs.
+/// * `kind` - The kind of the proven block.
+/// * `block_hash` - The hash of the proven block.
+/// * `ep2_last_block_hash` - The stored hash of Block_n-1 (Epoch_i-2), where Epoch_i is the epoch of the proven block.
+/// * `ep1_first_block_hash` - The stored hash of Block_0 (Epoch_i-1).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockProofPublicInputs {
+    pub version: PublicInputsVersion,
+    pub kind: ProofKind,
+    pub block_hash: CryptoHash,
+    pub ep2_last_block_hash: CryptoHash,
+    pub ep1_first_block_hash: CryptoHash,
+}
+
+impl BlockProofPublicInputs {
+    /// Creates public inputs of the latest version.
+    pub fn new(
+        kind: ProofKind,
+        block_hash: CryptoHash,
+        ep2_last_block_hash: CryptoHash,
+        ep1_first_block_hash: CryptoHash,
+    ) -> Self {
+        Self {
+            version: PublicInputsVersion::LATEST,
+            kind,
+            block_hash,
+            ep2_last_block_hash,
+            ep1_first_block_hash,
+        }
+    }
+
+    /// Extracts public inputs from the proof of block finality (see `prove_block_finality`). Its public inputs
+    /// start with the hash of the proven block and end with hashes of Block_n-1 (Epoch_i-2) & Block_0 (Epoch_i-1).
+    pub fn from_finality_pi<F: PrimeField64>(kind: ProofKind, pi: &[F]) -> Result<Self> {
+        let [block_hash, ep2_last_block_hash, ep1_first_block_hash] =
+            finality_hash_ranges(pi.len())?;
+        Ok(Self::new(
+            kind,
+            hash_from_pi(&pi[block_hash])?,
+            hash_from_pi(&pi[ep2_last_block_hash])?,
+            hash_from_pi(&pi[ep1_first_block_hash])?,
+        ))
+    }
+
+    /// Returns public inputs of the final proof of the latest version, in the order of `encode`, as public inputs
+    /// of the proof of block finality with `len` public inputs. The kind is a constant of the final circuit.
+    pub fn exposed_finality_pi<F: Field>(
+        kind: ProofKind,
+        len: usize,
+    ) -> Result<Vec<ExposedInput<F>>> {
+        let [block_hash, ep2_last_block_hash, ep1_first_block_hash] = finality_hash_ranges(len)?;
+        let kind = ExposedInput::Constant(F::from_canonical_u8(kind_byte(kind)));
+        Ok(iter::once(kind)
+            .chain(
+                [block_hash, ep2_last_block_hash, ep1_first_block_hash]
+                    .into_iter()
+                    .flatten()
+                    .map(ExposedInput::Inner),
+            )
+            .collect())
+    }
+
+    /// Encodes public inputs as field elements, one element per byte.
+    pub fn encode<F: Field>(&self) -> Vec<F> {
+        match self.version {
+            PublicInputsVersion::V1 => [
+                &[kind_byte(self.kind)][..],
+                &self.block_hash.0,
+                &self.ep2_last_block_hash.0,
+                &self.ep1_first_block_hash.0,
+            ]
+            .concat()
+            .into_iter()
+            .map(F::from_canonical_u8)
+            .collect(),
+        }
+    }
+
+    /// Decodes public inputs of the final proof.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if the number of public inputs doesn't match any version,
+    /// some element is not a canonical byte or the kind is unknown.
+    pub fn decode<F: PrimeField64>(pi: &[F]) -> Result<Self> {
+        let version = PublicInputsVersion::from_len(pi.len())?;
+        let bytes = pi_to_bytes(pi)?;
+        match version {
+            PublicInputsVersion::V1 => {
+                let kind = match bytes[KIND_INDEX] {
+                    0 => ProofKind::Random,
+                    1 => ProofKind::Epoch,
+                    kind => {
+                        return Err(BftError::InvalidInput(format!(
+                            "Unknown kind of proof: {}",
+                            kind
+                        )))
+                    }
+                };
+                let hash = |range: Range<usize>| {
+                    CryptoHash(
+                        bytes[range]
+                            .try_into()
+                            .expect("length is checked by version"),
+                    )
+                };
+                Ok(Self {
+                    version,
+                    kind,
+                    block_hash: hash(BLOCK_HASH_RANGE),
+                    ep2_last_block_hash: hash(EP2_LAST_BLOCK_HASH_RANGE),
+                    ep1_first_block_hash: hash(EP1_FIRST_BLOCK_HASH_RANGE),
+                })
+            }
+        }
+    }
+}
+
+/// Returns the encoding of the kind in public inputs of the final proof.
+fn kind_byte(kind: ProofKind) -> u8 {
+    match kind {
+        ProofKind::Random => 0,
+        ProofKind::Epoch => 1,
+    }
+}
+
+/// Returns ranges of the hash of the proven block and hashes of Block_n-1 (Epoch_i-2) & Block_0 (Epoch_i-1)
+/// in public inputs of the proof of block finality with `len` public inputs.
+fn finality_hash_ranges(len: usize) -> Result<[Range<usize>; 3]> {
+    if len < 3 * PK_HASH_BYTES {
+        return Err(BftError::InvalidInput(format!(
+            "finality proof has {} public inputs, expected at least {}",
+            len,
+            3 * PK_HASH_BYTES
+        )));
+    }
+    Ok([
+        HEADER_HASH_RANGE,
+        (len - 2 * PK_HASH_BYTES)..(len - PK_HASH_BYTES),
+        (len - PK_HASH_BYTES)..len,
+    ])
+}
+
+/// Converts public inputs to bytes, checking that every element is a canonical byte.
+pub fn pi_to_bytes<F: PrimeField64>(pi: &[F]) -> Result<Vec<u8>> {
+    pi.iter()
+        .enumerate()
+        .map(|(i, x)| {
+            u8::try_from(x.to_canonical_u64()).map_err(|_| {
+                BftError::InvalidInput(format!("Public input {} is not a byte: {}", i, x))
+            })
+        })
+        .collect()
+}
+
+/// Extracts a hash (32 bytes) from public inputs of a proof.
+pub fn hash_from_pi<F: PrimeField64>(pi: &[F]) -> Result<CryptoHash> {
+    let hash: [u8; PK_HASH_BYTES] = pi_to_bytes(pi)?.try_into().map_err(|_| {
+        BftError::InvalidInput(format!("expected {} bytes of hash in PI", PK_HASH_BYTES))
+    })?;
+    Ok(CryptoHash(hash))
+}
+
+/// Extracts a height (8 bytes, little-endian) from public inputs of a proof.
+pub fn height_from_pi<F: PrimeField64>(pi: &[F]) -> Result<u64> {
+    let height: [u8; BLOCK_HEIGHT_BYTES] = pi_to_bytes(pi)?.try_into().map_err(|_| {
+        BftError::InvalidInput(format!(
+            "expected {} bytes of height in PI",
+            BLOCK_HEIGHT_BYTES
+        ))
+    })?;
+    Ok(u64::from_le_bytes(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    type F = GoldilocksField;
+
+    fn public_inputs(kind: ProofKind) -> BlockProofPublicInputs {
+        BlockProofPublicInputs::new(
+            kind,
+            CryptoHash([1; 32]),
+            CryptoHash([2; 32]),
+            CryptoHash([3; 32]),
+        )
+    }
+
+    #[test]
+    fn test_encode_decode_public_inputs() -> Result<()> {
+        for kind in [ProofKind::Random, ProofKind::Epoch] {
+            let pi = public_inputs(kind).encode::<F>();
+            assert_eq!(pi.len(), PublicInputsVersion::V1.num_public_inputs());
+            assert_eq!(BlockProofPublicInputs::decode(&pi)?, public_inputs(kind));
+        }
+        // The layout used by contracts: the hash of the proven block follows the kind.
+        let pi = public_inputs(ProofKind::Epoch).encode::<F>();
+        assert_eq!(pi[0], F::ONE);
+        assert_eq!(hash_from_pi(&pi[1..33])?, CryptoHash([1; 32]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_rejects_non_canonical_bytes() {
+        let mut pi = public_inputs(ProofKind::Random).encode::<F>();
+        pi[40] = F::from_canonical_u16(256);
+        assert!(matches!(
+            BlockProofPublicInputs::decode(&pi),
+            Err(BftError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_layout_and_kind() {
+        let pi = public_inputs(ProofKind::Random).encode::<F>();
+        assert!(BlockProofPublicInputs::decode(&pi[1..]).is_err());
+        let mut pi = pi;
+        pi[0] = F::TWO;
+        assert!(BlockProofPublicInputs::decode(&pi).is_err());
+    }
+
+    #[test]
+    fn test_exposed_finality_pi_match_encoding() -> Result<()> {
+        // Header PI (hash, height), then PI of other proofs ending with hashes of Block_n-1 & Block_0.
+        let finality_pi: Vec<F> = [[1u8; 32].as_slice(), &[7; 8], &[9; 5], &[2; 32], &[3; 32]]
+            .concat()
+            .into_iter()
+            .map(F::from_canonical_u8)
+            .collect();
+        for kind in [ProofKind::Random, ProofKind::Epoch] {
+            let exposed: Vec<F> =
+                BlockProofPublicInputs::exposed_finality_pi::<F>(kind, finality_pi.len())?
+                    .into_iter()
+                    .map(|input| match input {
+                        ExposedInput::Inner(index) => finality_pi[index],
+                        ExposedInput::Constant(value) => value,
+                    })
+                    .collect();
+            assert_eq!(exposed, public_inputs(kind).encode::<F>());
+            assert_eq!(
+                BlockProofPublicInputs::from_finality_pi(kind, &finality_pi)?,
+                public_inputs(kind)
+            );
+        }
+        assert!(BlockProofPublicInputs::exposed_finality_pi::<F>(ProofKind::Random, 95).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_height_from_pi() -> Result<()> {
+        let pi: Vec<F> = 121794708u64
+            .to_le_bytes()
+            .into_iter()
+            .map(F::from_canonical_u8)
+            .collect();
+        assert_eq!(height_from_pi(&pi)?, 121794708);
+        assert!(height_from_pi(&pi[1..]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,451 @@
+use crate::error::{BftError, Result};
+use crate::input::{BlockProvingInput, EpochProvingInput};
+use crate::network::NetworkProfile;
+use crate::pipeline::{ProvingPipeline, StoredProof};
+use crate::public_inputs::BlockProofPublicInputs;
+use crate::source::BlockSource;
+use crate::utils::{parse_hash, resolve_block_input, resolve_epoch_input};
+use log::Level;
+use near_primitives::block_header::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use plonky2::util::timing::TimingTree;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+/// Represents the task to prove Block_0 (Epoch_i) & Block_n-1 (Epoch_i-1).
+/// It has the same JSON as the task of the risc0 proving server.
+///
+/// # Fields
+///
+/// * `epoch_id_i_block_hash` - The hash of Block_0 (Epoch_i).
+/// * `epoch_id_i_1_block_hash` - The hash of Block_0 (Epoch_i-1).
+/// * `epoch_id_i_2_block_hash` - The hash of Block_n-1 (Epoch_i-2).
+/// * `epoch_id_i_3_block_hash_last` - The hash of Block_n-1 (Epoch_i-3).
+/// * `epoch_id_i_1_block_hash_last` - The hash of Block_n-1 (Epoch_i-1).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EpochProvingTask {
+    #[serde(rename = "currentEpochHash")]
+    pub epoch_id_i_block_hash: String,
+
+    #[serde(rename = "prevEpochStartHash")]
+    pub epoch_id_i_1_block_hash: String,
+
+    #[serde(rename = "prevEpochMinus1EndHash")]
+    pub epoch_id_i_2_block_hash: String,
+
+    #[serde(rename = "prevEpochMinus2EndHash")]
+    pub epoch_id_i_3_block_hash_last: String,
+
+    #[serde(rename = "prevEpochEndHash")]
+    pub epoch_id_i_1_block_hash_last: String,
+}
+
+/// Represents the task to prove a randomly selected Block_i (Epoch_i).
+/// It has the same JSON as the task of the risc0 proving server.
+///
+/// # Fields
+///
+/// * `epoch_id_i_hash_i` - The hash of Block_i (Epoch_i).
+/// * `epoch_id_i_1_hash_0` - The hash of Block_0 (Epoch_i-1).
+/// * `epoch_id_i_2_hash_last_str` - The hash of Block_n-1 (Epoch_i-2).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RandomProvingTask {
+    #[serde(rename = "currentBlockHash")]
+    pub epoch_id_i_hash_i: String,
+
+    #[serde(rename = "previousEpochStartHash")]
+    pub epoch_id_i_1_hash_0: String,
+
+    #[serde(rename = "previousEpochEndHash")]
+    pub epoch_id_i_2_hash_last_str: String,
+}
+
+/// Represents the final proof of a block returned by the proving server.
+///
+/// # Fields
+///
+/// * `block_hash` - The hash of the proven block.
+/// * `block_height` - The height of the proven block.
+/// * `public_inputs` - The decoded public inputs of the proof.
+/// * `proof` - `ProofWithPublicInputs` in binary form, hex encoded.
+/// * `verifier_data` - `VerifierCircuitData` in binary form, hex encoded.
+/// * `proof_json` - `ProofWithPublicInputs` in json.
+/// * `verifier_data_json` - `VerifierOnlyCircuitData` in json.
+/// * `common_data_json` - `CommonCircuitData` in json.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofOutput {
+    pub block_hash: CryptoHash,
+    pub block_height: u64,
+    pub public_inputs: BlockProofPublicInputs,
+    pub proof: String,
+    pub verifier_data: String,
+    pub proof_json: serde_json::Value,
+    pub verifier_data_json: serde_json::Value,
+    pub common_data_json: serde_json::Value,
+}
+
+impl ProofOutput {
+    /// Encodes the proof stored by the pipeline.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::Serialization` if json artifacts can't be parsed.
+    pub fn new(stored: &StoredProof) -> Result<Self> {
+        let artifacts = &stored.artifacts;
+        Ok(Self {
+            block_hash: stored.record.block_hash,
+            block_height: stored.record.height,
+            public_inputs: stored.public_inputs.clone(),
+            proof: hex::encode(&artifacts.proof),
+            verifier_data: hex::encode(&artifacts.verifier_data),
+            proof_json: serde_json::from_slice(&artifacts.proof_json)?,
+            verifier_data_json: serde_json::from_slice(&artifacts.verifier_data_json)?,
+            common_data_json: serde_json::from_slice(&artifacts.common_data_json)?,
+        })
+    }
+}
+
+/// Represents the result of `EpochProvingTask`. Fields of the risc0 result are followed by proofs.
+///
+/// # Fields
+///
+/// * `block_hash_n_0` - The hash of Block_0 (Epoch_i).
+/// * `block_height_n_0` - The height of Block_0 (Epoch_i).
+/// * `block_hash_n_1` - The hash of Block_n-1 (Epoch_i-1).
+/// * `status` - `OK` if both blocks are proven.
+/// * `block_0` - The proof of Block_0 (Epoch_i).
+/// * `block_n_1` - The proof of Block_n-1 (Epoch_i-1).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EpochProvingResult {
+    #[serde(rename = "currentBlockHash")]
+    pub block_hash_n_0: String,
+
+    #[serde(rename = "currentBlockHeight")]
+    pub block_height_n_0: u64,
+
+    #[serde(rename = "previousBlockHash")]
+    pub block_hash_n_1: String,
+
+    pub status: String,
+
+    #[serde(rename = "currentBlockProof")]
+    pub block_0: ProofOutput,
+
+    #[serde(rename = "previousBlockProof")]
+    pub block_n_1: ProofOutput,
+}
+
+/// Represents the result of `RandomProvingTask`.
+///
+/// # Fields
+///
+/// * `epoch_id_i_block_hash` - The hash of Block_i (Epoch_i).
+/// * `status` - `OK` if the block is proven.
+/// * `proof` - The proof of Block_i.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RandomProvingResult {
+    #[serde(rename = "currentBlockHash")]
+    pub epoch_id_i_block_hash: String,
+
+    pub status: String,
+
+    pub proof: ProofOutput,
+}
+
+/// Represents the service proving tasks of the proving server with the plonky2 pipeline.
+///
+/// # Fields
+///
+/// * `profile` - The network of proven blocks.
+/// * `source` - The source of blocks and validators.
+/// * `pipeline` - Proves inputs and stores final proofs. Stored hashes of epoch blocks are read
+///   from its store.
+/// * `proving` - Serializes proving: a single run takes all cores and most of the memory.
+pub struct ProvingService {
+    profile: NetworkProfile,
+    source: Box<dyn BlockSource>,
+    pipeline: ProvingPipeline,
+    proving: Mutex<()>,
+}
+
+impl ProvingService {
+    /// Creates the service proving blocks of `source` with `pipeline`.
+    pub fn new(
+        profile: NetworkProfile,
+        source: Box<dyn BlockSource>,
+        pipeline: ProvingPipeline,
+    ) -> Self {
+        Self {
+            profile,
+            source,
+            pipeline,
+            proving: Mutex::new(()),
+        }
+    }
+
+    /// Returns the network of proven blocks.
+    pub fn profile(&self) -> &NetworkProfile {
+        &self.profile
+    }
+
+    /// Resolves the input of the epoch task from its Block_0 and checks the other hashes of the
+    /// task against the chain.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::HashMismatch` if the task doesn't match the chain and errors of
+    /// `resolve_epoch_input`.
+    pub async fn epoch_input(&self, task: &EpochProvingTask) -> Result<EpochProvingInput> {
+        let hash = parse_hash(&task.epoch_id_i_block_hash)?;
+        let input = resolve_epoch_input(
+            hash,
+            self.source.as_ref(),
+            self.pipeline.store(),
+            &self.profile,
+        )
+        .await?;
+        check_task_hash(
+            "prevEpochStartHash",
+            &task.epoch_id_i_1_block_hash,
+            &input.ep1_first_block.header,
+        )?;
+        check_task_hash(
+            "prevEpochMinus1EndHash",
+            &task.epoch_id_i_2_block_hash,
+            &input.ep2_last_block.header,
+        )?;
+        check_task_hash(
+            "prevEpochMinus2EndHash",
+            &task.epoch_id_i_3_block_hash_last,
+            &input.ep3_last_block.header,
+        )?;
+        check_task_hash(
+            "prevEpochEndHash",
+            &task.epoch_id_i_1_block_hash_last,
+            &input.ep1_last_block,
+        )?;
+        Ok(input)
+    }
+
+    /// Resolves the input of the random task from its Block_i and checks the other hashes of the
+    /// task against the chain.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::HashMismatch` if the task doesn't match the chain and errors of
+    /// `resolve_block_input`.
+    pub async fn random_input(&self, task: &RandomProvingTask) -> Result<BlockProvingInput> {
+        let hash = parse_hash(&task.epoch_id_i_hash_i)?;
+        let input = resolve_block_input(
+            hash,
+            self.source.as_ref(),
+            self.pipeline.store(),
+            &self.profile,
+        )
+        .await?;
+        check_task_hash(
+            "previousEpochStartHash",
+            &task.epoch_id_i_1_hash_0,
+            &input.ep1_first_block.header,
+        )?;
+        check_task_hash(
+            "previousEpochEndHash",
+            &task.epoch_id_i_2_hash_last_str,
+            &input.ep2_last_block.header,
+        )?;
+        Ok(input)
+    }
+
+    /// Proves and stores Block_0 (Epoch_i) & Block_n-1 (Epoch_i-1) of the task.
+    /// Proving blocks the worker thread, so the service runs on the multi-threaded runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors of `epoch_input` and `ProvingPipeline::prove_epoch`.
+    pub async fn prove_epoch(&self, task: &EpochProvingTask) -> Result<EpochProvingResult> {
+        let _proving = self.proving.lock().await;
+        let input = self.epoch_input(task).await?;
+        let (b0, bn_1) = tokio::task::block_in_place(|| {
+            let mut timing = TimingTree::new("To prove epoch", Level::Info);
+            let proofs = self.pipeline.prove_epoch(&input, &mut timing)?;
+            timing.print();
+            Ok::<_, BftError>(proofs)
+        })?;
+        Ok(EpochProvingResult {
+            block_hash_n_0: b0.record.block_hash.to_string(),
+            block_height_n_0: b0.record.height,
+            block_hash_n_1: bn_1.record.block_hash.to_string(),
+            status: "OK".to_string(),
+            block_0: ProofOutput::new(&b0)?,
+            block_n_1: ProofOutput::new(&bn_1)?,
+        })
+    }
+
+    /// Proves and stores Block_i of the task.
+    /// Proving blocks the worker thread, so the service runs on the multi-threaded runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors of `random_input` and `ProvingPipeline::prove_block`.
+    pub async fn prove_random(&self, task: &RandomProvingTask) -> Result<RandomProvingResult> {
+        let _proving = self.proving.lock().await;
+        let input = self.random_input(task).await?;
+        let bi = tokio::task::block_in_place(|| {
+            let mut timing = TimingTree::new("To prove block", Level::Info);
+            let proof = self.pipeline.prove_block(&input, &mut timing)?;
+            timing.print();
+            Ok::<_, BftError>(proof)
+        })?;
+        Ok(RandomProvingResult {
+            epoch_id_i_block_hash: bi.record.block_hash.to_string(),
+            status: "OK".to_string(),
+            proof: ProofOutput::new(&bi)?,
+        })
+    }
+}
+
+/// Checks that the hash `name` of the task is the hash of the resolved block.
+fn check_task_hash(name: &str, task_hash: &str, block: &BlockHeader) -> Result<()> {
+    let hash = parse_hash(task_hash)?;
+    if hash != *block.hash() {
+        return Err(BftError::HashMismatch(format!(
+            "{} of the task is {}, but the chain has {}",
+            name,
+            hash,
+            block.hash()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::GenesisAnchor;
+    use crate::prove_block_data::signature_prover::RayonSignatureProver;
+    use crate::source::FixtureBlockSource;
+    use crate::store::InMemoryProofStore;
+    use crate::utils::{
+        load_block_header, load_block_input, load_epoch_input, DEFAULT_EPOCHS_PATH,
+    };
+    use anyhow::Result;
+    use std::sync::Arc;
+
+    const EPOCH_ID_I: &str = "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t";
+    const EPOCH_ID_I_1: &str = "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae";
+    const EPOCH_ID_I_2: &str = "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi";
+    const EPOCH_ID_I_3: &str = "89PT9SkLXB1FZHvW7EdQHxiSpm5ybuTCvjrGZWWhXMTz";
+
+    fn block_hash(epoch_id: &str, file: &str) -> Result<String> {
+        let (hash, _) = load_block_header(&format!("{DEFAULT_EPOCHS_PATH}/{epoch_id}/{file}"))?;
+        Ok(hash.to_string())
+    }
+
+    /// Creates the service over fixtures, Epoch_i-1 is the genesis anchor.
+    fn fixture_service() -> Result<ProvingService> {
+        let dir = DEFAULT_EPOCHS_PATH;
+        let (block_hash, block) = load_block_header(&format!("{dir}/{EPOCH_ID_I_1}/block-0.json"))?;
+        let profile = NetworkProfile {
+            genesis: Some(GenesisAnchor {
+                epoch_id: block.epoch_id().0,
+                next_epoch_id: block.next_epoch_id().0,
+                block_hash,
+                bp_hash: *block.next_bp_hash(),
+                height: block.height(),
+            }),
+            ..NetworkProfile::mainnet()
+        };
+        let pipeline = ProvingPipeline::new(
+            Arc::new(RayonSignatureProver::new()),
+            Arc::new(InMemoryProofStore::new()),
+        );
+        Ok(ProvingService::new(
+            profile,
+            Box::new(FixtureBlockSource::open(dir)?),
+            pipeline,
+        ))
+    }
+
+    #[test]
+    fn test_tasks_json() -> Result<()> {
+        let task: EpochProvingTask = serde_json::from_str(
+            r#"{
+                "currentEpochHash": "a",
+                "prevEpochStartHash": "b",
+                "prevEpochMinus1EndHash": "c",
+                "prevEpochMinus2EndHash": "d",
+                "prevEpochEndHash": "e"
+            }"#,
+        )?;
+        assert_eq!(task.epoch_id_i_block_hash, "a");
+        assert_eq!(task.epoch_id_i_1_block_hash, "b");
+        assert_eq!(task.epoch_id_i_2_block_hash, "c");
+        assert_eq!(task.epoch_id_i_3_block_hash_last, "d");
+        assert_eq!(task.epoch_id_i_1_block_hash_last, "e");
+
+        let task: RandomProvingTask = serde_json::from_str(
+            r#"{
+                "currentBlockHash": "a",
+                "previousEpochStartHash": "b",
+                "previousEpochEndHash": "c"
+            }"#,
+        )?;
+        assert_eq!(task.epoch_id_i_hash_i, "a");
+        assert_eq!(task.epoch_id_i_1_hash_0, "b");
+        assert_eq!(task.epoch_id_i_2_hash_last_str, "c");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_epoch_input_checks_task_hashes() -> Result<()> {
+        let dir = DEFAULT_EPOCHS_PATH;
+        let expected = load_epoch_input(dir, EPOCH_ID_I, EPOCH_ID_I_1, EPOCH_ID_I_2, EPOCH_ID_I_3)?;
+        let service = fixture_service()?;
+        let task = EpochProvingTask {
+            epoch_id_i_block_hash: block_hash(EPOCH_ID_I, "block-0.json")?,
+            epoch_id_i_1_block_hash: block_hash(EPOCH_ID_I_1, "block-0.json")?,
+            epoch_id_i_2_block_hash: block_hash(EPOCH_ID_I_2, "block-last.json")?,
+            epoch_id_i_3_block_hash_last: block_hash(EPOCH_ID_I_3, "block-last.json")?,
+            epoch_id_i_1_block_hash_last: block_hash(EPOCH_ID_I_1, "block-last.json")?,
+        };
+        let input = service.epoch_input(&task).await?;
+        assert_eq!(input.epoch_blocks()?, expected.epoch_blocks()?);
+        assert_eq!(input.blocks, expected.blocks);
+
+        let task = EpochProvingTask {
+            epoch_id_i_1_block_hash_last: task.epoch_id_i_2_block_hash.clone(),
+            ..task
+        };
+        assert!(matches!(
+            service.epoch_input(&task).await,
+            Err(BftError::HashMismatch(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_random_input_checks_task_hashes() -> Result<()> {
+        let dir = DEFAULT_EPOCHS_PATH;
+        let expected = load_block_input(dir, EPOCH_ID_I, EPOCH_ID_I_1, EPOCH_ID_I_2)?;
+        let service = fixture_service()?;
+        let task = RandomProvingTask {
+            epoch_id_i_hash_i: block_hash(EPOCH_ID_I, "random-0.json")?,
+            epoch_id_i_1_hash_0: block_hash(EPOCH_ID_I_1, "block-0.json")?,
+            epoch_id_i_2_hash_last_str: block_hash(EPOCH_ID_I_2, "block-last.json")?,
+        };
+        let input = service.random_input(&task).await?;
+        assert_eq!(input.epoch_blocks()?, expected.epoch_blocks()?);
+        assert_eq!(input.blocks, expected.blocks);
+
+        let task = RandomProvingTask {
+            epoch_id_i_1_hash_0: task.epoch_id_i_hash_i.clone(),
+            ..task
+        };
+        assert!(matches!(
+            service.random_input(&task).await,
+            Err(BftError::HashMismatch(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,896 @@
+use crate::error::{BftError, Result};
+use crate::network::NetworkProfile;
+use crate::store::write_atomic;
+use crate::types::{
+    BlockResponse, BlockView, LightClientBlockResponse, RpcError, RpcErrorResponse,
+    ValidatorsOrderedResponse,
+};
+use crate::utils::{
+    load_block_header, load_validators, validator_ordered, validator_stake_from_ordered,
+};
+use futures::future::{BoxFuture, FutureExt};
+use near_primitives::block_header::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::validator_stake::ValidatorStake;
+use near_primitives::views::{BlockHeaderView, LightClientBlockView};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Represents a source of blocks, validators and light client blocks.
+///
+/// Methods return boxed futures, so sources are used as `&dyn BlockSource` like other pluggable
+/// parts of the crate (`ProofStore`, `SignatureProver`).
+pub trait BlockSource: Send + Sync {
+    /// Loads a block header by its hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::BlockNotFound` if the source doesn't know the block.
+    fn block_by_hash(&self, hash: CryptoHash) -> BoxFuture<'_, Result<(CryptoHash, BlockHeader)>>;
+
+    /// Loads a block header by its height. Returns `None` if the height was skipped.
+    fn block_by_height(
+        &self,
+        height: u64,
+    ) -> BoxFuture<'_, Result<Option<(CryptoHash, BlockHeader)>>>;
+
+    /// Loads validators of the epoch of the block, ordered as in `EXPERIMENTAL_validators_ordered`.
+    fn validators(&self, block_hash: CryptoHash) -> BoxFuture<'_, Result<Vec<ValidatorStake>>>;
+
+    /// Loads the light client block following the block with `last_block_hash`.
+    /// Returns `None` if there is no newer light client block yet.
+    fn next_light_client_block(
+        &self,
+        last_block_hash: CryptoHash,
+    ) -> BoxFuture<'_, Result<Option<LightClientBlockView>>>;
+}
+
+/// Represents the blocks loaded from a NEAR JSON-RPC endpoint.
+///
+/// # Fields
+///
+/// * `url` - The URL of the RPC endpoint.
+/// * `client` - The HTTP client.
+#[derive(Debug, Clone)]
+pub struct RpcBlockSource {
+    url: String,
+    client: Client,
+}
+
+impl RpcBlockSource {
+    /// Creates the source for the RPC endpoint at `url`.
+    pub fn new(url: impl Into<String>) -> Result<Self> {
+        let client = Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()?;
+        Ok(Self {
+            url: url.into(),
+            client,
+        })
+    }
+
+    /// Creates the source for the RPC endpoint of the network profile set in the environment,
+    /// see `NetworkProfile::from_env`.
+    pub fn from_env() -> Result<Self> {
+        Self::from_profile(&NetworkProfile::from_env()?)
+    }
+
+    /// Creates the source for the RPC endpoint of the network.
+    pub fn from_profile(profile: &NetworkProfile) -> Result<Self> {
+        Self::new(profile.rpc_url.as_str())
+    }
+
+    /// Creates the source for the archival RPC endpoint of the network, which keeps old blocks.
+    pub fn archival(profile: &NetworkProfile) -> Result<Self> {
+        Self::new(profile.archival_rpc_url.as_str())
+    }
+
+    /// Returns the URL of the RPC endpoint.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Sends the request and parses either the response `R` or the RPC error.
+    /// `id` identifies the requested block in `BftError::BlockNotFound`.
+    async fn call<R: DeserializeOwned>(&self, method: &str, params: Value, id: &str) -> Result<R> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "dontcare",
+            "method": method,
+            "params": params,
+        });
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?
+            .text()
+            .await?;
+        if let Ok(error_response) = serde_json::from_str::<RpcErrorResponse>(&response) {
+            return Err(rpc_error(id, error_response.error));
+        }
+        serde_json::from_str(&response)
+            .map_err(|e| BftError::Rpc(format!("Failed to parse {method} response: {e}")))
+    }
+}
+
+impl BlockSource for RpcBlockSource {
+    fn block_by_hash(&self, hash: CryptoHash) -> BoxFuture<'_, Result<(CryptoHash, BlockHeader)>> {
+        async move {
+            let id = hash.to_string();
+            let response: BlockResponse =
+                self.call("block", json!({ "block_id": id }), &id).await?;
+            let header = BlockHeader::from(response.result.header);
+            Ok((*header.hash(), header))
+        }
+        .boxed()
+    }
+
+    fn block_by_height(
+        &self,
+        height: u64,
+    ) -> BoxFuture<'_, Result<Option<(CryptoHash, BlockHeader)>>> {
+        async move {
+            let params = json!({ "block_id": height });
+            match self
+                .call::<BlockResponse>("block", params, &height.to_string())
+                .await
+            {
+                Ok(response) => {
+                    let header = BlockHeader::from(response.result.header);
+                    Ok(Some((*header.hash(), header)))
+                }
+                Err(BftError::BlockNotFound(_)) => Ok(None),
+                Err(e) => Err(e),
+            }
+        }
+        .boxed()
+    }
+
+    fn validators(&self, block_hash: CryptoHash) -> BoxFuture<'_, Result<Vec<ValidatorStake>>> {
+        async move {
+            let id = block_hash.to_string();
+            let response: ValidatorsOrderedResponse = self
+                .call("EXPERIMENTAL_validators_ordered", json!([id]), &id)
+                .await?;
+            response
+                .result
+                .into_iter()
+                .map(validator_stake_from_ordered)
+                .collect()
+        }
+        .boxed()
+    }
+
+    fn next_light_client_block(
+        &self,
+        last_block_hash: CryptoHash,
+    ) -> BoxFuture<'_, Result<Option<LightClientBlockView>>> {
+        async move {
+            let id = last_block_hash.to_string();
+            let params = json!({ "last_block_hash": id });
+            let response: LightClientBlockResponse =
+                self.call("next_light_client_block", params, &id).await?;
+            light_client_block_from_value(response.result)
+        }
+        .boxed()
+    }
+}
+
+/// Converts the RPC error response into `BftError`.
+fn rpc_error(block_id: &str, error: RpcError) -> BftError {
+    match error.cause {
+        Some(cause) if cause.name == "UNKNOWN_BLOCK" => {
+            BftError::BlockNotFound(block_id.to_string())
+        }
+        _ => BftError::Rpc(format!("RPC request failed: {}", error.message)),
+    }
+}
+
+/// Parses the result of `next_light_client_block`, which is an empty object if there is no newer
+/// light client block.
+fn light_client_block_from_value(value: Value) -> Result<Option<LightClientBlockView>> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Object(ref fields) if fields.is_empty() => Ok(None),
+        value => Ok(Some(serde_json::from_value(value)?)),
+    }
+}
+
+/// Represents the blocks stored in the directory with the layout of `DEFAULT_EPOCHS_PATH`:
+/// `{dir}/{epoch_id}/{random-0..4,block-0..4,block-last,validators}.json`.
+///
+/// All headers are read and indexed by hash and height when the source is opened. Validators of a
+/// block are read from the directory of its epoch. Fixtures don't contain light client blocks, so
+/// `next_light_client_block` always fails, use `RecordingBlockSource` to replay them.
+///
+/// # Fields
+///
+/// * `dir` - The path to the directory with epochs.
+/// * `by_hash` - Block headers indexed by their hashes.
+/// * `by_height` - Block hashes indexed by heights.
+#[derive(Debug, Clone)]
+pub struct FixtureBlockSource {
+    dir: PathBuf,
+    by_hash: HashMap<CryptoHash, BlockHeader>,
+    by_height: HashMap<u64, CryptoHash>,
+}
+
+impl FixtureBlockSource {
+    /// Opens the directory with epochs and indexes all block headers in it.
+    /// Directories with stored hashes (`{epoch_id}_STORED`) and validators are skipped.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let mut by_hash = HashMap::new();
+        let mut by_height = HashMap::new();
+        for epoch_dir in sorted_entries(&dir)? {
+            let is_stored = epoch_dir
+                .file_name()
+                .map_or(false, |name| name.to_string_lossy().ends_with("_STORED"));
+            if !epoch_dir.is_dir() || is_stored {
+                continue;
+            }
+            for path in sorted_entries(&epoch_dir)? {
+                let is_json = path.extension().map_or(false, |ext| ext == "json");
+                let is_validators = path.file_stem().map_or(false, |stem| stem == "validators");
+                if !is_json || is_validators {
+                    continue;
+                }
+                let (hash, header) = load_block_header(&path.to_string_lossy())?;
+                by_height.insert(header.height(), hash);
+                by_hash.insert(hash, header);
+            }
+        }
+        Ok(Self {
+            dir,
+            by_hash,
+            by_height,
+        })
+    }
+
+    /// Returns the number of indexed blocks.
+    pub fn len(&self) -> usize {
+        self.by_hash.len()
+    }
+
+    /// Returns `true` if there are no blocks in the directory.
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+
+    fn header(&self, hash: &CryptoHash) -> Result<&BlockHeader> {
+        self.by_hash
+            .get(hash)
+            .ok_or_else(|| BftError::BlockNotFound(hash.to_string()))
+    }
+}
+
+impl BlockSource for FixtureBlockSource {
+    fn block_by_hash(&self, hash: CryptoHash) -> BoxFuture<'_, Result<(CryptoHash, BlockHeader)>> {
+        let block = self.header(&hash).map(|header| (hash, header.clone()));
+        futures::future::ready(block).boxed()
+    }
+
+    /// Heights missing in the directory are reported as skipped.
+    fn block_by_height(
+        &self,
+        height: u64,
+    ) -> BoxFuture<'_, Result<Option<(CryptoHash, BlockHeader)>>> {
+        let block = self
+            .by_height
+            .get(&height)
+            .map(|hash| (*hash, self.by_hash[hash].clone()));
+        futures::future::ready(Ok(block)).boxed()
+    }
+
+    fn validators(&self, block_hash: CryptoHash) -> BoxFuture<'_, Result<Vec<ValidatorStake>>> {
+        let validators = self.header(&block_hash).and_then(|header| {
+            let path = self
+                .dir
+                .join(header.epoch_id().0.to_string())
+                .join("validators.json");
+            load_validators(&path.to_string_lossy())
+        });
+        futures::future::ready(validators).boxed()
+    }
+
+    fn next_light_client_block(
+        &self,
+        last_block_hash: CryptoHash,
+    ) -> BoxFuture<'_, Result<Option<LightClientBlockView>>> {
+        futures::future::ready(Err(BftError::InvalidInput(format!(
+            "No light client block after {last_block_hash} in {}",
+            self.dir.display()
+        ))))
+        .boxed()
+    }
+}
+
+/// Returns the entries of the directory sorted by their paths.
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// Represents the name of the file with a block in a NEAR Lake export.
+pub const LAKE_BLOCK_FILE: &str = "block.json";
+/// Represents the directory with validators of epochs in a NEAR Lake export.
+pub const LAKE_VALIDATORS_DIR: &str = "validators";
+
+/// Represents the blocks exported by NEAR Lake Framework to a local directory (a copy of the S3
+/// bucket), used to backfill old epochs without RPC.
+///
+/// Each block is stored in `{dir}/{height:012}/block.json` as `BlockView` next to `shard_N.json`
+/// files with chunks and outcomes of shards, which aren't needed to prove headers and are skipped.
+/// Lake doesn't export validators, so validators of an epoch are read from
+/// `{dir}/validators/{epoch_id}.json` in the format of `EXPERIMENTAL_validators_ordered`. Light
+/// client blocks aren't exported either, so `next_light_client_block` always fails.
+///
+/// Opening the source parses every `block.json` to index hashes, so it takes time linear in the
+/// size of the export. Only hashes and paths are kept, headers are parsed again on demand to keep
+/// months of blocks out of memory.
+///
+/// # Fields
+///
+/// * `dir` - The directory of the export.
+/// * `by_hash` - Heights of blocks indexed by their hashes.
+/// * `by_height` - Hashes of blocks and paths to their `block.json` indexed by heights.
+#[derive(Debug, Clone)]
+pub struct LakeBlockSource {
+    dir: PathBuf,
+    by_hash: HashMap<CryptoHash, u64>,
+    by_height: BTreeMap<u64, (CryptoHash, PathBuf)>,
+}
+
+impl LakeBlockSource {
+    /// Opens the export and indexes hashes of its blocks by reading every `block.json`.
+    /// Directories which names aren't heights are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory can't be read or a block is malformed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let mut by_hash = HashMap::new();
+        let mut by_height = BTreeMap::new();
+        for block_dir in sorted_entries(&dir)? {
+            let height = block_dir
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.parse::<u64>().ok());
+            let Some(height) = height else {
+                continue;
+            };
+            let path = block_dir.join(LAKE_BLOCK_FILE);
+            if !path.is_file() {
+                continue;
+            }
+            let (hash, _) = read_lake_block(&path)?;
+            by_hash.insert(hash, height);
+            by_height.insert(height, (hash, path));
+        }
+        Ok(Self {
+            dir,
+            by_hash,
+            by_height,
+        })
+    }
+
+    /// Returns the number of indexed blocks.
+    pub fn len(&self) -> usize {
+        self.by_hash.len()
+    }
+
+    /// Returns `true` if there are no blocks in the export.
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+
+    /// Returns heights of the first and the last exported blocks.
+    pub fn heights(&self) -> Option<(u64, u64)> {
+        let first = self.by_height.keys().next()?;
+        let last = self.by_height.keys().next_back()?;
+        Some((*first, *last))
+    }
+
+    fn block_by_hash_sync(&self, hash: &CryptoHash) -> Result<(CryptoHash, BlockHeader)> {
+        let (_, path) = self
+            .by_hash
+            .get(hash)
+            .and_then(|height| self.by_height.get(height))
+            .ok_or_else(|| BftError::BlockNotFound(hash.to_string()))?;
+        read_lake_block(path)
+    }
+
+    fn block_by_height_sync(&self, height: u64) -> Result<Option<(CryptoHash, BlockHeader)>> {
+        match self.heights() {
+            Some((first, last)) if (first..=last).contains(&height) => self
+                .by_height
+                .get(&height)
+                .map(|(_, path)| read_lake_block(path))
+                .transpose(),
+            _ => Err(BftError::BlockNotFound(height.to_string())),
+        }
+    }
+
+    fn validators_sync(&self, block_hash: &CryptoHash) -> Result<Vec<ValidatorStake>> {
+        let (_, header) = self.block_by_hash_sync(block_hash)?;
+        let path = self
+            .dir
+            .join(LAKE_VALIDATORS_DIR)
+            .join(format!("{}.json", header.epoch_id().0));
+        if !path.is_file() {
+            return Err(BftError::InvalidInput(format!(
+                "No validators of epoch {} in {}",
+                header.epoch_id().0,
+                self.dir.display()
+            )));
+        }
+        load_validators(&path.to_string_lossy())
+    }
+}
+
+impl BlockSource for LakeBlockSource {
+    fn block_by_hash(&self, hash: CryptoHash) -> BoxFuture<'_, Result<(CryptoHash, BlockHeader)>> {
+        futures::future::ready(self.block_by_hash_sync(&hash)).boxed()
+    }
+
+    /// Heights missing between the first and the last exported blocks are reported as skipped,
+    /// heights out of the export aren't found, so the follower waits for newer exports.
+    fn block_by_height(
+        &self,
+        height: u64,
+    ) -> BoxFuture<'_, Result<Option<(CryptoHash, BlockHeader)>>> {
+        futures::future::ready(self.block_by_height_sync(height)).boxed()
+    }
+
+    fn validators(&self, block_hash: CryptoHash) -> BoxFuture<'_, Result<Vec<ValidatorStake>>> {
+        futures::future::ready(self.validators_sync(&block_hash)).boxed()
+    }
+
+    fn next_light_client_block(
+        &self,
+        last_block_hash: CryptoHash,
+    ) -> BoxFuture<'_, Result<Option<LightClientBlockView>>> {
+        futures::future::ready(Err(BftError::InvalidInput(format!(
+            "No light client block after {last_block_hash} in {}",
+            self.dir.display()
+        ))))
+        .boxed()
+    }
+}
+
+/// Reads a block of a NEAR Lake export and returns its hash and header.
+fn read_lake_block(path: &Path) -> Result<(CryptoHash, BlockHeader)> {
+    let data = fs::read_to_string(path)?;
+    let block: BlockView = serde_json::from_str(&data)?;
+    Ok((block.header.hash, BlockHeader::from(block.header)))
+}
+
+/// Represents a source that snapshots responses of another source to disk and replays them
+/// offline, e.g. in tests.
+///
+/// Snapshots are stored in `{dir}/block/{hash}.json` and `{dir}/height/{height}.json` as
+/// `BlockHeaderView` (`null` for a skipped height), in `{dir}/validators/{hash}.json` in the format
+/// of `EXPERIMENTAL_validators_ordered` and in `{dir}/light_client_block/{hash}.json`.
+/// Errors of the inner source aren't recorded.
+///
+/// # Fields
+///
+/// * `dir` - The directory with snapshots.
+/// * `inner` - The recorded source, `None` when replaying.
+pub struct RecordingBlockSource {
+    dir: PathBuf,
+    inner: Option<Box<dyn BlockSource>>,
+}
+
+impl RecordingBlockSource {
+    /// Creates the source that forwards requests to `inner` and snapshots responses in `dir`.
+    pub fn record(inner: Box<dyn BlockSource>, dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            inner: Some(inner),
+        })
+    }
+
+    /// Creates the source that replays snapshots in `dir` without network access.
+    ///
+    /// # Errors
+    ///
+    /// Requests without snapshots fail with `BftError::InvalidInput`.
+    pub fn replay(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            inner: None,
+        }
+    }
+
+    /// Returns `true` if the source replays snapshots.
+    pub fn is_replay(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    fn snapshot_path(&self, kind: &str, key: &str) -> PathBuf {
+        self.dir.join(kind).join(format!("{key}.json"))
+    }
+
+    fn read<T: DeserializeOwned>(&self, kind: &str, key: &str) -> Result<T> {
+        let path = self.snapshot_path(kind, key);
+        let data = fs::read_to_string(&path)
+            .map_err(|e| BftError::InvalidInput(format!("No snapshot {}: {e}", path.display())))?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    fn write<T: Serialize>(&self, kind: &str, key: &str, value: &T) -> Result<()> {
+        let path = self.snapshot_path(kind, key);
+        fs::create_dir_all(self.dir.join(kind))?;
+        write_atomic(&path, &serde_json::to_vec_pretty(value)?)
+    }
+}
+
+impl BlockSource for RecordingBlockSource {
+    fn block_by_hash(&self, hash: CryptoHash) -> BoxFuture<'_, Result<(CryptoHash, BlockHeader)>> {
+        async move {
+            let key = hash.to_string();
+            let Some(inner) = &self.inner else {
+                let view: BlockHeaderView = self.read("block", &key)?;
+                let header = BlockHeader::from(view);
+                return Ok((*header.hash(), header));
+            };
+            let (hash, header) = inner.block_by_hash(hash).await?;
+            self.write("block", &key, &BlockHeaderView::from(header.clone()))?;
+            Ok((hash, header))
+        }
+        .boxed()
+    }
+
+    fn block_by_height(
+        &self,
+        height: u64,
+    ) -> BoxFuture<'_, Result<Option<(CryptoHash, BlockHeader)>>> {
+        async move {
+            let key = height.to_string();
+            let Some(inner) = &self.inner else {
+                let view: Option<BlockHeaderView> = self.read("height", &key)?;
+                return Ok(view.map(|view| {
+                    let header = BlockHeader::from(view);
+                    (*header.hash(), header)
+                }));
+            };
+            let block = inner.block_by_height(height).await?;
+            let view = block
+                .as_ref()
+                .map(|(_, header)| BlockHeaderView::from(header.clone()));
+            self.write("height", &key, &view)?;
+            Ok(block)
+        }
+        .boxed()
+    }
+
+    fn validators(&self, block_hash: CryptoHash) -> BoxFuture<'_, Result<Vec<ValidatorStake>>> {
+        async move {
+            let key = block_hash.to_string();
+            let Some(inner) = &self.inner else {
+                let response: ValidatorsOrderedResponse = self.read("validators", &key)?;
+                return response
+                    .result
+                    .into_iter()
+                    .map(validator_stake_from_ordered)
+                    .collect();
+            };
+            let validators = inner.validators(block_hash).await?;
+            let response = ValidatorsOrderedResponse {
+                result: validators.iter().map(validator_ordered).collect(),
+            };
+            self.write("validators", &key, &response)?;
+            Ok(validators)
+        }
+        .boxed()
+    }
+
+    fn next_light_client_block(
+        &self,
+        last_block_hash: CryptoHash,
+    ) -> BoxFuture<'_, Result<Option<LightClientBlockView>>> {
+        async move {
+            let key = last_block_hash.to_string();
+            let Some(inner) = &self.inner else {
+                return self.read("light_client_block", &key);
+            };
+            let block = inner.next_light_client_block(last_block_hash).await?;
+            self.write("light_client_block", &key, &block)?;
+            Ok(block)
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::DEFAULT_EPOCHS_PATH;
+    use anyhow::Result;
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::env;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    const EPOCH_ID: &str = "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t";
+
+    fn temp_dir() -> PathBuf {
+        env::temp_dir().join(format!("block_source_{:016x}", rand::random::<u64>()))
+    }
+
+    #[tokio::test]
+    async fn test_fixture_block_source() -> Result<()> {
+        let source = FixtureBlockSource::open(DEFAULT_EPOCHS_PATH)?;
+        let (hash, header) =
+            load_block_header(&format!("{DEFAULT_EPOCHS_PATH}/{EPOCH_ID}/block-0.json"))?;
+
+        let (found_hash, found) = source.block_by_hash(hash).await?;
+        assert_eq!(found_hash, hash);
+        assert_eq!(found.height(), header.height());
+        let (found_hash, _) = source.block_by_height(header.height()).await?.unwrap();
+        assert_eq!(found_hash, hash);
+        // Heights that aren't stored are skipped.
+        assert!(source.block_by_height(header.height() - 2).await?.is_none());
+        assert!(matches!(
+            source.block_by_hash(CryptoHash::default()).await,
+            Err(BftError::BlockNotFound(_))
+        ));
+        // Blocks are read from indexed directories, which names aren't necessarily padded.
+        fs::rename(
+            dir.join(format!("{:012}", header.height())),
+            dir.join(header.height().to_string()),
+        )?;
+        let source = LakeBlockSource::open(&dir)?;
+        let (found_hash, _) = source.block_by_height(header.height()).await?.unwrap();
+        assert_eq!(found_hash, hash);
+        assert_eq!(source.block_by_hash(hash).await?.0, hash);
+
+        let validators = source.validators(hash).await?;
+        let expected =
+            load_validators(&format!("{DEFAULT_EPOCHS_PATH}/{EPOCH_ID}/validators.json"))?;
+        assert_eq!(validators, expected);
+        Ok(())
+    }
+
+    /// Exports blocks of the epoch in the layout of NEAR Lake and returns their heights.
+    fn export_lake(dir: &Path, epoch_id: &str) -> Result<Vec<u64>> {
+        let mut heights = vec![];
+        for path in sorted_entries(&Path::new(DEFAULT_EPOCHS_PATH).join(epoch_id))? {
+            if path.file_stem().map_or(false, |stem| stem == "validators") {
+                continue;
+            }
+            let header: BlockHeaderView = serde_json::from_str(&fs::read_to_string(&path)?)?;
+            let block_dir = dir.join(format!("{:012}", header.height));
+            heights.push(header.height);
+            fs::create_dir_all(&block_dir)?;
+            let block = BlockView {
+                author: "test.near".parse()?,
+                header,
+                chunks: vec![],
+            };
+            fs::write(block_dir.join(LAKE_BLOCK_FILE), serde_json::to_vec(&block)?)?;
+            fs::write(
+                block_dir.join("shard_0.json"),
+                r#"{"shard_id": 0, "chunk": null}"#,
+            )?;
+        }
+        fs::create_dir_all(dir.join(LAKE_VALIDATORS_DIR))?;
+        fs::copy(
+            Path::new(DEFAULT_EPOCHS_PATH)
+                .join(epoch_id)
+                .join("validators.json"),
+            dir.join(LAKE_VALIDATORS_DIR)
+                .join(format!("{epoch_id}.json")),
+        )?;
+        heights.sort();
+        Ok(heights)
+    }
+
+    #[tokio::test]
+    async fn test_lake_block_source() -> Result<()> {
+        let dir = temp_dir();
+        let heights = export_lake(&dir, EPOCH_ID)?;
+        let source = LakeBlockSource::open(&dir)?;
+        assert_eq!(source.len(), heights.len());
+        assert_eq!(
+            source.heights(),
+            Some((heights[0], heights[heights.len() - 1]))
+        );
+
+        let (hash, header) =
+            load_block_header(&format!("{DEFAULT_EPOCHS_PATH}/{EPOCH_ID}/block-0.json"))?;
+        let (found_hash, found) = source.block_by_hash(hash).await?;
+        assert_eq!(found_hash, hash);
+        assert_eq!(found.height(), header.height());
+        let (found_hash, _) = source.block_by_height(header.height()).await?.unwrap();
+        assert_eq!(found_hash, hash);
+        // Heights missing inside the export are skipped, heights out of it aren't found.
+        let skipped = (heights[0]..heights[heights.len() - 1])
+            .find(|height| !heights.contains(height))
+            .unwrap();
+        assert!(source.block_by_height(skipped).await?.is_none());
+        assert!(matches!(
+            source.block_by_height(heights[heights.len() - 1] + 1).await,
+            Err(BftError::BlockNotFound(_))
+        ));
+        assert!(matches!(
+            source.block_by_hash(CryptoHash::default()).await,
+            Err(BftError::BlockNotFound(_))
+        ));
+        // Blocks are read from indexed directories, which names aren't necessarily padded.
+        fs::rename(
+            dir.join(format!("{:012}", header.height())),
+            dir.join(header.height().to_string()),
+        )?;
+        let source = LakeBlockSource::open(&dir)?;
+        let (found_hash, _) = source.block_by_height(header.height()).await?.unwrap();
+        assert_eq!(found_hash, hash);
+        assert_eq!(source.block_by_hash(hash).await?.0, hash);
+
+        let validators = source.validators(hash).await?;
+        let expected =
+            load_validators(&format!("{DEFAULT_EPOCHS_PATH}/{EPOCH_ID}/validators.json"))?;
+        assert_eq!(validators, expected);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() -> Result<()> {
+        let dir = temp_dir();
+        let (hash, header) =
+            load_block_header(&format!("{DEFAULT_EPOCHS_PATH}/{EPOCH_ID}/random-0.json"))?;
+        let skipped = header.height() - 2;
+
+        let recording = RecordingBlockSource::record(
+            Box::new(FixtureBlockSource::open(DEFAULT_EPOCHS_PATH)?),
+            &dir,
+        )?;
+        let recorded_block = recording.block_by_hash(hash).await?;
+        let recorded_next = recording.block_by_height(header.height() + 1).await?;
+        assert!(recording.block_by_height(skipped).await?.is_none());
+        let recorded_validators = recording.validators(hash).await?;
+
+        let replay = RecordingBlockSource::replay(&dir);
+        assert!(replay.is_replay());
+        let block = replay.block_by_hash(hash).await?;
+        assert_eq!(block.0, recorded_block.0);
+        assert_eq!(block.1.height(), recorded_block.1.height());
+        let next = replay.block_by_height(header.height() + 1).await?;
+        assert_eq!(next.map(|b| b.0), recorded_next.map(|b| b.0));
+        assert!(replay.block_by_height(skipped).await?.is_none());
+        assert_eq!(replay.validators(hash).await?, recorded_validators);
+        // Requests that weren't recorded fail.
+        assert!(matches!(
+            replay.block_by_height(header.height() + 10).await,
+            Err(BftError::InvalidInput(_))
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_light_client_block_from_value() -> Result<()> {
+        assert!(light_client_block_from_value(json!({}))?.is_none());
+        assert!(light_client_block_from_value(Value::Null)?.is_none());
+        assert!(light_client_block_from_value(json!({ "prev_block_hash": 1 })).is_err());
+        Ok(())
+    }
+
+    /// Answers JSON-RPC requests with blocks of `DEFAULT_EPOCHS_PATH` in place of a NEAR node.
+    /// Unknown blocks and skipped heights are answered with `UNKNOWN_BLOCK`.
+    async fn fixture_rpc(
+        State(source): State<Arc<FixtureBlockSource>>,
+        Json(request): Json<Value>,
+    ) -> Json<Value> {
+        let params = &request["params"];
+        let hash = |id: &Value| id.as_str().and_then(|id| CryptoHash::from_str(id).ok());
+        let result = match request["method"].as_str() {
+            Some("block") => {
+                let block = match &params["block_id"] {
+                    Value::Number(height) => match height.as_u64() {
+                        Some(height) => source.block_by_height(height).await.ok().flatten(),
+                        None => None,
+                    },
+                    id => match hash(id) {
+                        Some(hash) => source.block_by_hash(hash).await.ok(),
+                        None => None,
+                    },
+                };
+                block.map(|(_, header)| {
+                    json!(BlockView {
+                        author: "test.near".parse().unwrap(),
+                        header: BlockHeaderView::from(header),
+                        chunks: vec![],
+                    })
+                })
+            }
+            Some("EXPERIMENTAL_validators_ordered") => match hash(&params[0]) {
+                Some(hash) => source.validators(hash).await.ok().map(|validators| {
+                    json!(validators.iter().map(validator_ordered).collect::<Vec<_>>())
+                }),
+                None => None,
+            },
+            Some("next_light_client_block") => Some(json!({})),
+            _ => None,
+        };
+        Json(match result {
+            Some(result) => json!({ "jsonrpc": "2.0", "id": "dontcare", "result": result }),
+            None => json!({
+                "jsonrpc": "2.0",
+                "id": "dontcare",
+                "error": {
+                    "code": -32000,
+                    "message": "Server error",
+                    "cause": { "name": "UNKNOWN_BLOCK" },
+                },
+            }),
+        })
+    }
+
+    /// Starts the RPC endpoint serving fixtures and returns its URL.
+    async fn serve_fixture_rpc() -> Result<String> {
+        let source = Arc::new(FixtureBlockSource::open(DEFAULT_EPOCHS_PATH)?);
+        let app = Router::new()
+            .route("/", post(fixture_rpc))
+            .with_state(source);
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        Ok(url)
+    }
+
+    #[tokio::test]
+    async fn test_rpc_block_source() -> Result<()> {
+        let source = RpcBlockSource::new(serve_fixture_rpc().await?)?;
+        let (hash, header) =
+            load_block_header(&format!("{DEFAULT_EPOCHS_PATH}/{EPOCH_ID}/block-0.json"))?;
+
+        let (found_hash, found) = source.block_by_hash(hash).await?;
+        assert_eq!(found_hash, hash);
+        assert_eq!(found.height(), header.height());
+        let (found_hash, _) = source.block_by_height(header.height()).await?.unwrap();
+        assert_eq!(found_hash, hash);
+        // UNKNOWN_BLOCK is a skipped height or an unknown block.
+        assert!(source.block_by_height(header.height() - 2).await?.is_none());
+        assert!(matches!(
+            source.block_by_hash(CryptoHash::default()).await,
+            Err(BftError::BlockNotFound(_))
+        ));
+        // Blocks are read from indexed directories, which names aren't necessarily padded.
+        fs::rename(
+            dir.join(format!("{:012}", header.height())),
+            dir.join(header.height().to_string()),
+        )?;
+        let source = LakeBlockSource::open(&dir)?;
+        let (found_hash, _) = source.block_by_height(header.height()).await?.unwrap();
+        assert_eq!(found_hash, hash);
+        assert_eq!(source.block_by_hash(hash).await?.0, hash);
+
+        let validators = source.validators(hash).await?;
+        let expected =
+            load_validators(&format!("{DEFAULT_EPOCHS_PATH}/{EPOCH_ID}/validators.json"))?;
+        assert_eq!(validators, expected);
+        assert!(source.next_light_client_block(hash).await?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use crate::error::{BftError, Result};
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::{
+    CircuitData, CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
+};
+use plonky2::plonk::config::GenericConfig;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::serialization::DefaultGateSerializer;
+use plonky2_field::extension::Extendable;
+pub use proof_store::{Artifacts, ProofKind, ProofRecord, StoreError, DEFAULT_PROOFS_PATH};
+use serde::Serialize;
+use std::path::Path;
+
+/// Represents the serialized proof and circuit data of a proven block.
+///
+/// # Fields
+///
+/// * `proof` - `ProofWithPublicInputs` in binary form (`proof.bin`).
+/// * `proof_json` - `ProofWithPublicInputs` in json (`proof.json`).
+/// * `verifier_data` - `VerifierCircuitData` in binary form (`verifier_data.bin`).
+/// * `verifier_data_json` - `VerifierOnlyCircuitData` in json (`verifier_data.json`).
+/// * `common_data_json` - `CommonCircuitData` in json (`common_data.json`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProofArtifacts {
+    pub proof: Vec<u8>,
+    pub proof_json: Vec<u8>,
+    pub verifier_data: Vec<u8>,
+    pub verifier_data_json: Vec<u8>,
+    pub common_data_json: Vec<u8>,
+}
+
+impl ProofArtifacts {
+    /// Serializes the proof and its circuit data.
+    pub fn from_proof<F, C, const D: usize>(
+        data: &CircuitData<F, C, D>,
+        proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<Self>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        ProofWithPublicInputs<F, C, D>: Serialize,
+        VerifierOnlyCircuitData<C, D>: Serialize,
+        CommonCircuitData<F, D>: Serialize,
+    {
+        Self::from_verifier_data(&data.verifier_data(), proof)
+    }
+
+    /// Serializes the proof and its verifier circuit data, e.g. read from checkpoints.
+    pub fn from_verifier_data<F, C, const D: usize>(
+        data: &VerifierCircuitData<F, C, D>,
+        proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<Self>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        ProofWithPublicInputs<F, C, D>: Serialize,
+        VerifierOnlyCircuitData<C, D>: Serialize,
+        CommonCircuitData<F, D>: Serialize,
+    {
+        let verifier_data = data.to_bytes(&DefaultGateSerializer).map_err(|e| {
+            BftError::Serialization(format!("Error writing verifier data: {:?}", e))
+        })?;
+        Ok(Self {
+            proof: proof.to_bytes(),
+            proof_json: serde_json::to_vec_pretty(proof)?,
+            verifier_data,
+            verifier_data_json: serde_json::to_vec_pretty(&data.verifier_only)?,
+            common_data_json: serde_json::to_vec_pretty(&data.common)?,
+        })
+    }
+}
+
+impl Artifacts for ProofArtifacts {
+    fn files(&self) -> Vec<(&'static str, &[u8])> {
+        vec![
+            ("proof.bin", &self.proof),
+            ("proof.json", &self.proof_json),
+            ("verifier_data.bin", &self.verifier_data),
+            ("verifier_data.json", &self.verifier_data_json),
+            ("common_data.json", &self.common_data_json),
+        ]
+    }
+
+    fn from_files(
+        mut read: impl FnMut(&'static str) -> proof_store::Result<Vec<u8>>,
+    ) -> proof_store::Result<Self> {
+        Ok(Self {
+            proof: read("proof.bin")?,
+            proof_json: read("proof.json")?,
+            verifier_data: read("verifier_data.bin")?,
+            verifier_data_json: read("verifier_data.json")?,
+            common_data_json: read("common_data.json")?,
+        })
+    }
+}
+
+/// Represents a storage of plonky2 proofs indexed by block hash, height and epoch_id.
+/// It is implemented by every `proof_store::ProofStore` of `ProofArtifacts`.
+pub trait ProofStore: proof_store::ProofStore<ProofArtifacts> {}
+
+impl<T: proof_store::ProofStore<ProofArtifacts> + ?Sized> ProofStore for T {}
+
+/// Stores plonky2 proofs in `{root}/{random|epoch}/{block_hash}` with the index in `{root}/index.json`.
+pub type FsProofStore = proof_store::FsProofStore<ProofArtifacts>;
+
+/// Keeps plonky2 proofs in memory. Used in tests.
+pub type InMemoryProofStore = proof_store::InMemoryProofStore<ProofArtifacts>;
+
+/// Writes `bytes` to a temporary file next to `path` and renames it to `path`.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    Ok(proof_store::write_atomic(path, bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::load_block_header;
+    use anyhow::Result;
+
+    #[test]
+    fn test_proof_artifacts_dir() -> Result<()> {
+        let artifacts = ProofArtifacts {
+            proof: vec![1; 4],
+            proof_json: vec![2; 4],
+            verifier_data: vec![3; 4],
+            verifier_data_json: vec![4; 4],
+            common_data_json: vec![5; 4],
+        };
+        let dir = std::env::temp_dir().join(format!("artifacts_{:016x}", rand::random::<u64>()));
+        artifacts.write_dir(&dir)?;
+        assert_eq!(ProofArtifacts::read_dir(&dir)?, artifacts);
+        assert_eq!(std::fs::read(dir.join("verifier_data.bin"))?, vec![3; 4]);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_record_from_header() -> Result<()> {
+        let (hash, header) = load_block_header("../data/block_header_small.json")?;
+        let record = ProofRecord::new(ProofKind::Random, &header);
+        assert_eq!(record.block_hash, hash);
+        assert_eq!(record.height, header.height());
+        assert_eq!(record.epoch_id, header.epoch_id().0);
+        Ok(())
+    }
+}
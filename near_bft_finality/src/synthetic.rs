@@ -0,0 +1,554 @@
+use crate::error::{BftError, Result};
+use crate::input::FINALITY_BLOCKS;
+use crate::prove_block_data::signatures::generate_signed_message;
+use crate::types::ValidatorsOrderedResponse;
+use crate::utils::validator_ordered;
+use near_crypto::{KeyType, SecretKey, Signature};
+use near_primitives::block_header::BlockHeader;
+use near_primitives::borsh;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::types::validator_stake::ValidatorStake;
+use near_primitives::types::AccountId;
+use near_primitives::views::BlockHeaderView;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Represents the protocol version of generated headers, the same as of blocks in `data/epochs`.
+pub const SYNTHETIC_PROTOCOL_VERSION: u32 = 66;
+/// Represents the default number of blocks in a synthetic epoch.
+pub const DEFAULT_SYNTHETIC_EPOCH_LENGTH: u64 = 64;
+
+/// Represents the parameters of a synthetic chain.
+///
+/// # Fields
+///
+/// * `seed` - The seed of keys, stakes and other random values. The same config gives the same chain.
+/// * `num_epochs` - The number of epochs. At least 4 epochs are needed to prove epoch blocks of the last one.
+/// * `epoch_length` - The number of heights in an epoch.
+/// * `num_validators` - The number of validators in each epoch.
+/// * `rotated_validators` - The number of validators with the lowest stakes replaced in each epoch.
+/// * `missing_approvals` - The number of validators with the lowest stakes that don't sign approvals.
+/// * `skipped_heights` - Heights without blocks. Blocks after them contain `Skip` approvals.
+/// * `genesis_height` - The height of the first block.
+/// * `random_offset` - The offset from the start of an epoch of the block written to `random-0.json`.
+#[derive(Debug, Clone)]
+pub struct SyntheticChainConfig {
+    pub seed: u64,
+    pub num_epochs: usize,
+    pub epoch_length: u64,
+    pub num_validators: usize,
+    pub rotated_validators: usize,
+    pub missing_approvals: usize,
+    pub skipped_heights: BTreeSet<u64>,
+    pub genesis_height: u64,
+    pub random_offset: u64,
+}
+
+impl Default for SyntheticChainConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            num_epochs: 5,
+            epoch_length: DEFAULT_SYNTHETIC_EPOCH_LENGTH,
+            num_validators: 16,
+            rotated_validators: 1,
+            missing_approvals: 0,
+            skipped_heights: BTreeSet::new(),
+            genesis_height: 1,
+            random_offset: DEFAULT_SYNTHETIC_EPOCH_LENGTH / 2,
+        }
+    }
+}
+
+impl SyntheticChainConfig {
+    fn check(&self) -> Result<()> {
+        let min_length = 2 * FINALITY_BLOCKS as u64 + 2;
+        if self.num_epochs == 0 || self.epoch_length < min_length {
+            return Err(BftError::InvalidInput(format!(
+                "At least 1 epoch of {min_length} heights is required, got {} epochs of {}",
+                self.num_epochs, self.epoch_length
+            )));
+        }
+        if self.num_validators == 0
+            || self.rotated_validators > self.num_validators
+            || self.missing_approvals >= self.num_validators
+        {
+            return Err(BftError::InvalidInput(format!(
+                "Invalid validators: {} validators, {} rotated, {} missing approvals",
+                self.num_validators, self.rotated_validators, self.missing_approvals
+            )));
+        }
+        if self.random_offset + FINALITY_BLOCKS as u64 >= self.epoch_length {
+            return Err(BftError::InvalidInput(format!(
+                "Random offset {} doesn't fit epoch of {} heights",
+                self.random_offset, self.epoch_length
+            )));
+        }
+        if self.skipped_heights.contains(&self.genesis_height) {
+            return Err(BftError::InvalidInput(
+                "Genesis height can't be skipped".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Represents a validator of a synthetic epoch together with its secret key.
+#[derive(Debug, Clone)]
+pub struct SyntheticValidator {
+    pub secret_key: SecretKey,
+    pub stake: ValidatorStake,
+}
+
+/// Represents an epoch of a synthetic chain.
+///
+/// # Fields
+///
+/// * `epoch_id` - The epoch_id of blocks of the epoch.
+/// * `validators` - Validators of the epoch ordered by stakes, as in `EXPERIMENTAL_validators_ordered`.
+/// * `blocks` - Blocks of the epoch in ascending order of heights.
+#[derive(Debug, Clone)]
+pub struct SyntheticEpoch {
+    pub epoch_id: CryptoHash,
+    pub validators: Vec<SyntheticValidator>,
+    pub blocks: Vec<BlockHeader>,
+}
+
+impl SyntheticEpoch {
+    /// Returns validators of the epoch without their secret keys.
+    pub fn validator_stakes(&self) -> Vec<ValidatorStake> {
+        self.validators.iter().map(|v| v.stake.clone()).collect()
+    }
+
+    /// Returns the first block of the epoch.
+    pub fn first_block(&self) -> &BlockHeader {
+        &self.blocks[0]
+    }
+
+    /// Returns the last block of the epoch.
+    pub fn last_block(&self) -> &BlockHeader {
+        &self.blocks[self.blocks.len() - 1]
+    }
+
+    /// Returns `FINALITY_BLOCKS` blocks starting from the first block at `height` or above.
+    pub fn blocks_from(&self, height: u64) -> &[BlockHeader] {
+        let start = self.blocks.partition_point(|block| block.height() < height);
+        &self.blocks[start..(start + FINALITY_BLOCKS).min(self.blocks.len())]
+    }
+}
+
+/// Represents a deterministic fake NEAR chain to test provers without network.
+///
+/// Blocks have consistent hashes, `prev_hash`, `epoch_id`/`next_epoch_id`, `next_bp_hash`,
+/// `last_ds_final_block` and `last_final_block`. Approvals in a block are signed by validators of
+/// the epoch of its previous block: `Endorsement` of the previous block, or `Skip` of its height if
+/// heights in between are skipped.
+///
+/// # Fields
+///
+/// * `config` - The parameters of the chain.
+/// * `epochs` - Epochs of the chain, the genesis block is the first block of the first epoch.
+#[derive(Debug, Clone)]
+pub struct SyntheticChain {
+    pub config: SyntheticChainConfig,
+    pub epochs: Vec<SyntheticEpoch>,
+}
+
+impl SyntheticChain {
+    /// Generates the chain.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BftError::InvalidInput` if the config is inconsistent, e.g. an epoch has no blocks.
+    pub fn generate(config: SyntheticChainConfig) -> Result<Self> {
+        config.check()?;
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+        let mut next_validator_id = 0;
+        // Validators of Epoch_k+1 are known in Epoch_k, since they define `next_bp_hash`.
+        let mut validator_sets = vec![sample_validators(
+            &mut rng,
+            &mut next_validator_id,
+            config.num_validators,
+        )?];
+        for _ in 0..config.num_epochs {
+            let mut validators = validator_sets[validator_sets.len() - 1].clone();
+            validators.truncate(config.num_validators - config.rotated_validators);
+            validators.extend(sample_validators(
+                &mut rng,
+                &mut next_validator_id,
+                config.rotated_validators,
+            )?);
+            sort_validators(&mut validators);
+            validator_sets.push(validators);
+        }
+
+        let mut epochs: Vec<SyntheticEpoch> = vec![];
+        let mut ordinal = 1;
+        for (k, validators) in validator_sets[..config.num_epochs].iter().enumerate() {
+            // epoch_id of Epoch_k is the hash of the last block of Epoch_k-2,
+            // the genesis block is the last block of Epoch_-1.
+            let epoch_id = match k {
+                0 => CryptoHash::default(),
+                1 => *epochs[0].first_block().hash(),
+                _ => *epochs[k - 2].last_block().hash(),
+            };
+            let next_bp_hash = bp_hash(&validator_sets[k + 1])?;
+            let start = config.genesis_height + k as u64 * config.epoch_length;
+            let mut blocks = vec![];
+            for height in start..(start + config.epoch_length) {
+                if config.skipped_heights.contains(&height) {
+                    continue;
+                }
+                // Approvals in Block_0 are signed by validators of the previous epoch.
+                let prev_validators = if blocks.is_empty() && k > 0 {
+                    &epochs[k - 1].validators
+                } else {
+                    validators
+                };
+                // next_epoch_id is epoch_id of Epoch_k+1, the genesis block can't refer to itself.
+                let next_epoch_id = match blocks.first() {
+                    _ if k > 0 => *epochs[k - 1].last_block().hash(),
+                    Some(genesis) => *genesis.hash(),
+                    None => CryptoHash::default(),
+                };
+                let header = BlockFields {
+                    height,
+                    epoch_id,
+                    next_epoch_id,
+                    next_bp_hash,
+                    ordinal,
+                };
+                let prev = blocks
+                    .last()
+                    .or_else(|| epochs.last().map(|e| e.last_block()));
+                let header = header.build(&mut rng, prev, prev_validators, validators, &config)?;
+                blocks.push(header);
+                ordinal += 1;
+            }
+            if blocks.is_empty() {
+                return Err(BftError::InvalidInput(format!("Epoch {k} has no blocks")));
+            }
+            epochs.push(SyntheticEpoch {
+                epoch_id,
+                validators: validators.clone(),
+                blocks,
+            });
+        }
+        Ok(Self { config, epochs })
+    }
+
+    /// Returns epoch_ids of all epochs in the chain.
+    pub fn epoch_ids(&self) -> Vec<CryptoHash> {
+        self.epochs.iter().map(|epoch| epoch.epoch_id).collect()
+    }
+
+    /// Writes the chain in the layout of `DEFAULT_EPOCHS_PATH`:
+    /// `{dir}/{epoch_id}/{random-0..4,block-0..4,block-last,validators}.json` and stored hashes in
+    /// `{dir}/{epoch_id}_STORED/{block-0,block-last}.json`.
+    ///
+    /// `block-0..4` are the first blocks of an epoch, `random-0..4` start at `random_offset`.
+    pub fn write(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        for (k, epoch) in self.epochs.iter().enumerate() {
+            let epoch_dir = dir.join(epoch.epoch_id.to_string());
+            let stored_dir = dir.join(format!("{}_STORED", epoch.epoch_id));
+            fs::create_dir_all(&epoch_dir)?;
+            fs::create_dir_all(&stored_dir)?;
+            let random_start = self.config.genesis_height
+                + k as u64 * self.config.epoch_length
+                + self.config.random_offset;
+            for (prefix, blocks) in [
+                (
+                    "block-",
+                    &epoch.blocks[..FINALITY_BLOCKS.min(epoch.blocks.len())],
+                ),
+                ("random-", epoch.blocks_from(random_start)),
+            ] {
+                for (i, block) in blocks.iter().enumerate() {
+                    let view = BlockHeaderView::from(block.clone());
+                    write_json(&epoch_dir.join(format!("{prefix}{i}.json")), &view)?;
+                }
+            }
+            let last_view = BlockHeaderView::from(epoch.last_block().clone());
+            write_json(&epoch_dir.join("block-last.json"), &last_view)?;
+            let validators = ValidatorsOrderedResponse {
+                result: epoch
+                    .validators
+                    .iter()
+                    .map(|v| validator_ordered(&v.stake))
+                    .collect(),
+            };
+            write_json(&epoch_dir.join("validators.json"), &validators)?;
+            write_json(&stored_dir.join("block-0.json"), epoch.first_block().hash())?;
+            write_json(
+                &stored_dir.join("block-last.json"),
+                epoch.last_block().hash(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Represents the fields of a block header that depend on its epoch.
+struct BlockFields {
+    height: u64,
+    epoch_id: CryptoHash,
+    next_epoch_id: CryptoHash,
+    next_bp_hash: CryptoHash,
+    ordinal: u64,
+}
+
+impl BlockFields {
+    /// Builds the header following `prev`, with approvals signed by `prev_validators`
+    /// and signed by a block producer from `validators`.
+    fn build(
+        self,
+        rng: &mut ChaCha8Rng,
+        prev: Option<&BlockHeader>,
+        prev_validators: &[SyntheticValidator],
+        validators: &[SyntheticValidator],
+        config: &SyntheticChainConfig,
+    ) -> Result<BlockHeader> {
+        let prev_hash = prev.map_or(CryptoHash::default(), |prev| *prev.hash());
+        let prev_height = prev.map_or(0, |prev| prev.height());
+        // Doomslug finality: the previous block is final if heights are consecutive,
+        // the block before it is final if it is also the last doomslug final block.
+        let (last_ds_final_block, last_final_block) = match prev {
+            None => (CryptoHash::default(), CryptoHash::default()),
+            Some(prev) if prev.height() + 1 == self.height => {
+                let last_final_block = if prev.last_ds_final_block() == prev.prev_hash() {
+                    *prev.prev_hash()
+                } else {
+                    *prev.last_final_block()
+                };
+                (*prev.hash(), last_final_block)
+            }
+            Some(prev) => (*prev.last_ds_final_block(), *prev.last_final_block()),
+        };
+        let approvals: Vec<Option<String>> = match prev {
+            None => vec![],
+            Some(prev) => {
+                let msg = generate_signed_message(prev.height(), self.height, *prev.hash());
+                let signers = prev_validators.len() - config.missing_approvals;
+                prev_validators
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| (i < signers).then(|| v.secret_key.sign(&msg).to_string()))
+                    .collect()
+            }
+        };
+        let timestamp = 1_700_000_000_000_000_000u64 + self.height * 1_000_000_000;
+        let random_hash = |rng: &mut ChaCha8Rng| CryptoHash(rng.gen()).to_string();
+        let mut view = json!({
+            "height": self.height,
+            "prev_height": prev_height,
+            "epoch_id": self.epoch_id.to_string(),
+            "next_epoch_id": self.next_epoch_id.to_string(),
+            "hash": CryptoHash::default().to_string(),
+            "prev_hash": prev_hash.to_string(),
+            "prev_state_root": random_hash(rng),
+            "block_body_hash": random_hash(rng),
+            "chunk_receipts_root": random_hash(rng),
+            "chunk_headers_root": random_hash(rng),
+            "chunk_tx_root": random_hash(rng),
+            "outcome_root": random_hash(rng),
+            "chunks_included": 1,
+            "challenges_root": CryptoHash::default().to_string(),
+            "timestamp": timestamp,
+            "timestamp_nanosec": timestamp.to_string(),
+            "random_value": random_hash(rng),
+            "validator_proposals": [],
+            "chunk_mask": [true],
+            "gas_price": "100000000",
+            "block_ordinal": self.ordinal,
+            "rent_paid": "0",
+            "validator_reward": "0",
+            "total_supply": "1000000000000000000000000000000000",
+            "challenges_result": [],
+            "last_final_block": last_final_block.to_string(),
+            "last_ds_final_block": last_ds_final_block.to_string(),
+            "next_bp_hash": self.next_bp_hash.to_string(),
+            "block_merkle_root": random_hash(rng),
+            "epoch_sync_data_hash": null,
+            "approvals": approvals,
+            "signature": Signature::empty(KeyType::ED25519).to_string(),
+            "latest_protocol_version": SYNTHETIC_PROTOCOL_VERSION,
+        });
+        // The signature of the block producer isn't a part of the hash.
+        let header = BlockHeader::from(serde_json::from_value::<BlockHeaderView>(view.clone())?);
+        let producer = &validators[(self.height % validators.len() as u64) as usize];
+        view["hash"] = json!(header.hash().to_string());
+        view["signature"] = json!(producer.secret_key.sign(header.hash().as_ref()).to_string());
+        Ok(BlockHeader::from(
+            serde_json::from_value::<BlockHeaderView>(view)?,
+        ))
+    }
+}
+
+/// Samples validators with fresh ed25519 keys and stakes, ordered by stakes.
+fn sample_validators(
+    rng: &mut ChaCha8Rng,
+    next_id: &mut usize,
+    count: usize,
+) -> Result<Vec<SyntheticValidator>> {
+    let mut validators = (0..count)
+        .map(|_| {
+            let account_id = format!("validator-{}.poolv1.near", *next_id);
+            *next_id += 1;
+            let account_id = AccountId::from_str(&account_id)
+                .map_err(|e| BftError::InvalidInput(format!("{account_id}: {e}")))?;
+            let secret_key =
+                SecretKey::from_seed(KeyType::ED25519, &hex::encode(rng.gen::<[u8; 32]>()));
+            // Between 10^4 and 10^7 NEAR.
+            let stake = rng.gen_range(10u128.pow(28)..10u128.pow(31));
+            Ok(SyntheticValidator {
+                stake: ValidatorStake::new_v1(account_id, secret_key.public_key(), stake),
+                secret_key,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    sort_validators(&mut validators);
+    Ok(validators)
+}
+
+fn sort_validators(validators: &mut [SyntheticValidator]) {
+    validators.sort_by(|a, b| b.stake.stake().cmp(&a.stake.stake()));
+}
+
+/// Returns `next_bp_hash` for the list of validators.
+fn bp_hash(validators: &[SyntheticValidator]) -> Result<CryptoHash> {
+    let stakes: Vec<ValidatorStake> = validators.iter().map(|v| v.stake.clone()).collect();
+    Ok(hash(&borsh::to_vec(&stakes)?))
+}
+
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    Ok(fs::write(path, serde_json::to_vec_pretty(value)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{load_block_header, load_block_input, load_epoch_input, load_validators};
+    use anyhow::Result;
+
+    fn temp_dir() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("synthetic_{:016x}", rand::random::<u64>()))
+    }
+
+    fn small_config() -> SyntheticChainConfig {
+        SyntheticChainConfig {
+            num_epochs: 4,
+            epoch_length: 24,
+            num_validators: 4,
+            random_offset: 12,
+            ..Default::default()
+        }
+    }
+
+    // Checks that the approvals in `next` are signed by `validators` for `block`.
+    fn check_approvals(block: &BlockHeader, next: &BlockHeader, validators: &[ValidatorStake]) {
+        let msg = generate_signed_message(block.height(), next.height(), *next.prev_hash());
+        assert_eq!(next.approvals().len(), validators.len());
+        for (approval, validator) in next.approvals().iter().zip(validators) {
+            if let Some(signature) = approval {
+                assert!(signature.verify(&msg, validator.public_key()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_synthetic_chain_is_consistent() -> Result<()> {
+        let chain = SyntheticChain::generate(small_config())?;
+        let blocks: Vec<&BlockHeader> = chain.epochs.iter().flat_map(|e| &e.blocks).collect();
+        for pair in blocks.windows(2) {
+            assert_eq!(pair[1].prev_hash(), pair[0].hash());
+        }
+        for (k, epoch) in chain.epochs.iter().enumerate().skip(2) {
+            let ep2_last = chain.epochs[k - 2].last_block();
+            let ep1_first = chain.epochs[k - 1].first_block();
+            assert_eq!(epoch.first_block().epoch_id().0, *ep2_last.hash());
+            assert_eq!(
+                *ep1_first.next_bp_hash(),
+                hash(&borsh::to_vec(&epoch.validator_stakes())?)
+            );
+            // Approvals for Block_n-1 of the previous epoch are signed by its validators.
+            let ep1 = &chain.epochs[k - 1];
+            check_approvals(
+                ep1.last_block(),
+                epoch.first_block(),
+                &ep1.validator_stakes(),
+            );
+            check_approvals(
+                &epoch.blocks[0],
+                &epoch.blocks[1],
+                &epoch.validator_stakes(),
+            );
+            // Block_0 is doomslug final in Block_1 and final in Block_2.
+            assert_eq!(
+                epoch.blocks[1].last_ds_final_block(),
+                epoch.blocks[0].hash()
+            );
+            assert_eq!(epoch.blocks[2].last_final_block(), epoch.blocks[0].hash());
+        }
+        // The same config gives the same chain.
+        let again = SyntheticChain::generate(small_config())?;
+        assert_eq!(again.epoch_ids(), chain.epoch_ids());
+        Ok(())
+    }
+
+    #[test]
+    fn test_synthetic_chain_skips() -> Result<()> {
+        let config = small_config();
+        let skipped = config.genesis_height + 3 * config.epoch_length + config.random_offset + 1;
+        let config = SyntheticChainConfig {
+            skipped_heights: [skipped].into(),
+            missing_approvals: 1,
+            ..config
+        };
+        let chain = SyntheticChain::generate(config)?;
+        let epoch = &chain.epochs[3];
+        let blocks = epoch.blocks_from(skipped - 1);
+        assert_eq!(blocks[1].height(), skipped + 1);
+        // Block_i isn't doomslug final, approvals skip its height.
+        assert_eq!(
+            blocks[1].last_ds_final_block(),
+            blocks[0].last_ds_final_block()
+        );
+        check_approvals(&blocks[0], &blocks[1], &epoch.validator_stakes());
+        assert!(blocks[1].approvals().last().unwrap().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_synthetic_chain() -> Result<()> {
+        let dir = temp_dir();
+        let chain = SyntheticChain::generate(small_config())?;
+        chain.write(&dir)?;
+        let ids: Vec<String> = chain.epoch_ids().iter().map(|id| id.to_string()).collect();
+        let dir_str = dir.to_string_lossy().to_string();
+
+        let (hash, header) = load_block_header(&format!("{dir_str}/{}/block-last.json", ids[3]))?;
+        assert_eq!(hash, *chain.epochs[3].last_block().hash());
+        assert_eq!(header.hash(), chain.epochs[3].last_block().hash());
+        let validators = load_validators(&format!("{dir_str}/{}/validators.json", ids[3]))?;
+        assert_eq!(validators, chain.epochs[3].validator_stakes());
+
+        let input = load_block_input(&dir_str, &ids[3], &ids[2], &ids[1])?;
+        check_approvals(&input.blocks[0], &input.blocks[1], &input.validators);
+        let input = load_epoch_input(&dir_str, &ids[3], &ids[2], &ids[1], &ids[0])?;
+        check_approvals(
+            &input.ep1_last_block,
+            &input.blocks[0],
+            &input.validators_n_1,
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
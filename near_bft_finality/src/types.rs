@@ -9,6 +9,8 @@ pub const TYPE_BYTE: usize = 1;
 pub const PROTOCOL_VERSION_BYTES: usize = 4;
 /// Represents the size of block height in bytes.
 pub const BLOCK_HEIGHT_BYTES: usize = 8;
+/// Represents the size of a validator index (u32, little-endian) in the list of valid keys.
+pub const VALIDATOR_INDEX_BYTES: usize = 4;
 /// Represents the size of stake in bytes.
 pub const STAKE_BYTES: usize = 16;
 /// Represents the size of a public key or hash in bytes.
@@ -17,7 +19,7 @@ pub const PK_HASH_BYTES: usize = 32;
 pub const SIG_BYTES: usize = 64;
 /// Represents the size of a inner lite part of a block in bytes.
 pub const INNER_LITE_BYTES: usize = 208;
-/// Represents the lenght of one epoch.
+/// Represents the lenght of one epoch of mainnet, see `NetworkProfile::epoch_length` for other networks.
 pub const EPOCH_DURATION: u64 = 43200;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,6 +60,7 @@ pub struct HeaderData {
 /// * `last_final_hash` - A vector of bytes representing the last_final_hash field of the current block.
 /// * `approvals` - A vector of bytes representing the signatures exracted from the current block, that were created for the previous block.
 ///
+#[derive(Debug, Clone, PartialEq)]
 pub struct HeaderDataFields {//BlockDataForFinality HeaderDataFields
     pub hash: Vec<u8>,
     pub height: Option<u64>,
@@ -70,6 +73,13 @@ pub struct HeaderDataFields {//BlockDataForFinality HeaderDataFields
     pub approvals: Option<Vec<Vec<u8>>>,
 }
 
+/// Represents the response of `next_light_client_block`.
+/// The result is an empty object if there is no newer light client block.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LightClientBlockResponse {
+    pub result: serde_json::Value,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ValidatorsOrderedResponse {
     pub result: Vec<ValidatorOrdered>,
@@ -88,7 +98,7 @@ pub struct ValidatorsOrderedResponse {
 /// * `stake` - A string representing the stake amount of the validator.
 /// * `validator_stake_struct_version` - A string representing the version of the validator stake structure.
 ///
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ValidatorOrdered {
     pub account_id: String,
     pub public_key: String,
@@ -140,6 +150,13 @@ pub struct RpcError {
     pub code: i64,
     pub message: String,
     pub data: Option<String>,
+    pub cause: Option<RpcErrorCause>,
+}
+
+// Cause of the RPC error, e.g. UNKNOWN_BLOCK for a skipped height.
+#[derive(Deserialize, Debug)]
+pub struct RpcErrorCause {
+    pub name: String,
 }
 
 /// Represents a view of a block.
@@ -167,9 +184,20 @@ pub struct ValidatorsOrderedRequest {
     pub params: Vec<&'static str>,
 }
 
+/// Represents the NATS subject to publish signature proving tasks.
+pub const PROVE_SIGNATURE_SUBJECT: &str = "PROVE_SIGNATURE";
+/// Represents the NATS subject prefix for results. Results are published to `{prefix}.{request_id}`.
+pub const SIGNATURE_RESULT_SUBJECT: &str = "PROCESS_SIGNATURE_RESULT";
+/// Represents the time to wait for any signature proof before republishing lost tasks.
+pub const SIGNATURE_TASK_TIMEOUT_SECS: u64 = 300;
+/// Represents the number of times lost signature tasks are republished.
+pub const SIGNATURE_TASK_MAX_RETRIES: usize = 3;
+
 /// Represents the input data for proving signatures in parallel using distributed system nats.
 #[derive(Deserialize, Serialize)]
 pub struct InputTask {
+    /// The id of the request used to correlate results with the coordinator.
+    pub request_id: String,
     /// The message data for the signature.
     pub message: Vec<u8>,
     /// The approval data for the signature.
@@ -183,6 +211,8 @@ pub struct InputTask {
 /// Represents the output data after proving signatures in parallel using distributed system nats.
 #[derive(Serialize, Deserialize)]
 pub struct OutputTask {
+    /// The id of the request taken from the input task.
+    pub request_id: String,
     /// The proof data generated during the signature proving process.
     pub proof: Vec<u8>,
     /// The verifier data generated during the proving process.
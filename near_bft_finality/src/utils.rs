@@ -1,16 +1,20 @@
-use anyhow::Result;
-use crate::types::{BlockParamString, BlockParamHeight, BlockRequest, BlockRequestByHeight, BlockResponse, RpcErrorResponse, ValidatorsOrderedResponse, HeaderDataFields};
+use crate::error::{BftError, Result};
+use crate::input::{AnchorBlock, BlockProvingInput, EpochProvingInput, FINALITY_BLOCKS};
+use crate::network::NetworkProfile;
+use crate::source::{BlockSource, RpcBlockSource};
+use crate::store::ProofStore;
+use crate::types::{ValidatorOrdered, ValidatorsOrderedResponse, HeaderDataFields};
 use near_crypto::PublicKey;
 use near_primitives::{
     block_header::BlockHeader,
     hash::CryptoHash,
     types::{validator_stake::ValidatorStake, AccountId},
     views::BlockHeaderView,
-    borsh,
 };
-use reqwest::Client;
-use serde_json::json;
-use std::{env, fs::File, io::Read, str::FromStr};
+use std::{fs::File, future::Future, io::Read, str::FromStr};
+
+/// Represents the default path to the directory with stored epochs.
+pub const DEFAULT_EPOCHS_PATH: &str = "../data/epochs";
 
 pub fn vec_u32_to_u8(data: &Vec<u32>) -> Vec<u8> {
     let capacity = 32 / 8 * data.len();
@@ -24,6 +28,30 @@ pub fn vec_u32_to_u8(data: &Vec<u32>) -> Vec<u8> {
     output
 }
 
+/// Converts a validator returned by `EXPERIMENTAL_validators_ordered` into `ValidatorStake`.
+pub(crate) fn validator_stake_from_ordered(validator: ValidatorOrdered) -> Result<ValidatorStake> {
+    let account_id = AccountId::from_str(&validator.account_id).map_err(|e| {
+        BftError::Serialization(format!("invalid account id {}: {}", validator.account_id, e))
+    })?;
+    let public_key = PublicKey::from_str(&validator.public_key).map_err(|e| {
+        BftError::Serialization(format!("invalid public key {}: {}", validator.public_key, e))
+    })?;
+    let stake = validator.stake.parse().map_err(|e| {
+        BftError::Serialization(format!("invalid stake {}: {}", validator.stake, e))
+    })?;
+    Ok(ValidatorStake::new_v1(account_id, public_key, stake))
+}
+
+/// Converts `ValidatorStake` into the format of `EXPERIMENTAL_validators_ordered`.
+pub(crate) fn validator_ordered(validator: &ValidatorStake) -> ValidatorOrdered {
+    ValidatorOrdered {
+        account_id: validator.account_id().to_string(),
+        public_key: validator.public_key().to_string(),
+        stake: validator.stake().to_string(),
+        validator_stake_struct_version: "V1".to_string(),
+    }
+}
+
 /// Loads a block hash from a JSON file, simulating loading a hash from a contract.
 ///
 /// # Arguments
@@ -33,7 +61,7 @@ pub fn vec_u32_to_u8(data: &Vec<u32>) -> Vec<u8> {
 /// # Returns
 ///
 /// Returns a result containing the block hash and the corresponding `BlockHeader` if the operation succeeds.
-pub fn load_block_hash(path: &str) -> Result<CryptoHash, anyhow::Error> {
+pub fn load_block_hash(path: &str) -> Result<CryptoHash> {
     let mut file = File::open(path)?;
     let mut data = String::new();
     file.read_to_string(&mut data)?;
@@ -50,7 +78,7 @@ pub fn load_block_hash(path: &str) -> Result<CryptoHash, anyhow::Error> {
 /// # Returns
 ///
 /// Returns a result containing the block hash and the corresponding `BlockHeader` if the operation succeeds.
-pub fn load_block_header(path: &str) -> Result<(CryptoHash, BlockHeader), anyhow::Error> {
+pub fn load_block_header(path: &str) -> Result<(CryptoHash, BlockHeader)> {
     let mut file = File::open(path)?;
     let mut data = String::new();
     file.read_to_string(&mut data)?;
@@ -75,53 +103,8 @@ pub fn load_block_header(path: &str) -> Result<(CryptoHash, BlockHeader), anyhow
 /// # Errors
 ///
 /// Returns an error if there are any issues with the RPC request or response handling.
-pub async fn load_block_from_rpc(hash: &str) -> Result<(CryptoHash, BlockHeader), anyhow::Error> {
-    let rpc_url = env::var("NEAR_RPC").expect("NEAR_PRC parameter missed");
-
-    let client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .build()?;
-
-    let block_request = BlockRequest {
-        jsonrpc: "2.0",
-        id: "dontcare",
-        method: "block",
-        params: BlockParamString {
-            block_id: hash.parse().unwrap(),
-        },
-    };
-
-    let block_response_text = client
-        .post(rpc_url)
-        .json(&block_request)
-        .send()
-        .await?
-        .text()
-        .await?;
-
-    println!("Raw response: {}", block_response_text);
-
-    // Try to parse it as a successful BlockResponse or an error RpcErrorResponse
-    if let Ok(block_response) = serde_json::from_str::<BlockResponse>(&block_response_text) {
-        // Handle the successful block response
-println!("HASH: {:#?}", block_response);
-        let header = BlockHeader::from(block_response.result.header);
-        Ok((*header.hash(), header))
-    } else if let Ok(rpc_error_response) = serde_json::from_str::<RpcErrorResponse>(&block_response_text) {
-        // Handle the error response
-        eprintln!(
-            "RPC Error: {} - {:?}",
-            rpc_error_response.error.message,
-            rpc_error_response.error
-        );
-        Err(anyhow::Error::msg(format!(
-            "RPC request failed: {}",
-            rpc_error_response.error.message
-        )))
-    } else {
-        // If we couldn't parse the response, return a generic error
-        Err(anyhow::Error::msg("Failed to parse RPC response"))
-    }
+pub async fn load_block_from_rpc(hash: &str) -> Result<(CryptoHash, BlockHeader)> {
+    RpcBlockSource::from_env()?.block_by_hash(parse_hash(hash)?).await
 }
 
 /// Loads a block and its header from an RPC endpoint.
@@ -140,52 +123,11 @@ println!("HASH: {:#?}", block_response);
 /// # Errors
 ///
 /// Returns an error if there are any issues with the RPC request or response handling.
-pub async fn load_block_by_height_from_rpc(height: u64) -> Result<(CryptoHash, BlockHeader), anyhow::Error> {
-    let rpc_url = env::var("NEAR_RPC").expect("NEAR_PRC parameter missed");
-
-    let client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .build()?;
-
-    let block_request = BlockRequestByHeight {
-        jsonrpc: "2.0",
-        id: "dontcare",
-        method: "block",
-        params: BlockParamHeight {
-            block_id: height,
-        },
-    };
-
-    let block_response_text = client
-        .post(rpc_url)
-        .json(&block_request)
-        .send()
+pub async fn load_block_by_height_from_rpc(height: u64) -> Result<(CryptoHash, BlockHeader)> {
+    RpcBlockSource::from_env()?
+        .block_by_height(height)
         .await?
-	.text()
-        .await?;
-
-    println!("Raw response: {}", block_response_text);
-
-    // Try to parse it as a successful BlockResponse or an error RpcErrorResponse
-    if let Ok(block_response) = serde_json::from_str::<BlockResponse>(&block_response_text) {
-        // Handle the successful block response
-        let header = BlockHeader::from(block_response.result.header);
-        Ok((*header.hash(), header))
-    } else if let Ok(rpc_error_response) = serde_json::from_str::<RpcErrorResponse>(&block_response_text) {
-        // Handle the error response
-        eprintln!(
-            "RPC Error: {} - {:?}",
-            rpc_error_response.error.message,
-            rpc_error_response.error
-        );
-        Err(anyhow::Error::msg(format!(
-            "RPC request failed: {}",
-            rpc_error_response.error.message
-        )))
-    } else {
-        // If we couldn't parse the response, return a generic error
-        Err(anyhow::Error::msg("Failed to parse RPC response"))
-    }
+        .ok_or_else(|| BftError::BlockNotFound(height.to_string()))
 }
 
 /// Loads validators and their stakes from a JSON file.
@@ -206,7 +148,7 @@ pub async fn load_block_by_height_from_rpc(height: u64) -> Result<(CryptoHash, B
 /// # Errors
 ///
 /// Returns an error if there are any issues reading or parsing the JSON file.
-pub fn load_validators(path: &str) -> Result<Vec<ValidatorStake>, anyhow::Error> {
+pub fn load_validators(path: &str) -> Result<Vec<ValidatorStake>> {
     let mut file = File::open(path)?;
     let mut data = String::new();
     file.read_to_string(&mut data)?;
@@ -214,14 +156,8 @@ pub fn load_validators(path: &str) -> Result<Vec<ValidatorStake>, anyhow::Error>
     let validator_stakes: Vec<ValidatorStake> = validator_response
         .result
         .into_iter()
-        .map(|validator| {
-            ValidatorStake::new_v1(
-                AccountId::from_str(&validator.account_id).unwrap(),
-                PublicKey::from_str(&validator.public_key).unwrap(),
-                validator.stake.parse().unwrap(),
-            )
-        })
-        .collect();
+        .map(validator_stake_from_ordered)
+        .collect::<Result<Vec<ValidatorStake>>>()?;
     Ok(validator_stakes)
 }
 
@@ -245,49 +181,18 @@ pub fn load_validators(path: &str) -> Result<Vec<ValidatorStake>, anyhow::Error>
 /// * Validator data cannot be deserialized into `ValidatorStake` objects.
 pub async fn load_validators_from_rpc(
     block_hash: &str,
-) -> Result<Vec<ValidatorStake>, anyhow::Error> {
-    let rpc_url = env::var("NEAR_RPC").expect("NEAR_PRC parameter missed");
-
-    let client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .build()?;
-
-    let validators_ordered_request = json!({
-        "jsonrpc": "2.0",
-        "method": "EXPERIMENTAL_validators_ordered",
-        "params": vec![block_hash],
-        "id": "dontcare"
-    });
-
-    let validators_ordered_response: ValidatorsOrderedResponse = client
-        .post(rpc_url)
-        .json(&validators_ordered_request)
-        .send()
-        .await?
-        .json()
-        .await?;
-
-    let _validators_ordered_json_data =
-        serde_json::to_string(&validators_ordered_response).unwrap();
-
-    // -------------- serializing EXPERIMENTAL_validators_ordered into ValidatorStake structure --------------
-
-    let validator_stakes = validators_ordered_response
-        .result
-        .into_iter()
-        .map(|validator| {
-            ValidatorStake::new_v1(
-                AccountId::from_str(&validator.account_id).unwrap(),
-                PublicKey::from_str(&validator.public_key).unwrap(),
-                validator.stake.parse().unwrap(),
-            )
-        })
-        .collect();
+) -> Result<Vec<ValidatorStake>> {
+    RpcBlockSource::from_env()?.validators(parse_hash(block_hash)?).await
+}
 
-    Ok(validator_stakes)
+/// Parses a base58 block hash.
+pub fn parse_hash(hash: &str) -> Result<CryptoHash> {
+    CryptoHash::from_str(hash)
+        .map_err(|e| BftError::InvalidInput(format!("Invalid block hash {hash}: {e}")))
 }
 
-/// Set data for the given epochs.
+/// Set data for the given epochs stored in `DEFAULT_EPOCHS_PATH`.
+/// Use `load_block_input`/`load_epoch_input` or the builders of `crate::input` to set data from other sources.
 ///
 /// # Arguments
 ///
@@ -325,120 +230,421 @@ pub fn set_blocks(
     // for Block_n-1
     epoch_id_i_3: Option<String>,
 ) -> Result<(Vec<(Vec<u8>, Vec<u8>)>, Vec<(HeaderDataFields, Vec<u8>)>)> {
-    // Extract epoch blocks: Block_0, Block_n-1, Block_n-1 (optionally).
-    let mut epoch_blocks: Vec<(Vec<u8>, Vec<u8>)> = vec![];
-    // Extract blocks to prove finality: Block_i+4, Block_i+3, Block_i+2, Block_i+1, Block_i/Block_0, Block_n-1.
-    let mut blocks: Vec<(HeaderDataFields, Vec<u8>)> = vec![];
-
-    // Search for folder by epoch_id_i_1.
-    // Extract Block_0 header.
-    let mut folder = epoch_id_i_1.clone();
-    let mut file = "block-0.json".to_string();
-    let mut path = format!("../data/epochs/{folder}/{file}");
-    let (_, mut block_data) = load_block_header(&path)?;
-    // Extract Block_0 hash (it should be stored in the contract).
-    folder = epoch_id_i_1.clone() + "_STORED";
-    path = format!("../data/epochs/{folder}/{file}");
-    let mut block_hash = load_block_hash(&path)?;
-    epoch_blocks.push((borsh::to_vec(&block_hash)?, borsh::to_vec(&block_data)?));
-
-    // Search for folder by epoch_id_i_2.
-    // Extract Block_n-1.
-    folder = epoch_id_i_2.clone();
-    file = "block-last.json".to_string();
-    path = format!("../data/epochs/{folder}/{file}");
-    (_, block_data) = load_block_header(&path)?;
-    // Extract Block_n-1 hash (it should be stored in the contract).
-    folder = epoch_id_i_2.clone() + "_STORED";
-    path = format!("../data/epochs/{folder}/{file}");
-    block_hash = load_block_hash(&path)?;
-    epoch_blocks.push((borsh::to_vec(&block_hash)?, borsh::to_vec(&block_data)?));
-
-    // Set Block_n-1 (Epoch_i-3) optionally.
-    if let Some(epoch_id_i_3) = epoch_id_i_3.clone() {
-        // Search for folder by epoch_id_i_3.
-        // Extract Block_n-1.
-        folder = epoch_id_i_3.clone();
-        file = "block-last.json".to_string();
-        path = format!("../data/epochs/{folder}/{file}");
-        (_, block_data) = load_block_header(&path)?;
-        // Extract Block_n-1 hash (it should be stored in the contract).
-        folder = epoch_id_i_3.clone() + "_STORED";
-        path = format!("../data/epochs/{folder}/{file}");
-        block_hash = load_block_hash(&path)?;
-        epoch_blocks.push((borsh::to_vec(&block_hash)?, borsh::to_vec(&block_data)?));
+    match epoch_id_i_3 {
+        Some(epoch_id_i_3) => {
+            let input = load_epoch_input(
+                DEFAULT_EPOCHS_PATH,
+                &epoch_id_i,
+                &epoch_id_i_1,
+                &epoch_id_i_2,
+                &epoch_id_i_3,
+            )?;
+            Ok((input.epoch_blocks()?, input.blocks_data()?))
+        }
+        None => {
+            let input =
+                load_block_input(DEFAULT_EPOCHS_PATH, &epoch_id_i, &epoch_id_i_1, &epoch_id_i_2)?;
+            Ok((input.epoch_blocks()?, input.blocks_data()?))
+        }
     }
+}
 
-    // Define block type.
-    let block_type = match epoch_id_i_3 {
-        Some(_) => "block-".to_string(),
-        None => "random-".to_string(),
-    };
+/// Loads a block header from `{dir}/{epoch_id}/{file}` and its stored hash from
+/// `{dir}/{epoch_id}_STORED/{file}`.
+fn load_anchor_block(dir: &str, epoch_id: &str, file: &str) -> Result<AnchorBlock> {
+    let (_, header) = load_block_header(&format!("{dir}/{epoch_id}/{file}"))?;
+    let stored_hash = load_block_hash(&format!("{dir}/{epoch_id}_STORED/{file}"))?;
+    Ok(AnchorBlock::new(header, stored_hash))
+}
 
-    // There should be at least five consecutive blocks to prove BFT finality.
-    let mut block_num = 4;
-    let mut i = 0;
-    folder = epoch_id_i.clone();
-    while block_num >= 0 {
-        // Search for folders by blocks_hash_i. Extract blocks.
-        file = block_type.clone() + block_num.to_string().as_str() + ".json";
-        path = format!("../data/epochs/{folder}/{file}");
-        (block_hash, block_data) = load_block_header(&path)?;
-        let mut approvals: Option<Vec<Vec<u8>>> = None;
-        approvals = Some(
-            block_data
-                .approvals()
-                .iter()
-                .map(|approval| borsh::to_vec(approval).unwrap())
-                .collect(),
-        );
+/// Loads consecutive blocks `{dir}/{epoch_id}/{prefix}0.json`, ..., `{prefix}4.json`.
+fn load_finality_blocks(dir: &str, epoch_id: &str, prefix: &str) -> Result<Vec<BlockHeader>> {
+    (0..FINALITY_BLOCKS)
+        .map(|i| Ok(load_block_header(&format!("{dir}/{epoch_id}/{prefix}{i}.json"))?.1))
+        .collect()
+}
 
-        let block = HeaderDataFields {
-            hash: block_hash.0.to_vec(),
-            height: Some(block_data.height()),
-            prev_hash: Some(block_data.prev_hash().0.to_vec()),
-            bp_hash: Some(block_data.next_bp_hash().0.to_vec()),
-            epoch_id: Some(block_data.epoch_id().0 .0.to_vec()),
-            next_epoch_id: Some(block_data.next_epoch_id().0 .0.to_vec()),
-            last_ds_final_hash: Some(block_data.last_ds_final_block().0.to_vec()),
-            last_final_hash: Some(block_data.last_final_block().0.to_vec()),
-            approvals,
-        };
+/// Loads the data to prove a randomly selected block from the directory with the following layout:
+/// `{dir}/{epoch_id}/{random-0..4,block-0,block-last,validators}.json` and stored hashes in
+/// `{dir}/{epoch_id}_STORED/{block-0,block-last}.json`.
+///
+/// # Arguments
+///
+/// * `dir` - The path to the directory with epochs.
+/// * `epoch_id_i` - Epoch_id of the epoch, where the chosen block is.
+/// * `epoch_id_i_1` - Epoch_id of Epoch_i-1. Is used for Block_0 (Epoch_i-1).
+/// * `epoch_id_i_2` - Epoch_id of Epoch_i-2. Is used for Block_n-1 (Epoch_i-2).
+///
+/// # Returns
+///
+/// Returns `BlockProvingInput` for Block_i stored in `random-0.json`.
+pub fn load_block_input(
+    dir: &str,
+    epoch_id_i: &str,
+    epoch_id_i_1: &str,
+    epoch_id_i_2: &str,
+) -> Result<BlockProvingInput> {
+    let ep1_first_block = load_anchor_block(dir, epoch_id_i_1, "block-0.json")?;
+    let ep2_last_block = load_anchor_block(dir, epoch_id_i_2, "block-last.json")?;
+    BlockProvingInput::builder()
+        .ep1_first_block(ep1_first_block.header, ep1_first_block.stored_hash)
+        .ep2_last_block(ep2_last_block.header, ep2_last_block.stored_hash)
+        .blocks(load_finality_blocks(dir, epoch_id_i, "random-")?)
+        .validators(load_validators(&format!("{dir}/{epoch_id_i}/validators.json"))?)
+        .build()
+}
 
-        blocks.push((block, borsh::to_vec(&block_data)?));
+/// Loads the data to prove epoch blocks from the directory with the layout described in `load_block_input`.
+///
+/// # Arguments
+///
+/// * `dir` - The path to the directory with epochs.
+/// * `epoch_id_i` - Epoch_id of the epoch, where Block_0 is.
+/// * `epoch_id_i_1` - Epoch_id of Epoch_i-1. Is used for Block_0 (Epoch_i-1) and Block_n-1 (Epoch_i-1).
+/// * `epoch_id_i_2` - Epoch_id of Epoch_i-2. Is used for Block_n-1 (Epoch_i-2).
+/// * `epoch_id_i_3` - Epoch_id of Epoch_i-3. Is used for Block_n-1 (Epoch_i-3).
+///
+/// # Returns
+///
+/// Returns `EpochProvingInput` for Block_0 (Epoch_i) and Block_n-1 (Epoch_i-1).
+pub fn load_epoch_input(
+    dir: &str,
+    epoch_id_i: &str,
+    epoch_id_i_1: &str,
+    epoch_id_i_2: &str,
+    epoch_id_i_3: &str,
+) -> Result<EpochProvingInput> {
+    let ep1_first_block = load_anchor_block(dir, epoch_id_i_1, "block-0.json")?;
+    let ep2_last_block = load_anchor_block(dir, epoch_id_i_2, "block-last.json")?;
+    let ep3_last_block = load_anchor_block(dir, epoch_id_i_3, "block-last.json")?;
+    let (_, ep1_last_block) = load_block_header(&format!("{dir}/{epoch_id_i_1}/block-last.json"))?;
+    EpochProvingInput::builder()
+        .ep1_first_block(ep1_first_block.header, ep1_first_block.stored_hash)
+        .ep2_last_block(ep2_last_block.header, ep2_last_block.stored_hash)
+        .ep3_last_block(ep3_last_block.header, ep3_last_block.stored_hash)
+        .ep1_last_block(ep1_last_block)
+        .blocks(load_finality_blocks(dir, epoch_id_i, "block-")?)
+        .validators(load_validators(&format!("{dir}/{epoch_id_i}/validators.json"))?)
+        .validators_n_1(load_validators(&format!("{dir}/{epoch_id_i_1}/validators.json"))?)
+        .build()
+}
 
-        block_num -= 1;
-        i += 1;
+/// Searches for the first block of an epoch by binary search over heights.
+///
+/// Blocks are ordered by heights and epochs, so all blocks before the first block of the epoch
+/// don't satisfy `in_epoch`, while all blocks starting from it (up to `hi`) do.
+/// Skipped heights are allowed.
+///
+/// # Arguments
+///
+/// * `lo` - The lowest height where the first block of the epoch can be.
+/// * `hi` - The height of a known block of the epoch.
+/// * `load` - Loads a block by height. Returns `None` if the height was skipped.
+/// * `height` - Returns the height of a block.
+/// * `in_epoch` - Returns `true` if a block belongs to the epoch.
+///
+/// # Returns
+///
+/// Returns the first block of the epoch.
+///
+/// # Errors
+///
+/// Returns `BftError::InvalidInput` if there is no block of the epoch at height `hi`.
+pub async fn find_epoch_start<T, L, Fut>(
+    lo: u64,
+    hi: u64,
+    load: L,
+    height: impl Fn(&T) -> u64,
+    in_epoch: impl Fn(&T) -> bool,
+) -> Result<T>
+where
+    L: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<Option<T>>>,
+{
+    let mut best = match load(hi).await? {
+        Some(block) if in_epoch(&block) => block,
+        _ => {
+            return Err(BftError::InvalidInput(format!(
+                "No block of the epoch at height {hi}"
+            )))
+        }
+    };
+    // The first block of the epoch is either `best` or in [lo, hi).
+    let (mut lo, mut hi) = (lo, hi);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        // Take the first existing block in [mid, hi).
+        let mut block = None;
+        for h in mid..hi {
+            if let Some(b) = load(h).await? {
+                block = Some(b);
+                break;
+            }
+        }
+        match block {
+            Some(b) if in_epoch(&b) => {
+                hi = mid;
+                best = b;
+            }
+            Some(b) => lo = height(&b) + 1,
+            None => hi = mid,
+        }
     }
+    Ok(best)
+}
 
-    // Set the sixth block (Block_n-1) if the function proves the epoch blocks.
-    if let Some(epoch_id_i_3) = epoch_id_i_3.clone() {
-        // Search for folders by blocks_hash_i. Extract blocks. Extract Block_n-1.
-        folder = epoch_id_i_1.clone();
-        file = "block-last.json".to_string();
-        path = format!("../data/epochs/{folder}/{file}");
-        (block_hash, block_data) = load_block_header(&path)?;
-        let block = HeaderDataFields {
-            hash: block_hash.0.to_vec(),
-            height: Some(block_data.height()),
-            prev_hash: Some(block_data.prev_hash().0.to_vec()),
-            bp_hash: Some(block_data.next_bp_hash().0.to_vec()),
-            epoch_id: Some(block_data.epoch_id().0 .0.to_vec()),
-            next_epoch_id: Some(block_data.next_epoch_id().0 .0.to_vec()),
-            last_ds_final_hash: Some(block_data.last_ds_final_block().0.to_vec()),
-            last_final_hash: Some(block_data.last_final_block().0.to_vec()),
-            approvals: None,
-        };
-        blocks.push((block, borsh::to_vec(&block_data)?));
+/// Resolves the data to prove a randomly selected block given only its hash.
+///
+/// Block_n-1 (Epoch_i-2) is found by following `next_epoch_id` of Block_i and Block_n-1 (Epoch_i-1).
+/// Block_0 (Epoch_i-1) is found by binary search between heights of these blocks.
+/// Stored hashes of the epoch blocks are taken from the proof store or the genesis anchor of the network
+/// (see `NetworkProfile::stored_hash`), blocks and validators are loaded from `source`.
+///
+/// # Arguments
+///
+/// * `hash` - The hash of Block_i.
+/// * `source` - The source of blocks and validators, e.g. `RpcBlockSource`.
+/// * `store` - The store with proofs of epoch blocks.
+/// * `profile` - The network of the block.
+///
+/// # Returns
+///
+/// Returns `BlockProvingInput` for Block_i.
+///
+/// # Errors
+///
+/// Returns `BftError::BlockNotFound` if Block_i+1...Block_i+4 aren't produced yet,
+/// `BftError::SkippedBlock` if they have skipped heights, or an error if requests to the source
+/// fail, the epoch blocks weren't proven or loaded blocks aren't consistent.
+pub async fn resolve_block_input(
+    hash: CryptoHash,
+    source: &dyn BlockSource,
+    store: &dyn ProofStore,
+    profile: &NetworkProfile,
+) -> Result<BlockProvingInput> {
+    // Load Block_i and Block_i+1...Block_i+4.
+    let (bi_hash, bi_block) = source.block_by_hash(hash).await?;
+    let mut blocks = vec![bi_block.clone()];
+    blocks.extend(load_finality_blocks(&bi_block, source).await?);
+    // Load Block_n-1 (Epoch_i-1). It is used to load Block_n-1 (Epoch_i-2).
+    let ep1_bn_1_hash = bi_block.next_epoch_id().0;
+    let (_, ep1_bn_1_block) = source.block_by_hash(ep1_bn_1_hash).await?;
+    // Load Block_n-1 (Epoch_i-2).
+    let ep2_bn_1_hash = ep1_bn_1_block.next_epoch_id().0;
+    if ep2_bn_1_hash != bi_block.epoch_id().0 {
+        return Err(BftError::HashMismatch(format!(
+            "Block_n-1 (Epoch_i-2) {} doesn't match epoch_id {} of Block_i",
+            ep2_bn_1_hash,
+            bi_block.epoch_id().0
+        )));
     }
+    let (_, ep2_bn_1_block) = source.block_by_hash(ep2_bn_1_hash).await?;
+    // Search for Block_0 (Epoch_i-1).
+    let ep1_epoch_id = ep1_bn_1_block.epoch_id().clone();
+    let (ep1_b0_hash, ep1_b0_block) = find_epoch_start(
+        ep2_bn_1_block.height() + 1,
+        ep1_bn_1_block.height(),
+        |height| source.block_by_height(height),
+        |(_, block): &(CryptoHash, BlockHeader)| block.height(),
+        |(_, block): &(CryptoHash, BlockHeader)| *block.epoch_id() == ep1_epoch_id,
+    )
+    .await?;
+    if *ep1_b0_block.prev_hash() != ep2_bn_1_hash {
+        return Err(BftError::HashMismatch(format!(
+            "Block_0 (Epoch_i-1) {} doesn't follow Block_n-1 (Epoch_i-2) {}",
+            ep1_b0_hash, ep2_bn_1_hash
+        )));
+    }
+    // Search for stored hashes.
+    let ep2_bn_1_stored_hash = profile.stored_hash(store, &ep2_bn_1_hash)?;
+    let ep1_b0_stored_hash = profile.stored_hash(store, &ep1_b0_hash)?;
+    // Load validators for Block_i.
+    let validators = source.validators(bi_hash).await?;
+
+    BlockProvingInput::builder()
+        .ep2_last_block(ep2_bn_1_block, ep2_bn_1_stored_hash)
+        .ep1_first_block(ep1_b0_block, ep1_b0_stored_hash)
+        .blocks(blocks)
+        .validators(validators)
+        .build()
+}
 
-    Ok((epoch_blocks, blocks))
+/// Loads the blocks at the heights following `block` which finalize it by BFT.
+///
+/// A missing height is skipped permanently if a block at a later height exists, otherwise the block
+/// may be produced later. Heights after the missing one are probed up to `MAX_SKIPPED_HEIGHTS`.
+///
+/// # Arguments
+///
+/// * `block` - The finalized block, i.e. Block_i.
+/// * `source` - The source of blocks, e.g. `RpcBlockSource`.
+///
+/// # Returns
+///
+/// Returns headers of Block_i+1...Block_i+4.
+///
+/// # Errors
+///
+/// Returns `BftError::SkippedBlock` if a height is skipped permanently, `BftError::BlockNotFound`
+/// if no block after the missing height exists yet, or an error if requests to the source fail.
+async fn load_finality_blocks(
+    block: &BlockHeader,
+    source: &dyn BlockSource,
+) -> Result<Vec<BlockHeader>> {
+    let mut blocks = Vec::with_capacity(FINALITY_BLOCKS - 1);
+    for i in 1..FINALITY_BLOCKS as u64 {
+        let height = block.height() + i;
+        match source.block_by_height(height).await? {
+            Some((_, block)) => blocks.push(block),
+            None => {
+                for later in height + 1..=height + MAX_SKIPPED_HEIGHTS {
+                    if source.block_by_height(later).await?.is_some() {
+                        return Err(BftError::SkippedBlock(height.to_string()));
+                    }
+                }
+                return Err(BftError::BlockNotFound(height.to_string()));
+            }
+        }
+    }
+    Ok(blocks)
+}
+
+/// Represents the maximal number of consecutive skipped heights while searching for the first block
+/// of an epoch. A longer gap means that the epoch hasn't started yet.
+pub const MAX_SKIPPED_HEIGHTS: u64 = 64;
+
+/// Searches for Block_0 of the epoch by its epoch_id.
+///
+/// epoch_id of Epoch_i is the hash of Block_n-1 (Epoch_i-2), and Epoch_i-1 lasts at least
+/// `profile.epoch_length` heights, so Block_0 (Epoch_i) is the first block of the epoch starting
+/// from that height.
+///
+/// # Arguments
+///
+/// * `epoch_id` - The epoch_id of Epoch_i.
+/// * `source` - The source of blocks, e.g. `RpcBlockSource`.
+/// * `profile` - The network of the epoch.
+///
+/// # Returns
+///
+/// Returns the hash and the header of Block_0 (Epoch_i).
+///
+/// # Errors
+///
+/// Returns `BftError::BlockNotFound` if the epoch hasn't started yet, i.e. there are more than
+/// `MAX_SKIPPED_HEIGHTS` consecutive skipped heights or Epoch_i-1 lasts longer than two epochs.
+pub async fn find_epoch_first_block(
+    epoch_id: CryptoHash,
+    source: &dyn BlockSource,
+    profile: &NetworkProfile,
+) -> Result<(CryptoHash, BlockHeader)> {
+    let (_, ep2_bn_1_block) = source.block_by_hash(epoch_id).await?;
+    let lo = ep2_bn_1_block.height() + 1 + profile.epoch_length;
+    let mut skipped = 0;
+    for height in lo..lo + profile.epoch_length {
+        match source.block_by_height(height).await? {
+            Some((hash, block)) if block.epoch_id().0 == epoch_id => return Ok((hash, block)),
+            // A block of Epoch_i-1, which lasts longer than the epoch length.
+            Some(_) => skipped = 0,
+            None if skipped == MAX_SKIPPED_HEIGHTS => break,
+            None => skipped += 1,
+        }
+    }
+    Err(BftError::BlockNotFound(format!("Block_0 of epoch {epoch_id}")))
+}
+
+/// Resolves the data to prove epoch blocks given only the hash of Block_0 (Epoch_i).
+///
+/// Block_n-1 (Epoch_i-1) is the previous block of Block_0, Block_n-1 (Epoch_i-2) and
+/// Block_n-1 (Epoch_i-3) are found by following epoch ids. Block_0 (Epoch_i-1) is found by binary
+/// search between heights of Block_n-1 (Epoch_i-2) and Block_n-1 (Epoch_i-1). Stored hashes are
+/// taken as in `resolve_block_input`.
+///
+/// # Arguments
+///
+/// * `hash` - The hash of Block_0 (Epoch_i).
+/// * `source` - The source of blocks and validators, e.g. `RpcBlockSource`.
+/// * `store` - The store with proofs of epoch blocks.
+/// * `profile` - The network of the blocks.
+///
+/// # Returns
+///
+/// Returns `EpochProvingInput` for Block_0 (Epoch_i) and Block_n-1 (Epoch_i-1).
+///
+/// # Errors
+///
+/// Returns `BftError::BlockNotFound` if Block_1...Block_4 aren't produced yet,
+/// `BftError::SkippedBlock` if they have skipped heights, or an error if requests to the source
+/// fail, the epoch blocks of previous epochs weren't proven or loaded blocks aren't consistent.
+pub async fn resolve_epoch_input(
+    hash: CryptoHash,
+    source: &dyn BlockSource,
+    store: &dyn ProofStore,
+    profile: &NetworkProfile,
+) -> Result<EpochProvingInput> {
+    // Load Block_0 and Block_1...Block_4.
+    let (b0_hash, b0_block) = source.block_by_hash(hash).await?;
+    let mut blocks = vec![b0_block.clone()];
+    blocks.extend(load_finality_blocks(&b0_block, source).await?);
+    // Load Block_n-1 (Epoch_i-1), its hash is next_epoch_id of Block_0.
+    let ep1_bn_1_hash = *b0_block.prev_hash();
+    if ep1_bn_1_hash != b0_block.next_epoch_id().0 {
+        return Err(BftError::HashMismatch(format!(
+            "Block {} isn't the first block of its epoch",
+            b0_hash
+        )));
+    }
+    let (_, ep1_bn_1_block) = source.block_by_hash(ep1_bn_1_hash).await?;
+    // Load Block_n-1 (Epoch_i-2) and Block_n-1 (Epoch_i-3).
+    let ep2_bn_1_hash = b0_block.epoch_id().0;
+    let (_, ep2_bn_1_block) = source.block_by_hash(ep2_bn_1_hash).await?;
+    let ep3_bn_1_hash = ep2_bn_1_block.next_epoch_id().0;
+    let (_, ep3_bn_1_block) = source.block_by_hash(ep3_bn_1_hash).await?;
+    // Search for Block_0 (Epoch_i-1).
+    let ep1_epoch_id = ep1_bn_1_block.epoch_id().clone();
+    let (ep1_b0_hash, ep1_b0_block) = find_epoch_start(
+        ep2_bn_1_block.height() + 1,
+        ep1_bn_1_block.height(),
+        |height| source.block_by_height(height),
+        |(_, block): &(CryptoHash, BlockHeader)| block.height(),
+        |(_, block): &(CryptoHash, BlockHeader)| *block.epoch_id() == ep1_epoch_id,
+    )
+    .await?;
+    if *ep1_b0_block.prev_hash() != ep2_bn_1_hash {
+        return Err(BftError::HashMismatch(format!(
+            "Block_0 (Epoch_i-1) {} doesn't follow Block_n-1 (Epoch_i-2) {}",
+            ep1_b0_hash, ep2_bn_1_hash
+        )));
+    }
+    // Search for stored hashes.
+    let ep3_bn_1_stored_hash = profile.stored_hash(store, &ep3_bn_1_hash)?;
+    let ep2_bn_1_stored_hash = profile.stored_hash(store, &ep2_bn_1_hash)?;
+    let ep1_b0_stored_hash = profile.stored_hash(store, &ep1_b0_hash)?;
+    // Load validators for Block_0 and Block_n-1.
+    let validators = source.validators(b0_hash).await?;
+    let validators_n_1 = source.validators(ep1_bn_1_hash).await?;
+
+    EpochProvingInput::builder()
+        .ep3_last_block(ep3_bn_1_block, ep3_bn_1_stored_hash)
+        .ep2_last_block(ep2_bn_1_block, ep2_bn_1_stored_hash)
+        .ep1_first_block(ep1_b0_block, ep1_b0_stored_hash)
+        .ep1_last_block(ep1_bn_1_block)
+        .blocks(blocks)
+        .validators(validators)
+        .validators_n_1(validators_n_1)
+        .build()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Result;
+    use crate::source::FixtureBlockSource;
+    use crate::network::GenesisAnchor;
+    use crate::store::{FsProofStore, InMemoryProofStore, ProofKind};
+    use crate::synthetic::{SyntheticChain, SyntheticChainConfig};
     use rand::random;
+    use std::env;
 
     #[test]
     fn test_vec_u32_to_u8() {
@@ -479,6 +685,201 @@ mod tests {
         Ok(())
     }
 
+    // Block at `height` represented as (height, epoch), None if the height is skipped.
+    fn synthetic_block(height: u64, boundary: u64) -> Option<(u64, u64)> {
+        if height % 7 == 0 || (40..50).contains(&height) {
+            return None;
+        }
+        Some((height, (height >= boundary) as u64))
+    }
+
+    #[tokio::test]
+    async fn test_find_epoch_start() -> Result<()> {
+        let hi = 99;
+        for boundary in 1..=hi {
+            let expected = (boundary..=hi)
+                .find_map(|h| synthetic_block(h, boundary))
+                .unwrap();
+            let found = find_epoch_start(
+                1,
+                hi,
+                |h| async move { Ok::<_, BftError>(synthetic_block(h, boundary)) },
+                |block: &(u64, u64)| block.0,
+                |block: &(u64, u64)| block.1 == 1,
+            )
+            .await?;
+            assert_eq!(found, expected, "boundary {}", boundary);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_epoch_start_hi_not_in_epoch() {
+        let result = find_epoch_start(
+            1,
+            50,
+            |h| async move { Ok::<_, BftError>(synthetic_block(h, 60)) },
+            |block: &(u64, u64)| block.0,
+            |block: &(u64, u64)| block.1 == 1,
+        )
+        .await;
+        assert!(matches!(result, Err(BftError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_block_input_from_fixtures() -> Result<()> {
+        let (epoch_id_i, epoch_id_i_1, epoch_id_i_2) = (
+            "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t",
+            "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae",
+            "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi",
+        );
+        let dir = DEFAULT_EPOCHS_PATH;
+        let expected = load_block_input(dir, epoch_id_i, epoch_id_i_1, epoch_id_i_2)?;
+
+        // Stored hashes of the epoch blocks are taken from the proof store.
+        let root = env::temp_dir().join(format!("resolve_{:016x}", random::<u64>()));
+        let store = FsProofStore::open(&root)?;
+        let anchors = [(epoch_id_i_1, "block-0.json"), (epoch_id_i_2, "block-last.json")];
+        for (epoch_id, file) in anchors {
+            let (hash, _) = load_block_header(&format!("{dir}/{epoch_id}/{file}"))?;
+            let stored_hash = load_block_hash(&format!("{dir}/{epoch_id}_STORED/{file}"))?;
+            let proof_dir = store.proof_dir(ProofKind::Epoch, &hash);
+            std::fs::create_dir_all(&proof_dir)?;
+            std::fs::write(proof_dir.join("hash.json"), stored_hash.to_string())?;
+        }
+
+        let source = FixtureBlockSource::open(dir)?;
+        let (hash, _) = load_block_header(&format!("{dir}/{epoch_id_i}/random-0.json"))?;
+        let input = resolve_block_input(hash, &source, &store, &NetworkProfile::mainnet()).await?;
+        assert_eq!(input.epoch_blocks()?, expected.epoch_blocks()?);
+        assert_eq!(input.blocks, expected.blocks);
+        assert_eq!(input.validators, expected.validators);
+
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_block_input_from_genesis_anchor() -> Result<()> {
+        let (epoch_id_i, epoch_id_i_1, epoch_id_i_2) = (
+            "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t",
+            "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae",
+            "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi",
+        );
+        let dir = DEFAULT_EPOCHS_PATH;
+        let expected = load_block_input(dir, epoch_id_i, epoch_id_i_1, epoch_id_i_2)?;
+
+        // Epoch_i-1 is the genesis anchor, so its epoch blocks aren't proven.
+        let (block_hash, block) = load_block_header(&format!("{dir}/{epoch_id_i_1}/block-0.json"))?;
+        let (next_epoch_id, _) =
+            load_block_header(&format!("{dir}/{epoch_id_i_2}/block-last.json"))?;
+        let profile = NetworkProfile {
+            genesis: Some(GenesisAnchor {
+                epoch_id: block.epoch_id().0,
+                next_epoch_id,
+                block_hash,
+                bp_hash: *block.next_bp_hash(),
+                height: block.height(),
+            }),
+            ..NetworkProfile::mainnet()
+        };
+        let store = InMemoryProofStore::new();
+        let source = FixtureBlockSource::open(dir)?;
+        let (hash, _) = load_block_header(&format!("{dir}/{epoch_id_i}/random-0.json"))?;
+        assert!(resolve_block_input(hash, &source, &store, &NetworkProfile::mainnet())
+            .await
+            .is_err());
+        let input = resolve_block_input(hash, &source, &store, &profile).await?;
+        assert_eq!(input.epoch_blocks()?, expected.epoch_blocks()?);
+        assert_eq!(input.blocks, expected.blocks);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_epoch_input_from_genesis_anchor() -> Result<()> {
+        let (epoch_id_i, epoch_id_i_1, epoch_id_i_2, epoch_id_i_3) = (
+            "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t",
+            "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae",
+            "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi",
+            "89PT9SkLXB1FZHvW7EdQHxiSpm5ybuTCvjrGZWWhXMTz",
+        );
+        let dir = DEFAULT_EPOCHS_PATH;
+        let expected = load_epoch_input(dir, epoch_id_i, epoch_id_i_1, epoch_id_i_2, epoch_id_i_3)?;
+
+        // Epoch_i-1 is the genesis anchor, so epoch blocks of Epoch_i-1...Epoch_i-3 aren't proven.
+        let (block_hash, block) = load_block_header(&format!("{dir}/{epoch_id_i_1}/block-0.json"))?;
+        let profile = NetworkProfile {
+            genesis: Some(GenesisAnchor {
+                epoch_id: block.epoch_id().0,
+                next_epoch_id: block.next_epoch_id().0,
+                block_hash,
+                bp_hash: *block.next_bp_hash(),
+                height: block.height(),
+            }),
+            ..NetworkProfile::mainnet()
+        };
+        let store = InMemoryProofStore::new();
+        let source = FixtureBlockSource::open(dir)?;
+        let (b0_hash, b0) =
+            find_epoch_first_block(parse_hash(epoch_id_i)?, &source, &profile).await?;
+        assert_eq!(b0_hash, expected.block_hash());
+        assert_eq!(b0.epoch_id().0, parse_hash(epoch_id_i)?);
+        let input = resolve_epoch_input(b0_hash, &source, &store, &profile).await?;
+        assert_eq!(input.epoch_blocks()?, expected.epoch_blocks()?);
+        assert_eq!(input.blocks, expected.blocks);
+        assert_eq!(input.ep1_last_block, expected.ep1_last_block);
+        assert_eq!(input.validators, expected.validators);
+        assert_eq!(input.validators_n_1, expected.validators_n_1);
+
+        // epoch_id of Epoch_i+3 is the hash of Block_n-1 (Epoch_i+1), the epoch isn't in fixtures.
+        let epoch_id_i_plus_1 = "4RjXBrNcu39wutFTuFpnRHgNqgHxLMcGBKNEQdtkSBhy";
+        let (epoch_id_i_plus_3, _) =
+            load_block_header(&format!("{dir}/{epoch_id_i_plus_1}/block-last.json"))?;
+        assert!(matches!(
+            find_epoch_first_block(epoch_id_i_plus_3, &source, &profile).await,
+            Err(BftError::BlockNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_finality_blocks_with_skipped_height() -> Result<()> {
+        let config = SyntheticChainConfig {
+            num_epochs: 4,
+            epoch_length: 24,
+            num_validators: 4,
+            random_offset: 12,
+            ..Default::default()
+        };
+        let random_start = config.genesis_height + 3 * config.epoch_length + config.random_offset;
+        let config = SyntheticChainConfig {
+            skipped_heights: [random_start + 2].into(),
+            ..config
+        };
+        let chain = SyntheticChain::generate(config)?;
+        let dir = env::temp_dir().join(format!("finality_{:016x}", random::<u64>()));
+        chain.write(&dir)?;
+        let source = FixtureBlockSource::open(&dir)?;
+
+        // A later block exists, so the height is skipped permanently.
+        let epoch = &chain.epochs[3];
+        let block = &epoch.blocks_from(random_start)[0];
+        assert!(matches!(
+            load_finality_blocks(block, &source).await,
+            Err(BftError::SkippedBlock(height)) if height == (random_start + 2).to_string()
+        ));
+        // No blocks after the last one yet.
+        assert!(matches!(
+            load_finality_blocks(epoch.last_block(), &source).await,
+            Err(BftError::BlockNotFound(_))
+        ));
+        let blocks = load_finality_blocks(epoch.first_block(), &source).await?;
+        assert_eq!(blocks, epoch.blocks[1..FINALITY_BLOCKS]);
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_load_validators_from_rpc() -> Result<(), anyhow::Error> {
         env::set_var("NEAR_RPC", "https://rpc.mainnet.near.org");
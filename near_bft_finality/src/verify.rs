@@ -0,0 +1,581 @@
+use crate::circuits::{final_proof_stages, CircuitManifest, ConfigName, ANCHORED_BLOCK_STAGE};
+use crate::error::{BftError, Result};
+use crate::pipeline::{ANCHORED_DIR, EPOCH_CHAIN_DIR};
+use crate::prove_bft::epoch_chain::{AnchoredBlockPublicInputs, EpochAnchors};
+use crate::public_inputs::BlockProofPublicInputs;
+use crate::store::{Artifacts, ProofArtifacts, ProofKind};
+use near_primitives::hash::CryptoHash;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::{VerifierCircuitData, VerifierOnlyCircuitData};
+use plonky2::plonk::config::{GenericConfig, GenericHashOut};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::serialization::DefaultGateSerializer;
+use plonky2_field::extension::Extendable;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Represents the files of stored artifacts used to verify the proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactsFormat {
+    /// `proof.bin` & `verifier_data.bin`.
+    Binary,
+    /// `proof.json` & `verifier_data.json`. `CommonCircuitData` can't be deserialized from json,
+    /// so it is still read from `verifier_data.bin`.
+    Json,
+}
+
+/// Represents statistics of the circuit of a stored proof.
+///
+/// # Fields
+///
+/// * `degree_bits` - Log2 of the number of rows of the circuit.
+/// * `rows` - The number of rows of the circuit. Gates are padded to it, the number of gates
+///   before padding isn't kept in circuit data.
+/// * `gate_types` - Ids of gate types used in the circuit.
+/// * `num_public_inputs` - The number of public inputs.
+/// * `proof_size` - The size of the proof in binary form, in bytes.
+/// * `circuit_digest` - The digest of the circuit in hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitStats {
+    pub degree_bits: usize,
+    pub rows: usize,
+    pub gate_types: Vec<String>,
+    pub num_public_inputs: usize,
+    pub proof_size: usize,
+    pub circuit_digest: String,
+}
+
+/// Represents the result of verification of a stored proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofReport {
+    pub public_inputs: BlockProofPublicInputs,
+    pub stats: CircuitStats,
+}
+
+impl fmt::Display for ProofReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pi = &self.public_inputs;
+        let stats = &self.stats;
+        writeln!(f, "kind: {:?} (public inputs {:?})", pi.kind, pi.version)?;
+        writeln!(f, "block hash: {}", pi.block_hash)?;
+        writeln!(f, "Block_n-1 (Epoch_i-2) hash: {}", pi.ep2_last_block_hash)?;
+        writeln!(f, "Block_0 (Epoch_i-1) hash: {}", pi.ep1_first_block_hash)?;
+        writeln!(f, "degree bits: {}", stats.degree_bits)?;
+        writeln!(f, "rows: {}", stats.rows)?;
+        writeln!(
+            f,
+            "gate types ({}): {}",
+            stats.gate_types.len(),
+            stats.gate_types.join(", ")
+        )?;
+        writeln!(f, "public inputs: {}", stats.num_public_inputs)?;
+        writeln!(f, "proof size: {} bytes", stats.proof_size)?;
+        write!(f, "circuit digest: {}", stats.circuit_digest)
+    }
+}
+
+/// Verifies the stored proof and decodes its public inputs.
+///
+/// # Arguments
+///
+/// * `artifacts` - The serialized proof and circuit data, e.g. read by `ProofArtifacts::read_dir`.
+/// * `format` - Which files of artifacts are used.
+///
+/// # Errors
+///
+/// Returns `BftError::Serialization` if artifacts can't be deserialized, `BftError::Proving`
+/// if the proof is invalid and `BftError::InvalidInput` if public inputs have an unknown layout.
+pub fn verify_artifacts<F, C, const D: usize>(
+    artifacts: &ProofArtifacts,
+    format: ArtifactsFormat,
+) -> Result<ProofReport>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    ProofWithPublicInputs<F, C, D>: DeserializeOwned,
+    VerifierOnlyCircuitData<C, D>: DeserializeOwned,
+{
+    let (data, proof) = read_artifacts::<F, C, D>(artifacts, format)?;
+    let report = report(&data, &proof)?;
+    data.verify(proof)
+        .map_err(|e| BftError::Proving(format!("Proof verification failed: {:#}", e)))?;
+    Ok(report)
+}
+
+/// Decodes public inputs of the stored proof and collects statistics of its circuit without
+/// verifying the proof.
+///
+/// # Errors
+///
+/// Returns `BftError::Serialization` if artifacts can't be deserialized and
+/// `BftError::InvalidInput` if public inputs have an unknown layout.
+pub fn inspect_artifacts<F, C, const D: usize>(artifacts: &ProofArtifacts) -> Result<ProofReport>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    ProofWithPublicInputs<F, C, D>: DeserializeOwned,
+    VerifierOnlyCircuitData<C, D>: DeserializeOwned,
+{
+    let (data, proof) = read_artifacts::<F, C, D>(artifacts, ArtifactsFormat::Binary)?;
+    report(&data, &proof)
+}
+
+/// Returns directories of all proofs in the proof store: `{root}/{random|epoch}/{hash}`.
+pub fn stored_proof_dirs(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = vec![];
+    for kind in [ProofKind::Random, ProofKind::Epoch] {
+        let kind_dir = root.join(kind.dir_name());
+        if !kind_dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(kind_dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            }
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Verifies the proof stored in `dir`. The stored verifier data only proves that the proof is
+/// consistent with itself, so its circuit is also checked against production circuits in
+/// `manifest`. If the directory follows the layout of the proof store, the kind & the block hash
+/// in public inputs are checked against its path.
+///
+/// # Errors
+///
+/// Returns errors of `verify_artifacts`, `BftError::CircuitDrift` if the circuit isn't a circuit
+/// of final proofs of its kind in `manifest` and `BftError::HashMismatch` /
+/// `BftError::InvalidInput` if the proof is stored for another block or kind.
+pub fn verify_proof_dir<F, C, const D: usize>(
+    dir: &Path,
+    format: ArtifactsFormat,
+    manifest: &CircuitManifest,
+) -> Result<ProofReport>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + ConfigName,
+    ProofWithPublicInputs<F, C, D>: DeserializeOwned,
+    VerifierOnlyCircuitData<C, D>: DeserializeOwned,
+{
+    let artifacts = ProofArtifacts::read_dir(dir)?;
+    let report = verify_artifacts::<F, C, D>(&artifacts, format)?;
+    check_circuit(manifest, &report, C::NAME)?;
+    let pi = &report.public_inputs;
+    check_stored_hash(dir, &pi.block_hash)?;
+    let kind_dir = dir
+        .parent()
+        .and_then(|parent| parent.file_name())
+        .and_then(|name| name.to_str());
+    if let Some(kind_dir) = kind_dir {
+        let stored_kind = [ProofKind::Random, ProofKind::Epoch]
+            .into_iter()
+            .find(|kind| kind.dir_name() == kind_dir);
+        if stored_kind.is_some_and(|kind| kind != pi.kind) {
+            return Err(BftError::InvalidInput(format!(
+                "Proof is stored in {}, but its kind is {:?}",
+                kind_dir, pi.kind
+            )));
+        }
+    }
+    Ok(report)
+}
+
+/// Returns directories of proofs of blocks anchored to the epoch chain:
+/// `{root}/chain/anchored/{hash}`.
+pub fn anchored_proof_dirs(root: &Path) -> Result<Vec<PathBuf>> {
+    let anchored_dir = root.join(EPOCH_CHAIN_DIR).join(ANCHORED_DIR);
+    if !anchored_dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut dirs = vec![];
+    for entry in fs::read_dir(anchored_dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Returns true if `dir` follows the layout of proofs anchored to the epoch chain, see
+/// `anchored_proof_dirs`.
+pub fn is_anchored_proof_dir(dir: &Path) -> bool {
+    dir.parent()
+        .and_then(|parent| parent.file_name())
+        .is_some_and(|name| name == ANCHORED_DIR)
+}
+
+/// Verifies the proof of a block anchored to the epoch chain stored in `dir` (see
+/// `AnchoredBlockCircuit`). Its circuit fixes circuits of the chain, of epoch proofs and of block
+/// proofs, so it is checked against the anchoring circuit in `manifest`. The chain is valid for any
+/// genesis, so it must start from trusted `genesis`, e.g. the genesis anchor of the network profile.
+///
+/// # Errors
+///
+/// Returns errors of `verify_artifacts`, `BftError::CircuitDrift` if the circuit isn't the anchoring
+/// circuit in `manifest` and `BftError::HashMismatch` if the chain starts from another genesis or
+/// the proof is stored for another block.
+pub fn verify_anchored_proof_dir<F, C, const D: usize>(
+    dir: &Path,
+    format: ArtifactsFormat,
+    manifest: &CircuitManifest,
+    genesis: &EpochAnchors,
+) -> Result<ProofReport>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + ConfigName,
+    ProofWithPublicInputs<F, C, D>: DeserializeOwned,
+    VerifierOnlyCircuitData<C, D>: DeserializeOwned,
+{
+    let (data, proof) = read_artifacts::<F, C, D>(&ProofArtifacts::read_dir(dir)?, format)?;
+    let pi = AnchoredBlockPublicInputs::<F>::decode(&proof.public_inputs)?;
+    let report = ProofReport {
+        public_inputs: pi.block.clone(),
+        stats: circuit_stats(&data, &proof),
+    };
+    data.verify(proof)
+        .map_err(|e| BftError::Proving(format!("Proof verification failed: {:#}", e)))?;
+    let stages = [ANCHORED_BLOCK_STAGE.to_string()];
+    if !is_manifest_circuit(manifest, &stages, &report.stats, C::NAME) {
+        return Err(BftError::CircuitDrift(format!(
+            "circuit {} of the anchored proof isn't in the manifest stages {:?}",
+            report.stats.circuit_digest, stages
+        )));
+    }
+    pi.check_genesis(&genesis.commitment())?;
+    check_stored_hash(dir, &pi.block.block_hash)?;
+    Ok(report)
+}
+
+/// Checks that the proof stored in `dir` proves the block it is named after, if it is named after
+/// a block.
+fn check_stored_hash(dir: &Path, block_hash: &CryptoHash) -> Result<()> {
+    let dir_name = dir.file_name().and_then(|name| name.to_str());
+    if let Some(hash) = dir_name.and_then(|name| CryptoHash::from_str(name).ok()) {
+        if hash != *block_hash {
+            return Err(BftError::HashMismatch(format!(
+                "Proof is stored as {}, but proves {}",
+                hash, block_hash
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if the circuit with `stats` is the circuit of one of `stages` in `manifest`.
+fn is_manifest_circuit(
+    manifest: &CircuitManifest,
+    stages: &[String],
+    stats: &CircuitStats,
+    config: &str,
+) -> bool {
+    manifest.entries.iter().any(|entry| {
+        stages.contains(&entry.stage)
+            && entry.circuit_digest == stats.circuit_digest
+            && entry.degree_bits == stats.degree_bits
+            && entry.num_public_inputs == stats.num_public_inputs
+            && entry.config == config
+    })
+}
+
+/// Checks that the circuit of the proof is one of the circuits of final proofs of its kind.
+fn check_circuit(manifest: &CircuitManifest, report: &ProofReport, config: &str) -> Result<()> {
+    let kind = report.public_inputs.kind;
+    let stages = final_proof_stages(kind);
+    let stats = &report.stats;
+    if is_manifest_circuit(manifest, &stages, stats, config) {
+        Ok(())
+    } else {
+        Err(BftError::CircuitDrift(format!(
+            "circuit {} of the {:?} proof isn't in the manifest stages {:?}",
+            stats.circuit_digest, kind, stages
+        )))
+    }
+}
+
+/// Deserializes the proof and its circuit data.
+pub(crate) fn read_artifacts<F, C, const D: usize>(
+    artifacts: &ProofArtifacts,
+    format: ArtifactsFormat,
+) -> Result<(VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    ProofWithPublicInputs<F, C, D>: DeserializeOwned,
+    VerifierOnlyCircuitData<C, D>: DeserializeOwned,
+{
+    let mut data = VerifierCircuitData::<F, C, D>::from_bytes(
+        artifacts.verifier_data.clone(),
+        &DefaultGateSerializer,
+    )
+    .map_err(|e| BftError::Serialization(format!("Error reading verifier data: {:?}", e)))?;
+    let proof = match format {
+        ArtifactsFormat::Binary => {
+            ProofWithPublicInputs::<F, C, D>::from_bytes(artifacts.proof.clone(), &data.common)
+                .map_err(|e| BftError::Serialization(format!("Error reading proof: {:#}", e)))?
+        }
+        ArtifactsFormat::Json => {
+            data.verifier_only = serde_json::from_slice(&artifacts.verifier_data_json)?;
+            serde_json::from_slice(&artifacts.proof_json)?
+        }
+    };
+    Ok((data, proof))
+}
+
+/// Decodes public inputs of the proof and collects statistics of its circuit.
+fn report<F, C, const D: usize>(
+    data: &VerifierCircuitData<F, C, D>,
+    proof: &ProofWithPublicInputs<F, C, D>,
+) -> Result<ProofReport>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    Ok(ProofReport {
+        public_inputs: BlockProofPublicInputs::decode(&proof.public_inputs)?,
+        stats: circuit_stats(data, proof),
+    })
+}
+
+/// Collects statistics of the circuit of the proof.
+fn circuit_stats<F, C, const D: usize>(
+    data: &VerifierCircuitData<F, C, D>,
+    proof: &ProofWithPublicInputs<F, C, D>,
+) -> CircuitStats
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    CircuitStats {
+        degree_bits: data.common.degree_bits(),
+        rows: data.common.degree(),
+        gate_types: data.common.gates.iter().map(|gate| gate.0.id()).collect(),
+        num_public_inputs: proof.public_inputs.len(),
+        proof_size: proof.to_bytes().len(),
+        circuit_digest: hex::encode(data.verifier_only.circuit_digest.to_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::CircuitManifestEntry;
+    use anyhow::Result;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+    use plonky2_field::types::Field;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Proves a circuit exposing `pi` as public inputs, in place of the final block proof.
+    fn artifacts(pi: &[F]) -> Result<ProofArtifacts> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = builder.add_virtual_targets(pi.len());
+        builder.register_public_inputs(&targets);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        for (target, value) in targets.into_iter().zip(pi) {
+            pw.set_target(target, *value);
+        }
+        let proof = data.prove(pw)?;
+        Ok(ProofArtifacts::from_proof(&data, &proof)?)
+    }
+
+    #[test]
+    fn test_verify_artifacts() -> Result<()> {
+        let public_inputs = BlockProofPublicInputs::new(
+            ProofKind::Epoch,
+            CryptoHash([1; 32]),
+            CryptoHash([2; 32]),
+            CryptoHash([3; 32]),
+        );
+        let artifacts = artifacts(&public_inputs.encode::<F>())?;
+        let report = verify_artifacts::<F, C, D>(&artifacts, ArtifactsFormat::Binary)?;
+        assert_eq!(report.public_inputs, public_inputs);
+        assert_eq!(report.stats.num_public_inputs, 97);
+        assert_eq!(report.stats.proof_size, artifacts.proof.len());
+        assert_eq!(report.stats.circuit_digest.len(), 64);
+        let json_report = verify_artifacts::<F, C, D>(&artifacts, ArtifactsFormat::Json)?;
+        assert_eq!(json_report, report);
+        assert_eq!(inspect_artifacts::<F, C, D>(&artifacts)?, report);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_proof_dir() -> Result<()> {
+        let block_hash = CryptoHash([7; 32]);
+        let public_inputs = BlockProofPublicInputs::new(
+            ProofKind::Epoch,
+            block_hash,
+            CryptoHash([8; 32]),
+            CryptoHash([9; 32]),
+        );
+        let artifacts = artifacts(&public_inputs.encode::<F>())?;
+        let root = std::env::temp_dir().join(format!("verify_{:016x}", rand::random::<u64>()));
+        let dir = root
+            .join(ProofKind::Epoch.dir_name())
+            .join(block_hash.to_string());
+        artifacts.write_dir(&dir)?;
+        assert_eq!(stored_proof_dirs(&root)?, vec![dir.clone()]);
+        let stats = verify_artifacts::<F, C, D>(&artifacts, ArtifactsFormat::Binary)?.stats;
+        let manifest = |stages: Vec<String>| CircuitManifest {
+            entries: stages
+                .into_iter()
+                .map(|stage| CircuitManifestEntry {
+                    stage,
+                    circuit_digest: stats.circuit_digest.clone(),
+                    degree_bits: stats.degree_bits,
+                    num_public_inputs: stats.num_public_inputs,
+                    config: C::NAME.to_string(),
+                })
+                .collect(),
+        };
+        let epoch_manifest = manifest(final_proof_stages(ProofKind::Epoch));
+        let report = verify_proof_dir::<F, C, D>(&dir, ArtifactsFormat::Binary, &epoch_manifest)?;
+        assert_eq!(report.public_inputs, public_inputs);
+
+        // The circuit isn't a production circuit of epoch proofs.
+        for other_manifest in [
+            CircuitManifest::default(),
+            manifest(final_proof_stages(ProofKind::Random)),
+        ] {
+            assert!(matches!(
+                verify_proof_dir::<F, C, D>(&dir, ArtifactsFormat::Binary, &other_manifest),
+                Err(BftError::CircuitDrift(_))
+            ));
+        }
+
+        // The proof is stored for another block.
+        let other_dir = root
+            .join(ProofKind::Epoch.dir_name())
+            .join(CryptoHash([1; 32]).to_string());
+        artifacts.write_dir(&other_dir)?;
+        assert!(matches!(
+            verify_proof_dir::<F, C, D>(&other_dir, ArtifactsFormat::Binary, &epoch_manifest),
+            Err(BftError::HashMismatch(_))
+        ));
+        // The proof is stored as a proof of another kind.
+        let random_dir = root
+            .join(ProofKind::Random.dir_name())
+            .join(block_hash.to_string());
+        artifacts.write_dir(&random_dir)?;
+        assert!(matches!(
+            verify_proof_dir::<F, C, D>(&random_dir, ArtifactsFormat::Binary, &epoch_manifest),
+            Err(BftError::InvalidInput(_))
+        ));
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_anchored_proof_dir() -> Result<()> {
+        let genesis = EpochAnchors {
+            prev_last_block_hash: CryptoHash([1; 32]),
+            last_block_hash: CryptoHash([2; 32]),
+            first_block_hash: CryptoHash([3; 32]),
+        };
+        let block_hash = CryptoHash([7; 32]);
+        let block_pi = BlockProofPublicInputs::new(
+            ProofKind::Random,
+            block_hash,
+            CryptoHash([8; 32]),
+            CryptoHash([9; 32]),
+        );
+        // Public inputs of the anchored proof: genesis, the number of epochs & the block.
+        let pi = [
+            genesis.commitment::<F>().elements.to_vec(),
+            vec![F::TWO],
+            block_pi.encode::<F>(),
+        ]
+        .concat();
+        let artifacts = artifacts(&pi)?;
+        let root = std::env::temp_dir().join(format!("anchored_{:016x}", rand::random::<u64>()));
+        assert!(anchored_proof_dirs(&root)?.is_empty());
+        let dir = root
+            .join(EPOCH_CHAIN_DIR)
+            .join(ANCHORED_DIR)
+            .join(block_hash.to_string());
+        artifacts.write_dir(&dir)?;
+        assert_eq!(anchored_proof_dirs(&root)?, vec![dir.clone()]);
+        assert!(stored_proof_dirs(&root)?.is_empty());
+        assert!(is_anchored_proof_dir(&dir));
+        let (data, proof) = read_artifacts::<F, C, D>(&artifacts, ArtifactsFormat::Binary)?;
+        let stats = circuit_stats(&data, &proof);
+        let manifest = CircuitManifest {
+            entries: vec![CircuitManifestEntry {
+                stage: ANCHORED_BLOCK_STAGE.to_string(),
+                circuit_digest: stats.circuit_digest,
+                degree_bits: stats.degree_bits,
+                num_public_inputs: stats.num_public_inputs,
+                config: C::NAME.to_string(),
+            }],
+        };
+        let report = verify_anchored_proof_dir::<F, C, D>(
+            &dir,
+            ArtifactsFormat::Binary,
+            &manifest,
+            &genesis,
+        )?;
+        assert_eq!(report.public_inputs, block_pi);
+
+        // The circuit isn't the anchoring circuit.
+        assert!(matches!(
+            verify_anchored_proof_dir::<F, C, D>(
+                &dir,
+                ArtifactsFormat::Binary,
+                &CircuitManifest::default(),
+                &genesis
+            ),
+            Err(BftError::CircuitDrift(_))
+        ));
+        // The chain starts from another genesis.
+        let other_genesis = EpochAnchors {
+            first_block_hash: CryptoHash([4; 32]),
+            ..genesis
+        };
+        assert!(matches!(
+            verify_anchored_proof_dir::<F, C, D>(
+                &dir,
+                ArtifactsFormat::Binary,
+                &manifest,
+                &other_genesis
+            ),
+            Err(BftError::HashMismatch(_))
+        ));
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_artifacts_rejects_invalid_proof() -> Result<()> {
+        let public_inputs = BlockProofPublicInputs::new(
+            ProofKind::Random,
+            CryptoHash([4; 32]),
+            CryptoHash([5; 32]),
+            CryptoHash([6; 32]),
+        );
+        let mut artifacts = artifacts(&public_inputs.encode::<F>())?;
+        // The last bytes of the binary proof are public inputs, the proof doesn't match them.
+        let len = artifacts.proof.len();
+        artifacts.proof[len - 8] ^= 1;
+        assert!(verify_artifacts::<F, C, D>(&artifacts, ArtifactsFormat::Binary).is_err());
+        // Public inputs of an unknown layout.
+        let artifacts = self::artifacts(&[F::ONE; 3])?;
+        assert!(matches!(
+            verify_artifacts::<F, C, D>(&artifacts, ArtifactsFormat::Binary),
+            Err(BftError::InvalidInput(_))
+        ));
+        Ok(())
+    }
+}
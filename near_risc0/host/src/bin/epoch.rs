@@ -1,14 +1,18 @@
 use alloy_sol_types::SolType;
+use anyhow::ensure;
 use clap::{Parser, ValueEnum};
-use std::fs;
 
 use near_primitives_core::borsh::to_vec;
 use near_primitives_core::hash::CryptoHash;
 use risc0_zkvm::{default_prover, ExecutorEnv, ProverOpts, VerifierContext};
 
-use lib::rpc::ARCHIVAL_RPC;
+use host::store::{
+    load_last_known_height, load_stored_hash, FsProofStore, ProofKind, ProofRecord, ProofStore,
+    ReceiptArtifacts,
+};
+use lib::network::NetworkProfile;
+use lib::rpc::JsonClient;
 use lib::types::types::PublicValuesEpoch;
-use lib::{rpc::JsonClient, types::types::EPOCH_DURATION};
 // These constants represent the RISC-V ELF and the image ID generated by risc0-build.
 // The ELF is used for proving and the ID is used for verification.
 use methods::{
@@ -41,18 +45,20 @@ async fn main() -> anyhow::Result<()> {
         mode,
     } = Cli::parse();
 
+    let profile = NetworkProfile::from_env()?;
+    let store = FsProofStore::<ReceiptArtifacts>::open(PATH)?;
+
     loop {
         // Read last know height. It is the height of B0 of Ei.
-        let path = format!("{PATH}/last_known_height.json");
-        let height_string = fs::read_to_string(path).expect("Height reading error.");
-        let last_known_height: u64 = height_string.parse::<u64>().expect("Error parsing height.");
+        // The first run on the network starts from the trusted epoch of the profile.
+        let last_known_height: u64 = load_last_known_height(&store, &profile)?;
 
         println!("The last known height is: {}", last_known_height);
 
-        let mut last_block_height: u64 = last_known_height + (EPOCH_DURATION - 1);
-        let mut first_block_height: u64 = last_known_height + EPOCH_DURATION;
+        let (mut last_block_height, mut first_block_height) =
+            profile.next_epoch_heights(last_known_height);
 
-        let mut client = JsonClient::setup(Some(ARCHIVAL_RPC.to_string())).expect("Error setup client");
+        let mut client = JsonClient::archival(profile.clone()).expect("Error setup client");
 
         // Load block with last known height B0 Ei-1. 
         let (mut lkb_hash,  lkb_block) = client.load_block_by_height_from_rpc(last_known_height).await?;
@@ -76,23 +82,20 @@ async fn main() -> anyhow::Result<()> {
             first_block_height += 1;
         }
 
-        // Search & load hashes from files.
+        // Search & load hashes from the proof store.
         // Load stored hash of B0 Ei-1 for new B0 Ei.
-        let hash = lkb_hash.to_string();
+        let hash = lkb_hash;
         println!("search for: {}", hash);
-        let path = format!("{PATH}/{hash}/hash.json");
-        let epoch_id_i_1_hash_0: String = fs::read_to_string(path).expect("Hash reading error.");
+        let epoch_id_i_1_hash_0: String = load_stored_hash(&store, &profile, &hash)?.to_string();
         // Load stored Bn-1 Ei-2 for new B0 Ei & Bn-1 Ei-1.
-        let hash = lkb_block.next_epoch_id().0.to_string();
+        let hash = CryptoHash(lkb_block.next_epoch_id().0.0);
         println!("search for: {}", hash);
-        let path = format!("{PATH}/{hash}/hash.json");
-        let epoch_id_i_2_hash_last: String = fs::read_to_string(path).expect("Hash reading error.");
+        let epoch_id_i_2_hash_last: String = load_stored_hash(&store, &profile, &hash)?.to_string();
         let (_, epoch_id_i_2_block_last) = client.load_block_by_hash_from_rpc(&epoch_id_i_2_hash_last).await?;
         // Load stored Bn-1 Ei-3 for new Bn-1 Ei-1.
-        let hash = epoch_id_i_2_block_last.next_epoch_id().0.to_string();
+        let hash = CryptoHash(epoch_id_i_2_block_last.next_epoch_id().0.0);
         println!("search for: {}", hash);
-        let path = format!("{PATH}/{hash}/hash.json");
-        let epoch_id_i_3_hash_last: String = fs::read_to_string(path).expect("Hash reading error.");
+        let epoch_id_i_3_hash_last: String = load_stored_hash(&store, &profile, &hash)?.to_string();
 
         let input = client.prepare_input(
             &epoch_id_i_hash_0.to_string(),
@@ -147,21 +150,17 @@ async fn main() -> anyhow::Result<()> {
         println!("Height B_0: {:?}", output.currentBlockHashHeight);
 
 
-        // Save last known height.
+        // Save the receipt of both blocks. B0 is stored last, its height is the last known height.
+        ensure!(
+            previous_hash == epoch_id_i_1_hash_last && current_hash == epoch_id_i_hash_0,
+            "The receipt proves blocks {} & {} instead of the loaded blocks",
+            previous_hash,
+            current_hash
+        );
         let last_known_height: u64 = output.currentBlockHashHeight;
         println!("Last known height: {}", last_known_height);
-        let last_known_height_str: String = last_known_height.to_string();
-        let path = format!("{PATH}/last_known_height.json");
-        fs::write(path, last_known_height_str).expect("hash writing error");
-
-        // Save hashes to files.
-        let hash = previous_hash.to_string();
-        let _ = fs::create_dir_all(format!("{PATH}/{hash}"));
-        let path = format!("{PATH}/{hash}/hash.json");
-        fs::write(path.clone(), hash).expect("hash writing error");
-        let hash = current_hash.to_string();
-        let _ = fs::create_dir_all(format!("{PATH}/{hash}"));
-        let path = format!("{PATH}/{hash}/hash.json");
-        fs::write(path.clone(), hash).expect("hash writing error");
+        let artifacts = ReceiptArtifacts::from_receipt(&receipt)?;
+        store.put(&ProofRecord::new(ProofKind::Epoch, &epoch_id_i_1_block_last), &artifacts)?;
+        store.put(&ProofRecord::new(ProofKind::Epoch, &epoch_id_i_block_0), &artifacts)?;
     }
 }
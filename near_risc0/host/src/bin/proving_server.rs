@@ -9,6 +9,7 @@ use std::time::{Duration, Instant};
 use clap::Parser;
 use host::service::{generate_epoch_proof, generate_random_proof};
 use host::types::{EpochProvingTask,RandomProvingTask};
+use lib::network::NetworkProfile;
 use log::{error, info};
 
 use axum::body::Body;
@@ -31,6 +32,7 @@ struct Cli {
 #[derive(Clone)]
 struct ServerState {
     active_requests: Arc<AtomicUsize>,
+    profile: Arc<NetworkProfile>,
 }
 
 #[tokio::main]
@@ -44,6 +46,7 @@ async fn main() -> anyhow::Result<()> {
 
     let state = ServerState {
         active_requests: Arc::new(AtomicUsize::new(0)),
+        profile: Arc::new(NetworkProfile::from_env()?),
     };
 
     let app = Router::new()
@@ -67,7 +70,7 @@ async fn main() -> anyhow::Result<()> {
 }
 
 async fn epoch_proof(
-    State(_state): State<ServerState>,
+    State(state): State<ServerState>,
     request: axum::extract::Request,
 ) -> Result<impl IntoResponse, StatusCode> {
     let bytes = axum::body::to_bytes(request.into_body(), usize::MAX)
@@ -85,7 +88,7 @@ async fn epoch_proof(
 
     let res = {
         info!("Start proving epoch block");
-        generate_epoch_proof(&payload).await.map_err(|err| {
+        generate_epoch_proof(&payload, &state.profile).await.map_err(|err| {
             error!("Failed to generate epoch change proof: {err}");
             StatusCode::INTERNAL_SERVER_ERROR
         })
@@ -110,7 +113,7 @@ async fn epoch_proof(
 }
 
 async fn random_proof(
-    State(_state): State<ServerState>,
+    State(state): State<ServerState>,
     request: axum::extract::Request,
 ) -> Result<impl IntoResponse, StatusCode> {
     let bytes = axum::body::to_bytes(request.into_body(), usize::MAX)
@@ -128,7 +131,7 @@ async fn random_proof(
 
     let res = {
         info!("Start proving epoch block");
-        generate_random_proof(&payload).await.map_err(|err| {
+        generate_random_proof(&payload, &state.profile).await.map_err(|err| {
             error!("Failed to generate epoch change proof: {err}");
             StatusCode::INTERNAL_SERVER_ERROR
         })
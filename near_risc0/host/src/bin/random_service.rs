@@ -4,6 +4,7 @@ use async_nats::jetstream::stream::{ConsumerLimits, RetentionPolicy};
 use futures::stream::StreamExt;
 use host::service::generate_random_proof;
 use host::types::{RandomProvingResult, RandomProvingTask};
+use lib::network::NetworkProfile;
 use log::info;
 use serde_json::json;
 use std::env;
@@ -16,6 +17,8 @@ async fn main() -> Result<(), async_nats::Error> {
         .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
         .init();
 
+    let profile = NetworkProfile::from_env()?;
+
     //let nats_url = env::var("NATS_URL").expect("NATS_URL parameter missed").as_str();
     let nats_url = "nats://127.0.0.1:4222";
 
@@ -65,7 +68,7 @@ async fn main() -> Result<(), async_nats::Error> {
         let message = message.unwrap();
         if let Ok(payload) = serde_json::from_slice::<RandomProvingTask>(&message.payload) {
             info!("Received payload: {:?}", payload);
-            let result = generate_random_proof(&payload).await;
+            let result = generate_random_proof(&payload, &profile).await;
             let output = if result.is_err() {
                 RandomProvingResult {
                     epoch_id_i_block_hash: payload.epoch_id_i_hash_i,
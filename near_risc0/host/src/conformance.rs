@@ -0,0 +1,77 @@
+use alloy_sol_types::SolType;
+use anyhow::{bail, Result};
+use near_primitives_core::borsh::to_vec;
+use near_primitives_core::hash::CryptoHash;
+use risc0_zkvm::{default_executor, ExecutorEnv};
+
+use lib::conformance::{BlockVector, VectorKind, VectorOutputs, Verdict};
+use lib::types::types::{PublicValuesEpoch, PublicValuesRandom};
+use methods::NEAR_RISC0_ELF;
+
+/// Runs the vector through the guest in execute-only mode, without proving.
+/// A failed execution (the guest panics) means that the input is rejected.
+pub fn run_guest(vector: &BlockVector, epochs_dir: &str) -> Result<Verdict> {
+    let input = vector.input(epochs_dir)?;
+    let encoded = to_vec(&input)?;
+    let env = ExecutorEnv::builder().write_slice(&encoded).build()?;
+    let session = match default_executor().execute(env, NEAR_RISC0_ELF) {
+        Ok(session) => session,
+        Err(_) => return Ok(Verdict::reject()),
+    };
+    Ok(Verdict::accept(decode_journal(
+        vector.kind,
+        &session.journal.bytes,
+    )?))
+}
+
+/// Decodes hashes committed to the journal by the guest.
+pub fn decode_journal(kind: VectorKind, journal: &[u8]) -> Result<VectorOutputs> {
+    let outputs = match kind {
+        VectorKind::Random => {
+            let output = PublicValuesRandom::abi_decode(journal, true)?;
+            if output.selector != 0 {
+                bail!("Invalid selector {} of a random block.", output.selector);
+            }
+            VectorOutputs {
+                block_hash: CryptoHash(output.currentBlockHash.0),
+                epoch_hash: Some(CryptoHash(output.currentEpochHash.0)),
+                previous_epoch_hash: Some(CryptoHash(output.previousEpochHash.0)),
+                previous_block_hash: None,
+            }
+        }
+        VectorKind::Epoch => {
+            let output = PublicValuesEpoch::abi_decode(journal, true)?;
+            if output.selector != 1 {
+                bail!("Invalid selector {} of epoch blocks.", output.selector);
+            }
+            VectorOutputs {
+                block_hash: CryptoHash(output.currentBlockHash.0),
+                epoch_hash: None,
+                previous_epoch_hash: None,
+                previous_block_hash: Some(CryptoHash(output.previousBlockHash.0)),
+            }
+        }
+    };
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::conformance::{load_block_vectors, run_native, DEFAULT_CONFORMANCE_PATH};
+
+    use crate::conformance::*;
+
+    const DEFAULT_PATH: &str = "../../data/epochs";
+
+    #[test]
+    fn test_block_vectors_on_guest() {
+        for vector in load_block_vectors(DEFAULT_CONFORMANCE_PATH).expect("Failed to read vectors")
+        {
+            let verdict = run_guest(&vector, DEFAULT_PATH).expect("Failed to run vector");
+            assert_eq!(verdict, vector.verdict(), "{}", vector.name);
+            // The guest must agree with the native check it runs.
+            let native = run_native(&vector, DEFAULT_PATH).expect("Failed to run vector");
+            assert_eq!(verdict, native, "{}", vector.name);
+        }
+    }
+}
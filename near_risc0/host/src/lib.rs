@@ -1,4 +1,7 @@
 pub mod service;
 pub mod types;
 pub mod error;
-pub mod util;
\ No newline at end of file
+pub mod util;
+pub mod store;
+pub mod conformance;
+pub mod prover;
@@ -0,0 +1,356 @@
+use std::fs;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use near_primitives::block_header::BlockHeader;
+use near_primitives::views::BlockHeaderView;
+use near_primitives_core::borsh::to_vec;
+use near_primitives_core::hash::CryptoHash;
+use near_primitives_core::types::AccountId;
+use risc0_zkvm::{default_prover, ExecutorEnv, ProverOpts, Receipt, VerifierContext};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use lib::conformance::{VectorKind, VectorOutputs};
+use lib::loaders::load_block_hash;
+use lib::types::native::ProverInput;
+use lib::types::responses::{ValidatorOrdered, ValidatorsOrderedResponse};
+use lib::types::signature::PublicKey;
+use lib::types::types::{Block, BlockType, Validators};
+use lib::types::validators::ValidatorStake;
+use methods::{NEAR_RISC0_ELF, NEAR_RISC0_ID};
+
+use crate::conformance::decode_journal;
+
+/// An epoch block of `CommonInput` together with its hash stored in the contract.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchorBlockView {
+    pub header: BlockHeaderView,
+    pub stored_hash: CryptoHash,
+}
+
+/// The input of a proof shared with the plonky2 backend (`near_bft_finality::prover::CommonInput`),
+/// so both backends are compared on identical inputs.
+/// Blocks are Block_i, ..., Block_i+4 in ascending order of heights, Block_n-1 (Epoch_i-1),
+/// Block_n-1 (Epoch_i-3) and validators of Epoch_i-1 are set only for epoch transitions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommonInput {
+    pub ep1_first_block: AnchorBlockView,
+    pub ep2_last_block: AnchorBlockView,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ep3_last_block: Option<AnchorBlockView>,
+    pub blocks: Vec<BlockHeaderView>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ep1_last_block: Option<BlockHeaderView>,
+    pub validators: Vec<ValidatorOrdered>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validators_n_1: Option<Vec<ValidatorOrdered>>,
+}
+
+impl CommonInput {
+    /// Loads the input from the directory with the layout of data/epochs:
+    /// [Epoch_i, Epoch_i-1, Epoch_i-2] for a random block,
+    /// [Epoch_i, Epoch_i-1, Epoch_i-2, Epoch_i-3] for the epoch transition.
+    pub fn load(epochs_dir: &str, epoch_ids: &[&str]) -> Result<Self> {
+        let (epoch_id_i, epoch_id_i_1, epoch_id_i_2, epoch_id_i_3) = match epoch_ids {
+            [i, i_1, i_2] => (*i, *i_1, *i_2, None),
+            [i, i_1, i_2, i_3] => (*i, *i_1, *i_2, Some(*i_3)),
+            _ => bail!("Expected 3 or 4 epoch ids, got {}", epoch_ids.len()),
+        };
+        let prefix = match epoch_id_i_3 {
+            Some(_) => "block-",
+            None => "random-",
+        };
+        let blocks = (0..5)
+            .map(|i| {
+                read_json(&format!(
+                    "{}/{}/{}{}.json",
+                    epochs_dir, epoch_id_i, prefix, i
+                ))
+            })
+            .collect::<Result<Vec<BlockHeaderView>>>()?;
+        let ep1_last_block = match epoch_id_i_3 {
+            Some(_) => Some(read_json(&format!(
+                "{}/{}/block-last.json",
+                epochs_dir, epoch_id_i_1
+            ))?),
+            None => None,
+        };
+        let validators_n_1 = match epoch_id_i_3 {
+            Some(_) => Some(load_validators_ordered(epochs_dir, epoch_id_i_1)?),
+            None => None,
+        };
+        Ok(Self {
+            ep1_first_block: load_anchor_block(epochs_dir, epoch_id_i_1, "block-0.json")?,
+            ep2_last_block: load_anchor_block(epochs_dir, epoch_id_i_2, "block-last.json")?,
+            ep3_last_block: epoch_id_i_3
+                .map(|id| load_anchor_block(epochs_dir, id, "block-last.json"))
+                .transpose()?,
+            blocks,
+            ep1_last_block,
+            validators: load_validators_ordered(epochs_dir, epoch_id_i)?,
+            validators_n_1,
+        })
+    }
+
+    /// Block_n-1 (Epoch_i-1) is set only for epoch transitions.
+    pub fn kind(&self) -> VectorKind {
+        match self.ep1_last_block {
+            Some(_) => VectorKind::Epoch,
+            None => VectorKind::Random,
+        }
+    }
+
+    /// Converts the input into the input of the guest, see `loaders::set_blocks` for the order
+    /// of blocks.
+    pub fn to_prover_input(&self) -> Result<ProverInput> {
+        let mut epoch_blocks = vec![
+            anchor_block(&self.ep1_first_block)?,
+            anchor_block(&self.ep2_last_block)?,
+        ];
+        let mut blocks = self
+            .blocks
+            .iter()
+            .rev()
+            .map(block)
+            .collect::<Result<Vec<Block>>>()?;
+        let mut validators_n_1 = None;
+        if self.kind() == VectorKind::Epoch {
+            let ep3_last_block = self
+                .ep3_last_block
+                .as_ref()
+                .ok_or_else(|| anyhow!("Block_n-1 (Epoch_i-3) is not set"))?;
+            epoch_blocks.push(anchor_block(ep3_last_block)?);
+            if let Some(ep1_last_block) = &self.ep1_last_block {
+                blocks.push(block(ep1_last_block)?);
+            }
+            let validators = self
+                .validators_n_1
+                .as_ref()
+                .ok_or_else(|| anyhow!("Validators of Epoch_i-1 are not set"))?;
+            validators_n_1 = Some(validator_stakes(validators)?);
+        }
+        Ok(ProverInput {
+            epoch_blocks,
+            blocks,
+            validators: Validators {
+                validators_n: validator_stakes(&self.validators)?,
+                validators_n_1,
+            },
+        })
+    }
+
+    fn expect_kind(&self, kind: VectorKind) -> Result<()> {
+        if self.kind() != kind {
+            bail!(
+                "Expected the input of {:?} block, got {:?}",
+                kind,
+                self.kind()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T> {
+    let data = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+fn load_anchor_block(epochs_dir: &str, epoch_id: &str, file: &str) -> Result<AnchorBlockView> {
+    Ok(AnchorBlockView {
+        header: read_json(&format!("{}/{}/{}", epochs_dir, epoch_id, file))?,
+        // The hash stored in the contract.
+        stored_hash: load_block_hash(&format!("{}/{}_STORED/{}", epochs_dir, epoch_id, file))?,
+    })
+}
+
+fn load_validators_ordered(epochs_dir: &str, epoch_id: &str) -> Result<Vec<ValidatorOrdered>> {
+    let path = format!("{}/{}/validators.json", epochs_dir, epoch_id);
+    Ok(read_json::<ValidatorsOrderedResponse>(&path)?.result)
+}
+
+fn anchor_block(anchor: &AnchorBlockView) -> Result<Block> {
+    let header = BlockHeader::from(anchor.header.clone());
+    Ok(Block::try_from((
+        anchor.stored_hash,
+        header,
+        BlockType::BLOCK,
+    ))?)
+}
+
+fn block(view: &BlockHeaderView) -> Result<Block> {
+    let header = BlockHeader::from(view.clone());
+    let block_hash = CryptoHash(header.hash().0);
+    Ok(Block::try_from((block_hash, header, BlockType::RANDOM))?)
+}
+
+fn validator_stakes(validators: &[ValidatorOrdered]) -> Result<Vec<ValidatorStake>> {
+    validators
+        .iter()
+        .map(|validator| {
+            let account_id = AccountId::from_str(&validator.account_id)
+                .map_err(|e| anyhow!("Invalid account id {}: {}", validator.account_id, e))?;
+            let public_key = PublicKey::from_str(&validator.public_key)
+                .map_err(|e| anyhow!("Invalid public key {}: {}", validator.public_key, e))?;
+            let stake = validator
+                .stake
+                .parse()
+                .map_err(|e| anyhow!("Invalid stake {}: {}", validator.stake, e))?;
+            Ok(ValidatorStake::new_v1(account_id, public_key, stake))
+        })
+        .collect()
+}
+
+/// A backend proving NEAR blocks, mirrors `near_bft_finality::prover::Prover`.
+/// Proofs of both backends are verified into the same hashes.
+pub trait Prover {
+    type Proof;
+
+    /// Proves a randomly selected Block_i.
+    fn prove_random_block(&self, input: &CommonInput) -> Result<Self::Proof>;
+
+    /// Proves the transition to Epoch_i by Block_0 (Epoch_i) and Block_n-1 (Epoch_i-1).
+    fn prove_epoch_transition(&self, input: &CommonInput) -> Result<Self::Proof>;
+
+    /// Verifies the proof and returns hashes it commits to.
+    fn verify(&self, proof: &Self::Proof) -> Result<VectorOutputs>;
+}
+
+/// The receipt of the guest and the kind of the proven block to decode its journal.
+#[derive(Debug, Clone)]
+pub struct Risc0Proof {
+    pub kind: VectorKind,
+    pub receipt: Receipt,
+}
+
+/// The risc0 backend.
+pub struct Risc0Prover {
+    opts: ProverOpts,
+}
+
+impl Risc0Prover {
+    pub fn new(opts: ProverOpts) -> Self {
+        Self { opts }
+    }
+
+    /// Wraps receipts in Groth16 to be verified on-chain.
+    pub fn groth16() -> Self {
+        Self::new(ProverOpts::groth16())
+    }
+
+    fn prove(&self, input: &CommonInput) -> Result<Risc0Proof> {
+        let encoded = to_vec(&input.to_prover_input()?)?;
+        let env = ExecutorEnv::builder().write_slice(&encoded).build()?;
+        let receipt = default_prover()
+            .prove_with_ctx(env, &VerifierContext::default(), NEAR_RISC0_ELF, &self.opts)?
+            .receipt;
+        Ok(Risc0Proof {
+            kind: input.kind(),
+            receipt,
+        })
+    }
+}
+
+impl Default for Risc0Prover {
+    fn default() -> Self {
+        Self::new(ProverOpts::default())
+    }
+}
+
+impl Prover for Risc0Prover {
+    type Proof = Risc0Proof;
+
+    fn prove_random_block(&self, input: &CommonInput) -> Result<Risc0Proof> {
+        input.expect_kind(VectorKind::Random)?;
+        self.prove(input)
+    }
+
+    fn prove_epoch_transition(&self, input: &CommonInput) -> Result<Risc0Proof> {
+        input.expect_kind(VectorKind::Epoch)?;
+        self.prove(input)
+    }
+
+    fn verify(&self, proof: &Risc0Proof) -> Result<VectorOutputs> {
+        proof.receipt.verify(NEAR_RISC0_ID)?;
+        decode_journal(proof.kind, &proof.receipt.journal.bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::loaders::{set_blocks, set_validators};
+
+    use crate::prover::*;
+
+    const DEFAULT_PATH: &str = "../../data/epochs";
+    const COMMON_INPUT_PATH: &str = "../../data/common_input";
+    const EPOCH_IDS: [&str; 4] = [
+        "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t",
+        "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae",
+        "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi",
+        "89PT9SkLXB1FZHvW7EdQHxiSpm5ybuTCvjrGZWWhXMTz",
+    ];
+
+    fn expected_input(epoch_ids: &[&str]) -> ProverInput {
+        let epoch_id_i_3 = epoch_ids.get(3).map(|id| id.to_string());
+        let num_epoch_blocks = if epoch_id_i_3.is_some() { 3 } else { 2 };
+        let (epoch_blocks, blocks) = set_blocks(
+            DEFAULT_PATH,
+            epoch_ids[0].to_string(),
+            epoch_ids[1].to_string(),
+            epoch_ids[2].to_string(),
+            epoch_id_i_3,
+        )
+        .expect("Failed to set blocks");
+        let validators = set_validators(DEFAULT_PATH, num_epoch_blocks, epoch_ids[0], epoch_ids[1])
+            .expect("Failed to set validators");
+        ProverInput {
+            epoch_blocks,
+            blocks,
+            validators,
+        }
+    }
+
+    #[test]
+    fn test_common_input_of_random_block() {
+        let epoch_ids = &EPOCH_IDS[..3];
+        let input = CommonInput::load(DEFAULT_PATH, epoch_ids).expect("Failed to load input");
+        assert_eq!(input.kind(), VectorKind::Random);
+        let json = serde_json::to_string(&input).unwrap();
+        let input: CommonInput = serde_json::from_str(&json).unwrap();
+        let prover_input = input.to_prover_input().expect("Failed to convert input");
+        assert_eq!(prover_input, expected_input(epoch_ids));
+    }
+
+    #[test]
+    fn test_common_input_of_epoch_transition() {
+        let input = CommonInput::load(DEFAULT_PATH, &EPOCH_IDS).expect("Failed to load input");
+        assert_eq!(input.kind(), VectorKind::Epoch);
+        let json = serde_json::to_string(&input).unwrap();
+        let input: CommonInput = serde_json::from_str(&json).unwrap();
+        let prover_input = input.to_prover_input().expect("Failed to convert input");
+        assert_eq!(prover_input, expected_input(&EPOCH_IDS));
+        assert!(Risc0Prover::default().prove_random_block(&input).is_err());
+    }
+
+    /// Fixtures are read by `near_bft_finality::prover::CommonInput` too, so both backends agree
+    /// on the JSON of their inputs.
+    #[test]
+    fn test_common_input_fixtures() {
+        let fixtures = [
+            ("random_block.json", &EPOCH_IDS[..3]),
+            ("epoch_transition.json", &EPOCH_IDS[..]),
+        ];
+        for (file, epoch_ids) in fixtures {
+            let path = format!("{}/{}", COMMON_INPUT_PATH, file);
+            let data = fs::read_to_string(&path).expect("Failed to read fixture");
+            let fixture: serde_json::Value = serde_json::from_str(&data).unwrap();
+            let input = CommonInput::load(DEFAULT_PATH, epoch_ids).expect("Failed to load input");
+            assert_eq!(serde_json::to_value(&input).unwrap(), fixture, "{}", file);
+            let input: CommonInput = serde_json::from_value(fixture).unwrap();
+            let prover_input = input.to_prover_input().expect("Failed to convert input");
+            assert_eq!(prover_input, expected_input(epoch_ids), "{}", file);
+        }
+    }
+}
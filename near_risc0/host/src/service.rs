@@ -2,6 +2,7 @@ use crate::error::ServiceError;
 use crate::types::{EpochProvingResult, EpochProvingTask, RandomProvingResult, RandomProvingTask};
 use crate::util::encode_seal;
 use alloy_sol_types::SolType;
+use lib::network::NetworkProfile;
 use lib::rpc::JsonClient;
 use lib::types::types::{PublicValuesEpoch, PublicValuesRandom};
 use methods::{NEAR_RISC0_ELF, NEAR_RISC0_ID};
@@ -9,8 +10,11 @@ use near_primitives_core::borsh::to_vec;
 use near_primitives_core::hash::CryptoHash;
 use risc0_zkvm::{default_prover, ExecutorEnv, ProverOpts, VerifierContext};
 
-pub async fn generate_epoch_proof(task: &EpochProvingTask) -> Result<EpochProvingResult, ServiceError> {
-    let mut client = JsonClient::setup(None)
+pub async fn generate_epoch_proof(
+    task: &EpochProvingTask,
+    profile: &NetworkProfile,
+) -> Result<EpochProvingResult, ServiceError> {
+    let mut client = JsonClient::from_profile(profile.clone())
         .map_err(|_| ServiceError::InternalServiceError(String::from("Can't create client")))?;
     let input = client.prepare_input(
         task.epoch_id_i_block_hash.as_str(),
@@ -56,8 +60,11 @@ pub async fn generate_epoch_proof(task: &EpochProvingTask) -> Result<EpochProvin
     })
 }
 
-pub async fn generate_random_proof(task: &RandomProvingTask) -> Result<RandomProvingResult, ServiceError> {
-    let mut client = JsonClient::setup(None)
+pub async fn generate_random_proof(
+    task: &RandomProvingTask,
+    profile: &NetworkProfile,
+) -> Result<RandomProvingResult, ServiceError> {
+    let mut client = JsonClient::from_profile(profile.clone())
         .map_err(|_| ServiceError::InternalServiceError(String::from("Can't create client")))?;
     let input = client.prepare_input(
         task.epoch_id_i_hash_i.as_str(),
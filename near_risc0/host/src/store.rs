@@ -0,0 +1,85 @@
+use anyhow::{anyhow, Context};
+use lib::network::NetworkProfile;
+use near_primitives_core::hash::CryptoHash;
+pub use proof_store::{
+    Artifacts, FsProofStore, InMemoryProofStore, ProofKind, ProofRecord, ProofStore,
+};
+use risc0_zkvm::Receipt;
+
+/// The receipt of the guest that proved Block_n-1 (Epoch_i-1) and Block_0 (Epoch_i).
+///
+/// Receipts are kept in the proof store shared with near_bft_finality: `{root}/epoch/{hash}` has
+/// `receipt.json`, `journal.bin` and the stored hash of the block, `{root}/index.json` indexes them
+/// by hash, height and epoch_id. Both epoch blocks are stored with the same receipt.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReceiptArtifacts {
+    /// `Receipt` in json.
+    pub receipt: Vec<u8>,
+    /// The journal of the receipt, ABI-encoded `PublicValuesEpoch`.
+    pub journal: Vec<u8>,
+}
+
+impl ReceiptArtifacts {
+    pub fn from_receipt(receipt: &Receipt) -> anyhow::Result<Self> {
+        Ok(Self {
+            receipt: serde_json::to_vec(receipt)?,
+            journal: receipt.journal.bytes.clone(),
+        })
+    }
+
+    /// Deserializes the stored receipt.
+    pub fn receipt(&self) -> anyhow::Result<Receipt> {
+        serde_json::from_slice(&self.receipt).context("Error parsing stored receipt")
+    }
+}
+
+impl Artifacts for ReceiptArtifacts {
+    fn files(&self) -> Vec<(&'static str, &[u8])> {
+        vec![
+            ("receipt.json", &self.receipt),
+            ("journal.bin", &self.journal),
+        ]
+    }
+
+    fn from_files(
+        mut read: impl FnMut(&'static str) -> proof_store::Result<Vec<u8>>,
+    ) -> proof_store::Result<Self> {
+        Ok(Self {
+            receipt: read("receipt.json")?,
+            journal: read("journal.bin")?,
+        })
+    }
+}
+
+/// Returns the height of Block_0 of the last proven epoch. The first run on the network starts
+/// from Block_0 of the trusted epoch of the profile.
+pub fn load_last_known_height(
+    store: &dyn ProofStore<ReceiptArtifacts>,
+    profile: &NetworkProfile,
+) -> anyhow::Result<u64> {
+    match (store.latest(ProofKind::Epoch)?, &profile.genesis) {
+        (Some(record), _) => Ok(record.height),
+        (None, Some(genesis)) => Ok(genesis.height),
+        (None, None) => Err(anyhow!(
+            "No proven epochs and no genesis anchor in the profile of {}",
+            profile.chain_id
+        )),
+    }
+}
+
+/// Returns the hash of the epoch block stored in the contract. Blocks of the genesis anchor
+/// aren't proven, so their hashes are returned as is.
+pub fn load_stored_hash(
+    store: &dyn ProofStore<ReceiptArtifacts>,
+    profile: &NetworkProfile,
+    block_hash: &CryptoHash,
+) -> anyhow::Result<CryptoHash> {
+    match &profile.genesis {
+        Some(genesis) if genesis.contains(block_hash) => Ok(*block_hash),
+        _ => {
+            let stored_hash =
+                store.stored_hash(&near_primitives::hash::CryptoHash(block_hash.0))?;
+            Ok(CryptoHash(stored_hash.0))
+        }
+    }
+}
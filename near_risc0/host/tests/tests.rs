@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use lib::test_utils::{parse_block_hash, set_blocks, set_validators};
+    use lib::loaders::{parse_block_hash, set_blocks, set_validators};
     use lib::types::native::ProverInput;
     use methods::{NEAR_RISC0_ELF, NEAR_RISC0_ID};
     use near_primitives_core::borsh::to_vec;
@@ -66,6 +66,9 @@ mod tests {
 
         assert_eq!(previous_hash, epoch_id_i_1_hash_last);
         assert_eq!(current_hash, epoch_id_i_hash_0);
+        // Anchors of the light client state.
+        assert_eq!(CryptoHash(output.epochId.0), parse_block_hash(&epoch_id_i)?);
+        assert_eq!(CryptoHash(output.nextEpochId.0), epoch_id_i_1_hash_last);
 
         receipt.verify(NEAR_RISC0_ID).unwrap();
 
@@ -115,4 +118,54 @@ mod tests {
             .prove(env, NEAR_RISC0_ELF)
             .unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_epoch_receipt_store() -> anyhow::Result<()> {
+        use host::store::{
+            load_last_known_height, load_stored_hash, Artifacts, FsProofStore, ProofKind,
+            ProofRecord, ProofStore, ReceiptArtifacts,
+        };
+        use lib::network::{GenesisAnchor, NetworkProfile};
+
+        let root = env::temp_dir().join(format!("epoch_receipt_store_{}", std::process::id()));
+        let store = FsProofStore::<ReceiptArtifacts>::open(&root)?;
+        let mut profile = NetworkProfile::localnet();
+        assert!(load_last_known_height(&store, &profile).is_err());
+
+        // The first run starts from the genesis anchor, its blocks aren't proven.
+        let genesis = GenesisAnchor {
+            epoch_id: CryptoHash([4; 32]),
+            next_epoch_id: CryptoHash([5; 32]),
+            block_hash: CryptoHash([6; 32]),
+            bp_hash: CryptoHash([7; 32]),
+            height: 61,
+        };
+        profile.genesis = Some(genesis);
+        assert_eq!(load_last_known_height(&store, &profile)?, 61);
+        assert_eq!(load_stored_hash(&store, &profile, &genesis.block_hash)?, genesis.block_hash);
+
+        let artifacts = ReceiptArtifacts {
+            receipt: b"receipt".to_vec(),
+            journal: b"journal".to_vec(),
+        };
+        let record = |hash: u8, height: u64| ProofRecord {
+            kind: ProofKind::Epoch,
+            block_hash: near_primitives::hash::CryptoHash([hash; 32]),
+            height,
+            epoch_id: near_primitives::hash::CryptoHash([7; 32]),
+        };
+        store.put(&record(1, 120), &artifacts)?;
+        store.put(&record(2, 121), &artifacts)?;
+        assert_eq!(load_last_known_height(&store, &profile)?, 121);
+        assert_eq!(load_stored_hash(&store, &profile, &CryptoHash([1; 32]))?, CryptoHash([1; 32]));
+        assert!(load_stored_hash(&store, &profile, &CryptoHash([3; 32])).is_err());
+
+        // Receipts are read back from the reopened store.
+        let store = FsProofStore::<ReceiptArtifacts>::open(&root)?;
+        let dir = store.proof_dir(ProofKind::Epoch, &near_primitives::hash::CryptoHash([2; 32]));
+        assert_eq!(ReceiptArtifacts::read_dir(dir)?, artifacts);
+        assert_eq!(load_last_known_height(&store, &profile)?, 121);
+        std::fs::remove_dir_all(root)?;
+        Ok(())
+    }
+}
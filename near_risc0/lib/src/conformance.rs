@@ -0,0 +1,334 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::panic;
+
+use anyhow::{bail, Result};
+use near_primitives_core::hash::CryptoHash;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::mutation::Mutation;
+use crate::loaders::{set_blocks, set_validators};
+use crate::types::native::ProverInput;
+use crate::verification::check_input;
+
+/// Represents the directory with conformance vectors shared by the risc0 and plonky2 backends.
+pub const DEFAULT_CONFORMANCE_PATH: &str = "../../data/conformance";
+/// Represents the file with vectors of random and epoch blocks.
+pub const BLOCK_VECTORS_FILE: &str = "blocks.json";
+/// Represents the file with vectors of messages signed by validators.
+pub const SIGNED_MESSAGE_VECTORS_FILE: &str = "signed_messages.json";
+/// Represents the file with vectors of hashes of validators.
+pub const BP_HASH_VECTORS_FILE: &str = "bp_hashes.json";
+
+/// Represents the kind of the block proven by a vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorKind {
+    /// Block_i of Epoch_i, loaded from `random-*.json`.
+    Random,
+    /// Block_0 of Epoch_i and Block_n-1 of Epoch_i-1, loaded from `block-*.json`.
+    Epoch,
+}
+
+/// Represents the kind of the error that rejects an input in the plonky2 backend.
+/// The guest panics on any failed check, so the kind isn't checked here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorKind {
+    HashMismatch,
+    InsufficientStake,
+}
+
+/// Represents hashes committed to the journal for an accepted vector.
+/// Heights of epoch blocks are committed only by risc0, so they aren't part of vectors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorOutputs {
+    /// Hash of Block_i or Block_0.
+    pub block_hash: CryptoHash,
+    /// Hash of Block_n-1 (Epoch_i-2), epoch_id of Epoch_i, set for random blocks.
+    /// Epoch blocks the journal of epoch blocks (`PublicValuesEpoch`) is proven against aren't part of vectors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch_hash: Option<CryptoHash>,
+    /// Hash of Block_0 (Epoch_i-1), set for random blocks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_epoch_hash: Option<CryptoHash>,
+    /// Hash of Block_n-1 (Epoch_i-1), set for epoch blocks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_block_hash: Option<CryptoHash>,
+}
+
+impl VectorOutputs {
+    /// Collects hashes in the same way as the guest commits them to the journal.
+    pub fn from_input(input: &ProverInput) -> Result<Self> {
+        let (epoch_blocks, blocks) = (&input.epoch_blocks, &input.blocks);
+        if epoch_blocks.len() < 2 || blocks.len() < 5 {
+            bail!(
+                "Invalid number of blocks: {}, {}",
+                epoch_blocks.len(),
+                blocks.len()
+            );
+        }
+        let outputs = match blocks.get(5) {
+            None => Self {
+                block_hash: blocks[4].header.hash,
+                epoch_hash: Some(epoch_blocks[1].header.hash),
+                previous_epoch_hash: Some(epoch_blocks[0].header.hash),
+                previous_block_hash: None,
+            },
+            Some(previous_block) => Self {
+                block_hash: blocks[4].header.hash,
+                epoch_hash: None,
+                previous_epoch_hash: None,
+                previous_block_hash: Some(previous_block.header.hash),
+            },
+        };
+        Ok(outputs)
+    }
+}
+
+/// Represents the result of running a vector through a prover.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    /// Whether the input is accepted.
+    pub accept: bool,
+    /// Committed hashes, set only if the input is accepted.
+    pub outputs: Option<VectorOutputs>,
+}
+
+impl Verdict {
+    pub fn accept(outputs: VectorOutputs) -> Self {
+        Self {
+            accept: true,
+            outputs: Some(outputs),
+        }
+    }
+
+    pub fn reject() -> Self {
+        Self {
+            accept: false,
+            outputs: None,
+        }
+    }
+}
+
+/// Represents a conformance vector of a random or an epoch block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockVector {
+    /// Unique name of the vector.
+    pub name: String,
+    /// What the vector checks.
+    pub description: String,
+    /// Kind of the proven block.
+    pub kind: VectorKind,
+    /// [Epoch_i, Epoch_i-1, Epoch_i-2] or [Epoch_i, Epoch_i-1, Epoch_i-2, Epoch_i-3] for epoch blocks.
+    pub epoch_ids: Vec<String>,
+    /// Mutation applied to the loaded input.
+    pub mutation: Option<Mutation>,
+    /// Whether the input must be accepted.
+    pub accept: bool,
+    /// Kinds of errors, one of which must reject an input that isn't accepted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ErrorKind>,
+    /// Hashes that must be committed for an accepted input.
+    pub outputs: Option<VectorOutputs>,
+}
+
+impl BlockVector {
+    /// Returns the verdict expected from every backend.
+    pub fn verdict(&self) -> Verdict {
+        Verdict {
+            accept: self.accept,
+            outputs: self.outputs.clone(),
+        }
+    }
+
+    /// Loads the input of the vector from the directory with epochs and applies its mutation.
+    /// An error means that the vector itself is broken.
+    pub fn input(&self, epochs_dir: &str) -> Result<ProverInput> {
+        let (epoch_blocks, blocks, validators) = match (self.kind, self.epoch_ids.as_slice()) {
+            (VectorKind::Random, [i, i_1, i_2]) => {
+                let (epoch_blocks, blocks) =
+                    set_blocks(epochs_dir, i.clone(), i_1.clone(), i_2.clone(), None)?;
+                (epoch_blocks, blocks, set_validators(epochs_dir, 2, i, i_1)?)
+            }
+            (VectorKind::Epoch, [i, i_1, i_2, i_3]) => {
+                let (epoch_blocks, blocks) = set_blocks(
+                    epochs_dir,
+                    i.clone(),
+                    i_1.clone(),
+                    i_2.clone(),
+                    Some(i_3.clone()),
+                )?;
+                (epoch_blocks, blocks, set_validators(epochs_dir, 3, i, i_1)?)
+            }
+            _ => bail!(
+                "Vector {} of kind {:?} has {} epoch_ids.",
+                self.name,
+                self.kind,
+                self.epoch_ids.len()
+            ),
+        };
+        let mut input = ProverInput {
+            epoch_blocks,
+            blocks,
+            validators,
+        };
+        if let Some(mutation) = self.mutation {
+            mutation.apply(&mut input)?;
+        }
+        Ok(input)
+    }
+}
+
+/// Represents a vector of the message signed by validators, see `generate_signed_message`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedMessageVector {
+    pub name: String,
+    pub ch_height: u64,
+    pub nb_height: u64,
+    pub nb_prev_hash: CryptoHash,
+    /// Hex-encoded message.
+    pub message: String,
+}
+
+/// Represents a vector of the hash of validators stored in `{epoch_id}/validators.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BpHashVector {
+    pub epoch_id: String,
+    pub bp_hash: CryptoHash,
+}
+
+fn load_vectors<T: DeserializeOwned>(dir: &str, file: &str) -> Result<Vec<T>> {
+    let file = File::open(format!("{}/{}", dir, file))?;
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+pub fn load_block_vectors(dir: &str) -> Result<Vec<BlockVector>> {
+    load_vectors(dir, BLOCK_VECTORS_FILE)
+}
+
+pub fn load_signed_message_vectors(dir: &str) -> Result<Vec<SignedMessageVector>> {
+    load_vectors(dir, SIGNED_MESSAGE_VECTORS_FILE)
+}
+
+pub fn load_bp_hash_vectors(dir: &str) -> Result<Vec<BpHashVector>> {
+    load_vectors(dir, BP_HASH_VECTORS_FILE)
+}
+
+/// Runs the vector through `check_input`, which is the same check the guest does before
+/// committing the journal. A panic of `check_input` means that the input is rejected.
+pub fn run_native(vector: &BlockVector, epochs_dir: &str) -> Result<Verdict> {
+    let input = vector.input(epochs_dir)?;
+    if panic::catch_unwind(|| check_input(&input)).is_err() {
+        return Ok(Verdict::reject());
+    }
+    Ok(Verdict::accept(VectorOutputs::from_input(&input)?))
+}
+
+#[cfg(test)]
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::conformance::*;
+    use crate::mutation::ALL_MUTATIONS;
+    use crate::loaders::load_validators;
+    use crate::verification::{compute_bp_hash, generate_signed_message};
+
+    const DEFAULT_PATH: &str = "../../data/epochs";
+
+    #[test]
+    fn test_block_vectors_are_consistent() {
+        let vectors = load_block_vectors(DEFAULT_CONFORMANCE_PATH).expect("Failed to read vectors");
+        let names: HashSet<&str> = vectors.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(
+            names.len(),
+            vectors.len(),
+            "Names of vectors aren't unique."
+        );
+        for vector in vectors.iter() {
+            let input = vector.input(DEFAULT_PATH).expect("Failed to load input");
+            assert_eq!(vector.accept, vector.outputs.is_some(), "{}", vector.name);
+            if let Some(outputs) = &vector.outputs {
+                assert!(vector.mutation.is_none(), "{}", vector.name);
+                let expected = VectorOutputs::from_input(&input).expect("Invalid input");
+                assert_eq!(outputs, &expected, "{}", vector.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_block_vectors_on_check_input() {
+        for vector in load_block_vectors(DEFAULT_CONFORMANCE_PATH).expect("Failed to read vectors")
+        {
+            let verdict = run_native(&vector, DEFAULT_PATH).expect("Failed to run vector");
+            assert_eq!(verdict, vector.verdict(), "{}", vector.name);
+        }
+    }
+
+    /// Vectors are shared with near_bft_finality, which has its own definitions of them. Both crates
+    /// check that their definitions read and write vector files without changes, so they agree on them.
+    #[test]
+    fn test_vector_files_round_trip() {
+        fn round_trip<T: Serialize + DeserializeOwned>(file: &str) {
+            let path = format!("{}/{}", DEFAULT_CONFORMANCE_PATH, file);
+            let file = File::open(&path).expect("Failed to open vectors");
+            let json: serde_json::Value =
+                serde_json::from_reader(BufReader::new(file)).expect("Failed to read vectors");
+            let vectors: Vec<T> =
+                serde_json::from_value(json.clone()).expect("Failed to parse vectors");
+            let written = serde_json::to_value(&vectors).expect("Failed to write vectors");
+            assert_eq!(written, json, "{}", path);
+        }
+        round_trip::<BlockVector>(BLOCK_VECTORS_FILE);
+        round_trip::<SignedMessageVector>(SIGNED_MESSAGE_VECTORS_FILE);
+        round_trip::<BpHashVector>(BP_HASH_VECTORS_FILE);
+    }
+
+    #[test]
+    fn test_block_vectors_cover_every_mutation() {
+        let vectors = load_block_vectors(DEFAULT_CONFORMANCE_PATH).expect("Failed to read vectors");
+        for mutation in ALL_MUTATIONS {
+            assert!(
+                vectors.iter().any(|v| v.mutation == Some(mutation)),
+                "No vector of {:?}",
+                mutation
+            );
+        }
+    }
+
+    #[test]
+    fn test_signed_message_vectors() {
+        let vectors =
+            load_signed_message_vectors(DEFAULT_CONFORMANCE_PATH).expect("Failed to read vectors");
+        for vector in vectors {
+            let message =
+                generate_signed_message(vector.ch_height, vector.nb_height, vector.nb_prev_hash);
+            let expected = hex_decode(&vector.message);
+            assert_eq!(message, expected, "{}", vector.name);
+        }
+    }
+
+    #[test]
+    fn test_bp_hash_vectors() {
+        for vector in
+            load_bp_hash_vectors(DEFAULT_CONFORMANCE_PATH).expect("Failed to read vectors")
+        {
+            let path = format!("{}/{}/validators.json", DEFAULT_PATH, vector.epoch_id);
+            let validators = load_validators(&path).expect("Failed to read validators");
+            assert_eq!(
+                compute_bp_hash(&validators),
+                vector.bp_hash,
+                "{}",
+                vector.epoch_id
+            );
+        }
+    }
+
+    fn hex_decode(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..(i + 2)], 16).expect("Invalid hex"))
+            .collect()
+    }
+}
@@ -1,8 +1,17 @@
 pub mod types;
 pub mod verification;
 
-#[cfg(feature = "test-utils")]
-pub mod test_utils;
+#[cfg(feature = "non-zkvm")]
+pub mod network;
+
+#[cfg(feature = "non-zkvm")]
+pub mod loaders;
+
+#[cfg(feature = "non-zkvm")]
+pub mod mutation;
+
+#[cfg(feature = "non-zkvm")]
+pub mod conformance;
 
 #[cfg(feature = "rpc")]
 pub mod rpc;
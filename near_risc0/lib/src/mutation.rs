@@ -0,0 +1,220 @@
+use anyhow::{anyhow, ensure, Result};
+use near_primitives_core::borsh;
+use near_primitives_core::hash::hash;
+use serde::{Deserialize, Serialize};
+
+use crate::types::native::ProverInput;
+use crate::types::types::{BLOCK_HEIGHT_BYTES, PK_HASH_BYTES, SIG_BYTES, TYPE_BYTE};
+
+/// Represents the index of Block_i+1 in `ProverInput::blocks`.
+const BLOCK_I_1: usize = 3;
+/// Represents the index of Block_i/Block_0 in `ProverInput::blocks`.
+const BLOCK_I: usize = 4;
+/// Represents the index of Block_n-1 (Epoch_i-2) in `ProverInput::epoch_blocks`.
+const EP2_LAST_BLOCK: usize = 1;
+/// Represents the offset of epoch_id in a serialized header: type, prev_hash and height precede it.
+const EPOCH_ID_OFFSET: usize = TYPE_BYTE + PK_HASH_BYTES + BLOCK_HEIGHT_BYTES;
+
+/// Represents a targeted mutation of a valid proving input. Every mutation must be rejected by
+/// `check_input`. The plonky2 prover is tested against the same list of mutations, see
+/// `near_bft_finality::mutation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mutation {
+    /// Flips one byte of the first approval of Block_i+1, both in its header and in its data.
+    FlipApprovalByte,
+    /// Swaps the first two validators of Epoch_i.
+    SwapValidators,
+    /// Adds one to the stake of the last validator of Epoch_i.
+    ChangeStake,
+    /// Replaces epoch_id of Block_i with its next_epoch_id, both in its header and in its data.
+    ReplaceEpochId,
+    /// Replaces prev_hash of Block_i+1, so that it doesn't point to Block_i.
+    BreakPrevHash,
+    /// Drops approvals of Block_i+1 until their stake is below 2/3 of the total stake.
+    DropApprovals,
+    /// Removes the last byte of inner_rest of Block_i.
+    TruncateInnerRest,
+    /// Flips one byte of the stored hash of Block_n-1 (Epoch_i-2).
+    ReplaceStoredHash,
+}
+
+/// Represents all mutations in the order they are applied by tests.
+pub const ALL_MUTATIONS: [Mutation; 8] = [
+    Mutation::FlipApprovalByte,
+    Mutation::SwapValidators,
+    Mutation::ChangeStake,
+    Mutation::ReplaceEpochId,
+    Mutation::BreakPrevHash,
+    Mutation::DropApprovals,
+    Mutation::TruncateInnerRest,
+    Mutation::ReplaceStoredHash,
+];
+
+impl Mutation {
+    /// Applies the mutation to the input of a random or an epoch block.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input doesn't contain the data to mutate, e.g. approvals of Block_i+1.
+    pub fn apply(&self, input: &mut ProverInput) -> Result<()> {
+        ensure!(
+            input.blocks.len() > BLOCK_I && input.epoch_blocks.len() > EP2_LAST_BLOCK,
+            "Input doesn't contain Block_i or Block_n-1 (Epoch_i-2)"
+        );
+        match self {
+            Mutation::FlipApprovalByte => {
+                let block = &mut input.blocks[BLOCK_I_1];
+                let approval = block
+                    .header
+                    .approvals
+                    .as_mut()
+                    .and_then(|approvals| approvals.iter_mut().flatten().next())
+                    .ok_or_else(|| anyhow!("No approvals of Block_i+1"))?;
+                let mut signature = borsh::to_vec(&**approval)?;
+                let pos = find(&block.data, &signature)
+                    .ok_or_else(|| anyhow!("Approval is not found in data of Block_i+1"))?;
+                signature[TYPE_BYTE] ^= 1;
+                block.data[pos + TYPE_BYTE] ^= 1;
+                **approval = borsh::from_slice(&signature)?;
+            }
+            Mutation::SwapValidators => {
+                let validators = &mut input.validators.validators_n;
+                ensure!(validators.len() > 1, "Less than two validators");
+                validators.swap(0, 1);
+            }
+            Mutation::ChangeStake => {
+                let validator = input
+                    .validators
+                    .validators_n
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("No validators"))?;
+                *validator.stake_mut() += 1;
+            }
+            Mutation::ReplaceEpochId => {
+                let block = &mut input.blocks[BLOCK_I];
+                let next_epoch_id = block
+                    .header
+                    .next_epoch_id
+                    .ok_or_else(|| anyhow!("No next_epoch_id of Block_i"))?;
+                ensure!(
+                    block.header.epoch_id != Some(next_epoch_id),
+                    "epoch_id and next_epoch_id of Block_i are equal"
+                );
+                block.header.epoch_id = Some(next_epoch_id);
+                block.data[EPOCH_ID_OFFSET..(EPOCH_ID_OFFSET + PK_HASH_BYTES)]
+                    .copy_from_slice(&next_epoch_id.0);
+            }
+            Mutation::BreakPrevHash => {
+                let block = &mut input.blocks[BLOCK_I_1];
+                let prev_hash = block
+                    .header
+                    .prev_hash
+                    .ok_or_else(|| anyhow!("No prev_hash of Block_i+1"))?;
+                let prev_hash = hash(&prev_hash.0);
+                block.header.prev_hash = Some(prev_hash);
+                block.data[TYPE_BYTE..(TYPE_BYTE + PK_HASH_BYTES)].copy_from_slice(&prev_hash.0);
+            }
+            Mutation::DropApprovals => {
+                let validators = &input.validators.validators_n;
+                let approvals = input.blocks[BLOCK_I_1]
+                    .header
+                    .approvals
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("No approvals of Block_i+1"))?;
+                let total_stake: u128 = validators
+                    .iter()
+                    .take(approvals.len())
+                    .map(|validator| validator.stake())
+                    .sum();
+                let mut approved_stake: u128 = approvals
+                    .iter()
+                    .zip(validators)
+                    .filter(|(approval, _)| approval.is_some())
+                    .map(|(_, validator)| validator.stake())
+                    .sum();
+                for (approval, validator) in approvals.iter_mut().zip(validators) {
+                    if approved_stake * 3 < total_stake * 2 {
+                        break;
+                    }
+                    if approval.take().is_some() {
+                        approved_stake -= validator.stake();
+                    }
+                }
+            }
+            Mutation::TruncateInnerRest => {
+                let data = &mut input.blocks[BLOCK_I].data;
+                ensure!(
+                    data.len() > EPOCH_ID_OFFSET + TYPE_BYTE + SIG_BYTES,
+                    "Data of Block_i is too short"
+                );
+                data.remove(data.len() - TYPE_BYTE - SIG_BYTES - 1);
+            }
+            Mutation::ReplaceStoredHash => {
+                input.epoch_blocks[EP2_LAST_BLOCK].header.hash.0[0] ^= 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns a copy of the input with the mutation applied.
+pub fn mutate(input: &ProverInput, mutation: Mutation) -> Result<ProverInput> {
+    let mut mutated = input.clone();
+    mutation.apply(&mut mutated)?;
+    Ok(mutated)
+}
+
+/// Returns the position of `needle` in `data`.
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use std::panic;
+
+    use crate::mutation::*;
+    use crate::loaders::{set_blocks, set_validators};
+    use crate::verification::check_input;
+
+    const DEFAULT_PATH: &str = "../../data/epochs";
+
+    fn valid_input() -> ProverInput {
+        let epoch_id_i = "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae".to_string();
+        let epoch_id_i_1 = "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi".to_string();
+        let epoch_id_i_2 = "89PT9SkLXB1FZHvW7EdQHxiSpm5ybuTCvjrGZWWhXMTz".to_string();
+
+        let (epoch_blocks, blocks) = set_blocks(
+            DEFAULT_PATH,
+            epoch_id_i.clone(),
+            epoch_id_i_1.clone(),
+            epoch_id_i_2,
+            None,
+        )
+        .expect("Failed to read expected test data");
+        let validators = set_validators(DEFAULT_PATH, 2, &epoch_id_i, &epoch_id_i_1)
+            .expect("Failed to read expected test data");
+        ProverInput {
+            epoch_blocks,
+            blocks,
+            validators,
+        }
+    }
+
+    #[test]
+    fn test_check_input_accepts_valid_input() {
+        check_input(&valid_input());
+    }
+
+    #[test]
+    fn test_check_input_rejects_every_mutation() {
+        let input = valid_input();
+        for mutation in ALL_MUTATIONS {
+            let mutated = mutate(&input, mutation).expect("Error applying mutation.");
+            assert_ne!(mutated, input, "{:?} doesn't change the input", mutation);
+            let result = panic::catch_unwind(|| check_input(&mutated));
+            assert!(result.is_err(), "{:?} is accepted by check_input", mutation);
+        }
+    }
+}
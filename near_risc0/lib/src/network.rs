@@ -0,0 +1,173 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, ensure, Result};
+use near_primitives_core::hash::CryptoHash;
+use serde::{Deserialize, Serialize};
+
+use crate::types::types::EPOCH_DURATION;
+
+/// Path to the JSON file of the network profile.
+pub const NETWORK_CONFIG_ENV: &str = "NETWORK_CONFIG";
+/// Name of a built-in network profile: mainnet, testnet or localnet.
+pub const NEAR_NETWORK_ENV: &str = "NEAR_NETWORK";
+/// Overrides the RPC endpoint of the network profile.
+pub const NEAR_RPC_ENV: &str = "NEAR_RPC";
+/// Directory with JSON files of built-in network profiles.
+pub const DEFAULT_NETWORKS_PATH: &str = "../../data/networks";
+
+/// The trusted epoch the chain of proofs starts from. Its epoch blocks are never proven,
+/// so the epoch follower stores their hashes as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisAnchor {
+    /// epoch_id of Epoch_i, hash of Block_n-1 (Epoch_i-2).
+    pub epoch_id: CryptoHash,
+    /// epoch_id of Epoch_i+1, hash of Block_n-1 (Epoch_i-1).
+    pub next_epoch_id: CryptoHash,
+    /// Hash of Block_0 (Epoch_i).
+    pub block_hash: CryptoHash,
+    /// next_bp_hash of Block_0 (Epoch_i), hash of validators of Epoch_i+1.
+    pub bp_hash: CryptoHash,
+    /// Height of Block_0 (Epoch_i), the first last known height of the epoch follower.
+    pub height: u64,
+}
+
+impl GenesisAnchor {
+    /// Returns true if the block is one of the anchored epoch blocks.
+    pub fn contains(&self, block_hash: &CryptoHash) -> bool {
+        [self.epoch_id, self.next_epoch_id, self.block_hash].contains(block_hash)
+    }
+}
+
+/// Parameters of a NEAR network the prover runs against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkProfile {
+    /// Chain id of the network, e.g. `mainnet`.
+    pub chain_id: String,
+    /// Number of heights in an epoch.
+    pub epoch_length: u64,
+    /// RPC endpoint for recent blocks.
+    pub rpc_url: String,
+    /// Archival RPC endpoint for old blocks and validators.
+    pub archival_rpc_url: String,
+    /// The trusted epoch the chain of proofs starts from.
+    #[serde(default)]
+    pub genesis: Option<GenesisAnchor>,
+}
+
+impl Default for NetworkProfile {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+impl NetworkProfile {
+    pub fn mainnet() -> Self {
+        Self {
+            chain_id: "mainnet".to_string(),
+            epoch_length: EPOCH_DURATION,
+            rpc_url: "https://rpc.mainnet.near.org".to_string(),
+            archival_rpc_url: "https://archival-rpc.mainnet.near.org".to_string(),
+            genesis: None,
+        }
+    }
+
+    pub fn testnet() -> Self {
+        Self {
+            chain_id: "testnet".to_string(),
+            epoch_length: 43_200,
+            rpc_url: "https://rpc.testnet.near.org".to_string(),
+            archival_rpc_url: "https://archival-rpc.testnet.near.org".to_string(),
+            genesis: None,
+        }
+    }
+
+    /// A local node started by `neard init`: no separate archival node and short epochs.
+    pub fn localnet() -> Self {
+        Self {
+            chain_id: "localnet".to_string(),
+            epoch_length: 500,
+            rpc_url: "http://127.0.0.1:3030".to_string(),
+            archival_rpc_url: "http://127.0.0.1:3030".to_string(),
+            genesis: None,
+        }
+    }
+
+    /// Returns the built-in profile by the chain id.
+    pub fn named(chain_id: &str) -> Result<Self> {
+        match chain_id {
+            "mainnet" => Ok(Self::mainnet()),
+            "testnet" => Ok(Self::testnet()),
+            "localnet" => Ok(Self::localnet()),
+            _ => Err(anyhow!(
+                "Unknown network {}, expected mainnet, testnet or localnet.",
+                chain_id
+            )),
+        }
+    }
+
+    /// Loads the profile from the JSON file, e.g. `data/networks/localnet.json`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let profile: Self = serde_json::from_slice(&fs::read(path)?)?;
+        ensure!(
+            profile.epoch_length > 0,
+            "Epoch length of {} must be positive.",
+            profile.chain_id
+        );
+        Ok(profile)
+    }
+
+    /// Returns the profile from the file in `NETWORK_CONFIG`, the built-in profile named in
+    /// `NEAR_NETWORK` or mainnet. `NEAR_RPC` overrides the RPC endpoint of the profile.
+    pub fn from_env() -> Result<Self> {
+        let mut profile = match (env::var(NETWORK_CONFIG_ENV), env::var(NEAR_NETWORK_ENV)) {
+            (Ok(path), _) => Self::load(path)?,
+            (_, Ok(chain_id)) => Self::named(&chain_id)?,
+            _ => Self::mainnet(),
+        };
+        if let Ok(url) = env::var(NEAR_RPC_ENV) {
+            profile.rpc_url = url;
+        }
+        Ok(profile)
+    }
+
+    /// Returns heights of Block_n-1 (Epoch_i) and Block_0 (Epoch_i+1) if no heights are skipped,
+    /// where `first_block_height` is the height of Block_0 (Epoch_i).
+    pub fn next_epoch_heights(&self, first_block_height: u64) -> (u64, u64) {
+        let next_first_block_height = first_block_height + self.epoch_length;
+        (next_first_block_height - 1, next_first_block_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::*;
+
+    #[test]
+    fn test_builtin_profiles_match_files() {
+        // near_bft_finality and the state proofs script parse the same files.
+        let mut chain_ids = vec![];
+        for entry in fs::read_dir(DEFAULT_NETWORKS_PATH).expect("Failed to read profiles") {
+            let path = entry.unwrap().path();
+            let profile = NetworkProfile::load(&path).expect("Failed to load profile");
+            assert_eq!(path.file_stem(), Some(profile.chain_id.as_ref()));
+            assert_eq!(profile, NetworkProfile::named(&profile.chain_id).unwrap());
+            chain_ids.push(profile.chain_id);
+        }
+        chain_ids.sort();
+        assert_eq!(chain_ids, ["localnet", "mainnet", "testnet"]);
+        assert!(NetworkProfile::named("betanet").is_err());
+    }
+
+    #[test]
+    fn test_next_epoch_heights() {
+        let profile = NetworkProfile {
+            epoch_length: 60,
+            ..NetworkProfile::localnet()
+        };
+        assert_eq!(profile.next_epoch_heights(61), (120, 121));
+    }
+}
@@ -7,7 +7,8 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use thiserror::Error;
 
-use crate::test_utils::parse_block_hash;
+use crate::network::NetworkProfile;
+use crate::loaders::parse_block_hash;
 use crate::types::native::ProverInput;
 use crate::types::responses::{BlockParamHeight, BlockParamString, BlockResponse, ValidatorsOrderedResponse};
 use crate::types::types::{Block, BlockType};
@@ -147,30 +148,49 @@ impl From<RpcError> for JsonClientError {
     }
 }
 
+/// Endpoints of mainnet. Other networks are set by `NetworkProfile`.
 pub const ARCHIVAL_RPC: &str = "https://archival-rpc.mainnet.near.org";
 pub const MAIN_NET_RPC: &str = "https://rpc.mainnet.near.org";
 
-const NUM_BLOCKS_EPOCH: u64 = 43_200;
-
 pub struct JsonClient {
     reqwest_client: Client,
     url: String,
+    profile: NetworkProfile,
 }
 
 impl JsonClient {
+    /// Creates the client for the network set in the environment (see `NetworkProfile::from_env`).
+    /// `url` overrides the RPC endpoint of the network.
     pub fn setup(url: Option<String>) -> anyhow::Result<Self> {
+        let mut client = Self::from_profile(NetworkProfile::from_env()?)?;
+        if let Some(url) = url {
+            client.set_url(&url);
+        }
+        Ok(client)
+    }
+
+    /// Creates the client for the RPC endpoint of the network.
+    pub fn from_profile(profile: NetworkProfile) -> anyhow::Result<Self> {
         Ok(
             JsonClient {
                 reqwest_client: Client::builder().danger_accept_invalid_certs(true).build()?,
-                url: if url.is_some() {
-                    url.unwrap()
-                } else {
-                    MAIN_NET_RPC.to_string()
-                },
+                url: profile.rpc_url.clone(),
+                profile,
             }
         )
     }
 
+    /// Creates the client for the archival RPC endpoint of the network.
+    pub fn archival(profile: NetworkProfile) -> anyhow::Result<Self> {
+        let mut client = Self::from_profile(profile)?;
+        client.url = client.profile.archival_rpc_url.clone();
+        Ok(client)
+    }
+
+    pub fn profile(&self) -> &NetworkProfile {
+        &self.profile
+    }
+
     fn set_url(&mut self, url: &str) {
         self.url = url.to_string();
     }
@@ -179,8 +199,9 @@ impl JsonClient {
         self.url.to_string()
     }
 
-    /// This function checks whether to use the MainNet or an archival node.
-    /// It compares the height of requested block against the latest optimistic block on MainNet.
+    /// This function checks whether to use the RPC or the archival node of the network.
+    /// It compares the height of requested block against the latest optimistic block
+    /// of the network.
     /// If the block lags behind by more than 4 epochs, it switches to an archival node.
     /// If the block is not found, it switches to the archival node.
     ///
@@ -223,14 +244,16 @@ impl JsonClient {
                     }
                 );
 
-                let latest_block_text = self.send_request(MAIN_NET_RPC, &optimistic_block_request).await?;
+                let latest_block_text =
+                    self.send_request(&self.profile.rpc_url, &optimistic_block_request).await?;
                 let latest_block_response: BlockResponse = serde_json::from_str(&latest_block_text)
                     .map_err(|e| JsonClientError::UnexpectedError(format!("Cannot serialize response: {}", e).to_string()))?;
                 let current_block_header = BlockHeader::from(current_block_response.result.header);
                 let latest_block_header = BlockHeader::from(latest_block_response.result.header);
 
-                if current_block_header.height() < latest_block_header.height() - NUM_BLOCKS_EPOCH * 4 {
-                    self.set_url(ARCHIVAL_RPC);
+                let num_blocks = self.profile.epoch_length * 4;
+                if current_block_header.height() < latest_block_header.height().saturating_sub(num_blocks) {
+                    self.set_url(&self.profile.archival_rpc_url.clone());
                 }
 
                 Ok(())
@@ -251,7 +274,7 @@ impl JsonClient {
                 // Assume the block is not present on MainNet. If the block hash is incorrect,
                 // the program will panic when trying to fetch the inputs required for proving,
                 // which may lead to unexpected behavior or crashes.
-                self.set_url(ARCHIVAL_RPC);
+                self.set_url(&self.profile.archival_rpc_url.clone());
 
                 Ok(())
             }
@@ -352,7 +375,7 @@ impl JsonClient {
         )
             .await?;
 
-        self.set_url(MAIN_NET_RPC);
+        self.set_url(&self.profile.rpc_url.clone());
 
         Ok(ProverInput {
             epoch_blocks,
@@ -391,7 +414,8 @@ impl JsonClient {
             }
         );
 
-        let response_text = self.send_request(ARCHIVAL_RPC, &validators_ordered_request).await?;
+        let response_text =
+            self.send_request(&self.profile.archival_rpc_url, &validators_ordered_request).await?;
         let validators_ordered_response: ValidatorsOrderedResponse = serde_json::from_str(&response_text)
             .map_err(|e| JsonClientError::UnexpectedError(format!("Cannot serialize response: {}", e).to_string()))?;
 
@@ -646,7 +670,7 @@ fn convert_validator_stake(
 #[cfg(all(test, feature = "rpc", feature = "test-utils"))]
 mod tests {
     use crate::rpc::{JsonClient, ARCHIVAL_RPC, MAIN_NET_RPC};
-    use crate::test_utils::{set_blocks, set_validators};
+    use crate::loaders::{set_blocks, set_validators};
     use crate::types::native::ProverInput;
 
     const DEFAULT_PATH: &str = "../../data/epochs";
@@ -2,7 +2,7 @@ use near_primitives_core::borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
 use crate::types::types::*;
 
-#[derive(Debug, Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq)]
 pub struct ProverInput{
     pub epoch_blocks: Vec<Block>,
     pub blocks: Vec<Block>,
@@ -14,7 +14,7 @@ pub struct ProverInput{
 mod tests {
     use near_primitives_core::borsh::{from_slice, to_vec};
     use crate::types::native::ProverInput;
-    use crate::test_utils::{set_blocks, set_validators};
+    use crate::loaders::{set_blocks, set_validators};
 
     const DEFAULT_PATH: &str = "../../data/epochs";
 
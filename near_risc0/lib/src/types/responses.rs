@@ -33,7 +33,7 @@ pub struct ValidatorsOrderedResponse {
 /// * `stake` - A string representing the stake amount of the validator.
 /// * `validator_stake_struct_version` - A string representing the version of the validator stake structure.
 ///
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ValidatorOrdered {
     pub account_id: String,
     pub public_key: String,
@@ -28,7 +28,8 @@ pub const PK_HASH_BYTES: usize = 32;
 pub const SIG_BYTES: usize = 64;
 /// Represents the size of a inner lite part of a block in bytes.
 pub const INNER_LITE_BYTES: usize = 208;
-/// Represents the lenght of one epoch.
+/// Represents the lenght of one epoch of mainnet,
+/// see `NetworkProfile::epoch_length` for other networks.
 pub const EPOCH_DURATION: u64 = 43200;
 
 /// Represents the data of a block header.
@@ -198,6 +199,20 @@ sol! {
     }
 }
 
+/// Represents the inner lite part of a block header. It is hashed into the block hash, so its fields
+/// are trusted once the hash of the block is checked.
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct InnerLite {
+    pub height: BlockHeight,
+    pub epoch_id: CryptoHash,
+    pub next_epoch_id: CryptoHash,
+    pub prev_state_root: CryptoHash,
+    pub prev_outcome_root: CryptoHash,
+    pub timestamp: u64,
+    pub next_bp_hash: CryptoHash,
+    pub block_merkle_root: CryptoHash,
+}
+
 sol! {
     struct PublicValuesEpoch{
         uint32 selector;
@@ -205,5 +220,13 @@ sol! {
         bytes32 previousBlockHash;
         uint64 currentBlockHashHeight;
         uint64 previousBlockHashHeight;
+        // epoch_id, next_epoch_id & next_bp_hash of B0, the new trusted epoch of light clients.
+        bytes32 epochId;
+        bytes32 nextEpochId;
+        bytes32 nextBpHash;
+        // Hashes of B_n-1 Epoch_i-3 & B0 Epoch_i-1, with epochId they are the trusted epoch
+        // the blocks are proven against.
+        bytes32 previousEpochId;
+        bytes32 previousEpochBlockHash;
     }
 }
@@ -1,7 +1,8 @@
+use crate::types::native::ProverInput;
 use crate::types::signature::Signature;
 use crate::types::types::{
-    Approval, ApprovalInner, Block, BlockType, Validators, INNER_LITE_BYTES, PK_HASH_BYTES,
-    SIG_BYTES, TYPE_BYTE,
+    Approval, ApprovalInner, Block, BlockType, InnerLite, PublicValuesEpoch, Validators,
+    INNER_LITE_BYTES, PK_HASH_BYTES, SIG_BYTES, TYPE_BYTE,
 };
 use crate::types::validators::ValidatorStake;
 use near_primitives_core::{
@@ -89,6 +90,15 @@ pub fn check_hashes(blocks: &[Block]) {
     }
 }
 
+/// Decodes the inner lite part of the block. Its fields are bound to the hash checked by `check_hashes`,
+/// unlike fields of `HeaderDataFields`.
+pub fn inner_lite(block: &Block) -> InnerLite {
+    borsh::from_slice(
+        &block.data[(TYPE_BYTE + PK_HASH_BYTES)..(TYPE_BYTE + PK_HASH_BYTES + INNER_LITE_BYTES)],
+    )
+    .expect("Invalid inner lite part.")
+}
+
 /// Check if heights of consecutive blocks are also consecutive.
 ///
 /// height_l - Height of leading block.
@@ -323,16 +333,173 @@ pub fn check_signatures(blocks: &[Block], validators: &Validators) {
     }
 }
 
+/// Returns the public values of epoch blocks B0 Epoch_i & B_n-1 Epoch_i-1, which hashes, prev_hash links
+/// and epoch_id are already checked. epoch_id, next_epoch_id, next_bp_hash & the height of B0 are read
+/// from its inner lite part, so they are bound to its hash.
+///
+/// # Panics
+///
+/// Panics if there are no epoch blocks or next_epoch_id of B0 isn't the hash of B_n-1.
+pub fn epoch_public_values(epoch_blocks: &[Block], blocks: &[Block]) -> PublicValuesEpoch {
+    assert_eq!(blocks.len(), 6, "blocks length must be 6 for epoch blocks.");
+    assert_eq!(epoch_blocks.len(), 3, "epoch_blocks length must be 3 for epoch blocks.");
+    let (b0, bn_1) = (&blocks[4], &blocks[5]);
+    let b0_lite = inner_lite(b0);
+    assert_eq!(
+        b0_lite.epoch_id, epoch_blocks[1].header.hash,
+        "Wrong epoch_id {} of B0 or hash of B_n-1 Epoch_i-2: {}.",
+        b0_lite.epoch_id, epoch_blocks[1].header.hash
+    );
+    assert_eq!(
+        b0_lite.next_epoch_id, bn_1.header.hash,
+        "Wrong next_epoch_id {} of B0 or hash of B_n-1: {}.",
+        b0_lite.next_epoch_id, bn_1.header.hash
+    );
+    PublicValuesEpoch {
+        selector: 1,
+        // Hash of B0.
+        currentBlockHash: b0.header.hash.0.into(),
+        // Hash of B_n-1.
+        previousBlockHash: bn_1.header.hash.0.into(),
+        // Height of B0.
+        currentBlockHashHeight: b0_lite.height,
+        // Height of B_n-1.
+        previousBlockHashHeight: inner_lite(bn_1).height,
+        epochId: b0_lite.epoch_id.0.into(),
+        nextEpochId: b0_lite.next_epoch_id.0.into(),
+        nextBpHash: b0_lite.next_bp_hash.0.into(),
+        // Hash of B_n-1 Epoch_i-3.
+        previousEpochId: epoch_blocks[2].header.hash.0.into(),
+        // Hash of B0 Epoch_i-1.
+        previousEpochBlockHash: epoch_blocks[0].header.hash.0.into(),
+    }
+}
+
+/// Checks the whole proving input: hashes, heights, finality of blocks, prev_hash links, epoch_id,
+/// next_bp_hash and signatures of approvals. The guest runs it before committing public values,
+/// so native tests of it test the guest checks.
+///
+/// # Panics
+///
+/// Panics if any check fails.
+pub fn check_input(input: &ProverInput) {
+    let ProverInput {
+        epoch_blocks,
+        blocks,
+        validators,
+    } = input;
+    // Check the length of the extracted data for epochs.
+    assert!(
+        (2..=3).contains(&epoch_blocks.len()),
+        "epoch_blocks length must be between 3 and 4."
+    );
+    // Check the length of the extracted data for blocks.
+    assert!(
+        (5..=6).contains(&blocks.len()),
+        "blocks length must be between 5 and 6."
+    );
+    // Check the length of the list of validators.
+    assert!(
+        !validators.validators_n.is_empty(),
+        "validators list must contain data."
+    );
+
+    // Check block hashes for epoch blocks.
+    check_hashes(epoch_blocks);
+
+    // Check block hashes for B4, B3, B2.
+    check_hashes(&blocks[0..3]);
+
+    // Check heights for B4, B3, B2.
+    let b4_height = blocks[0].header.height.expect("No height.");
+    let b3_height = blocks[1].header.height.expect("No height.");
+    let b2_height = blocks[2].header.height.expect("No height.");
+    check_heights(b4_height, b3_height, b2_height);
+
+    // Check last_ds_final_block, last_final_block for B4, B3, B2.
+    let b4_ds = blocks[0]
+        .header
+        .last_ds_final_hash
+        .as_ref()
+        .expect("No last_ds_final_block for B4.");
+
+    let b4_bft = blocks[0]
+        .header
+        .last_final_hash
+        .as_ref()
+        .expect("No last_final_block for B4.");
+
+    let b3_ds = blocks[1]
+        .header
+        .last_ds_final_hash
+        .as_ref()
+        .expect("No last_ds_final_block for B3.");
+
+    assert_eq!(
+        &blocks[2].header.hash, b3_ds,
+        "Incorrect hash: {} or ds: {} for B2",
+        blocks[2].header.hash, b3_ds
+    );
+
+    assert_eq!(
+        &blocks[2].header.hash, b4_bft,
+        "Incorrect hash: {} or bft: {} for B2",
+        blocks[2].header.hash, b4_bft
+    );
+
+    assert_eq!(
+        &blocks[1].header.hash, b4_ds,
+        "Incorrect hash: {} or ds: {} for B3",
+        blocks[1].header.hash, b4_ds
+    );
+
+    // Check hashes for B1, Bi/B0 and Bn-1 (optionally).
+    check_hashes(&blocks[3..]);
+
+    // Check prev_hash for all blocks.
+    check_prev_hashes(blocks);
+
+    // Check epoch_id for all blocks.
+    check_epoch_id(epoch_blocks, blocks);
+
+    // Check next_bp_hash.
+    check_bp_hash(epoch_blocks, validators);
+
+    // Check signatures.
+    check_signatures(blocks, validators);
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::rpc::{JsonClient, ARCHIVAL_RPC};
-    use crate::test_utils::*;
+    use crate::loaders::*;
     use crate::verification::*;
     use near_primitives_core::{
         hash::{hash, CryptoHash},
         types::MerkleHash,
     };
 
+    const DEFAULT_PATH: &str = "../../data/epochs";
+    const EPOCH_ID_I: &str = "HPi5yyZHZ91t5S4SPAAfEZwGYEqq5i6QjzXoVMi8ksae";
+    const EPOCH_ID_I_1: &str = "3JMehuv86nBynJ33VBUGAvfd9Ts8EfvytGJ8i8e45XPi";
+    const EPOCH_ID_I_2: &str = "89PT9SkLXB1FZHvW7EdQHxiSpm5ybuTCvjrGZWWhXMTz";
+
+    /// Loads epoch blocks and blocks [Block_i+4, ..., Block_i] of a random block of Epoch_i.
+    fn random_blocks() -> (Vec<Block>, Vec<Block>) {
+        set_blocks(
+            DEFAULT_PATH,
+            EPOCH_ID_I.to_string(),
+            EPOCH_ID_I_1.to_string(),
+            EPOCH_ID_I_2.to_string(),
+            None,
+        )
+        .expect("Failed to read test data")
+    }
+
+    /// Loads validators of Epoch_i.
+    fn random_validators() -> Validators {
+        set_validators(DEFAULT_PATH, 2, EPOCH_ID_I, EPOCH_ID_I_1).expect("Failed to read test data")
+    }
+
     #[test]
     fn test_compute_hash() {
         let random_bytes: Vec<u8> = (0..32).map(|_| rand::random::<u8>()).collect();
@@ -363,35 +530,15 @@ mod tests {
         let c_h = compute_inner_hash(&random_bytes1, &random_bytes2);
     }
 
-    #[tokio::test]
-    async fn test_check_hashes() {
-        let client = JsonClient::setup(None).unwrap();
-        let mut blocks = vec![];
-        let mut height: u64 = 121751508;
-        for i in 0..5 {
-            let result = client.load_block_by_height_from_rpc(height).await;
-            assert!(result.is_ok(), "Failed to load block from RPC");
-            let block = result.unwrap();
-            blocks.push(Block::try_from((block.0, block.1, BlockType::RANDOM)).expect("Error creating block."));
-            height += 1;
-        }
-
+    #[test]
+    fn test_check_hashes() {
+        let (_, blocks) = random_blocks();
         check_hashes(&blocks);
     }
 
-    #[tokio::test]
-    async fn test_check_prev_hashes() {
-        let client = JsonClient::setup(Some(ARCHIVAL_RPC.to_string())).unwrap();
-        let mut blocks = vec![];
-        let mut height: u64 = 121857713;
-        for i in 0..5 {
-            let result = client.load_block_by_height_from_rpc(height).await;
-            assert!(result.is_ok(), "Failed to load block from RPC");
-            let block = result.unwrap();
-            blocks.push(Block::try_from((block.0, block.1, BlockType::RANDOM)).expect("Error creating block."));
-            height += 1;
-        }
-
+    #[test]
+    fn test_check_prev_hashes() {
+        let (_, blocks) = random_blocks();
         check_prev_hashes(&blocks);
     }
 
@@ -436,89 +583,55 @@ mod tests {
         assert!(msg.len() == 17);
     }
 
-    #[tokio::test]
-    async fn test_check_bp_hash() {
-        let hash = "317HEkq9TQ6fJ9qkHhiy9MbbXgKbFgMZyqqRjDGbBoiz";
-        let mut client = JsonClient::setup(None).unwrap();
-        client
-            .check_rpc_correctness(hash)
-            .await
-            .unwrap();
-        let result = client
-            .set_validators_from_rpc(hash, None)
-            .await;
-        assert!(result.is_ok(), "Failed to load validators from RPC");
-        let validators = result.unwrap();
-
-        let hash = "Envut7DwFF4Gbjg5uHHFnQ9om9Zo5FK43H6outpRJveV";
-        client
-            .check_rpc_correctness(hash)
-            .await
-            .unwrap();
-        let result = client.load_block_by_hash_from_rpc(hash).await;
-        assert!(result.is_ok(), "Failed to load block from RPC");
-        let block = result.unwrap();
-
-        check_bp_hash(
-            &[Block::try_from((block.0, block.1, BlockType::RANDOM)).expect("Error creating block.")].to_vec(),
-            &validators
-        );
+    #[test]
+    fn test_check_bp_hash() {
+        let (epoch_blocks, _) = random_blocks();
+        check_bp_hash(&epoch_blocks, &random_validators());
     }
 
-    #[tokio::test]
-    async fn test_check_epoch_id() {
-        let client = JsonClient::setup(None).unwrap();
-        let mut blocks = vec![];
-        let mut height: u64 = 121857713;
-        for i in 0..5 {
-            let result = client.load_block_by_height_from_rpc(height).await;
-            assert!(result.is_ok(), "Failed to load block from RPC");
-            let block = result.unwrap();
-            blocks.push(Block::try_from((block.0, block.1, BlockType::RANDOM)).expect("Error creating block."));
-            height += 1;
-        }
-
-        let hash = "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t";
-        let mut client = JsonClient::setup(None).unwrap();
-        client
-            .check_rpc_correctness(hash)
-            .await
-            .unwrap();
-        let result = client.load_block_by_hash_from_rpc(hash).await;
-        assert!(result.is_ok(), "Failed to load block from RPC");
-        let block = result.unwrap();
-
-        let mut epoch_blocks = vec![];
-        epoch_blocks.push(Block::try_from((block.0, block.1, BlockType::BLOCK)).expect("Error creating block."));
-        epoch_blocks.push(epoch_blocks[0].clone());
-
+    #[test]
+    fn test_check_epoch_id() {
+        let (epoch_blocks, blocks) = random_blocks();
         check_epoch_id(&epoch_blocks, &blocks);
     }
 
-    #[tokio::test]
-    async fn test_check_signatures() {
-        let mut client = JsonClient::setup(None).unwrap();
-        let mut blocks = vec![];
-        let mut height: u64 = 121798939;
-        for i in 0..5 {
-            let result = client.load_block_by_height_from_rpc(height).await;
-            assert!(result.is_ok(), "Failed to load block from RPC");
-            let block = result.unwrap();
-            blocks.push(Block::try_from((block.0, block.1, BlockType::RANDOM)).expect("Error creating block."));
-            height += 1;
-        }
+    #[test]
+    fn test_epoch_public_values() {
+        let (epoch_blocks, blocks) = set_blocks(
+            DEFAULT_PATH,
+            "CRTZ7cQd77rvfS57Y7M36P1vLhran9HyQFEpTLxHRf9t".to_string(),
+            EPOCH_ID_I.to_string(),
+            EPOCH_ID_I_1.to_string(),
+            Some(EPOCH_ID_I_2.to_string()),
+        )
+        .expect("Failed to read test data");
+        check_hashes(&epoch_blocks);
+        check_hashes(&blocks);
+        let output = epoch_public_values(&epoch_blocks, &blocks);
+        let b0 = &blocks[4].header;
+        let bn_1_hash = parse_block_hash("4RjXBrNcu39wutFTuFpnRHgNqgHxLMcGBKNEQdtkSBhy").unwrap();
+        assert_eq!(
+            CryptoHash(output.currentBlockHash.0),
+            parse_block_hash("CbAHBGJ8VQot2m6KhH9PLasMgcDtkPJBfp9bjAEMJ8UK").unwrap()
+        );
+        assert_eq!(CryptoHash(output.previousBlockHash.0), bn_1_hash);
+        assert_eq!(CryptoHash(output.nextEpochId.0), bn_1_hash);
+        assert_eq!(Some(CryptoHash(output.epochId.0)), b0.epoch_id);
+        assert_eq!(Some(CryptoHash(output.nextBpHash.0)), b0.bp_hash);
+        assert_eq!(Some(output.currentBlockHashHeight), b0.height);
+        assert_eq!(
+            CryptoHash(output.previousEpochId.0),
+            epoch_blocks[2].header.hash
+        );
+        assert_eq!(
+            CryptoHash(output.previousEpochBlockHash.0),
+            epoch_blocks[0].header.hash
+        );
+    }
 
-        let hash = blocks[0].header.hash.to_string();
-        client
-            .check_rpc_correctness(&hash)
-            .await
-            .unwrap();
-        let result = client
-            .set_validators_from_rpc(&hash, None)
-            .await;
-        assert!(result.is_ok(), "Failed to load validators from RPC");
-        let validators = result.unwrap();
-
-        check_signatures(&blocks, &validators);
+    #[test]
+    fn test_check_signatures() {
+        let (_, blocks) = random_blocks();
+        check_signatures(&blocks, &random_validators());
     }
 }
@@ -10,8 +10,8 @@ use tokio::time::{sleep, Duration};
 use crate::proof_verifier::ProofVerifier;
 use crate::utils::{
     BlockParamBlockHeight, BlockParamString, BlockRequestOptionOne, BlockRequestOptionTwo,
-    BlockResponse, Config, ViewStateParams, ViewStateRequest, ViewStateResponseForProof,
-    ViewStateResponseForValues,
+    BlockResponse, Config, NetworkProfile, ViewStateParams, ViewStateRequest,
+    ViewStateResponseForProof, ViewStateResponseForValues,
 };
 use near_primitives::types::AccountId;
 use reqwest::{Client, Error};
@@ -34,13 +34,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         let account_id = AccountId::from_str(&*config.account).unwrap();
 
-        let rpc_url = match config.network {
-            0 => "https://rpc.testnet.near.org",
-            1 => "https://rpc.mainnet.near.org",
-            _ => {
-                panic!("Wrong network. Should be testnet OR mainnet")
-            }
-        };
+        let profile = NetworkProfile::named(&config.network)?;
+        let rpc_url = profile.rpc_url.as_str();
 
         // querying state for the account
         let view_state_request = ViewStateRequest {
@@ -1,5 +1,10 @@
+use anyhow::Context;
 use near_primitives::views::ViewStateResult;
 use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Directory with JSON files of network profiles shared with the provers.
+pub const NETWORKS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../data/networks");
 
 #[derive(Debug, Serialize)]
 pub struct ViewStateRequest {
@@ -55,7 +60,24 @@ pub struct StateItemValues {
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub account: String,
-    pub network: u8,
+    /// Chain id of a network profile in data/networks: mainnet, testnet or localnet.
+    pub network: String,
+}
+
+/// The part of a network profile in data/networks used by the script, other fields are ignored.
+#[derive(Debug, Deserialize)]
+pub struct NetworkProfile {
+    pub rpc_url: String,
+}
+
+impl NetworkProfile {
+    /// Loads the profile `{NETWORKS_PATH}/{chain_id}.json`.
+    pub fn named(chain_id: &str) -> anyhow::Result<Self> {
+        let path = format!("{}/{}.json", NETWORKS_PATH, chain_id);
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("Unknown network {}, no profile {}", chain_id, path))?;
+        Ok(serde_json::from_str(&data)?)
+    }
 }
 
 #[derive(Debug, Serialize)]
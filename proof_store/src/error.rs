@@ -0,0 +1,39 @@
+use thiserror::Error;
+
+/// Represents errors returned by proof stores.
+#[derive(Debug, Error)]
+pub enum StoreError {
+    /// Indicates that the requested proof doesn't exist or the path is invalid.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the invalid input.
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    /// Indicates that the stored hash of a block differs from the hash of the block.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the mismatch.
+    #[error("Hash mismatch: {0}")]
+    HashMismatch(String),
+
+    /// Indicates an error while reading or writing files of the store.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Indicates an error while serializing or deserializing the index or a stored hash.
+    ///
+    /// # Arguments
+    /// * `String` - The description of the serialization error.
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(error: serde_json::Error) -> Self {
+        StoreError::Serialization(error.to_string())
+    }
+}
+
+/// Represents the result type of proof stores.
+pub type Result<T> = std::result::Result<T, StoreError>;
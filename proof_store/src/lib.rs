@@ -0,0 +1,591 @@
+//! This crate provides the storage of proofs indexed by block hash, height and epoch_id.
+//! It is shared by the plonky2 prover (near_bft_finality) and the risc0 prover (near_risc0),
+//! which store their own artifacts: plonky2 proofs with circuit data and risc0 receipts.
+//!
+//! # Modules
+//!
+//! - `error`: Defines the error type returned by proof stores.
+
+pub mod error;
+
+pub use crate::error::{Result, StoreError};
+use near_primitives::block_header::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, MutexGuard};
+
+/// Represents the default root directory of the filesystem proof store.
+pub const DEFAULT_PROOFS_PATH: &str = "./proofs";
+
+/// Represents the file with the stored hash of a proven block in base58.
+pub const HASH_FILE: &str = "hash.json";
+
+/// Represents the file with the stored hash of a proven block in hex.
+pub const HASH_HEX_FILE: &str = "hash_hex.json";
+
+const INDEX_FILE: &str = "index.json";
+
+/// Represents the kind of a proven block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProofKind {
+    /// A randomly selected block.
+    Random,
+    /// Block_0 or Block_n-1 of an epoch. Hashes of these blocks are stored in the contract.
+    Epoch,
+}
+
+impl ProofKind {
+    /// Returns the name of the directory with proofs of this kind.
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            ProofKind::Random => "random",
+            ProofKind::Epoch => "epoch",
+        }
+    }
+}
+
+/// Represents the metadata of a stored proof used to index it.
+///
+/// # Fields
+///
+/// * `kind` - The kind of the proven block.
+/// * `block_hash` - The hash of the proven block.
+/// * `height` - The height of the proven block.
+/// * `epoch_id` - The epoch_id of the proven block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofRecord {
+    pub kind: ProofKind,
+    pub block_hash: CryptoHash,
+    pub height: u64,
+    pub epoch_id: CryptoHash,
+}
+
+impl ProofRecord {
+    pub fn new(kind: ProofKind, header: &BlockHeader) -> Self {
+        Self {
+            kind,
+            block_hash: *header.hash(),
+            height: header.height(),
+            epoch_id: header.epoch_id().0,
+        }
+    }
+}
+
+/// Represents the serialized proof of a block, which is stored as a set of files.
+pub trait Artifacts: Clone + Send + Sync + 'static {
+    /// Returns artifacts with the names of files they are stored in.
+    fn files(&self) -> Vec<(&'static str, &[u8])>;
+
+    /// Creates artifacts from the contents of files returned by `read` for their names.
+    fn from_files(read: impl FnMut(&'static str) -> Result<Vec<u8>>) -> Result<Self>;
+
+    /// Reads artifacts from the directory of a proof, e.g. `./proofs/random/{hash}`.
+    fn read_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        Self::from_files(|name| Ok(fs::read(dir.join(name))?))
+    }
+
+    /// Writes artifacts to the directory, each file is written atomically.
+    fn write_dir(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        for (name, bytes) in self.files() {
+            write_atomic(&dir.join(name), bytes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Represents a storage of proofs indexed by block hash, height and epoch_id.
+pub trait ProofStore<A: Artifacts>: Send + Sync {
+    /// Stores the proof. A proof of the same kind for the same block is replaced.
+    fn put(&self, record: &ProofRecord, artifacts: &A) -> Result<()>;
+
+    /// Returns the proof of the block, `None` if the block wasn't proven.
+    fn get(&self, kind: ProofKind, block_hash: &CryptoHash) -> Result<Option<A>>;
+
+    /// Returns the record of the proven block.
+    fn record(&self, kind: ProofKind, block_hash: &CryptoHash) -> Result<Option<ProofRecord>>;
+
+    /// Returns the record of the proven block at `height`.
+    fn by_height(&self, kind: ProofKind, height: u64) -> Result<Option<ProofRecord>>;
+
+    /// Returns records of proven blocks of the epoch ordered by height.
+    fn by_epoch(&self, kind: ProofKind, epoch_id: &CryptoHash) -> Result<Vec<ProofRecord>>;
+
+    /// Returns the record of the proven block with the greatest height.
+    /// For epoch blocks it is Block_0 of the last proven epoch (the last known height).
+    fn latest(&self, kind: ProofKind) -> Result<Option<ProofRecord>>;
+
+    /// Returns the hash of the proven epoch block, which is stored in the contract. The hash is
+    /// read from the store (`hash.json` of the proof), not taken from the index.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StoreError::InvalidInput` if the epoch block wasn't proven and
+    /// `StoreError::HashMismatch` if the stored hash is the hash of another block.
+    fn stored_hash(&self, block_hash: &CryptoHash) -> Result<CryptoHash>;
+}
+
+/// Checks that the hash stored with the proof of the block is the hash of the block.
+fn check_stored_hash(block_hash: &CryptoHash, stored_hash: CryptoHash) -> Result<CryptoHash> {
+    if stored_hash != *block_hash {
+        return Err(StoreError::HashMismatch(format!(
+            "Stored hash {stored_hash} differs from the hash of block {block_hash}"
+        )));
+    }
+    Ok(stored_hash)
+}
+
+/// Represents the index of stored proofs. Only records are persisted, lookup tables are rebuilt on load.
+#[derive(Debug, Default)]
+struct ProofIndex {
+    records: HashMap<(ProofKind, CryptoHash), ProofRecord>,
+    heights: BTreeMap<(ProofKind, u64), CryptoHash>,
+    epochs: HashMap<(ProofKind, CryptoHash), BTreeSet<u64>>,
+}
+
+impl ProofIndex {
+    fn from_records(records: Vec<ProofRecord>) -> Self {
+        let mut index = Self::default();
+        for record in records {
+            index.insert(record);
+        }
+        index
+    }
+
+    fn insert(&mut self, record: ProofRecord) {
+        let key = (record.kind, record.block_hash);
+        if let Some(old) = self.records.remove(&key) {
+            self.heights.remove(&(old.kind, old.height));
+            if let Some(heights) = self.epochs.get_mut(&(old.kind, old.epoch_id)) {
+                heights.remove(&old.height);
+            }
+        }
+        // Only one block exists at a height, so a record with the same height is outdated.
+        if let Some(hash) = self
+            .heights
+            .insert((record.kind, record.height), record.block_hash)
+        {
+            if let Some(old) = self.records.remove(&(record.kind, hash)) {
+                if let Some(heights) = self.epochs.get_mut(&(old.kind, old.epoch_id)) {
+                    heights.remove(&old.height);
+                }
+            }
+        }
+        self.epochs
+            .entry((record.kind, record.epoch_id))
+            .or_default()
+            .insert(record.height);
+        self.records.insert(key, record);
+    }
+
+    fn record(&self, kind: ProofKind, block_hash: &CryptoHash) -> Option<ProofRecord> {
+        self.records.get(&(kind, *block_hash)).cloned()
+    }
+
+    fn by_height(&self, kind: ProofKind, height: u64) -> Option<ProofRecord> {
+        self.heights
+            .get(&(kind, height))
+            .and_then(|hash| self.record(kind, hash))
+    }
+
+    fn by_epoch(&self, kind: ProofKind, epoch_id: &CryptoHash) -> Vec<ProofRecord> {
+        self.epochs
+            .get(&(kind, *epoch_id))
+            .map(|heights| {
+                heights
+                    .iter()
+                    .filter_map(|height| self.by_height(kind, *height))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn latest(&self, kind: ProofKind) -> Option<ProofRecord> {
+        self.heights
+            .range((kind, 0)..=(kind, u64::MAX))
+            .next_back()
+            .and_then(|(_, hash)| self.record(kind, hash))
+    }
+
+    fn to_records(&self) -> Vec<ProofRecord> {
+        let mut records: Vec<ProofRecord> = self.records.values().cloned().collect();
+        records.sort_by_key(|record| (record.kind, record.height));
+        records
+    }
+}
+
+/// Stores proofs in `{root}/{random|epoch}/{block_hash}` with the index in `{root}/index.json`.
+///
+/// Each file is written to a temporary file and renamed, so readers never see partially written
+/// proofs. The index is updated after all files of the proof are written.
+/// The store is not intended to be shared by several processes.
+#[derive(Debug)]
+pub struct FsProofStore<A> {
+    root: PathBuf,
+    index: Mutex<ProofIndex>,
+    artifacts: PhantomData<fn() -> A>,
+}
+
+impl<A: Artifacts> FsProofStore<A> {
+    /// Opens the store in `root` and loads its index. The directory is created if it doesn't exist.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+        let index_path = root.join(INDEX_FILE);
+        let records: Vec<ProofRecord> = if index_path.exists() {
+            serde_json::from_slice(&fs::read(&index_path)?)?
+        } else {
+            vec![]
+        };
+        Ok(Self {
+            root,
+            index: Mutex::new(ProofIndex::from_records(records)),
+            artifacts: PhantomData,
+        })
+    }
+
+    /// Returns the directory with files of the proof.
+    pub fn proof_dir(&self, kind: ProofKind, block_hash: &CryptoHash) -> PathBuf {
+        self.root.join(kind.dir_name()).join(block_hash.to_string())
+    }
+
+    fn lock_index(&self) -> Result<MutexGuard<'_, ProofIndex>> {
+        self.index
+            .lock()
+            .map_err(|_| StoreError::Io(std::io::Error::other("proof index lock is poisoned")))
+    }
+}
+
+impl<A: Artifacts> ProofStore<A> for FsProofStore<A> {
+    fn put(&self, record: &ProofRecord, artifacts: &A) -> Result<()> {
+        let dir = self.proof_dir(record.kind, &record.block_hash);
+        artifacts.write_dir(&dir)?;
+        // The stored hash in base58 & hex, as it is passed to the contract.
+        write_atomic(
+            &dir.join(HASH_FILE),
+            record.block_hash.to_string().as_bytes(),
+        )?;
+        write_atomic(
+            &dir.join(HASH_HEX_FILE),
+            hex::encode(record.block_hash.0).as_bytes(),
+        )?;
+        let mut index = self.lock_index()?;
+        index.insert(record.clone());
+        write_atomic(
+            &self.root.join(INDEX_FILE),
+            &serde_json::to_vec_pretty(&index.to_records())?,
+        )
+    }
+
+    fn get(&self, kind: ProofKind, block_hash: &CryptoHash) -> Result<Option<A>> {
+        if self.lock_index()?.record(kind, block_hash).is_none() {
+            return Ok(None);
+        }
+        Ok(Some(A::read_dir(self.proof_dir(kind, block_hash))?))
+    }
+
+    fn record(&self, kind: ProofKind, block_hash: &CryptoHash) -> Result<Option<ProofRecord>> {
+        Ok(self.lock_index()?.record(kind, block_hash))
+    }
+
+    fn by_height(&self, kind: ProofKind, height: u64) -> Result<Option<ProofRecord>> {
+        Ok(self.lock_index()?.by_height(kind, height))
+    }
+
+    fn by_epoch(&self, kind: ProofKind, epoch_id: &CryptoHash) -> Result<Vec<ProofRecord>> {
+        Ok(self.lock_index()?.by_epoch(kind, epoch_id))
+    }
+
+    fn latest(&self, kind: ProofKind) -> Result<Option<ProofRecord>> {
+        Ok(self.lock_index()?.latest(kind))
+    }
+
+    /// Reads `{root}/epoch/{block_hash}/hash.json`, which also exists for proofs stored before
+    /// the index existed.
+    fn stored_hash(&self, block_hash: &CryptoHash) -> Result<CryptoHash> {
+        let path = self.proof_dir(ProofKind::Epoch, block_hash).join(HASH_FILE);
+        let stored_hash = fs::read_to_string(&path).map_err(|e| {
+            StoreError::InvalidInput(format!(
+                "No stored hash for block {block_hash} in {}: {e}",
+                path.display()
+            ))
+        })?;
+        let stored_hash = stored_hash.trim();
+        let stored_hash = CryptoHash::from_str(stored_hash).map_err(|e| {
+            StoreError::Serialization(format!("Invalid stored hash {stored_hash}: {e}"))
+        })?;
+        check_stored_hash(block_hash, stored_hash)
+    }
+}
+
+/// Represents the state of `InMemoryProofStore`: the index, artifacts and stored hashes of proofs.
+struct InMemoryState<A> {
+    index: ProofIndex,
+    artifacts: HashMap<(ProofKind, CryptoHash), A>,
+    stored_hashes: HashMap<CryptoHash, CryptoHash>,
+}
+
+/// Keeps proofs in memory. Used in tests.
+pub struct InMemoryProofStore<A> {
+    state: Mutex<InMemoryState<A>>,
+}
+
+impl<A: Artifacts> Default for InMemoryProofStore<A> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(InMemoryState {
+                index: ProofIndex::default(),
+                artifacts: HashMap::new(),
+                stored_hashes: HashMap::new(),
+            }),
+        }
+    }
+}
+
+impl<A: Artifacts> InMemoryProofStore<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock_state(&self) -> Result<MutexGuard<'_, InMemoryState<A>>> {
+        self.state
+            .lock()
+            .map_err(|_| StoreError::Io(std::io::Error::other("proof store lock is poisoned")))
+    }
+}
+
+impl<A: Artifacts> ProofStore<A> for InMemoryProofStore<A> {
+    fn put(&self, record: &ProofRecord, artifacts: &A) -> Result<()> {
+        let mut state = self.lock_state()?;
+        state.index.insert(record.clone());
+        state
+            .artifacts
+            .insert((record.kind, record.block_hash), artifacts.clone());
+        if record.kind == ProofKind::Epoch {
+            state
+                .stored_hashes
+                .insert(record.block_hash, record.block_hash);
+        }
+        Ok(())
+    }
+
+    fn get(&self, kind: ProofKind, block_hash: &CryptoHash) -> Result<Option<A>> {
+        let state = self.lock_state()?;
+        Ok(state
+            .index
+            .record(kind, block_hash)
+            .and_then(|_| state.artifacts.get(&(kind, *block_hash)).cloned()))
+    }
+
+    fn record(&self, kind: ProofKind, block_hash: &CryptoHash) -> Result<Option<ProofRecord>> {
+        Ok(self.lock_state()?.index.record(kind, block_hash))
+    }
+
+    fn by_height(&self, kind: ProofKind, height: u64) -> Result<Option<ProofRecord>> {
+        Ok(self.lock_state()?.index.by_height(kind, height))
+    }
+
+    fn by_epoch(&self, kind: ProofKind, epoch_id: &CryptoHash) -> Result<Vec<ProofRecord>> {
+        Ok(self.lock_state()?.index.by_epoch(kind, epoch_id))
+    }
+
+    fn latest(&self, kind: ProofKind) -> Result<Option<ProofRecord>> {
+        Ok(self.lock_state()?.index.latest(kind))
+    }
+
+    fn stored_hash(&self, block_hash: &CryptoHash) -> Result<CryptoHash> {
+        let stored_hash = self
+            .lock_state()?
+            .stored_hashes
+            .get(block_hash)
+            .copied()
+            .ok_or_else(|| {
+                StoreError::InvalidInput(format!("No stored hash for block {block_hash}"))
+            })?;
+        check_stored_hash(block_hash, stored_hash)
+    }
+}
+
+/// Writes `bytes` to a temporary file next to `path` and renames it to `path`.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| StoreError::InvalidInput(format!("Invalid path {}", path.display())))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+    let result = write_synced(&tmp_path, bytes).and_then(|_| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result.map_err(StoreError::from)
+}
+
+fn write_synced(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct TestArtifacts {
+        proof: Vec<u8>,
+        data: Vec<u8>,
+    }
+
+    impl Artifacts for TestArtifacts {
+        fn files(&self) -> Vec<(&'static str, &[u8])> {
+            vec![("proof.bin", &self.proof), ("data.bin", &self.data)]
+        }
+
+        fn from_files(mut read: impl FnMut(&'static str) -> Result<Vec<u8>>) -> Result<Self> {
+            Ok(Self {
+                proof: read("proof.bin")?,
+                data: read("data.bin")?,
+            })
+        }
+    }
+
+    fn record(kind: ProofKind, hash: u8, height: u64, epoch: u8) -> ProofRecord {
+        ProofRecord {
+            kind,
+            block_hash: CryptoHash([hash; 32]),
+            height,
+            epoch_id: CryptoHash([epoch; 32]),
+        }
+    }
+
+    fn artifacts(seed: u8) -> TestArtifacts {
+        TestArtifacts {
+            proof: vec![seed; 4],
+            data: vec![seed + 1; 4],
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("proof_store_{:016x}", rand::random::<u64>()))
+    }
+
+    fn check_store(store: &dyn ProofStore<TestArtifacts>) -> Result<()> {
+        let b0 = record(ProofKind::Epoch, 1, 100, 7);
+        let bn_1 = record(ProofKind::Epoch, 2, 99, 6);
+        let random = record(ProofKind::Random, 3, 150, 7);
+        store.put(&b0, &artifacts(10))?;
+        store.put(&bn_1, &artifacts(20))?;
+        store.put(&random, &artifacts(30))?;
+
+        assert_eq!(
+            store.get(ProofKind::Epoch, &b0.block_hash)?,
+            Some(artifacts(10))
+        );
+        assert_eq!(store.get(ProofKind::Random, &b0.block_hash)?, None);
+        assert_eq!(
+            store.record(ProofKind::Random, &random.block_hash)?,
+            Some(random.clone())
+        );
+        assert_eq!(store.by_height(ProofKind::Epoch, 99)?, Some(bn_1.clone()));
+        assert_eq!(store.by_height(ProofKind::Epoch, 150)?, None);
+        assert_eq!(
+            store.by_epoch(ProofKind::Epoch, &CryptoHash([7; 32]))?,
+            vec![b0.clone()]
+        );
+        assert_eq!(store.latest(ProofKind::Epoch)?, Some(b0.clone()));
+        assert_eq!(store.latest(ProofKind::Random)?, Some(random));
+        assert_eq!(store.stored_hash(&bn_1.block_hash)?, bn_1.block_hash);
+        // Hashes of random blocks aren't stored in the contract.
+        assert!(matches!(
+            store.stored_hash(&CryptoHash([3; 32])),
+            Err(StoreError::InvalidInput(_))
+        ));
+
+        // Replacing the proof of the same block keeps a single record.
+        store.put(&b0, &artifacts(40))?;
+        assert_eq!(
+            store.get(ProofKind::Epoch, &b0.block_hash)?,
+            Some(artifacts(40))
+        );
+        assert_eq!(
+            store
+                .by_epoch(ProofKind::Epoch, &CryptoHash([7; 32]))?
+                .len(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_memory_proof_store() -> Result<()> {
+        check_store(&InMemoryProofStore::<TestArtifacts>::new())
+    }
+
+    #[test]
+    fn test_fs_proof_store_persists_index() -> Result<()> {
+        let root = temp_dir();
+        check_store(&FsProofStore::<TestArtifacts>::open(&root)?)?;
+        // Reopened store loads the index & doesn't leave temporary files.
+        let store = FsProofStore::<TestArtifacts>::open(&root)?;
+        assert_eq!(
+            store.by_height(ProofKind::Epoch, 100)?.map(|r| r.height),
+            Some(100)
+        );
+        assert_eq!(
+            store.get(ProofKind::Epoch, &CryptoHash([1; 32]))?,
+            Some(artifacts(40))
+        );
+        let dir = store.proof_dir(ProofKind::Epoch, &CryptoHash([1; 32]));
+        assert_eq!(
+            fs::read_to_string(dir.join(HASH_FILE))?,
+            CryptoHash([1; 32]).to_string()
+        );
+        for entry in fs::read_dir(&dir)? {
+            assert!(!entry?.file_name().to_string_lossy().ends_with(".tmp"));
+        }
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fs_proof_store_checks_stored_hash() -> Result<()> {
+        let root = temp_dir();
+        let store = FsProofStore::<TestArtifacts>::open(&root)?;
+        // Proofs stored before the index existed only have `hash.json`.
+        let hash = CryptoHash([5; 32]);
+        let dir = store.proof_dir(ProofKind::Epoch, &hash);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(HASH_FILE), format!("{hash}\n"))?;
+        assert_eq!(store.stored_hash(&hash)?, hash);
+        assert_eq!(store.record(ProofKind::Epoch, &hash)?, None);
+
+        // An indexed proof without its stored hash or with the hash of another block is rejected.
+        let b0 = record(ProofKind::Epoch, 1, 100, 7);
+        store.put(&b0, &artifacts(10))?;
+        let b0_hash_file = store
+            .proof_dir(ProofKind::Epoch, &b0.block_hash)
+            .join(HASH_FILE);
+        fs::write(&b0_hash_file, hash.to_string())?;
+        assert!(matches!(
+            store.stored_hash(&b0.block_hash),
+            Err(StoreError::HashMismatch(_))
+        ));
+        fs::remove_file(&b0_hash_file)?;
+        assert!(matches!(
+            store.stored_hash(&b0.block_hash),
+            Err(StoreError::InvalidInput(_))
+        ));
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+}